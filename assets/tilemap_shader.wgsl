@@ -1,6 +1,6 @@
 #import bevy_sprite::{
     mesh2d_bindings::mesh,
-    mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_clip, mesh2d_position_local_to_world},
+    mesh2d_functions::{get_world_from_local, mesh2d_position_local_to_world, mesh2d_position_world_to_clip},
 }
 #import mesh_view_bindings::globals;
 
@@ -51,6 +51,66 @@ struct Map {
     global_transform_matrix: mat3x3<f32>,
     global_transform_translation: vec3<f32>,
 
+    /// Horizontal scale of the first (x) and last (y) row for depth-scaled rows,
+    /// linearly interpolated in between.
+    depth_scale: vec2<f32>,
+
+    /// Tile values mapped to the first and last color of the color ramp.
+    ramp_range: vec2<f32>,
+
+    /// Signed distance field atlas: spread, outline width and glow width (in atlas pixels)
+    sdf_params: vec4<f32>,
+    sdf_outline_color: vec4<f32>,
+    sdf_glow_color: vec4<f32>,
+
+    /// Ownership overlay: tint opacity and border width (as fraction of a tile)
+    owner_params: vec2<f32>,
+
+    /// Screen size of a tile (in pixels) below which only LOD colors are drawn (x)
+    /// and above which tiles are drawn in full detail (y)
+    lod_range: vec2<f32>,
+
+    /// Hole cut into the map: center (xy) and radii (zw) in world coordinates
+    reveal_shape: vec4<f32>,
+    /// Hole cut into the map: feather width (x) and opacity in the center (y)
+    reveal_params: vec2<f32>,
+
+    /// Shadow cast by an upper layer: offset (xy) and blur radius (z) in tiles, strength (w)
+    shadow_params: vec4<f32>,
+    shadow_color: vec4<f32>,
+
+    /// Number of tile layers, including the base layer
+    n_layers: u32,
+
+    /// Width of dithered terrain borders, as fraction of a tile
+    dither_width: f32,
+
+    /// Wind for swaying tiles: direction (xy), strength in pixels (z) and frequency (w)
+    wind: vec4<f32>,
+
+    /// Fog of war color, alpha is the opacity over hidden tiles
+    fog_color: vec4<f32>,
+    /// Fog of war blending between tile centers (1) or hard tile edges (0)
+    fog_softness: f32,
+
+    /// Tile the vertex map positions are relative to
+    origin: vec2<i32>,
+
+    /// Progress of the cross-fade from the previous atlas
+    atlas_fade: f32,
+
+    /// What is drawn outside of the map (EDGE_MODE_*) and the atlas index to fill with
+    edge_mode: u32,
+    edge_fill: u32,
+
+    /// Upper bound of the tile heights
+    max_height: f32,
+    /// Brightness of cliff faces below raised tiles
+    cliff_shade: f32,
+
+    /// Multiplied with the final output color (linear RGBA)
+    color: vec4<f32>,
+
     // -----
     /// [derived] Size of the map in world units necessary to display
     /// all tiles according to projection.
@@ -85,6 +145,141 @@ var atlas_texture: texture_2d<f32>;
 @group(2) @binding(102)
 var atlas_sampler: sampler;
 
+/// Per-tile and per-atlas-index data of the optional features, packed into one buffer to stay
+/// within the limit of 8 storage buffers per shader stage (see `upload.rs`).
+/// Starts with the offset and length of each channel (`CHANNEL_*`), the color channels are
+/// stored in `colors`.
+@group(2) @binding(103)
+var<storage> channels: array<u32>;
+
+/// Cumulative column widths followed by cumulative row heights (in tiles, f32 bits),
+/// only meaningful with VARIABLE_GRID.
+const CHANNEL_GRID_OFFSETS: u32 = 0u;
+/// Owner per tile packed four per u32, only meaningful with TILE_OWNERSHIP.
+const CHANNEL_OWNERS: u32 = 1u;
+/// Shadow coverage per tile (f32 bits), only meaningful with LAYER_SHADOWS.
+const CHANNEL_SHADOW_COVERAGE: u32 = 2u;
+/// Tiles of the layers above the base layer, one layer after the other,
+/// only meaningful with MAP_LAYERS.
+const CHANNEL_LAYER_TILES: u32 = 3u;
+/// Frame animations per atlas index, only meaningful with TILE_ANIMATIONS.
+/// Number of headers `n`, one header (offset or 0) per atlas index below `n`, then per
+/// animation the frame count, frame duration (f32 bits) and frames.
+const CHANNEL_TILE_ANIMATIONS: u32 = 4u;
+/// Terrain category per atlas index (0 for none), only meaningful with TERRAIN_DITHER.
+const CHANNEL_TERRAIN_CATEGORIES: u32 = 5u;
+/// Tint per tile (linear RGBA, 8 bit per channel), only meaningful with TILE_TINTS.
+const CHANNEL_TINTS: u32 = 6u;
+/// One bit per atlas index of the tiles that sway in the wind, only meaningful with TILE_SWAY.
+const CHANNEL_SWAY_TILES: u32 = 7u;
+/// Visibility per tile packed four per u32 (0 never seen, 255 in view),
+/// only meaningful with FOG_OF_WAR.
+const CHANNEL_FOG: u32 = 8u;
+/// Height per tile in world units (f32 bits), only meaningful with TILE_HEIGHTS.
+const CHANNEL_HEIGHTS: u32 = 9u;
+/// Evenly spaced color stops, only meaningful with DATA_RAMP.
+const CHANNEL_RAMP_COLORS: u32 = 10u;
+/// Color per owner, only meaningful with TILE_OWNERSHIP.
+const CHANNEL_TEAM_COLORS: u32 = 11u;
+
+fn channel_len(channel: u32) -> u32 {
+    return channels[2u * channel + 1u];
+}
+
+fn channel_u32(channel: u32, i: u32) -> u32 {
+    return channels[channels[2u * channel] + i];
+}
+
+fn channel_f32(channel: u32, i: u32) -> f32 {
+    return bitcast<f32>(channel_u32(channel, i));
+}
+
+fn channel_color(channel: u32, i: u32) -> vec4<f32> {
+    return colors[channels[2u * channel] + i];
+}
+
+struct HighContrastEntry {
+    /// Replacement color, alpha of zero means no replacement
+    color: vec4<f32>,
+    /// 0: none, 1: stripes, 2: dots, 3: checker
+    pattern: u32,
+};
+
+/// High contrast replacement per atlas index, only meaningful with HIGH_CONTRAST.
+@group(2) @binding(104)
+var<storage> high_contrast: array<HighContrastEntry>;
+
+/// Color channels, see `channels`.
+@group(2) @binding(105)
+var<storage> colors: array<vec4<f32>>;
+
+/// Noise for dithered terrain borders, only meaningful with TERRAIN_DITHER.
+@group(2) @binding(112)
+var dither_noise: texture_2d<f32>;
+
+/// Decal projected onto the map, see `DecalShaderData`.
+struct Decal {
+    /// min / max of the covered area in map-local world coordinates
+    rect: vec4<f32>,
+    /// min / max in the decal texture
+    uv: vec4<f32>,
+    color: vec4<f32>,
+    /// start time, lifetime, fade duration, unused
+    time: vec4<f32>,
+}
+
+/// Texture decals are taken from, only meaningful with MAP_DECALS.
+@group(2) @binding(115)
+var decal_texture: texture_2d<f32>;
+@group(2) @binding(116)
+var decal_sampler: sampler;
+
+/// Decals projected onto the map, only meaningful with MAP_DECALS.
+@group(2) @binding(117)
+var<storage> decals: array<Decal>;
+
+/// Atlas with one layer per atlas index, only meaningful with ATLAS_ARRAY.
+@group(2) @binding(120)
+var atlas_array: texture_2d_array<f32>;
+@group(2) @binding(121)
+var atlas_array_sampler: sampler;
+
+/// Previous atlas while cross-fading, only meaningful with ATLAS_FADE.
+@group(2) @binding(122)
+var fade_atlas: texture_2d<f32>;
+@group(2) @binding(123)
+var fade_atlas_sampler: sampler;
+
+/// Atlas pages stacked into layers, only meaningful with ATLAS_PAGES.
+@group(2) @binding(124)
+var atlas_pages: texture_2d_array<f32>;
+@group(2) @binding(125)
+var atlas_pages_sampler: sampler;
+
+/// One texel per tile in its flat color, only meaningful with DISTANCE_LOD.
+@group(2) @binding(126)
+var lod_texture: texture_2d<f32>;
+@group(2) @binding(127)
+var lod_sampler: sampler;
+
+
+#ifdef DEBUG_OVERDRAW
+/// Number of atlas samples taken for the current fragment
+var<private> debug_samples: u32 = 0u;
+/// Number of non-transparent colors blended on top of non-transparent colors
+var<private> debug_overdraw: u32 = 0u;
+
+/// Heat map color for debug visualizations: blue (0) -> green -> red (>= max_value)
+fn debug_heat(value: f32, max_value: f32) -> vec4<f32> {
+    let t = clamp(value / max_value, 0.0, 1.0);
+    return vec4<f32>(
+        clamp(2.0 * t - 1.0, 0.0, 1.0),
+        1.0 - abs(2.0 * t - 1.0),
+        clamp(1.0 - 2.0 * t, 0.0, 1.0),
+        1.0
+    );
+}
+#endif // DEBUG_OVERDRAW
 
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
@@ -92,6 +287,9 @@ struct Vertex {
     @location(1) map_position: vec2<f32>,
     @location(2) mix_color: vec4<f32>,
     @location(3) animation_state: f32,
+#ifdef ROW_SLICES
+    @location(4) row_range: vec2<u32>,
+#endif
 };
 
 struct VertexOutput {
@@ -102,8 +300,26 @@ struct VertexOutput {
     @location(1) map_position: vec2<f32>,
     @location(2) mix_color: vec4<f32>,
     @location(3) animation_state: f32,
+#ifdef ROW_SLICES
+    @location(4) @interpolate(flat) row_range: vec2<u32>,
+#endif
+#ifdef TILE_DEPTH
+    /// z of the map entity
+    @location(5) @interpolate(flat) entity_z: f32,
+#endif
 }
 
+struct DisplaceIn {
+    /// world position of the vertex
+    world_position: vec3<f32>,
+    /// map position (in tiles) of the vertex
+    map_position: vec2<f32>,
+    animation_state: f32,
+    instance_index: u32,
+};
+
+#[user_vertex_code]
+
 /// Custom vertex shader for passing along the UV coordinate
 @vertex
 fn vertex(v: Vertex) -> VertexOutput {
@@ -111,11 +327,23 @@ fn vertex(v: Vertex) -> VertexOutput {
 
     var model: mat4x4<f32> = get_world_from_local(v.instance_index);
 
-    out.position = mesh2d_position_local_to_clip(model, vec4<f32>(v.position, 1.0));
     out.world_position = mesh2d_position_local_to_world(model, vec4<f32>(v.position, 1.0));
+
+    var displace_in: DisplaceIn;
+    displace_in.world_position = out.world_position.xyz;
+    displace_in.map_position = v.map_position;
+    displace_in.animation_state = v.animation_state;
+    displace_in.instance_index = v.instance_index;
+    out.position = mesh2d_position_world_to_clip(vec4<f32>(displace_vertex(displace_in), 1.0));
     out.mix_color = v.mix_color;
     out.map_position = v.map_position;
     out.animation_state = v.animation_state;
+    #ifdef ROW_SLICES
+        out.row_range = v.row_range;
+    #endif
+    #ifdef TILE_DEPTH
+        out.entity_z = model[3].z;
+    #endif
     return out;
 }
 
@@ -180,31 +408,447 @@ fn world_to_tile_offset(world_position: vec2<f32>, world_tile_base: vec2<f32>) -
     return vec2<f32>(1.0, -1.0) * (world_position - world_tile_base);
 }
 
+#ifdef TERRAIN_DITHER
+fn terrain_category(index: u32) -> u32 {
+    if index >= channel_len(CHANNEL_TERRAIN_CATEGORIES) {
+        return 0u;
+    }
+    return channel_u32(CHANNEL_TERRAIN_CATEGORIES, index);
+}
+
+/// Atlas index to draw at the given position: the tile's own index, or near a border to a
+/// neighbor of a different terrain category, the neighbor's index for some pixels
+/// (thresholded by the noise texture).
+fn dither_tile_index(
+    index: u32,
+    tile: vec2<i32>,
+    offset: vec2<f32>,
+    world_position: vec2<f32>
+) -> u32 {
+    let category = terrain_category(index);
+    if category == 0u || map.dither_width <= 0.0 {
+        return index;
+    }
+
+    // Nearest edge of the tile
+    let edge_distance = min(offset, 1.0 - offset);
+    let direction = select(vec2<i32>(1), vec2<i32>(-1), offset < vec2<f32>(0.5));
+    var neighbor = tile + vec2<i32>(direction.x, 0);
+    var distance = edge_distance.x;
+    if edge_distance.y < edge_distance.x {
+        neighbor = tile + vec2<i32>(0, direction.y);
+        distance = edge_distance.y;
+    }
+    if distance >= map.dither_width || !is_valid_tile(neighbor) {
+        return index;
+    }
+
+    let neighbor_index = get_tile_index(neighbor);
+    let neighbor_category = terrain_category(neighbor_index);
+    if neighbor_category == 0u || neighbor_category == category {
+        return index;
+    }
+
+    // Half of the pixels directly at the border, none at `dither_width` from it
+    let size = vec2<i32>(textureDimensions(dither_noise));
+    let p = ((vec2<i32>(floor(world_position)) % size) + size) % size;
+    let noise = textureLoad(dither_noise, p, 0).r;
+    if noise < 0.5 * (1.0 - distance / map.dither_width) {
+        return neighbor_index;
+    }
+    return index;
+}
+#endif // TERRAIN_DITHER
+
+#ifdef TILE_ANIMATIONS
+/// Atlas index of the current frame of the animation of `index` (or `index` if not animated).
+fn animated_tile_index(index: u32, time: f32) -> u32 {
+    if index >= channel_u32(CHANNEL_TILE_ANIMATIONS, 0u) {
+        return index;
+    }
+    let offset = channel_u32(CHANNEL_TILE_ANIMATIONS, 1u + index);
+    if offset == 0u {
+        return index;
+    }
+    let n_frames = channel_u32(CHANNEL_TILE_ANIMATIONS, offset);
+    let duration = channel_f32(CHANNEL_TILE_ANIMATIONS, offset + 1u);
+    let frame = u32(max(floor(time / duration), 0.0)) % n_frames;
+    return channel_u32(CHANNEL_TILE_ANIMATIONS, offset + 2u + frame);
+}
+#endif // TILE_ANIMATIONS
+
+#ifdef TILE_SWAY
+/// Offset to sample a tile at so it bends from its bottom edge with the wind,
+/// `offset` if the tile does not sway.
+fn sway_offset(index: u32, tile: vec2<i32>, offset: vec2<f32>, time: f32) -> vec2<f32> {
+    let word = index / 32u;
+    if word >= channel_len(CHANNEL_SWAY_TILES)
+        || (channel_u32(CHANNEL_SWAY_TILES, word) & (1u << (index % 32u))) == 0u {
+        return offset;
+    }
+    // 1 at the top of the tile, 0 at its bottom
+    let y = (offset.y + map.tile_anchor_point.y * map.tile_size.y) / map.tile_size.y;
+    let height = 1.0 - clamp(y, 0.0, 1.0);
+    // Neighboring tiles are slightly out of phase, so the swaying looks like passing gusts
+    let phase = dot(vec2<f32>(tile), vec2<f32>(0.9, 0.4));
+    let wave = sin(6.2831853 * map.wind.w * time - phase);
+    let lean = map.wind.z * (0.6 + 0.4 * wave) * height * height;
+    // Offsets point down, the wind direction up
+    return offset - vec2<f32>(map.wind.x, -map.wind.y) * lean;
+}
+#endif // TILE_SWAY
+
+/// Mirror an offset from the tile anchor point according to the flip flags of a tile
+/// (diagonal first, then horizontal and vertical).
+fn flip_tile_offset(offset: vec2<f32>, flags: u32) -> vec2<f32> {
+    let anchor = map.tile_anchor_point * map.tile_size;
+    var uv = (offset + anchor) / map.tile_size;
+    if (flags & TILE_FLIP_DIAGONAL) != 0u {
+        uv = uv.yx;
+    }
+    if (flags & TILE_FLIP_X) != 0u {
+        uv.x = 1.0 - uv.x;
+    }
+    if (flags & TILE_FLIP_Y) != 0u {
+        uv.y = 1.0 - uv.y;
+    }
+    return uv * map.tile_size - anchor;
+}
+
 /// Sample tile from the tile atlas
 /// tile_index: Index of the tile in the atlas
 /// tile_offset: Offset from tile anchor point in pixel/world coordinates
 fn _sample_tile(
-    tile_index: u32,
+    tile_index_: u32,
     pos: MapPosition,
     animation_state: f32,
 ) -> vec4<f32> {
+    var tile_index = tile_index_;
+    #ifdef TILE_ANIMATIONS
+        tile_index = animated_tile_index(tile_index, animation_state);
+    #endif
+
+    #ifdef ROW_SLICES
+        if pos.tile.y < i32(row_range.x) || pos.tile.y >= i32(row_range.y) {
+            return vec4<f32>(0.0);
+        }
+    #endif
+
+    #ifdef DEBUG_OVERDRAW
+        debug_samples += 1u;
+    #endif
+
+    var offset = pos.offset;
+    #ifdef TILE_SWAY
+        offset = sway_offset(tile_index_, pos.tile, offset, animation_state);
+    #endif
 
     var e: ExtractIn;
     e.tile_index = tile_index;
     e.tile_position = pos.tile;
-    e.tile_offset = pos.offset;
+    e.tile_offset = offset;
     e.animation_state = animation_state;
 
-    return sample_tile(e);
+    let flags = get_tile_flags(pos.tile);
+    if flags != 0u {
+        e.tile_offset = flip_tile_offset(offset, flags);
+    }
+
+    var color = sample_tile(e);
+
+    #ifdef SDF_ATLAS
+        color = decode_sdf(color);
+    #endif
+
+    #ifdef TILE_TINTS
+    if is_valid_tile(pos.tile) {
+        let tint = channel_u32(CHANNEL_TINTS, u32(pos.tile.y) * map.map_size.x + u32(pos.tile.x));
+        color *= unpack4x8unorm(tint);
+    }
+    #endif
+
+    #ifdef HIGH_CONTRAST
+        color = apply_high_contrast(color, tile_index, pos.offset);
+    #endif
+
+    return color;
+}
+
+#ifdef TILE_OWNERSHIP
+fn get_owner(tile: vec2<i32>) -> u32 {
+    if !is_valid_tile(tile) {
+        return 0u;
+    }
+    let i = u32(tile.y) * map.map_size.x + u32(tile.x);
+    return (channel_u32(CHANNEL_OWNERS, i / 4u) >> ((i % 4u) * 8u)) & 0xffu;
+}
+
+/// Tint owned tiles in their team color and draw borders along edges to tiles of other owners.
+/// offset: position inside the tile in map space ([0..1]^2)
+fn apply_ownership(color: vec4<f32>, tile: vec2<i32>, offset: vec2<f32>) -> vec4<f32> {
+    let owner = get_owner(tile);
+    if owner == 0u || owner >= channel_len(CHANNEL_TEAM_COLORS) {
+        return color;
+    }
+    let team = channel_color(CHANNEL_TEAM_COLORS, owner);
+    var result = vec4<f32>(mix(color.rgb, team.rgb, map.owner_params.x * team.a), color.a);
+
+    let w = map.owner_params.y;
+    let border = (offset.x < w && get_owner(tile - vec2<i32>(1, 0)) != owner)
+        || (offset.x > 1.0 - w && get_owner(tile + vec2<i32>(1, 0)) != owner)
+        || (offset.y < w && get_owner(tile - vec2<i32>(0, 1)) != owner)
+        || (offset.y > 1.0 - w && get_owner(tile + vec2<i32>(0, 1)) != owner);
+    if border {
+        result = blend(result, team);
+    }
+    return result;
+}
+#endif // TILE_OWNERSHIP
+
+#ifdef REVEAL_HOLE
+/// Opacity factor of the map at the given world position, for the hole cut into the map.
+fn reveal_opacity(world_position: vec2<f32>) -> f32 {
+    let radius = max(map.reveal_shape.zw, vec2<f32>(1e-6));
+    // 1.0 on the edge of the ellipse
+    let d = length((world_position - map.reveal_shape.xy) / radius);
+    let feather = map.reveal_params.x / min(radius.x, radius.y);
+    return mix(map.reveal_params.y, 1.0, smoothstep(1.0 - feather, 1.0, d));
+}
+#endif // REVEAL_HOLE
+
+#ifdef MAP_DECALS
+/// Draw all active decals covering the given (linear) map position on top of `color`,
+/// keeping its alpha so decals only show where the map does.
+fn apply_decals(color: vec4<f32>, map_position: vec2<f32>) -> vec4<f32> {
+    // Map-local world position, see `MapUniform::map_to_local`
+    let local = (map.projection * vec3<f32>(map_position, 0.0)).xy * map.tile_size
+        + map.world_offset;
+
+    var result = color;
+    for (var i = 0u; i < arrayLength(&decals); i++) {
+        let decal = decals[i];
+        // False for decals that were not started yet (NaN start time)
+        let age = globals.time - decal.time.x;
+        if !(age >= 0.0 && age < decal.time.y) {
+            continue;
+        }
+
+        let p = (local - decal.rect.xy) / (decal.rect.zw - decal.rect.xy);
+        if any(p < vec2<f32>(0.0)) || any(p >= vec2<f32>(1.0)) {
+            continue;
+        }
+
+        // World y points up, texture v points down
+        let uv = mix(decal.uv.xy, decal.uv.zw, vec2<f32>(p.x, 1.0 - p.y));
+        var decal_color = textureSampleLevel(decal_texture, decal_sampler, uv, 0.0) * decal.color;
+        decal_color.a *= clamp((decal.time.y - age) / max(decal.time.z, 1e-6), 0.0, 1.0);
+        result = vec4<f32>(mix(result.rgb, decal_color.rgb, decal_color.a), result.a);
+    }
+    return result;
+}
+#endif // MAP_DECALS
+
+#ifdef LAYER_SHADOWS
+/// Coverage of the shadow casting layer at the given tile, 0 outside of the map.
+fn get_shadow_coverage(tile: vec2<i32>) -> f32 {
+    let map_size = vec2<i32>(map.map_size);
+    if any(tile < vec2<i32>(0)) || any(tile >= map_size) {
+        return 0.0;
+    }
+    return channel_f32(CHANNEL_SHADOW_COVERAGE, u32(tile.y * map_size.x + tile.x));
+}
+
+/// Bilinearly filtered coverage at a fractional map position.
+fn sample_shadow_coverage(p: vec2<f32>) -> f32 {
+    let q = p - 0.5;
+    let t = vec2<i32>(floor(q));
+    let f = fract(q);
+    return mix(
+        mix(get_shadow_coverage(t), get_shadow_coverage(t + vec2<i32>(1, 0)), f.x),
+        mix(get_shadow_coverage(t + vec2<i32>(0, 1)), get_shadow_coverage(t + vec2<i32>(1, 1)), f.x),
+        f.y
+    );
+}
+
+/// Shadow opacity at the given map position, blurred with a 3x3 tent filter.
+fn shadow_amount(map_position: vec2<f32>) -> f32 {
+    let p = map_position - map.shadow_params.xy;
+    var sum = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let w = f32((2 - abs(x)) * (2 - abs(y)));
+            sum += w * sample_shadow_coverage(p + vec2<f32>(f32(x), f32(y)) * map.shadow_params.z);
+        }
+    }
+    return sum / 16.0 * map.shadow_params.w;
+}
+#endif // LAYER_SHADOWS
+
+#ifdef FOG_OF_WAR
+/// Visibility of the given tile from 0 to 1, tiles outside the map take that of the closest
+/// tile so the map border does not darken.
+fn get_fog_visibility(tile: vec2<i32>) -> f32 {
+    let t = clamp(tile, vec2<i32>(0), vec2<i32>(map.map_size) - 1);
+    let i = u32(t.y) * map.map_size.x + u32(t.x);
+    return f32((channel_u32(CHANNEL_FOG, i / 4u) >> ((i % 4u) * 8u)) & 0xffu) / 255.0;
+}
+
+/// Visibility at a fractional map position, blended between tile centers by the fog softness.
+fn fog_visibility(map_position: vec2<f32>) -> f32 {
+    let hard = get_fog_visibility(vec2<i32>(floor(map_position)));
+    let q = map_position - 0.5;
+    let t = vec2<i32>(floor(q));
+    let f = fract(q);
+    let soft = mix(
+        mix(get_fog_visibility(t), get_fog_visibility(t + vec2<i32>(1, 0)), f.x),
+        mix(get_fog_visibility(t + vec2<i32>(0, 1)), get_fog_visibility(t + vec2<i32>(1, 1)), f.x),
+        f.y
+    );
+    return mix(hard, soft, map.fog_softness);
+}
+#endif // FOG_OF_WAR
+
+#ifdef ROW_SLICES
+/// Rows (`y` exclusive) this slice draws tiles of, set at the start of the fragment shader.
+var<private> row_range: vec2<u32>;
+#endif
+
+#ifdef MAP_LAYERS
+/// Layer that `get_tile_index` reads from, set while compositing the layers.
+var<private> current_layer: u32 = 0u;
+#endif
+
+#ifdef TILE_DEPTH
+/// Tile whose pixel was blended last with enough opacity to hide what is behind it
+/// (tiles are blended back to front).
+var<private> depth_tile: vec2<i32>;
+var<private> has_depth_tile: bool = false;
+
+fn record_depth_tile(tile: vec2<i32>, color: vec4<f32>) {
+    if color.a >= 0.5 {
+        depth_tile = tile;
+        has_depth_tile = true;
+    }
+}
+
+/// Depth of the recorded tile, from its logical depth like `Map::map_to_world_3d` plus the z of
+/// the map entity (`entity_z`). Far away where no tile is opaque.
+fn tile_depth(world_position: vec4<f32>, entity_z: f32) -> f32 {
+    if !has_depth_tile {
+        return 0.0;
+    }
+    let center = vec2<f32>(depth_tile) + vec2<f32>(0.5);
+    let z = (map.projection * vec3<f32>(center, 0.0)).z + entity_z;
+    let clip = mesh2d_position_world_to_clip(vec4<f32>(world_position.xy, z, 1.0));
+    return clamp(clip.z / clip.w, 0.0, 1.0);
+}
+#endif
+
+#ifdef SDF_ATLAS
+/// Size of a screen pixel in atlas pixels, set at the start of the fragment shader
+/// (derivatives are not available in non-uniform control flow).
+var<private> sdf_pixel: f32 = 1.0;
+
+/// Turn a sample of a signed distance field atlas into fill, outline and glow colors.
+fn decode_sdf(sample: vec4<f32>) -> vec4<f32> {
+    let spread = map.sdf_params.x;
+    let outline_width = map.sdf_params.y;
+    let glow_width = map.sdf_params.z;
+
+    // Distance to the shape edge in atlas pixels, positive inside
+    let d = (sample.a - 0.5) * spread;
+    let fill = clamp(d / sdf_pixel + 0.5, 0.0, 1.0);
+    let outline = clamp((d + outline_width) / sdf_pixel + 0.5, 0.0, 1.0);
+
+    var shape = vec4<f32>(mix(map.sdf_outline_color.rgb, sample.rgb, fill), outline);
+    if outline_width <= 0.0 {
+        shape = vec4<f32>(sample.rgb, fill);
+    }
+
+    var glow = vec4<f32>(0.0);
+    if glow_width > 0.0 {
+        let outside = max(-(d + outline_width), 0.0);
+        glow = vec4<f32>(
+            map.sdf_glow_color.rgb,
+            map.sdf_glow_color.a * (1.0 - smoothstep(0.0, glow_width, outside))
+        );
+    }
+    return blend(glow, shape);
+}
+#endif // SDF_ATLAS
+
+#ifdef HIGH_CONTRAST
+/// Replace color of the given sample by the high contrast color for its tile index (if any),
+/// keeping the alpha (shape) of the tile and drawing the pattern on top.
+fn apply_high_contrast(color: vec4<f32>, tile_index: u32, offset: vec2<f32>) -> vec4<f32> {
+    if tile_index >= arrayLength(&high_contrast) {
+        return color;
+    }
+    let entry = high_contrast[tile_index];
+    if entry.color.a == 0.0 {
+        return color;
+    }
+
+    var mark = false;
+    switch entry.pattern {
+        case 1u: {
+            mark = fract((offset.x + offset.y) / 8.0) < 0.5;
+        }
+        case 2u: {
+            mark = length(fract(offset / 8.0) - vec2<f32>(0.5, 0.5)) < 0.2;
+        }
+        case 3u: {
+            let cell = vec2<i32>(floor(offset / 8.0));
+            mark = ((cell.x + cell.y) & 1) == 0;
+        }
+        default: {}
+    }
+
+    var rgb = entry.color.rgb;
+    if mark {
+        // Pattern in black or white, whichever contrasts more with the color
+        let luminance = dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+        rgb = select(vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0), luminance < 0.5);
+    }
+    return vec4<f32>(rgb, color.a);
 }
+#endif // HIGH_CONTRAST
+
+#ifdef ATLAS_ARRAY
+/// Sample from the given array layer (the atlas index, counted over all pages),
+/// rect_offset: offset in pixels from the top left corner of the tile
+fn sample_tile_array(layer: u32, tile_position: vec2<i32>, rect_offset: vec2<f32>) -> vec4<f32> {
+    // Layers hold no padding, so there is nothing to overhang into
+    if any(rect_offset < vec2<f32>(0.0)) || any(rect_offset >= map.tile_size) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let factor = max(map.atlas_tile_size_factor, 1);
+    let cell_offset = rect_offset + map.tile_size * vec2<f32>(
+        f32(tile_position.x % factor),
+        f32(tile_position.y % factor)
+    );
+    return textureSample(
+        atlas_array, atlas_array_sampler,
+        cell_offset / (map.tile_size * f32(factor)), i32(layer)
+    );
+}
+#endif // ATLAS_ARRAY
 
 fn sample_tile_at(
     tile_index: u32,
     tile_position: vec2<i32>,
     tile_offset: vec2<f32>,
 ) -> vec4<f32> {
+    var index = tile_index;
+    #ifdef ATLAS_PAGES
+        // The high bits select the atlas page
+        let page = (tile_index & TILE_PAGE_MASK) >> TILE_PAGE_SHIFT;
+        index = tile_index & ~TILE_PAGE_MASK;
+    #endif
+
     // Tile start position in the atlas
-    var tile_start = atlas_index_to_position(tile_index, tile_position);
+    var tile_start = atlas_index_to_position(index, tile_position);
 
     // Offset in pixels from tile_start to sample from
     var rect_offset = tile_offset + map.tile_anchor_point * map.tile_size;
@@ -225,9 +869,31 @@ fn sample_tile_at(
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
 
-    return textureSample(
-        atlas_texture, atlas_sampler, total_offset / map.atlas_size
-    );
+    #ifdef ATLAS_ARRAY
+        var layer = index;
+        #ifdef ATLAS_PAGES
+            layer += page * map.n_tiles.x * map.n_tiles.y;
+        #endif
+        var color = sample_tile_array(layer, tile_position, rect_offset);
+    #else
+        #ifdef ATLAS_PAGES
+            var color = textureSample(
+                atlas_pages, atlas_pages_sampler, total_offset / map.atlas_size, i32(page)
+            );
+        #else
+            var color = textureSample(
+                atlas_texture, atlas_sampler, total_offset / map.atlas_size
+            );
+        #endif
+    #endif
+
+    #ifdef ATLAS_FADE
+        // The previous atlas is never split into an array
+        let previous = textureSample(fade_atlas, fade_atlas_sampler, total_offset / map.atlas_size);
+        color = mix(previous, color, map.atlas_fade);
+    #endif
+
+    return color;
 }
 
 /// Same as sample_tile_at, but allow control of all the parameters
@@ -302,9 +968,41 @@ struct MapPosition {
 };
 
 
-///
+/// Flip flags in the high bits of tile values, see `flip.rs`.
+const TILE_FLIP_X: u32 = 0x80000000u;
+const TILE_FLIP_Y: u32 = 0x40000000u;
+const TILE_FLIP_DIAGONAL: u32 = 0x20000000u;
+const TILE_FLIP_MASK: u32 = 0xe0000000u;
+
+/// Atlas page in the bits below the flip flags, see `pages.rs`.
+const TILE_PAGE_SHIFT: u32 = 24u;
+const TILE_PAGE_MASK: u32 = 0x0f000000u;
+
+/// Atlas index of the tile at the given position (without flip flags).
 fn get_tile_index(map_position: vec2<i32>) -> u32 {
-    return map_texture[map_position.y * i32(map.map_size.x) + map_position.x];
+    return get_tile_value(map_position) & ~TILE_FLIP_MASK;
+}
+
+/// Flip flags of the tile at the given position, 0 outside of the map.
+fn get_tile_flags(map_position: vec2<i32>) -> u32 {
+    if !is_valid_tile(map_position) {
+        return 0u;
+    }
+    return get_tile_value(map_position) & TILE_FLIP_MASK;
+}
+
+/// Raw tile value (atlas index and flip flags) at the given position.
+fn get_tile_value(map_position: vec2<i32>) -> u32 {
+    let i = map_position.y * i32(map.map_size.x) + map_position.x;
+    #ifdef MAP_LAYERS
+    if current_layer > 0u {
+        return channel_u32(
+            CHANNEL_LAYER_TILES,
+            (current_layer - 1u) * map.map_size.x * map.map_size.y + u32(i)
+        );
+    }
+    #endif
+    return map_texture[i];
 }
 
 fn get_tile_index_checked(map_position: vec2<i32>) -> u32 {
@@ -314,6 +1012,164 @@ fn get_tile_index_checked(map_position: vec2<i32>) -> u32 {
     return get_tile_index(map_position);
 }
 
+#ifdef VARIABLE_GRID
+/// Convert a linear position along one axis (as if all cells had size 1)
+/// to a fractional cell position, given cumulative cell sizes in
+/// `CHANNEL_GRID_OFFSETS[start .. start + n + 1]`.
+fn linear_to_cell(linear: f32, start: u32, n: u32) -> f32 {
+    if linear < 0.0 {
+        return linear;
+    }
+    let total = channel_f32(CHANNEL_GRID_OFFSETS, start + n);
+    if linear >= total {
+        return f32(n) + linear - total;
+    }
+
+    // Binary search for the last offset <= linear
+    var lo = 0u;
+    var hi = n;
+    while hi - lo > 1u {
+        let mid = (lo + hi) / 2u;
+        if channel_f32(CHANNEL_GRID_OFFSETS, start + mid) <= linear {
+            lo = mid;
+        }
+        else {
+            hi = mid;
+        }
+    }
+
+    let a = channel_f32(CHANNEL_GRID_OFFSETS, start + lo);
+    let b = channel_f32(CHANNEL_GRID_OFFSETS, start + lo + 1u);
+    return f32(lo) + (linear - a) / (b - a);
+}
+
+/// Convert linear map position (as interpolated from the vertices)
+/// to the actual fractional map position for maps with variable row/column sizes.
+fn linear_to_map_position(linear: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        linear_to_cell(linear.x, 0u, map.map_size.x),
+        linear_to_cell(linear.y, map.map_size.x + 1u, map.map_size.y),
+    );
+}
+#endif // VARIABLE_GRID
+
+#ifdef DEPTH_SCALED_ROWS
+/// Horizontal scale of the given (fractional) map row
+fn row_scale(map_y: f32) -> f32 {
+    let t = clamp(map_y / f32(map.map_size.y), 0.0, 1.0);
+    return mix(map.depth_scale.x, map.depth_scale.y, t);
+}
+
+/// Undo the horizontal row scaling (around the horizontal center of the map)
+/// of a linear map position.
+/// `map_y`: The (fractional) map row of the position
+fn unscale_row(linear: vec2<f32>, map_y: f32, extent_x: f32) -> vec2<f32> {
+    let center = extent_x / 2.0;
+    return vec2<f32>(center + (linear.x - center) / row_scale(map_y), linear.y);
+}
+#endif // DEPTH_SCALED_ROWS
+
+#ifdef DATA_RAMP
+/// Map a tile value through the color ramp
+fn ramp_color(value: f32) -> vec4<f32> {
+    let n = channel_len(CHANNEL_RAMP_COLORS);
+    let range = max(map.ramp_range.y - map.ramp_range.x, 1e-6);
+    let t = clamp((value - map.ramp_range.x) / range, 0.0, 1.0) * f32(n - 1u);
+    let i = min(u32(floor(t)), n - 1u);
+    let j = min(i + 1u, n - 1u);
+    return mix(
+        channel_color(CHANNEL_RAMP_COLORS, i),
+        channel_color(CHANNEL_RAMP_COLORS, j),
+        t - f32(i)
+    );
+}
+#endif // DATA_RAMP
+
+#ifdef EDGE_ANTIALIAS
+/// Smooth the silhouette of the map: Along cell edges at the border of the map, replace the
+/// (stair-stepped) atlas alpha of the tile with the analytic coverage of its cell.
+/// Edges inside the map are not touched, hex cells are the parallelograms of the projection.
+/// map_space_offset: Position within the cell in map coordinates
+/// pixel: Size of a screen pixel in map coordinates
+fn antialias_edges(
+    color: vec4<f32>,
+    index: u32,
+    pos: MapPosition,
+    map_space_offset: vec2<f32>,
+    pixel: vec2<f32>,
+    animation_state: f32
+) -> vec4<f32> {
+    let last = vec2<i32>(map.map_size) - vec2<i32>(1);
+    let far = vec2<f32>(1e9);
+    // Distance in pixels to the cell edges that lie on the map border
+    let to_min = select(far, map_space_offset / pixel, pos.tile == vec2<i32>(0));
+    let to_max = select(far, (1.0 - map_space_offset) / pixel, pos.tile == last);
+    let d = min(min(to_min.x, to_min.y), min(to_max.x, to_max.y));
+    if d >= 1.0 {
+        return color;
+    }
+
+    // Sample the tile a bit further inside the cell, where its art is opaque
+    let inward = normalize(vec2<f32>(0.5) - map_space_offset + vec2<f32>(1e-6)) * pixel * 1.5;
+    let world_inward = map.global_transform_matrix * (
+        map.projection * vec3<f32>(inward, 0.0)
+    ) * vec3<f32>(map.tile_size, 1.0);
+    var inner = pos;
+    inner.offset = pos.offset + vec2<f32>(1.0, -1.0) * world_inward.xy;
+    var c = _sample_tile(index, inner, animation_state);
+    c.a = c.a * clamp(d + 0.5, 0.0, 1.0);
+    return c;
+}
+#endif // EDGE_ANTIALIAS
+
+#ifdef DEBUG_INDEX_LABELS
+/// 3x5 pixel glyphs of the digits 0-9, rows top to bottom, 3 bits per row (left pixel highest)
+const DIGIT_GLYPHS = array<u32, 10>(
+    31599u, 11415u, 29671u, 29647u, 23497u, 31183u, 31215u, 29257u, 31727u, 31695u
+);
+
+/// Draw `value` as decimal number centered on the tile.
+/// uv: Position within the tile rectangle, (0, 0) is top left, (1, 1) bottom right
+fn draw_index_label(color: vec4<f32>, value: u32, uv: vec2<f32>) -> vec4<f32> {
+    var n_digits = 1u;
+    for (var v = value / 10u; v > 0u; v = v / 10u) {
+        n_digits = n_digits + 1u;
+    }
+
+    // Size of a glyph pixel (in atlas pixels) so the label fits the tile,
+    // digits are 3 pixels wide with 1 pixel spacing
+    let width = f32(4u * n_digits - 1u);
+    let px = min(0.8 * map.tile_size.x / width, 0.4 * map.tile_size.y / 5.0);
+    let label_size = vec2<f32>(width, 5.0) * px;
+    let p = (uv * map.tile_size - (map.tile_size - label_size) / 2.0) / px;
+
+    // Dark box with a margin of one glyph pixel for contrast
+    if p.x < -1.0 || p.y < -1.0 || p.x >= width + 1.0 || p.y >= 6.0 {
+        return color;
+    }
+    let background = blend(color, vec4<f32>(0.0, 0.0, 0.0, 0.6));
+    if p.x < 0.0 || p.y < 0.0 || p.x >= width || p.y >= 5.0 {
+        return background;
+    }
+
+    let cell = vec2<u32>(p);
+    let column = cell.x % 4u;
+    if column == 3u {
+        return background;
+    }
+    var digit = value;
+    for (var i = cell.x / 4u + 1u; i < n_digits; i = i + 1u) {
+        digit = digit / 10u;
+    }
+    var glyphs = DIGIT_GLYPHS;
+    let bit = 14u - (cell.y * 3u + column);
+    if ((glyphs[digit % 10u] >> bit) & 1u) == 1u {
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+    return background;
+}
+#endif // DEBUG_INDEX_LABELS
+
 /// Blend c1 on top of c0
 fn blend(c0: vec4<f32>, c1: vec4<f32>) -> vec4<f32> {
     // See https://de.wikipedia.org/wiki/Alpha_Blending
@@ -322,6 +1178,12 @@ fn blend(c0: vec4<f32>, c1: vec4<f32>) -> vec4<f32> {
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
 
+    #ifdef DEBUG_OVERDRAW
+    if c0.a > 0.0 && c1.a > 0.0 {
+        debug_overdraw += 1u;
+    }
+    #endif
+
     // If c1.a = 1, this is 1, if c1.a = 0, this is c0.a
     // That is if c1 is fully opaque, we take c1, otherwise we let some of c0 shine through
     let a_mix = c1.a + (1 - c1.a) * c0.a;
@@ -341,6 +1203,80 @@ fn is_valid_tile(tile: vec2<i32>) -> bool {
     return true;
 }
 
+#ifdef TILE_HEIGHTS
+/// Upper limit of the steps marched to find raised tiles
+const MAX_HEIGHT_STEPS: u32 = 64u;
+
+fn tile_height(tile: vec2<i32>) -> f32 {
+    if !is_valid_tile(tile) {
+        return 0.0;
+    }
+    return channel_f32(CHANNEL_HEIGHTS, u32(tile.y) * map.map_size.x + u32(tile.x));
+}
+
+/// Map position of the surface seen at `map_position` (xy) with raised tiles and its brightness
+/// (z, `map.cliff_shade` for cliff faces).
+/// Marches down the screen from the highest possible tile, as tiles lower on screen are in front.
+fn elevate(map_position: vec2<f32>) -> vec3<f32> {
+    if map.max_height <= 0.0 {
+        return vec3<f32>(map_position, 1.0);
+    }
+    // Map space offset of one world unit up the screen
+    let up = map.inverse_projection * vec2<f32>(0.0, 1.0 / map.tile_size.y);
+    let n_steps = min(u32(ceil(4.0 * map.max_height / abs(map.tile_size.y))), MAX_HEIGHT_STEPS);
+    let stride = map.max_height / f32(n_steps);
+
+    for (var k = n_steps; k > 0u; k = k - 1u) {
+        let d = f32(k) * stride;
+        let tile = vec2<i32>(floor(map_position - up * d));
+        let height = tile_height(tile);
+        if height < d {
+            continue;
+        }
+        // Top of the tile if its raised footprint covers the position, otherwise its cliff face
+        let top = map_position - up * height;
+        if all(vec2<i32>(floor(top)) == tile) {
+            return vec3<f32>(top, 1.0);
+        }
+        return vec3<f32>(map_position - up * d, map.cliff_shade);
+    }
+
+    // Ground level, or the cliff face right below a raised tile
+    if tile_height(vec2<i32>(floor(map_position))) > 0.0 {
+        return vec3<f32>(map_position, map.cliff_shade);
+    }
+    return vec3<f32>(map_position, 1.0);
+}
+#endif
+
+#ifdef EDGE_MODE
+const EDGE_MODE_CLAMP: u32 = 1u;
+const EDGE_MODE_WRAP: u32 = 2u;
+const EDGE_MODE_FILL: u32 = 3u;
+
+/// Map position of the tile drawn at `tile` outside of the map (unchanged for fill).
+fn edge_tile(tile: vec2<i32>) -> vec2<i32> {
+    let map_size = vec2<i32>(map.map_size);
+    if map.edge_mode == EDGE_MODE_CLAMP {
+        return clamp(tile, vec2<i32>(0), map_size - 1);
+    }
+    if map.edge_mode == EDGE_MODE_WRAP {
+        return ((tile % map_size) + map_size) % map_size;
+    }
+    return tile;
+}
+#endif
+
+/// Position of the tile `offset` tiles away from `tile`, across the map edges when wrapping
+/// or clamping.
+fn neighbor_tile(tile: vec2<i32>, offset: vec2<i32>) -> vec2<i32> {
+    #ifdef EDGE_MODE
+        return edge_tile(tile + offset);
+    #else
+        return tile + offset;
+    #endif
+}
+
 ///
 ///
 /// tile_index: Tile index in the atlas
@@ -354,16 +1290,20 @@ fn sample_neighbor_tile_index(tile_index: u32, pos_: MapPosition, tile_offset: v
     var overhang = (map.projection * vec3<f32>(vec2<f32>(-tile_offset), 0.0)).xy * map.tile_size;
 
     var pos = pos_;
-    pos.tile = pos.tile + tile_offset;
+    pos.tile = neighbor_tile(pos.tile, tile_offset);
     pos.offset = pos.offset + vec2<f32>(1.0, -1.0) * overhang;
-    return _sample_tile(tile_index, pos, animation_state);
+    let color = _sample_tile(tile_index, pos, animation_state);
+    #ifdef TILE_DEPTH
+        record_depth_tile(pos.tile, color);
+    #endif
+    return color;
 }
 
 /// pos: The map position to sample
 /// tile_offset: The offset of the tile (in number of whole tiles) to sample from
 fn sample_neighbor(pos: MapPosition, tile_offset: vec2<i32>, animation_state: f32) -> vec4<f32> {
     // integral position of the neighbouring tile
-    var tile = pos.tile + tile_offset;
+    var tile = neighbor_tile(pos.tile, tile_offset);
     if !is_valid_tile(tile) {
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
@@ -373,6 +1313,30 @@ fn sample_neighbor(pos: MapPosition, tile_offset: vec2<i32>, animation_state: f3
     return sample_neighbor_tile_index(tile_index, pos, tile_offset, animation_state);
 }
 
+/// Maximum distance (in tiles) of neighbors that may overhang into a tile
+const OVERHANG_REACH: u32 = #{OVERHANG_REACH};
+const N_DOMINANCE_NEIGHBORS: u32 = 8u * OVERHANG_REACH;
+
+/// Blend the neighbors in direction `direction` up to OVERHANG_REACH tiles away,
+/// farthest first (for underhangs, where farther tiles are further behind).
+fn blend_neighbors_far_first(color: vec4<f32>, pos: MapPosition, direction: vec2<i32>, animation_state: f32) -> vec4<f32> {
+    var c = color;
+    for (var k = i32(OVERHANG_REACH); k >= 1; k = k - 1) {
+        c = blend(c, sample_neighbor(pos, direction * k, animation_state));
+    }
+    return c;
+}
+
+/// Blend the neighbors in direction `direction` up to OVERHANG_REACH tiles away,
+/// nearest first (for overhangs, where farther tiles are further in front).
+fn blend_neighbors_near_first(color: vec4<f32>, pos: MapPosition, direction: vec2<i32>, animation_state: f32) -> vec4<f32> {
+    var c = color;
+    for (var k = 1; k <= i32(OVERHANG_REACH); k = k + 1) {
+        c = blend(c, sample_neighbor(pos, direction * k, animation_state));
+    }
+    return c;
+}
+
 fn render_dominance_overhangs(color: vec4<f32>, index: u32, pos: MapPosition, animation_state: f32) -> vec4<f32> {
     var c = color;
 
@@ -380,8 +1344,9 @@ fn render_dominance_overhangs(color: vec4<f32>, index: u32, pos: MapPosition, an
     // current tile index. More so we want to render them in order of tile index (from lowest to
     // highest) to ensure that the overhangs are rendered in the correct order.
 
-    // First, collect all the indices of the neighbors that are greater than the current tile index
-    var neighbor_offsets = array<vec2<i32>, 8>(
+    // First, collect the indices of all neighbors, in every direction up to OVERHANG_REACH tiles
+    // away
+    let directions = array<vec2<i32>, 8>(
         vec2<i32>(-1, -1),
         vec2<i32>(-1, 0),
         vec2<i32>(-1, 1),
@@ -391,20 +1356,19 @@ fn render_dominance_overhangs(color: vec4<f32>, index: u32, pos: MapPosition, an
         vec2<i32>(1, -1),
         vec2<i32>(0, -1),
     );
-    var neighbors: array<u32, 8> = array<u32, 8>(
-        get_tile_index_checked(pos.tile + neighbor_offsets[0]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[1]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[2]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[3]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[4]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[5]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[6]),
-        get_tile_index_checked(pos.tile + neighbor_offsets[7])
-    );
+    var neighbor_offsets: array<vec2<i32>, N_DOMINANCE_NEIGHBORS>;
+    var neighbors: array<u32, N_DOMINANCE_NEIGHBORS>;
+    for (var k = 0u; k < OVERHANG_REACH; k = k + 1u) {
+        for (var d = 0u; d < 8u; d = d + 1u) {
+            let offset = directions[d] * i32(k + 1u);
+            neighbor_offsets[k * 8u + d] = offset;
+            neighbors[k * 8u + d] = get_tile_index_checked(neighbor_tile(pos.tile, offset));
+        }
+    }
 
     // Then, sort the neighbors by index
-    for (var i = 0u; i < 8u; i = i + 1u) {
-        for (var j = i + 1u; j < 8u; j = j + 1u) {
+    for (var i = 0u; i < N_DOMINANCE_NEIGHBORS; i = i + 1u) {
+        for (var j = i + 1u; j < N_DOMINANCE_NEIGHBORS; j = j + 1u) {
             if neighbors[i] > neighbors[j] {
                 var tmp = neighbors[i];
                 neighbors[i] = neighbors[j];
@@ -418,7 +1382,7 @@ fn render_dominance_overhangs(color: vec4<f32>, index: u32, pos: MapPosition, an
     }
 
     // Finally, render the overhangs in order of index
-    for (var i = 0u; i < 8u; i = i + 1u) {
+    for (var i = 0u; i < N_DOMINANCE_NEIGHBORS; i = i + 1u) {
         if neighbors[i] > index {
             c = blend(c, sample_neighbor_tile_index(neighbors[i], pos, neighbor_offsets[i], animation_state));
         }
@@ -439,35 +1403,35 @@ fn render_perspective_underhangs(color: vec4<f32>, pos: MapPosition, animation_s
     // P: Positive (1)
     // Z: Zero (0)
     #ifdef PERSPECTIVE_UNDER_NN
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1, -1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>( -1, -1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_NP
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1,  1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>( -1,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PN
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1, -1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>(  1, -1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PP
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1,  1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>(  1,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_ZN
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  0, -1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>(  0, -1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_NZ
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1,  0), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>( -1,  0), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_ZP
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  0,  1), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>(  0,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PZ
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1,  0), animation_state));
+        c = blend_neighbors_far_first(c, pos, vec2<i32>(  1,  0), animation_state);
     #endif
 
     return c;
@@ -478,49 +1442,110 @@ fn render_perspective_overhangs(color: vec4<f32>, pos: MapPosition, animation_st
     var c = color;
 
     #ifdef PERSPECTIVE_UNDER_ZN
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  0,  1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>(  0,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_NZ
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1,  0), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>(  1,  0), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_ZP
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  0, -1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>(  0, -1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PZ
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1,  0), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>( -1,  0), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_NN
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1,  1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>(  1,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_NP
-        c = blend(c, sample_neighbor(pos, vec2<i32>(  1, -1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>(  1, -1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PN
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1,  1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>( -1,  1), animation_state);
     #endif
 
     #ifdef PERSPECTIVE_UNDER_PP
-        c = blend(c, sample_neighbor(pos, vec2<i32>( -1, -1), animation_state));
+        c = blend_neighbors_near_first(c, pos, vec2<i32>( -1, -1), animation_state);
     #endif
 
     return c;
 }
 
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+#ifdef TILE_DEPTH
+    @builtin(frag_depth) depth: f32,
+#endif
+}
+
 @fragment
-fn fragment(
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    var out: FragmentOutput;
+    out.color = fragment_color(in) * map.color;
+    #ifdef BLEND_PREMULTIPLY
+        out.color = vec4<f32>(out.color.rgb * out.color.a, out.color.a);
+    #endif
+    #ifdef BLEND_OPAQUE
+        out.color.a = 1.0;
+    #endif
+    #ifdef TILE_DEPTH
+        out.depth = tile_depth(in.world_position, in.entity_z);
+    #endif
+    return out;
+}
+
+fn fragment_color(
     in: VertexOutput
-) -> @location(0) vec4<f32> {
+) -> vec4<f32> {
     var world_position = in.world_position.xy;
     var color = vec4<f32>(0.0, 0.0, 0.0, 0.0);
 
-    var tile = floor(in.map_position);
-    var map_space_offset = in.map_position - tile;
+    var map_position = in.map_position;
+
+    #ifdef TILE_HEIGHTS
+        let elevation = elevate(map_position);
+        map_position = elevation.xy;
+    #endif
+
+    #ifdef DEPTH_SCALED_ROWS
+        // Depth scaled rows always come with a variable grid (for the row heights)
+        let row = linear_to_cell(map_position.y, map.map_size.x + 1u, map.map_size.y);
+        map_position = unscale_row(map_position, row, channel_f32(CHANNEL_GRID_OFFSETS, map.map_size.x));
+    #endif
+
+    #ifdef VARIABLE_GRID
+        map_position = linear_to_map_position(map_position);
+    #endif
+
+    #ifdef EDGE_ANTIALIAS
+        // Derivatives need uniform control flow, so compute them upfront
+        let pixel = max(fwidth(map_position), vec2<f32>(1e-6));
+    #endif
+
+    #ifdef DISTANCE_LOD
+        // Screen pixels per tile, 1 when far away (only flat colors), 0 when close
+        let tile_pixels = 1.0 / max(max(fwidth(map_position).x, fwidth(map_position).y), 1e-6);
+        let lod_weight = 1.0 - smoothstep(map.lod_range.x, map.lod_range.y, tile_pixels);
+    #endif
+
+    #ifdef SDF_ATLAS
+        let atlas_pixel = fwidth(map_position) * map.tile_size;
+        sdf_pixel = max(max(atlas_pixel.x, atlas_pixel.y), 1e-6);
+    #endif
+
+    var tile = floor(map_position);
+    var map_space_offset = map_position - tile;
+
+    #ifdef RELATIVE_ORIGIN
+        // Only whole tiles are added back, so the offset within the tile keeps full precision
+        tile += vec2<f32>(map.origin);
+        map_position += vec2<f32>(map.origin);
+    #endif
 
     var world_space_offset = map.global_transform_matrix * (
         map.projection * vec3<f32>(map_space_offset, 0.0)
@@ -530,12 +1555,67 @@ fn fragment(
     pos.tile = vec2<i32>(tile);
     pos.offset = vec2<f32>(1.0, -1.0) * world_space_offset.xy;
 
+    #ifdef EDGE_MODE
+        // Outside of the map draw the clamped or wrapped tile, fill is handled below
+        let edge = edge_tile(pos.tile);
+        if map.edge_mode == EDGE_MODE_WRAP {
+            // Fog of war and shadows repeat along with the tiles
+            map_position += vec2<f32>(edge - pos.tile);
+        }
+        pos.tile = edge;
+    #endif
+
+    #ifdef ROW_SLICES
+        // Placeholder of a map drawn in slices
+        if in.row_range.x >= in.row_range.y {
+            discard;
+        }
+        row_range = in.row_range;
+    #endif
+
+    #ifdef DISTANCE_LOD
+        // Far away only the baked texture is sampled, without looking up any tiles
+        var lod_color = vec4<f32>(0.0);
+        if is_valid_tile(pos.tile) {
+            let lod_uv = map_position / vec2<f32>(map.map_size);
+            lod_color = textureSampleLevel(lod_texture, lod_sampler, lod_uv, 0.0);
+        }
+        if lod_weight >= 1.0 {
+            return lod_color * in.mix_color;
+        }
+    #endif
+
     var index = get_tile_index(pos.tile);
     var is_valid = is_valid_tile(pos.tile);
     var sample_color = color;
 
+    #ifdef DATA_RAMP
+    if is_valid {
+        return ramp_color(f32(index)) * in.mix_color;
+    }
+    return color;
+    #endif // DATA_RAMP
+
+    #ifdef TERRAIN_DITHER
+    if is_valid {
+        index = dither_tile_index(index, pos.tile, map_space_offset, world_position);
+    }
+    #endif
+
+    #ifdef EDGE_MODE
+    if !is_valid && map.edge_mode == EDGE_MODE_FILL {
+        // Below the overhangs of the tiles along the map edges
+        color = _sample_tile(map.edge_fill, pos, in.animation_state);
+    }
+    #endif
+
     if is_valid {
         sample_color = _sample_tile(index, pos, in.animation_state);
+        #ifdef EDGE_ANTIALIAS
+            sample_color = antialias_edges(
+                sample_color, index, pos, map_space_offset, pixel, in.animation_state
+            );
+        #endif
     }
     else {
         // for invalid tile, assume low index so (almost) everything overlaps in dominance rendering
@@ -550,6 +1630,9 @@ fn fragment(
 
     if is_valid {
         color = blend(color, sample_color);
+        #ifdef TILE_DEPTH
+            record_depth_tile(pos.tile, sample_color);
+        #endif
     }
 
     #ifdef DOMINANCE_OVERHANGS
@@ -560,6 +1643,81 @@ fn fragment(
         color = render_perspective_overhangs(color, pos, in.animation_state);
     #endif
 
+    #ifdef MAP_LAYERS
+    // Composite the upper layers, each with its own overhangs
+    for (var layer = 1u; layer < map.n_layers; layer++) {
+        current_layer = layer;
+        var layer_color = vec4<f32>(0.0);
+        var layer_index = 0u;
+        if is_valid {
+            layer_index = get_tile_index(pos.tile);
+            layer_color = _sample_tile(layer_index, pos, in.animation_state);
+            #ifdef TILE_DEPTH
+                record_depth_tile(pos.tile, layer_color);
+            #endif
+        }
+        #ifdef DOMINANCE_OVERHANGS
+            layer_color = render_dominance_overhangs(layer_color, layer_index, pos, in.animation_state);
+        #endif
+        #ifdef PERSPECTIVE_OVERHANGS
+            layer_color = render_perspective_overhangs(layer_color, pos, in.animation_state);
+        #endif
+        color = blend(color, layer_color);
+    }
+    current_layer = 0u;
+    #endif
+
+    #ifdef TILE_OWNERSHIP
+    if is_valid {
+        color = apply_ownership(color, pos.tile, map_space_offset);
+    }
+    #endif
+
+    #ifdef MAP_DECALS
+        #ifdef RELATIVE_ORIGIN
+            color = apply_decals(color, map_position);
+        #else
+            color = apply_decals(color, in.map_position);
+        #endif
+    #endif
+
+    #ifdef LAYER_SHADOWS
+        let shadow = shadow_amount(map_position) * map.shadow_color.a;
+        color = vec4<f32>(mix(color.rgb, map.shadow_color.rgb, shadow), color.a);
+    #endif
+
+    #ifdef FOG_OF_WAR
+        let fog_amount = (1.0 - fog_visibility(map_position)) * map.fog_color.a;
+        color = vec4<f32>(mix(color.rgb, map.fog_color.rgb, fog_amount), color.a);
+    #endif
+
+    #ifdef DEBUG_INDEX_LABELS
+    if is_valid {
+        let uv = (pos.offset + map.tile_anchor_point * map.tile_size) / map.tile_size;
+        color = draw_index_label(color, get_tile_index(pos.tile), uv);
+    }
+    #endif
+
+    #ifdef DEBUG_OVERDRAW_SAMPLES
+        return debug_heat(f32(debug_samples), 9.0);
+    #endif
+
+    #ifdef DEBUG_OVERDRAW_BLENDS
+        return debug_heat(f32(debug_overdraw), 4.0);
+    #endif
+
+    #ifdef DISTANCE_LOD
+        color = mix(color, lod_color, lod_weight);
+    #endif
+
+    #ifdef REVEAL_HOLE
+        color.a *= reveal_opacity(world_position);
+    #endif
+
+    #ifdef TILE_HEIGHTS
+        color = vec4<f32>(color.rgb * elevation.z, color.a);
+    #endif
+
     color = color * in.mix_color;
 
     return color;
@@ -185,13 +185,13 @@ fn init_map(m: &mut MapIndexerMut<MyCustomization>) {
 fn update_cursor_position(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mut camera_query: Query<(&GlobalTransform, &Camera), With<OrthographicProjection>>,
-    maps: Query<&Handle<Map<MyCustomization>>>,
+    maps: Query<(&Handle<Map<MyCustomization>>, &GlobalTransform)>,
 
     // We'll actually change the map (by changing the user data), so we need to get a mutable
     mut materials: ResMut<Assets<Map<MyCustomization>>>,
 ) {
     for event in cursor_moved_events.read() {
-        for map_handle in maps.iter() {
+        for (map_handle, map_transform) in maps.iter() {
             let map = materials.get_mut(map_handle).unwrap();
 
             for (global, camera) in camera_query.iter_mut() {
@@ -201,7 +201,7 @@ fn update_cursor_position(
                     .map(|ray| ray.origin.truncate())
                 {
                     // The map can convert between world coordinates and map coordinates for us
-                    let coord = map.world_to_map(world);
+                    let coord = map.world_to_map(map_transform, world);
 
                     let coord = coord
                         .as_uvec2()
@@ -79,10 +79,10 @@ fn show_coordinate(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mut camera_query: Query<(&GlobalTransform, &Camera), With<OrthographicProjection>>,
     mut materials: ResMut<Assets<Map>>,
-    maps: Query<&Handle<Map>>,
+    maps: Query<(&Handle<Map>, &GlobalTransform)>,
 ) {
     for event in cursor_moved_events.read() {
-        for map_handle in maps.iter() {
+        for (map_handle, map_transform) in maps.iter() {
             let map = materials.get_mut(map_handle).unwrap();
             for (global, camera) in camera_query.iter_mut() {
                 // Translate viewport coordinates to world coordinates
@@ -91,7 +91,7 @@ fn show_coordinate(
                     .map(|ray| ray.origin.truncate())
                 {
                     // The map can convert between world coordinates and map coordinates
-                    let coord = map.world_to_map(world);
+                    let coord = map.world_to_map(map_transform, world);
                     println!("Map coordinate: {:?}", coord);
                 } // if Some(world)
             } // for (global, camera)
@@ -85,11 +85,11 @@ fn startup(
 fn show_coordinate(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mut camera_query: Query<(&GlobalTransform, &Camera), With<OrthographicProjection>>,
-    maps: Query<&Handle<Map>>,
+    maps: Query<(&Handle<Map>, &GlobalTransform)>,
     mut materials: ResMut<Assets<Map>>,
 ) {
     for event in cursor_moved_events.read() {
-        for map_handle in maps.iter() {
+        for (map_handle, map_transform) in maps.iter() {
             let map = materials.get_mut(map_handle).unwrap();
             for (global, camera) in camera_query.iter_mut() {
                 // Translate viewport coordinates to world coordinates
@@ -98,11 +98,11 @@ fn show_coordinate(
                     .map(|ray| ray.origin.truncate())
                 {
                     // The map can convert between world coordinates and map coordinates
-                    let coord = map.world_to_map(world);
+                    let coord = map.world_to_map(map_transform, world);
 
                     // Convert back to world coordinate to obtain a logical z index ("depth") of
                     // the tile
-                    let world2 = map.map_to_world_3d(coord.extend(0.0));
+                    let world2 = map.map_to_world_3d(map_transform, coord.extend(0.0));
                     println!("Map coordinate: {:?} World-Z: {:?}", coord, world2.z);
                 } // if Some(world)
             } // for (global, camera)
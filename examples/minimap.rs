@@ -0,0 +1,124 @@
+//! Minimap rendered by a second camera into an image shown in a UI node.
+//! The map is on render layers 0 (main camera) and 1 (minimap camera), the marker showing the
+//! main camera's position only on layer 1.
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    math::{uvec2, vec2},
+    prelude::*,
+    render::view::RenderLayers,
+    window::PresentMode,
+};
+use bevy_fast_tilemap::prelude::*;
+
+#[path = "common/mouse_controls_camera.rs"]
+mod mouse_controls_camera;
+use mouse_controls_camera::MouseControlsCameraPlugin;
+
+const MINIMAP_LAYER: usize = 1;
+
+/// Shows the position of the main camera on the minimap
+#[derive(Component)]
+struct ViewMarker;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: String::from("Fast Tilemap example"),
+                    resolution: (1820., 920.).into(),
+                    // disable vsync so we can see the raw FPS speed
+                    present_mode: PresentMode::Immediate,
+                    ..default()
+                }),
+                ..default()
+            }),
+            LogDiagnosticsPlugin::default(),
+            FrameTimeDiagnosticsPlugin::default(),
+            MouseControlsCameraPlugin::default(),
+            FastTileMapPlugin::default(),
+        ))
+        .add_systems(Startup, startup)
+        .add_systems(Update, update_view_marker)
+        .run();
+}
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<Map>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let map = Map::builder(
+        // Map size
+        uvec2(200, 200),
+        // Tile atlas
+        asset_server.load("pixel_tiles_16.png"),
+        // Tile Size
+        vec2(16., 16.),
+    )
+    .build_and_initialize(|m| {
+        for y in 0..m.size().y {
+            for x in 0..m.size().x {
+                m.set(x, y, ((x / 10 + y / 7) % 4 + 1) as u32);
+            }
+        }
+    });
+
+    // Zoom the minimap camera out to show the whole map
+    let (_, minimap) = MinimapCamera::new(uvec2(300, 300))
+        .with_layers(RenderLayers::layer(MINIMAP_LAYER))
+        .with_clear_color(Color::BLACK)
+        .fit(&map)
+        .spawn(&mut commands, &mut images);
+
+    commands.spawn((
+        MapBundleManaged::new(map, materials.as_mut()),
+        RenderLayers::from_layers(&[0, MINIMAP_LAYER]),
+    ));
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgba(1.0, 1.0, 1.0, 0.3),
+                custom_size: Some(vec2(1820., 920.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 10.0),
+            ..default()
+        },
+        RenderLayers::layer(MINIMAP_LAYER),
+        ViewMarker,
+    ));
+
+    commands.spawn(ImageBundle {
+        image: UiImage::new(minimap),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(300.0),
+            height: Val::Px(300.0),
+            ..default()
+        },
+        ..default()
+    });
+}
+
+/// Move the view marker along with the main camera
+fn update_view_marker(
+    cameras: Query<(&Camera, &GlobalTransform, &OrthographicProjection)>,
+    mut markers: Query<&mut Transform, With<ViewMarker>>,
+) {
+    let Some((_, camera, projection)) = cameras.iter().find(|(camera, _, _)| camera.order == 0)
+    else {
+        return;
+    };
+    for mut transform in markers.iter_mut() {
+        transform.translation = camera.translation().truncate().extend(10.0);
+        transform.scale = (projection.area.size() / vec2(1820., 920.)).extend(1.0);
+    }
+}
@@ -87,11 +87,11 @@ fn startup(
 fn highlight_hovered(
     mut cursor_moved_events: EventReader<CursorMoved>,
     mut camera_query: Query<(&GlobalTransform, &Camera), With<OrthographicProjection>>,
-    maps: Query<&Handle<Map>>,
+    maps: Query<(&Handle<Map>, &GlobalTransform)>,
     materials: Res<Assets<Map>>,
 ) {
     for event in cursor_moved_events.read() {
-        for map_handle in maps.iter() {
+        for (map_handle, map_transform) in maps.iter() {
             let map = materials.get(map_handle).unwrap();
 
             for (global, camera) in camera_query.iter_mut() {
@@ -101,7 +101,7 @@ fn highlight_hovered(
                     .map(|ray| ray.origin.truncate())
                 {
                     // The map can convert between world coordinates and map coordinates for us
-                    let coord = map.world_to_map(world);
+                    let coord = map.world_to_map(map_transform, world);
                     println!("Map coordinate: {:?}", coord);
                 } // if Some(world)
             } // for (global, camera)
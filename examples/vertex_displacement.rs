@@ -0,0 +1,138 @@
+//! Custom vertex code displacing the map mesh: the map gently sways and shakes when space is
+//! pressed, without moving the camera or any other entity.
+//! Tiles are still looked up from the undisplaced map position, so the tile contents move along
+//! with the mesh.
+
+use bevy::{
+    diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
+    math::{uvec2, vec2},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderType},
+    window::PresentMode,
+};
+use bevy_fast_tilemap::prelude::*;
+
+#[path = "common/mouse_controls_camera.rs"]
+mod mouse_controls_camera;
+use mouse_controls_camera::MouseControlsCameraPlugin;
+
+#[derive(Debug, Clone, Default, Reflect, AsBindGroup, ShaderType)]
+struct UserData {
+    /// Strength of the shake in world units, decays over time
+    shake: f32,
+}
+
+#[derive(Clone, TypePath, Default)]
+struct ShakeCustomization;
+
+impl Customization for ShakeCustomization {
+    const SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(0x5a3c_81d2_9e47_4b06_a1f3_6c2d);
+    type UserData = UserData;
+
+    fn custom_shader_code() -> String {
+        r#"
+        struct UserData {
+            shake: f32,
+        };
+
+        fn sample_tile(in: ExtractIn) -> vec4<f32> {
+            return sample_tile_at(in.tile_index, in.tile_position, in.tile_offset);
+        }
+    "#
+        .to_string()
+    }
+
+    fn custom_vertex_code() -> String {
+        r#"
+        fn displace_vertex(in: DisplaceIn) -> vec3<f32> {
+            let t = in.animation_state;
+
+            // Slow sway, out of phase between top and bottom so the map skews a little
+            let sway = sin(t * 1.5 + in.map_position.y * 0.1) * 0.1 * map.tile_size.x;
+
+            // Shake, the same for all vertices so the map moves as a whole
+            let shake = vec2<f32>(sin(t * 61.0), cos(t * 47.0)) * user_data.shake;
+
+            return in.world_position + vec3<f32>(sway + shake.x, shake.y, 0.0);
+        }
+    "#
+        .to_string()
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: String::from("Fast Tilemap example"),
+                    resolution: (1820., 920.).into(),
+                    // disable vsync so we can see the raw FPS speed
+                    present_mode: PresentMode::Immediate,
+                    ..default()
+                }),
+                ..default()
+            }),
+            LogDiagnosticsPlugin::default(),
+            FrameTimeDiagnosticsPlugin::default(),
+            MouseControlsCameraPlugin::default(),
+            CustomFastTileMapPlugin::<ShakeCustomization>::default(),
+        ))
+        .add_systems(Startup, startup)
+        .add_systems(Update, shake_on_space)
+        .run();
+}
+
+fn startup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<Map<ShakeCustomization>>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+
+    let map = Map::<ShakeCustomization>::builder(
+        // Map size
+        uvec2(51, 51),
+        // Tile atlas
+        asset_server.load("pixel_tiles_16.png"),
+        // Tile Size
+        vec2(16., 16.),
+    )
+    .build_and_initialize(|m| {
+        for y in 0..m.size().y {
+            for x in 0..m.size().x {
+                m.set(x, y, ((x + y) % 4 + 1) as u32);
+            }
+        }
+    });
+
+    commands.spawn(MapBundleManaged::<ShakeCustomization>::new(
+        map,
+        materials.as_mut(),
+    ));
+}
+
+/// Start a shake when space is pressed and let it decay
+fn shake_on_space(
+    keys: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    maps: Query<&Handle<Map<ShakeCustomization>>>,
+    mut materials: ResMut<Assets<Map<ShakeCustomization>>>,
+) {
+    for map_handle in maps.iter() {
+        let Some(shake) = materials.get(map_handle).map(|map| map.user_data.shake) else {
+            continue;
+        };
+        let new_shake = if keys.just_pressed(KeyCode::Space) {
+            12.0
+        } else {
+            (shake - 30.0 * time.delta_seconds()).max(0.0)
+        };
+        // Only touch the map while shaking, as that re-uploads it
+        if new_shake != shake {
+            if let Some(map) = materials.get_mut(map_handle) {
+                map.user_data.shake = new_shake;
+            }
+        }
+    }
+}
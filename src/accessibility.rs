@@ -0,0 +1,82 @@
+use bevy::{prelude::*, render::render_resource::ShaderType, utils::HashMap};
+
+/// Pattern drawn over tiles in high-contrast mode, so categories can be told apart
+/// without relying on color alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum ContrastPattern {
+    #[default]
+    None,
+    /// Diagonal stripes
+    Stripes,
+    /// Regular grid of dots
+    Dots,
+    /// Checkerboard
+    Checker,
+}
+
+/// How tiles of one category are rendered in high-contrast mode.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct HighContrastStyle {
+    /// Replaces the color of the tile, the alpha (shape) of the tile is kept.
+    pub color: Color,
+    pub pattern: ContrastPattern,
+}
+
+/// Replacement colors and patterns for high-contrast (accessibility) rendering,
+/// assigned to categories of atlas indices.
+///
+/// Enable per map with [`crate::map::Map::set_high_contrast`], this way games can offer an
+/// accessibility mode without shipping a duplicate tileset.
+/// Tiles without an assigned style are rendered as usual.
+#[derive(Debug, Clone, Default, Reflect)]
+pub struct HighContrastPalette {
+    styles: HashMap<u32, HighContrastStyle>,
+}
+
+impl HighContrastPalette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render all the given atlas indices (one tile category) with the given style.
+    pub fn with_category(
+        mut self,
+        indices: impl IntoIterator<Item = u32>,
+        style: HighContrastStyle,
+    ) -> Self {
+        for index in indices {
+            self.styles.insert(index, style);
+        }
+        self
+    }
+
+    pub fn style(&self, index: u32) -> Option<&HighContrastStyle> {
+        self.styles.get(&index)
+    }
+
+    /// Lookup table indexed by atlas index as it is uploaded to the shader.
+    pub(crate) fn shader_data(&self) -> Vec<HighContrastEntry> {
+        let len = self
+            .styles
+            .keys()
+            .max()
+            .map(|i| *i as usize + 1)
+            .unwrap_or(1);
+        let mut v = vec![HighContrastEntry::default(); len];
+        for (index, style) in self.styles.iter() {
+            v[*index as usize] = HighContrastEntry {
+                color: style.color.to_linear().to_vec4(),
+                pattern: style.pattern as u32,
+            };
+        }
+        v
+    }
+}
+
+/// Entry of the high contrast lookup table in the shader, see [`HighContrastPalette`].
+/// An alpha of zero means "no replacement".
+#[derive(ShaderType, Debug, Clone, Copy, Default, Reflect)]
+pub struct HighContrastEntry {
+    pub color: Vec4,
+    pub pattern: u32,
+}
@@ -0,0 +1,172 @@
+use bevy::{prelude::*, time::TimeSystem};
+
+use super::settings::FastTileMapSettings;
+
+/// Advances [`MapAnimationTime`] and all [`MapAnimationClock`]s,
+/// added automatically by the map plugin.
+pub(crate) struct MapAnimationPlugin;
+
+impl Plugin for MapAnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapAnimationTime>()
+            .add_systems(First, advance_map_animation_time.after(TimeSystem));
+    }
+}
+
+/// Global clock driving shader animations of all maps
+/// (the `animation_state` available to custom shader code).
+///
+/// Set `scale` for slow motion / bullet-time or `paused` to freeze all map animations,
+/// independently of bevy's `Time`. Maps with a [`MapAnimationClock`] additionally apply their
+/// own scale.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct MapAnimationTime {
+    pub scale: f32,
+    pub paused: bool,
+    elapsed: f32,
+}
+
+impl Default for MapAnimationTime {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl MapAnimationTime {
+    /// Scaled animation time in seconds, wrapped like `Time::elapsed_seconds_wrapped`.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+}
+
+/// Per-map animation clock, add this to a map entity to scale or pause its animations
+/// independently of other maps. Runs on top of the global [`MapAnimationTime`].
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MapAnimationClock {
+    pub scale: f32,
+    pub paused: bool,
+    elapsed: f32,
+}
+
+impl Default for MapAnimationClock {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            paused: false,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl MapAnimationClock {
+    pub fn new(scale: f32) -> Self {
+        Self { scale, ..default() }
+    }
+
+    /// Scaled animation time of this map in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+}
+
+/// Animation time for a map with the given (optional) clock.
+pub(crate) fn map_animation_time(
+    clock: Option<&MapAnimationClock>,
+    global: &MapAnimationTime,
+) -> f32 {
+    clock.map(|c| c.elapsed).unwrap_or(global.elapsed)
+}
+
+pub fn advance_map_animation_time(
+    time: Res<Time>,
+    settings: Res<FastTileMapSettings>,
+    mut global: ResMut<MapAnimationTime>,
+    mut clocks: Query<&mut MapAnimationClock>,
+) {
+    let wrap = time.wrap_period().as_secs_f32();
+    let delta = if global.paused || !settings.animations {
+        0.0
+    } else {
+        time.delta_seconds() * global.scale
+    };
+    global.elapsed = (global.elapsed + delta) % wrap;
+
+    for mut clock in clocks.iter_mut() {
+        if !clock.paused {
+            clock.elapsed = (clock.elapsed + delta * clock.scale) % wrap;
+        }
+    }
+}
+
+/// Frame based animation of a single atlas index, see [`TileAnimations`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TileAnimation {
+    /// Atlas indices of the frames, in order.
+    pub frames: Vec<u32>,
+    /// Duration of each frame in seconds (of map animation time).
+    pub frame_duration: f32,
+}
+
+impl TileAnimation {
+    pub fn new(frames: impl IntoIterator<Item = u32>, frame_duration: f32) -> Self {
+        Self {
+            frames: frames.into_iter().collect(),
+            frame_duration,
+        }
+    }
+}
+
+/// Animations of atlas indices that are played entirely in the shader, driven by the map
+/// animation time, see [`Map::set_tile_animations`](crate::map::Map::set_tile_animations).
+///
+/// Tiles with an animated atlas index (eg. water, fire, torches) cycle through the frames of
+/// the animation without any per-frame CPU work or uploads.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct TileAnimations {
+    animations: Vec<(u32, TileAnimation)>,
+}
+
+impl TileAnimations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Animate tiles with atlas index `index` (replacing any previous animation of it).
+    pub fn with(mut self, index: u32, animation: TileAnimation) -> Self {
+        self.set(index, animation);
+        self
+    }
+
+    pub fn set(&mut self, index: u32, animation: TileAnimation) {
+        self.animations.retain(|(i, _)| *i != index);
+        self.animations.push((index, animation));
+    }
+
+    /// Data for the shader: the number of headers `n`, then one header per atlas index below `n`
+    /// (offset of its animation or 0 if not animated), then per animation the frame count,
+    /// the frame duration (`f32` bits) and the frames.
+    pub(crate) fn shader_data(&self) -> Vec<u32> {
+        let animations: Vec<_> = self
+            .animations
+            .iter()
+            .filter(|(_, a)| !a.frames.is_empty() && a.frame_duration > 0.0)
+            .collect();
+        let n_headers = animations.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+
+        let mut data = vec![0; 1 + n_headers as usize];
+        data[0] = n_headers;
+        for (index, animation) in animations {
+            data[1 + *index as usize] = data.len() as u32;
+            data.push(animation.frames.len() as u32);
+            data.push(animation.frame_duration.to_bits());
+            data.extend(animation.frames.iter());
+        }
+        data
+    }
+}
@@ -0,0 +1,99 @@
+//! Atlas reinterpreted as 2d texture array with one layer per atlas cell,
+//! see [`crate::map_builder::MapBuilder::with_texture_array`].
+//!
+//! Sampling a packed atlas with filtering picks up pixels of neighbouring tiles at tile edges
+//! (bleeding), which is usually fought with padding. Each array layer is sampled on its own and
+//! clamped to its edges, so linear filtering can be used when minifying as well.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+        texture::ImageSampler,
+    },
+};
+
+use super::{map::Map, map_uniform::MapUniform, plugin::Customization};
+
+/// Copy the cells of the packed atlas pages (see [`crate::pages`]) into a texture array,
+/// one layer per atlas index, page after page.
+/// `None` for compressed formats or atlases without CPU side data.
+pub(crate) fn atlas_array_image(pages: &[&Image], uniform: &MapUniform) -> Option<Image> {
+    let atlas = pages.first()?;
+    let format = atlas.texture_descriptor.format;
+    if format.block_dimensions() != (1, 1) {
+        return None;
+    }
+    let pixel_size = format.block_copy_size(None)? as usize;
+
+    let cell_size = uniform.tile_size * uniform.atlas_tile_size_factor.max(1) as f32;
+    let cell = cell_size.round().as_uvec2();
+    let n_tiles = uniform.n_tiles;
+    let n_page_layers = n_tiles.x * n_tiles.y;
+    let n_layers = n_page_layers * pages.len() as u32;
+    if n_layers == 0 || cell.x == 0 || cell.y == 0 {
+        return None;
+    }
+
+    let atlas_width = atlas.width() as usize;
+    let row_bytes = cell.x as usize * pixel_size;
+    let mut data = Vec::with_capacity(row_bytes * (cell.y * n_layers) as usize);
+    for page in pages {
+        for index in 0..n_page_layers {
+            let index2d = UVec2::new(index % n_tiles.x, index / n_tiles.x).as_vec2();
+            let start = (index2d * (cell_size + uniform.inner_padding)
+                + uniform.outer_padding_topleft)
+                .round()
+                .as_uvec2();
+            for y in start.y..start.y + cell.y {
+                let offset = (y as usize * atlas_width + start.x as usize) * pixel_size;
+                data.extend_from_slice(page.data.get(offset..offset + row_bytes)?);
+            }
+        }
+    }
+
+    let mut image = Image::new(
+        Extent3d {
+            width: cell.x,
+            height: cell.y * n_layers,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.reinterpret_stacked_2d_as_array(n_layers);
+    Some(image)
+}
+
+impl<C: Customization> Map<C> {
+    /// Create the texture array from the loaded atlas, if requested and not done yet.
+    pub(crate) fn update_atlas_array(&mut self, images: &mut Assets<Image>, sampler: ImageSampler) {
+        if !self.texture_array || self.atlas_array.is_some() {
+            return;
+        }
+        let handles = match self.atlas_pages.is_empty() {
+            true => std::slice::from_ref(&self.atlas_texture),
+            false => self.atlas_pages.as_slice(),
+        };
+        let Some(pages) = handles
+            .iter()
+            .map(|page| images.get(page))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        match atlas_array_image(&pages, &self.map_uniform) {
+            Some(mut image) => {
+                image.sampler = sampler;
+                self.atlas_array = Some(images.add(image));
+            }
+            None => warn!(
+                "Atlas can not be split into a texture array (compressed format or no CPU side \
+                data), sampling the packed atlas instead"
+            ),
+        }
+    }
+}
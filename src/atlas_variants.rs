@@ -0,0 +1,129 @@
+//! Switching a map between atlases with the same layout at runtime, eg. summer / winter or
+//! day / night tilesets, optionally cross-fading between them.
+//!
+//! ```ignore
+//! let map = MapBuilder::new(size, summer.clone(), tile_size)
+//!     .with_atlas_variant("summer", summer)
+//!     .with_atlas_variant("winter", winter)
+//!     .build();
+//! // Later, fade to winter over two seconds
+//! map.switch_atlas("winter", 2.0);
+//! ```
+//!
+//! Tile data and pipelines are kept, only the atlas binding changes. Keep the handles of all
+//! variants alive so they stay loaded, the map is not drawn while its atlas is loading.
+
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization, settings::FastTileMapSettings};
+
+impl<C: Customization> Map<C> {
+    /// Register an atlas that can be switched to with [`Self::switch_atlas`].
+    /// It must have the same size and layout as the current atlas.
+    pub fn add_atlas_variant(&mut self, name: impl Into<String>, atlas: Handle<Image>) {
+        let name = name.into();
+        match self.atlas_variants.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = atlas,
+            None => self.atlas_variants.push((name, atlas)),
+        }
+    }
+
+    /// Name of the registered atlas variant currently in use,
+    /// `None` if none has been switched to yet.
+    pub fn atlas_variant(&self) -> Option<&str> {
+        self.atlas_variant.as_deref()
+    }
+
+    /// Switch to the registered atlas variant `name`, cross-fading from the current atlas over
+    /// `fade_duration` seconds (`0.0` switches immediately).
+    /// Returns `false` if no variant of that name is registered.
+    pub fn switch_atlas(&mut self, name: &str, fade_duration: f32) -> bool {
+        let Some(atlas) = self
+            .atlas_variants
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, atlas)| atlas.clone())
+        else {
+            return false;
+        };
+        self.atlas_variant = Some(name.to_string());
+        if atlas == self.atlas_texture {
+            return true;
+        }
+
+        let previous = std::mem::replace(&mut self.atlas_texture, atlas);
+        // The flat LOD colors are taken from the atlas
+        self.lod_baked = false;
+        if fade_duration > 0.0 {
+            self.fade_atlas = Some(previous);
+            self.atlas_fade_duration = fade_duration;
+            self.map_uniform.atlas_fade = 0.0;
+        } else {
+            self.fade_atlas = None;
+            self.map_uniform.atlas_fade = 1.0;
+        }
+        self.atlas_switched = true;
+        true
+    }
+
+    /// Progress of the running cross-fade from `0.0` to `1.0`, `None` if not fading.
+    pub fn atlas_fade(&self) -> Option<f32> {
+        self.fade_atlas
+            .as_ref()
+            .map(|_| self.map_uniform.atlas_fade)
+    }
+
+    fn advance_atlas_fade(&mut self, delta: f32) {
+        self.map_uniform.atlas_fade += delta / self.atlas_fade_duration;
+        if self.map_uniform.atlas_fade >= 1.0 {
+            self.map_uniform.atlas_fade = 1.0;
+            self.fade_atlas = None;
+        }
+    }
+}
+
+/// Advance cross-fades and prepare newly switched atlases.
+pub fn update_atlas_variants<C: Customization>(
+    time: Res<Time>,
+    settings: Res<FastTileMapSettings>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // Only touch maps with pending work so others are not re-prepared
+    let pending: Vec<_> = map_materials
+        .iter()
+        .filter(|(_, map)| {
+            map.fade_atlas.is_some() || (map.atlas_switched && images.contains(&map.atlas_texture))
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in pending {
+        let Some(map) = map_materials.get_mut(id) else {
+            continue;
+        };
+
+        if map.atlas_switched {
+            if let Some(atlas) = images.get_mut(&map.atlas_texture) {
+                atlas.sampler = settings.filtering.atlas_sampler();
+                if atlas.size().as_vec2() != map.map_uniform.atlas_size {
+                    warn!(
+                        "Atlas variant size {:?} differs from the map atlas size {:?}",
+                        atlas.size(),
+                        map.map_uniform.atlas_size
+                    );
+                }
+                map.atlas_switched = false;
+                // Replaced in the same frame, so the pipeline is kept
+                if map.texture_array {
+                    map.atlas_array = None;
+                    map.update_atlas_array(&mut images, settings.filtering.atlas_array_sampler());
+                }
+            }
+        }
+
+        if map.fade_atlas.is_some() {
+            map.advance_atlas_fade(time.delta_seconds());
+        }
+    }
+}
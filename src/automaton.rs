@@ -0,0 +1,354 @@
+//! Cellular automata (eg. water, fire or sand simulations) updating the tiles of a map entirely on
+//! the GPU, so large simulations do not round-trip through the CPU.
+//!
+//! The rule is WGSL code defining `fn next_tile(pos: vec2<i32>) -> u32`, which returns the next
+//! value of the tile at `pos`. It can read the current tiles with `tile_at(pos, outside)` and use
+//! `params.map_size` and `params.frame`:
+//!
+//! ```ignore
+//! // Sand falls down (towards higher y) into empty tiles
+//! const RULE: &str = r#"
+//! fn next_tile(pos: vec2<i32>) -> u32 {
+//!     let tile = tile_at(pos, 0u);
+//!     if tile == 0u && tile_at(pos - vec2<i32>(0, 1), 0u) == SAND { return SAND; }
+//!     if tile == SAND && tile_at(pos + vec2<i32>(0, 1), SAND) == 0u { return 0u; }
+//!     return tile;
+//! }
+//! const SAND: u32 = 3u;
+//! "#;
+//! let map = MapBuilder::new(size, atlas, tile_size)
+//!     .with_automaton(MapAutomaton::new(RULE))
+//!     .build();
+//! ```
+//!
+//! Each step computes all tiles from the previous state into a second buffer, which is then
+//! copied over the map data (ping-pong). Only the base layer is updated. The CPU-side map is not
+//! changed, use [`Map::read_back`] to get the simulated tiles. Modifying the map on the CPU side
+//! re-uploads it, discarding the GPU-side state.
+
+use std::sync::Arc;
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            binding_types::{
+                storage_buffer_read_only_sized, storage_buffer_sized, uniform_buffer_sized,
+            },
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer, BufferDescriptor,
+            BufferInitDescriptor, BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor,
+            ComputePipeline, OwnedBindingResource, PipelineLayoutDescriptor,
+            RawComputePipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    sprite::PreparedMaterial2d,
+    utils::HashMap,
+};
+
+use super::{
+    map::Map,
+    plugin::{Customization, NoCustomization},
+    readback::MAP_DATA_BINDING,
+};
+
+const WORKGROUP_SIZE: u32 = 8;
+
+const AUTOMATON_SHADER: &str = r#"
+struct AutomatonParams {
+    map_size: vec2<u32>,
+    /// Number of steps run so far
+    frame: u32,
+}
+
+@group(0) @binding(0) var<storage, read> tiles: array<u32>;
+@group(0) @binding(1) var<storage, read_write> next_tiles: array<u32>;
+@group(0) @binding(2) var<uniform> params: AutomatonParams;
+
+/// Current value of the tile at `pos`, `outside` outside of the map.
+fn tile_at(pos: vec2<i32>, outside: u32) -> u32 {
+    let size = vec2<i32>(params.map_size);
+    if pos.x < 0 || pos.y < 0 || pos.x >= size.x || pos.y >= size.y {
+        return outside;
+    }
+    return tiles[u32(pos.y) * params.map_size.x + u32(pos.x)];
+}
+
+#[automaton_code]
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.map_size.x || id.y >= params.map_size.y {
+        return;
+    }
+    next_tiles[id.y * params.map_size.x + id.x] = next_tile(vec2<i32>(id.xy));
+}
+"#;
+
+/// Rule of a cellular automaton run on a map, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapAutomaton {
+    code: Arc<str>,
+    /// Steps run per frame, `0` pauses the automaton.
+    pub steps_per_frame: u32,
+}
+
+impl MapAutomaton {
+    /// `code` must define `fn next_tile(pos: vec2<i32>) -> u32`.
+    pub fn new(code: impl Into<Arc<str>>) -> Self {
+        Self {
+            code: code.into(),
+            steps_per_frame: 1,
+        }
+    }
+
+    pub fn with_steps_per_frame(self, steps_per_frame: u32) -> Self {
+        Self {
+            steps_per_frame,
+            ..self
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Run a cellular automaton over the tiles of this map on the GPU every frame,
+    /// `None` to stop. Requires [`MapAutomatonPlugin`] (or [`CustomMapAutomatonPlugin`]).
+    pub fn set_automaton(&mut self, automaton: Option<MapAutomaton>) {
+        self.automaton = automaton;
+    }
+
+    pub fn automaton(&self) -> Option<&MapAutomaton> {
+        self.automaton.as_ref()
+    }
+}
+
+/// Plugin for [`Map::set_automaton`].
+pub type MapAutomatonPlugin = CustomMapAutomatonPlugin<NoCustomization>;
+
+/// Plugin for [`Map::set_automaton`].
+#[derive(Default)]
+pub struct CustomMapAutomatonPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomMapAutomatonPlugin<C> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<ExtractedAutomata<C>>()
+            .add_systems(ExtractSchedule, extract_map_automata::<C>)
+            // Submitted before the render graph, so the map is drawn with the new tiles
+            .add_systems(Render, run_map_automata::<C>.in_set(RenderSet::Prepare));
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<MapAutomatonPipelines>();
+        }
+    }
+}
+
+struct ExtractedAutomaton {
+    automaton: MapAutomaton,
+    map_size: UVec2,
+    /// Steps run so far
+    frame: u32,
+    /// Target of the steps, copied back over the map data
+    next_tiles: Option<Buffer>,
+}
+
+#[derive(Resource)]
+struct ExtractedAutomata<C: Customization>(HashMap<AssetId<Map<C>>, ExtractedAutomaton>);
+
+impl<C: Customization> Default for ExtractedAutomata<C> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+/// Compute pipelines per rule code, compiled on first use.
+#[derive(Resource)]
+struct MapAutomatonPipelines {
+    layout: BindGroupLayout,
+    pipelines: HashMap<Arc<str>, ComputePipeline>,
+}
+
+impl FromWorld for MapAutomatonPipelines {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "map_automaton_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                    uniform_buffer_sized(false, None),
+                ),
+            ),
+        );
+        Self {
+            layout,
+            pipelines: HashMap::default(),
+        }
+    }
+}
+
+impl MapAutomatonPipelines {
+    fn pipeline(&mut self, device: &RenderDevice, code: &Arc<str>) -> &ComputePipeline {
+        let layout = &self.layout;
+        self.pipelines.entry(code.clone()).or_insert_with(|| {
+            let source = AUTOMATON_SHADER.replace("#[automaton_code]", code);
+            let module = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some("map_automaton_shader"),
+                source: ShaderSource::Wgsl(source.into()),
+            });
+            let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("map_automaton_pipeline_layout"),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&RawComputePipelineDescriptor {
+                label: Some("map_automaton_pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: "main",
+                compilation_options: default(),
+                cache: None,
+            })
+        })
+    }
+}
+
+fn extract_map_automata<C: Customization>(
+    maps: Extract<Res<Assets<Map<C>>>>,
+    mut extracted: ResMut<ExtractedAutomata<C>>,
+) {
+    extracted
+        .0
+        .retain(|id, _| maps.get(*id).is_some_and(|map| map.automaton.is_some()));
+    for (id, map) in maps.iter() {
+        let Some(automaton) = &map.automaton else {
+            continue;
+        };
+        let map_size = map.map_size();
+        match extracted.0.get_mut(&id) {
+            Some(e) if e.automaton == *automaton && e.map_size == map_size => {}
+            Some(e) => {
+                e.automaton = automaton.clone();
+                e.map_size = map_size;
+                e.next_tiles = None;
+            }
+            None => {
+                extracted.0.insert(
+                    id,
+                    ExtractedAutomaton {
+                        automaton: automaton.clone(),
+                        map_size,
+                        frame: 0,
+                        next_tiles: None,
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn run_map_automata<C: Customization>(
+    mut extracted: ResMut<ExtractedAutomata<C>>,
+    materials: Res<RenderAssets<PreparedMaterial2d<Map<C>>>>,
+    pipelines: Option<ResMut<MapAutomatonPipelines>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    let Some(mut pipelines) = pipelines else {
+        return;
+    };
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("map_automaton_encoder"),
+    });
+    let mut any = false;
+
+    for (id, e) in extracted.0.iter_mut() {
+        let size = e.map_size;
+        if e.automaton.steps_per_frame == 0 || size.x == 0 || size.y == 0 {
+            continue;
+        }
+        // Maps that have not been prepared yet start later
+        let Some(material) = materials.get(*id) else {
+            continue;
+        };
+        let Some(OwnedBindingResource::Buffer(tiles)) = material
+            .bindings
+            .iter()
+            .find(|(binding, _)| *binding == MAP_DATA_BINDING)
+            .map(|(_, resource)| resource)
+        else {
+            continue;
+        };
+
+        let n_bytes = size.x as u64 * size.y as u64 * 4;
+        if tiles.size() < n_bytes {
+            continue;
+        }
+        let next_tiles = e.next_tiles.get_or_insert_with(|| {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("map_automaton_next_tiles"),
+                size: n_bytes,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+        let pipeline = pipelines.pipeline(&device, &e.automaton.code).clone();
+
+        for _ in 0..e.automaton.steps_per_frame {
+            let params: Vec<u8> = [size.x, size.y, e.frame, 0]
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect();
+            let params = device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("map_automaton_params"),
+                contents: &params,
+                usage: BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(
+                "map_automaton_bind_group",
+                &pipelines.layout,
+                &BindGroupEntries::sequential((
+                    tiles.as_entire_binding(),
+                    next_tiles.as_entire_binding(),
+                    params.as_entire_binding(),
+                )),
+            );
+            {
+                let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("map_automaton_pass"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(
+                    size.x.div_ceil(WORKGROUP_SIZE),
+                    size.y.div_ceil(WORKGROUP_SIZE),
+                    1,
+                );
+            }
+            encoder.copy_buffer_to_buffer(next_tiles, 0, tiles, 0, n_bytes);
+            e.frame = e.frame.wrapping_add(1);
+        }
+        any = true;
+    }
+
+    if any {
+        queue.submit([encoder.finish()]);
+    }
+}
@@ -0,0 +1,493 @@
+//! Automatic selection of atlas indices depending on neighboring cells.
+
+use bevy::{
+    math::URect,
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+    utils::HashMap,
+};
+
+use super::{map::Map, plugin::Customization, transaction::MapChange};
+
+/// Bits of an edge mask, one per 4-connected neighbor.
+pub const EDGE_NEG_Y: u8 = 1;
+pub const EDGE_POS_X: u8 = 2;
+pub const EDGE_POS_Y: u8 = 4;
+pub const EDGE_NEG_X: u8 = 8;
+
+const EDGES: [(IVec2, u8); 4] = [
+    (IVec2::NEG_Y, EDGE_NEG_Y),
+    (IVec2::X, EDGE_POS_X),
+    (IVec2::Y, EDGE_POS_Y),
+    (IVec2::NEG_X, EDGE_NEG_X),
+];
+
+/// Bits of a blob mask for the diagonal neighbors, in addition to the `EDGE_*` bits.
+pub const CORNER_POS_X_NEG_Y: u8 = 16;
+pub const CORNER_POS_X_POS_Y: u8 = 32;
+pub const CORNER_NEG_X_POS_Y: u8 = 64;
+pub const CORNER_NEG_X_NEG_Y: u8 = 128;
+
+const CORNERS: [(IVec2, u8, u8); 4] = [
+    (
+        IVec2::new(1, -1),
+        CORNER_POS_X_NEG_Y,
+        EDGE_POS_X | EDGE_NEG_Y,
+    ),
+    (
+        IVec2::new(1, 1),
+        CORNER_POS_X_POS_Y,
+        EDGE_POS_X | EDGE_POS_Y,
+    ),
+    (
+        IVec2::new(-1, 1),
+        CORNER_NEG_X_POS_Y,
+        EDGE_NEG_X | EDGE_POS_Y,
+    ),
+    (
+        IVec2::new(-1, -1),
+        CORNER_NEG_X_NEG_Y,
+        EDGE_NEG_X | EDGE_NEG_Y,
+    ),
+];
+
+/// Clear the corner bits of a blob mask whose adjacent edges are not both set,
+/// as these corners do not change the look of the tile.
+pub const fn reduce_blob_mask(mask: u8) -> u8 {
+    let mut reduced = mask & 0xf;
+    let mut i = 0;
+    while i < CORNERS.len() {
+        let (_, corner, edges) = CORNERS[i];
+        if mask & corner != 0 && mask & edges == edges {
+            reduced |= corner;
+        }
+        i += 1;
+    }
+    reduced
+}
+
+const fn blob_masks() -> [u8; 47] {
+    let mut masks = [0; 47];
+    let mut n = 0;
+    let mut mask = 0;
+    while mask < 256 {
+        if reduce_blob_mask(mask as u8) == mask as u8 {
+            masks[n] = mask as u8;
+            n += 1;
+        }
+        mask += 1;
+    }
+    masks
+}
+
+/// The 47 distinct reduced blob masks (see [`reduce_blob_mask`]) in ascending order,
+/// which is the order of the tiles in [`TerrainTiles::Blob47`].
+pub const BLOB_MASKS: [u8; 47] = blob_masks();
+
+/// Position of a blob mask in [`BLOB_MASKS`].
+pub fn blob_index(mask: u8) -> usize {
+    BLOB_MASKS
+        .binary_search(&reduce_blob_mask(mask))
+        .unwrap_or(0)
+}
+
+/// Rules for selecting cliff tiles from elevation data, see [`Map::apply_cliffs`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct CliffRules {
+    /// Tile for cells that are not higher than any of their neighbors.
+    pub flat: u32,
+    /// Tile per edge mask (`EDGE_*` bits) of the neighbors the cell drops towards.
+    /// Index 0 is never used, `flat` is used instead.
+    pub cliffs: [u32; 16],
+    /// Minimal elevation difference to a neighbor that counts as a cliff.
+    pub min_drop: i32,
+}
+
+impl CliffRules {
+    /// Use the same cliff tile for all drop directions, eg. for top-down maps where cliffs are
+    /// only drawn as a generic edge.
+    pub fn uniform(flat: u32, cliff: u32) -> Self {
+        let mut cliffs = [cliff; 16];
+        cliffs[0] = flat;
+        Self {
+            flat,
+            cliffs,
+            min_drop: 1,
+        }
+    }
+
+    pub fn with_min_drop(self, min_drop: i32) -> Self {
+        Self { min_drop, ..self }
+    }
+
+    /// Tile for a cell with the given drop mask.
+    pub fn tile(&self, mask: u8) -> u32 {
+        match mask & 0xf {
+            0 => self.flat,
+            m => self.cliffs[m as usize],
+        }
+    }
+}
+
+/// Edge mask of the neighbors of `pos` that are lower than `pos` by at least `min_drop`.
+/// Neighbors outside of a map of the given size never count as lower.
+pub fn drop_mask(size: UVec2, pos: UVec2, min_drop: i32, elevation: &impl Fn(UVec2) -> i32) -> u8 {
+    let height = elevation(pos);
+    EDGES
+        .iter()
+        .filter(|(d, _)| {
+            let n = pos.as_ivec2() + *d;
+            n.x >= 0
+                && n.y >= 0
+                && n.x < size.x as i32
+                && n.y < size.y as i32
+                && height - elevation(n.as_uvec2()) >= min_drop
+        })
+        .fold(0, |mask, (_, bit)| mask | bit)
+}
+
+/// Blob mask (`EDGE_*` and `CORNER_*` bits) of the neighbors of `pos` that have the same terrain
+/// as `pos`. Neighbors outside of a map of the given size always count as the same terrain,
+/// so terrain does not get borders along the map edges.
+pub fn terrain_mask(size: UVec2, pos: UVec2, terrain: &impl Fn(UVec2) -> u32) -> u8 {
+    let own = terrain(pos);
+    let same = |d: IVec2| {
+        let n = pos.as_ivec2() + d;
+        n.x < 0
+            || n.y < 0
+            || n.x >= size.x as i32
+            || n.y >= size.y as i32
+            || terrain(n.as_uvec2()) == own
+    };
+    let edges = EDGES
+        .iter()
+        .filter(|(d, _)| same(*d))
+        .fold(0, |mask, (_, bit)| mask | bit);
+    CORNERS
+        .iter()
+        .filter(|(d, _, _)| same(*d))
+        .fold(edges, |mask, (_, bit, _)| mask | bit)
+}
+
+/// Tiles of one terrain, selected by which neighbors have the same terrain.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum TerrainTiles {
+    /// 16 tiles indexed by edge mask (`EDGE_*` bits), diagonal neighbors are ignored.
+    Wang16([u32; 16]),
+    /// 47 tiles in the order of [`BLOB_MASKS`], also taking diagonal neighbors into account.
+    Blob47([u32; 47]),
+}
+
+impl TerrainTiles {
+    /// 16 consecutive atlas indices starting at `first`, see [`Self::Wang16`].
+    pub fn wang16(first: u32) -> Self {
+        Self::Wang16(std::array::from_fn(|i| first + i as u32))
+    }
+
+    /// 47 consecutive atlas indices starting at `first`, see [`Self::Blob47`].
+    pub fn blob47(first: u32) -> Self {
+        Self::Blob47(std::array::from_fn(|i| first + i as u32))
+    }
+
+    /// Tile for a cell with the given blob mask.
+    pub fn tile(&self, mask: u8) -> u32 {
+        match self {
+            Self::Wang16(tiles) => tiles[(mask & 0xf) as usize],
+            Self::Blob47(tiles) => tiles[blob_index(mask)],
+        }
+    }
+}
+
+/// Tiles per logical terrain ID, see [`Map::apply_autotiles`].
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct AutotileRules {
+    terrains: HashMap<u32, TerrainTiles>,
+}
+
+impl AutotileRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, terrain: u32, tiles: TerrainTiles) -> Self {
+        self.set(terrain, tiles);
+        self
+    }
+
+    pub fn set(&mut self, terrain: u32, tiles: TerrainTiles) {
+        self.terrains.insert(terrain, tiles);
+    }
+
+    /// Tile for a cell of `terrain` with the given blob mask,
+    /// `None` if there are no tiles for `terrain`.
+    pub fn tile(&self, terrain: u32, mask: u8) -> Option<u32> {
+        self.terrains.get(&terrain).map(|tiles| tiles.tile(mask))
+    }
+}
+
+/// Logical terrain ID per cell (row by row) from which tiles are selected,
+/// see [`Map::autotile_mut`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainLayer {
+    size: UVec2,
+    cells: Vec<u32>,
+}
+
+impl TerrainLayer {
+    /// Layer of the given size with all cells set to `terrain`.
+    pub fn new(size: UVec2, terrain: u32) -> Self {
+        Self {
+            size,
+            cells: vec![terrain; (size.x * size.y) as usize],
+        }
+    }
+
+    pub fn from_fn(size: UVec2, terrain: impl Fn(UVec2) -> u32) -> Self {
+        Self {
+            size,
+            cells: (0..size.y)
+                .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+                .map(terrain)
+                .collect(),
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Terrain of the given cell, `0` outside of the layer.
+    pub fn at(&self, pos: UVec2) -> u32 {
+        if pos.x >= self.size.x || pos.y >= self.size.y {
+            return 0;
+        }
+        self.cells[(pos.y * self.size.x + pos.x) as usize]
+    }
+
+    /// Set the terrain of the given cell, ignored outside of the layer.
+    /// Does not update any map, see [`AutotileIndexerMut`] for that.
+    pub fn set(&mut self, pos: UVec2, terrain: u32) {
+        if pos.x < self.size.x && pos.y < self.size.y {
+            self.cells[(pos.y * self.size.x + pos.x) as usize] = terrain;
+        }
+    }
+}
+
+/// Merge rectangles that overlap or touch into their bounding rectangles,
+/// so painted regions (eg. the stamps of a brush stroke) are processed once each.
+pub fn coalesce_regions(rects: impl IntoIterator<Item = URect>) -> Vec<URect> {
+    let touch = |a: &URect, b: &URect| {
+        a.min.x <= b.max.x && b.min.x <= a.max.x && a.min.y <= b.max.y && b.min.y <= a.max.y
+    };
+
+    let mut regions: Vec<URect> = Vec::new();
+    for rect in rects.into_iter().filter(|r| !r.is_empty()) {
+        let mut merged = rect;
+        // Merging may make the rectangle touch regions it did not touch before
+        while let Some(i) = regions.iter().position(|r| touch(r, &merged)) {
+            merged = merged.union(regions.swap_remove(i));
+        }
+        regions.push(merged);
+    }
+    regions
+}
+
+/// Rows per task when computing masks in parallel, smaller regions are not worth splitting.
+const MIN_BAND_ROWS: u32 = 16;
+
+/// `tile` for all cells of `rect`, row by row, computed in parallel over bands of rows.
+fn resolve_tiles<T: Send>(rect: URect, tile: &(impl Fn(UVec2) -> T + Sync)) -> Vec<T> {
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let band = (rect.height() / pool.thread_num().max(1) as u32).max(MIN_BAND_ROWS);
+
+    pool.scope(|s| {
+        for y0 in (rect.min.y..rect.max.y).step_by(band as usize) {
+            let y1 = (y0 + band).min(rect.max.y);
+            s.spawn(async move {
+                (y0..y1)
+                    .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| UVec2::new(x, y)))
+                    .map(tile)
+                    .collect::<Vec<_>>()
+            });
+        }
+    })
+    .concat()
+}
+
+impl<C: Customization> Map<C> {
+    /// Set every tile of the map to the flat or cliff tile given by `rules`
+    /// depending on which neighbors are lower according to `elevation`.
+    pub fn apply_cliffs(
+        &mut self,
+        elevation: impl Fn(UVec2) -> i32 + Sync,
+        rules: &CliffRules,
+    ) -> MapChange {
+        let rect = URect::from_corners(UVec2::ZERO, self.map_size());
+        self.apply_cliffs_in(rect, elevation, rules)
+    }
+
+    /// Like [`Self::apply_cliffs`] but only for the cells whose masks may have changed when the
+    /// elevation in `rect` (`max` exclusive) changed, ie. `rect` grown by one cell.
+    pub fn apply_cliffs_in(
+        &mut self,
+        rect: URect,
+        elevation: impl Fn(UVec2) -> i32 + Sync,
+        rules: &CliffRules,
+    ) -> MapChange {
+        self.apply_cliffs_in_regions([rect], elevation, rules)
+    }
+
+    /// Like [`Self::apply_cliffs_in`] for many changed regions at once (eg. a brush stroke).
+    /// Overlapping regions are coalesced, so each cell is evaluated at most once.
+    /// Masks are computed in parallel on the [`ComputeTaskPool`].
+    /// All tiles are applied in a single [`MapTransaction`](super::transaction::MapTransaction),
+    /// so the returned change undoes the whole stroke at once.
+    pub fn apply_cliffs_in_regions(
+        &mut self,
+        rects: impl IntoIterator<Item = URect>,
+        elevation: impl Fn(UVec2) -> i32 + Sync,
+        rules: &CliffRules,
+    ) -> MapChange {
+        let size = self.map_size();
+        let bounds = URect::from_corners(UVec2::ZERO, size);
+        let regions = coalesce_regions(rects.into_iter().map(|rect| {
+            URect::from_corners(rect.min.saturating_sub(UVec2::ONE), rect.max + UVec2::ONE)
+                .intersect(bounds)
+        }));
+
+        let mut tx = self.transaction();
+        for rect in regions {
+            let tiles = resolve_tiles(rect, &|pos| {
+                rules.tile(drop_mask(size, pos, rules.min_drop, &elevation))
+            });
+            for (i, index) in tiles.into_iter().enumerate() {
+                let i = i as u32;
+                tx.set(
+                    rect.min.x + i % rect.width(),
+                    rect.min.y + i / rect.width(),
+                    index,
+                );
+            }
+        }
+        tx.commit()
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Set every tile of the map with a terrain in `rules` to the tile selected by which
+    /// neighbors have the same terrain. Cells of terrains without tiles are left untouched.
+    pub fn apply_autotiles(
+        &mut self,
+        terrain: impl Fn(UVec2) -> u32 + Sync,
+        rules: &AutotileRules,
+    ) -> MapChange {
+        let rect = URect::from_corners(UVec2::ZERO, self.map_size());
+        self.apply_autotiles_in(rect, terrain, rules)
+    }
+
+    /// Like [`Self::apply_autotiles`] but only for the cells whose tiles may have changed when
+    /// the terrain in `rect` (`max` exclusive) changed, ie. `rect` grown by one cell.
+    pub fn apply_autotiles_in(
+        &mut self,
+        rect: URect,
+        terrain: impl Fn(UVec2) -> u32 + Sync,
+        rules: &AutotileRules,
+    ) -> MapChange {
+        self.apply_autotiles_in_regions([rect], terrain, rules)
+    }
+
+    /// Like [`Self::apply_autotiles_in`] for many changed regions at once,
+    /// see [`Self::apply_cliffs_in_regions`].
+    pub fn apply_autotiles_in_regions(
+        &mut self,
+        rects: impl IntoIterator<Item = URect>,
+        terrain: impl Fn(UVec2) -> u32 + Sync,
+        rules: &AutotileRules,
+    ) -> MapChange {
+        let size = self.map_size();
+        let bounds = URect::from_corners(UVec2::ZERO, size);
+        let regions = coalesce_regions(rects.into_iter().map(|rect| {
+            URect::from_corners(rect.min.saturating_sub(UVec2::ONE), rect.max + UVec2::ONE)
+                .intersect(bounds)
+        }));
+
+        let mut tx = self.transaction();
+        for rect in regions {
+            let tiles = resolve_tiles(rect, &|pos| {
+                rules.tile(terrain(pos), terrain_mask(size, pos, &terrain))
+            });
+            for (i, index) in tiles.into_iter().enumerate() {
+                let Some(index) = index else {
+                    continue;
+                };
+                let i = i as u32;
+                tx.set(
+                    rect.min.x + i % rect.width(),
+                    rect.min.y + i / rect.width(),
+                    index,
+                );
+            }
+        }
+        tx.commit()
+    }
+
+    /// Access for painting terrain into `layer`, which immediately updates the tiles of the
+    /// painted cells and their neighbors. `layer` should have the size of the map.
+    pub fn autotile_mut<'a>(
+        &'a mut self,
+        layer: &'a mut TerrainLayer,
+        rules: &'a AutotileRules,
+    ) -> AutotileIndexerMut<'a, C> {
+        AutotileIndexerMut {
+            map: self,
+            layer,
+            rules,
+        }
+    }
+}
+
+/// Paints terrain and keeps the autotiles of a map up to date, see [`Map::autotile_mut`].
+pub struct AutotileIndexerMut<'a, C: Customization> {
+    map: &'a mut Map<C>,
+    layer: &'a mut TerrainLayer,
+    rules: &'a AutotileRules,
+}
+
+impl<C: Customization> AutotileIndexerMut<'_, C> {
+    pub fn terrain(&self, pos: UVec2) -> u32 {
+        self.layer.at(pos)
+    }
+
+    /// Set the terrain of the given cell, ignored outside of the map.
+    pub fn set_terrain(&mut self, pos: UVec2, terrain: u32) {
+        self.fill_terrain(URect::from_corners(pos, pos + UVec2::ONE), terrain);
+    }
+
+    /// Set the terrain of all cells in `rect` (`max` exclusive), clamped to the map.
+    pub fn fill_terrain(&mut self, rect: URect, terrain: u32) {
+        let size = self.map.map_size();
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, size));
+        if rect.is_empty() {
+            return;
+        }
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.layer.set(UVec2::new(x, y), terrain);
+            }
+        }
+
+        let affected = URect::from_corners(rect.min.saturating_sub(UVec2::ONE), rect.max + 1)
+            .intersect(URect::from_corners(UVec2::ZERO, size));
+        let layer = &*self.layer;
+        let mut m = self.map.indexer_mut();
+        for y in affected.min.y..affected.max.y {
+            for x in affected.min.x..affected.max.x {
+                let pos = UVec2::new(x, y);
+                let mask = terrain_mask(size, pos, &|p| layer.at(p));
+                if let Some(tile) = self.rules.tile(layer.at(pos), mask) {
+                    m.set_uvec(pos, tile);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use bevy::{
+    math::Vec3Swizzles,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+
+use super::{map::Map, plugin::Customization};
+
+impl<C: Customization> Map<C> {
+    /// Render the map once (on the CPU) into a static image of [`Self::world_size`] pixels,
+    /// for backdrop layers that never change.
+    ///
+    /// Display the image with a sprite of size [`Self::world_size`] at the transform of the map
+    /// entity, and despawn the map entity to free the per-frame tile sampling and map data.
+    ///
+    /// Each pixel shows the tile it lies in, overhangs into neighboring tiles, custom shader
+    /// code and other shader effects are not baked.
+    /// Returns `None` if the atlas does not belong to this map (or the map is not loaded yet).
+    pub fn bake(&self, atlas: &Image) -> Option<Image> {
+        let u = &self.map_uniform;
+        if u.n_tiles.cmpeq(UVec2::ZERO).any() || atlas.size().as_vec2() != u.atlas_size {
+            return None;
+        }
+
+        let size = self.world_size().ceil().as_uvec2().max(UVec2::ONE);
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+
+        let indexer = self.indexer();
+        let half = size.as_vec2() / 2.0;
+        let max_overhang = u.inner_padding / 2.0;
+
+        for py in 0..size.y {
+            for px in 0..size.x {
+                let local = Vec2::new(px as f32 + 0.5 - half.x, half.y - py as f32 - 0.5);
+                let map_position = self.local_to_map(local);
+                let tile = map_position.floor();
+                if tile.cmplt(Vec2::ZERO).any() || tile.cmpge(self.map_size().as_vec2()).any() {
+                    continue;
+                }
+                let tile = tile.as_ivec2();
+                let index = indexer.at_ivec(tile);
+
+                // Same as `sample_tile_at` in the shader
+                let world_offset =
+                    (u.projection * (map_position - tile.as_vec2()).extend(0.0)).xy() * u.tile_size;
+                let rect_offset =
+                    Vec2::new(1.0, -1.0) * world_offset + u.tile_anchor_point * u.tile_size;
+                if rect_offset.cmplt(-max_overhang).any()
+                    || rect_offset.cmpge(u.tile_size + max_overhang).any()
+                {
+                    continue;
+                }
+
+                let p = (self.atlas_tile_start(index, tile) + rect_offset).as_uvec2();
+                if let Ok(color) = atlas.get_color_at(p.x, p.y) {
+                    let _ = image.set_color_at(px, py, color);
+                }
+            }
+        }
+
+        Some(image)
+    }
+
+    /// Position of the top left corner of the given tile in the atlas,
+    /// same as `atlas_index_to_position` in the shader.
+    fn atlas_tile_start(&self, index: u32, tile_position: IVec2) -> Vec2 {
+        let u = &self.map_uniform;
+        let cell = UVec2::new(index % u.n_tiles.x, index / u.n_tiles.x).as_vec2();
+        let factor = u.atlas_tile_size_factor;
+        if factor > 1 {
+            cell * (u.tile_size * factor as f32 + u.inner_padding)
+                + u.outer_padding_topleft
+                + u.tile_size * (tile_position % factor).as_vec2()
+        } else {
+            cell * (u.tile_size + u.inner_padding) + u.outer_padding_topleft
+        }
+    }
+}
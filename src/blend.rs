@@ -0,0 +1,80 @@
+//! How the map is blended over what is drawn below it, see [`MapBlendMode`].
+//!
+//! ```ignore
+//! // Glow overlay brightening the ground below
+//! let lights = MapBuilder::new(size, glow_atlas, tile_size)
+//!     .with_blend_mode(MapBlendMode::Additive)
+//!     .build();
+//! ```
+
+use bevy::{
+    prelude::*,
+    render::render_resource::{BlendComponent, BlendFactor, BlendOperation, BlendState},
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Blending of the map output with the render target, see [`Map::set_blend_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum MapBlendMode {
+    /// No blending, the map overwrites everything below it (transparent areas become black).
+    /// Cheapest for ground layers that cover the whole view.
+    Opaque,
+    /// Ordinary alpha blending.
+    #[default]
+    AlphaBlend,
+    /// Alpha blending of colors already multiplied by their alpha, eg. for atlases with
+    /// premultiplied alpha.
+    Premultiplied,
+    /// The map color (weighted by its alpha) is added to what is below, for lights and glows.
+    Additive,
+    /// What is below is multiplied with the map color (weighted by its alpha), for shadows and
+    /// darkening overlays.
+    Multiply,
+}
+
+impl MapBlendMode {
+    pub(crate) fn blend_state(self) -> Option<BlendState> {
+        match self {
+            Self::Opaque => None,
+            Self::AlphaBlend => Some(BlendState::ALPHA_BLENDING),
+            Self::Premultiplied => Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            Self::Additive => Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::One,
+                    dst_factor: BlendFactor::One,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            }),
+            Self::Multiply => Some(BlendState {
+                color: BlendComponent {
+                    src_factor: BlendFactor::Dst,
+                    dst_factor: BlendFactor::OneMinusSrcAlpha,
+                    operation: BlendOperation::Add,
+                },
+                alpha: BlendComponent::OVER,
+            }),
+        }
+    }
+
+    /// Shader def adjusting the output color to the blend state.
+    pub(crate) fn shader_def(self) -> Option<&'static str> {
+        match self {
+            Self::Opaque => Some("BLEND_OPAQUE"),
+            Self::AlphaBlend | Self::Premultiplied => None,
+            Self::Additive | Self::Multiply => Some("BLEND_PREMULTIPLY"),
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Choose how the map is blended over what is drawn below it.
+    pub fn set_blend_mode(&mut self, mode: MapBlendMode) {
+        self.blend_mode = mode;
+    }
+
+    pub fn blend_mode(&self) -> MapBlendMode {
+        self.blend_mode
+    }
+}
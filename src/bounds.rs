@@ -0,0 +1,56 @@
+//! Bounds of map entities for bevy's visibility culling and spatial queries.
+//!
+//! Bevy derives an [`Aabb`] once from the mesh, which for managed map meshes is larger than the
+//! map and never follows changes of the map. Maps with managed meshes get their [`Aabb`] from
+//! the map instead, maps with custom meshes keep the one of their mesh.
+
+use bevy::{prelude::*, render::primitives::Aabb};
+
+use super::{
+    map::{Map, MeshManagedByMap},
+    plugin::Customization,
+};
+
+impl<C: Customization> Map<C> {
+    /// Rectangle (in local coordinates of the map entity) that tiles of this map can be drawn
+    /// in, including overhangs reaching up to [`MapBuilder::with_overhang_reach`] tiles beyond the
+    /// map border and raised tiles (see [`crate::elevation`]).
+    ///
+    /// [`MapBuilder::with_overhang_reach`]: crate::map_builder::MapBuilder::with_overhang_reach
+    pub fn local_bounds(&self) -> Rect {
+        // The world size already leaves one tile on each side for overhangs
+        let reach = self.overhang_reach.saturating_sub(1) as f32;
+        let half_size = self.world_size() / 2.0 + self.tile_size().abs() * reach;
+        let mut bounds = Rect::from_center_half_size(Vec2::ZERO, half_size);
+        if self.heights_active() {
+            bounds.max.y += self.map_uniform.max_height;
+        }
+        bounds
+    }
+
+    /// [`Self::local_bounds`] as [`Aabb`].
+    pub fn aabb(&self) -> Aabb {
+        let bounds = self.local_bounds();
+        Aabb::from_min_max(bounds.min.extend(0.0), bounds.max.extend(0.0))
+    }
+}
+
+/// Keep the [`Aabb`] of map entities with managed meshes in sync with their map.
+pub fn update_map_aabbs<C: Customization>(
+    map_materials: Res<Assets<Map<C>>>,
+    maps: Query<(Entity, &Handle<Map<C>>, Option<&Aabb>), With<MeshManagedByMap>>,
+    mut commands: Commands,
+) {
+    for (entity, map_handle, aabb) in maps.iter() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        let new_aabb = map.aabb();
+        let unchanged = aabb.is_some_and(|aabb| {
+            aabb.center == new_aabb.center && aabb.half_extents == new_aabb.half_extents
+        });
+        if !unchanged {
+            commands.entity(entity).insert(new_aabb);
+        }
+    }
+}
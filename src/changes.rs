@@ -0,0 +1,163 @@
+//! Tracking which tiles of a map changed.
+//!
+//! Every write through [`crate::map::MapIndexerMut`] (to any layer) grows the changed rectangle
+//! of the map, which is reported once per frame with [`MapTilesChanged`] for each entity showing the map.
+//! Systems deriving data from the tiles (minimaps, baked lighting, pathfinding grids, ..)
+//! can use it to only update the affected region.
+//!
+//! The same rectangle decides which tiles are written to the GPU copy of the map, which is
+//! updated in place instead of being re-uploaded as a whole.
+
+use std::sync::Mutex;
+
+use bevy::{math::URect, prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization, upload::Channel};
+
+/// Changed tiles of a map since the last report and since the last upload, `max` is exclusive.
+///
+/// Behind a mutex so reporting and extraction do not need mutable access to the map
+/// (which would mark it as modified again).
+#[derive(Debug, Default)]
+pub(crate) struct ChangedTiles(Mutex<ChangedRects>);
+
+#[derive(Debug, Clone, Copy)]
+struct ChangedRects {
+    /// For [`MapTilesChanged`]
+    reported: Option<URect>,
+    /// For the GPU copy of the tiles
+    uploaded: Option<URect>,
+    /// Tiles of the additional layers, for their GPU copy (see [`crate::upload`])
+    layers: Option<URect>,
+    /// Packed channels to write to the GPU, bits of [`Channel`]
+    channels: u32,
+    /// For [`crate::persistence::IncrementalSave`]
+    saved: Option<URect>,
+    /// The GPU copy has to be replaced as a whole, eg. for new maps
+    upload_all: bool,
+}
+
+impl Default for ChangedRects {
+    fn default() -> Self {
+        Self {
+            reported: None,
+            uploaded: None,
+            layers: None,
+            channels: u32::MAX,
+            saved: None,
+            upload_all: true,
+        }
+    }
+}
+
+impl Clone for ChangedTiles {
+    /// Clones have their own GPU copy (or replace the contents of a map asset),
+    /// so they are uploaded as a whole.
+    fn clone(&self) -> Self {
+        Self(Mutex::new(ChangedRects {
+            reported: self.get(),
+            ..default()
+        }))
+    }
+}
+
+impl ChangedTiles {
+    pub(crate) fn get(&self) -> Option<URect> {
+        self.0.lock().ok().and_then(|rects| rects.reported)
+    }
+
+    pub(crate) fn add(&mut self, rect: URect) {
+        let Ok(rects) = self.0.get_mut() else {
+            return;
+        };
+        rects.reported = Some(rects.reported.map_or(rect, |changed| changed.union(rect)));
+        rects.uploaded = Some(rects.uploaded.map_or(rect, |changed| changed.union(rect)));
+        rects.saved = Some(rects.saved.map_or(rect, |changed| changed.union(rect)));
+    }
+
+    /// Tiles of the additional layers changed, see [`crate::map::MapIndexerMut::set_layer`].
+    pub(crate) fn add_layers(&mut self, rect: URect) {
+        let Ok(rects) = self.0.get_mut() else {
+            return;
+        };
+        rects.reported = Some(rects.reported.map_or(rect, |changed| changed.union(rect)));
+        rects.layers = Some(rects.layers.map_or(rect, |changed| changed.union(rect)));
+        rects.saved = Some(rects.saved.map_or(rect, |changed| changed.union(rect)));
+    }
+
+    /// Data of the given packed channel changed.
+    pub(crate) fn add_channel(&mut self, channel: Channel) {
+        if let Ok(rects) = self.0.get_mut() {
+            rects.channels |= channel.bit();
+        }
+    }
+
+    pub(crate) fn take(&self) -> Option<URect> {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut rects| rects.reported.take())
+    }
+
+    /// Tiles changed since the last call, for saving.
+    pub(crate) fn take_saved(&self) -> Option<URect> {
+        self.0.lock().ok().and_then(|mut rects| rects.saved.take())
+    }
+
+    /// Tiles to write to the GPU copy of a map of `map_size` tiles since the last call.
+    pub(crate) fn take_upload(&self, map_size: UVec2) -> Option<URect> {
+        let Ok(mut rects) = self.0.lock() else {
+            return None;
+        };
+        let rect = rects.uploaded.take();
+        if std::mem::take(&mut rects.upload_all) {
+            return Some(URect::from_corners(UVec2::ZERO, map_size));
+        }
+        rect
+    }
+
+    /// Packed channels (bits of [`Channel`]) and layer tiles to write to the GPU since the last
+    /// call.
+    pub(crate) fn take_channels(&self) -> (u32, Option<URect>) {
+        let Ok(mut rects) = self.0.lock() else {
+            return (0, None);
+        };
+        (std::mem::take(&mut rects.channels), rects.layers.take())
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Bounding rectangle of the tiles changed since the last [`MapTilesChanged`] report
+    /// (`max` exclusive), `None` if no tile changed.
+    pub fn changed_tiles(&self) -> Option<URect> {
+        self.changed_tiles.get()
+    }
+}
+
+/// Tiles of the map shown by `map` changed.
+/// Sent in `PostUpdate`, after map edits (see [`crate::commands::ApplyMapEdits`]) are applied.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapTilesChanged {
+    pub map: Entity,
+    /// Bounding rectangle of the changed tiles, `max` is exclusive.
+    pub rect: URect,
+}
+
+pub(crate) fn report_changed_tiles<C: Customization>(
+    map_materials: Res<Assets<Map<C>>>,
+    maps: Query<(Entity, &Handle<Map<C>>)>,
+    mut changed: EventWriter<MapTilesChanged>,
+) {
+    // Maps may be shown by multiple entities, take each map's changes only once
+    let mut taken: HashMap<AssetId<Map<C>>, Option<URect>> = HashMap::default();
+    for (entity, handle) in maps.iter() {
+        let rect = *taken.entry(handle.id()).or_insert_with(|| {
+            map_materials
+                .get(handle)
+                .and_then(|map| map.changed_tiles.take())
+        });
+        if let Some(rect) = rect {
+            changed.send(MapTilesChanged { map: entity, rect });
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use bevy::{
+    math::{uvec2, vec2, URect},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashSet,
+};
+
+use super::{map::Map, plugin::Customization, render_layers::renders_layers};
+
+/// Logically divide a map into rectangular chunks of `chunk_size` tiles.
+///
+/// For maps with this component, [`ChunkEntered`] and [`ChunkExited`] events are emitted
+/// whenever a chunk comes into or goes out of view of any 2d camera drawing the map,
+/// so gameplay can stream entities, enemies, audio, etc. alongside the visible map.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MapChunks {
+    /// Size of each chunk in tiles.
+    pub chunk_size: UVec2,
+
+    /// Number of chunks around the camera view that are considered visible as well.
+    /// Larger margins report chunks earlier (avoiding pop-in with fast moving cameras)
+    /// at the cost of keeping more chunks resident.
+    pub margin: UVec2,
+
+    /// Chunks currently in view of at least one camera.
+    #[reflect(ignore)]
+    pub(crate) visible: HashSet<UVec2>,
+}
+
+impl Default for MapChunks {
+    fn default() -> Self {
+        Self::new(uvec2(32, 32))
+    }
+}
+
+impl MapChunks {
+    pub fn new(chunk_size: UVec2) -> Self {
+        Self {
+            chunk_size,
+            margin: UVec2::ZERO,
+            visible: default(),
+        }
+    }
+
+    pub fn with_margin(self, margin: UVec2) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Chunk coordinate of the chunk holding the given tile.
+    pub fn chunk_of(&self, tile: UVec2) -> UVec2 {
+        tile / self.chunk_size
+    }
+
+    /// Number of chunks needed to cover a map of the given size.
+    pub fn n_chunks(&self, map_size: UVec2) -> UVec2 {
+        (map_size + self.chunk_size - UVec2::ONE) / self.chunk_size
+    }
+
+    /// Tiles covered by the given chunk (`max` is exclusive),
+    /// clipped to the given map size.
+    pub fn chunk_rect(&self, chunk: UVec2, map_size: UVec2) -> URect {
+        let min = chunk * self.chunk_size;
+        URect::from_corners(min, (min + self.chunk_size).min(map_size))
+    }
+
+    /// Chunks currently in view of at least one camera.
+    pub fn visible(&self) -> impl Iterator<Item = UVec2> + '_ {
+        self.visible.iter().copied()
+    }
+
+    pub fn is_visible(&self, chunk: UVec2) -> bool {
+        self.visible.contains(&chunk)
+    }
+}
+
+/// A chunk of `map` came into view.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkEntered {
+    pub map: Entity,
+    pub chunk: UVec2,
+}
+
+/// A chunk of `map` went out of view.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkExited {
+    pub map: Entity,
+    pub chunk: UVec2,
+}
+
+/// Range of chunks (`max` exclusive) of `map` overlapping the given world space rectangle,
+/// grown by [`MapChunks::margin`].
+pub(crate) fn chunks_in_world_rect<C: Customization>(
+    map: &Map<C>,
+    chunks: &MapChunks,
+    map_transform: &GlobalTransform,
+    world_rect: Rect,
+) -> URect {
+    let inverse = map_transform.affine().inverse();
+
+    // Projections may rotate/skew, so take the bounding box of all four corners in map space.
+    let mut low = Vec2::MAX;
+    let mut high = Vec2::MIN;
+    for corner in [
+        world_rect.min,
+        vec2(world_rect.min.x, world_rect.max.y),
+        vec2(world_rect.max.x, world_rect.min.y),
+        world_rect.max,
+    ] {
+        let local = inverse.transform_point3(corner.extend(0.0)).truncate();
+        let map_position = map.local_to_map(local);
+        low = low.min(map_position);
+        high = high.max(map_position);
+    }
+
+    let map_size = map.map_size().as_vec2();
+    let low = low.clamp(Vec2::ZERO, map_size).floor().as_uvec2();
+    let high = high.clamp(Vec2::ZERO, map_size).ceil().as_uvec2();
+    if low.x >= high.x || low.y >= high.y {
+        return URect::default();
+    }
+
+    URect::from_corners(
+        chunks.chunk_of(low).saturating_sub(chunks.margin),
+        (chunks.chunk_of(high - UVec2::ONE) + UVec2::ONE + chunks.margin)
+            .min(chunks.n_chunks(map.map_size())),
+    )
+}
+
+/// 2d camera whose view maps follow, see [`camera_world_rects`].
+pub(crate) type ViewCamera<'a> = (
+    &'a Camera,
+    &'a GlobalTransform,
+    &'a OrthographicProjection,
+    Option<&'a RenderLayers>,
+);
+
+/// World space rectangles currently visible through the active 2d cameras drawing an entity
+/// with `layers`.
+pub(crate) fn camera_world_rects<'a>(
+    cameras: impl Iterator<Item = ViewCamera<'a>>,
+    layers: Option<&RenderLayers>,
+) -> Vec<Rect> {
+    cameras
+        .filter(|(camera, _, _, camera_layers)| {
+            camera.is_active && renders_layers(*camera_layers, layers)
+        })
+        .map(|(_, transform, projection, _)| {
+            let center = transform.translation().truncate();
+            Rect {
+                min: projection.area.min + center,
+                max: projection.area.max + center,
+            }
+        })
+        .collect()
+}
+
+/// Track which chunks are in view and emit [`ChunkEntered`] / [`ChunkExited`] accordingly.
+pub fn update_chunk_visibility<C: Customization>(
+    map_materials: Res<Assets<Map<C>>>,
+    mut maps: Query<(
+        Entity,
+        &Handle<Map<C>>,
+        &GlobalTransform,
+        &mut MapChunks,
+        Option<&RenderLayers>,
+    )>,
+    cameras: Query<ViewCamera>,
+    mut entered: EventWriter<ChunkEntered>,
+    mut exited: EventWriter<ChunkExited>,
+) {
+    for (entity, map_handle, map_transform, mut chunks, layers) in maps.iter_mut() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        let view_rects = camera_world_rects(cameras.iter(), layers);
+
+        let mut visible = HashSet::new();
+        for view_rect in view_rects.iter() {
+            let range = chunks_in_world_rect(map, &chunks, map_transform, *view_rect);
+            for y in range.min.y..range.max.y {
+                for x in range.min.x..range.max.x {
+                    visible.insert(uvec2(x, y));
+                }
+            }
+        }
+
+        for &chunk in visible.difference(&chunks.visible) {
+            entered.send(ChunkEntered { map: entity, chunk });
+        }
+        for &chunk in chunks.visible.difference(&visible) {
+            exited.send(ChunkExited { map: entity, chunk });
+        }
+
+        if visible != chunks.visible {
+            chunks.visible = visible;
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use bevy::{
+    math::{uvec2, vec2, Mat3, URect},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+
+use super::{
+    bundle::MapBundleManaged,
+    chunk::{camera_world_rects, ViewCamera},
+    map::Map,
+    map_builder::MapBuilder,
+    plugin::{Customization, NoCustomization},
+};
+
+type BuilderFn<C> = dyn Fn(UVec2) -> MapBuilder<C> + Send + Sync;
+
+/// Projection and tile size shared by all chunks.
+#[derive(Debug, Clone, Copy)]
+struct ChunkLayout {
+    projection: Mat3,
+    tile_size: Vec2,
+}
+
+impl ChunkLayout {
+    /// Position relative to map position `(0, 0)`, in the local space of the [`ChunkedMap`].
+    fn to_local(&self, map_position: Vec2) -> Vec2 {
+        (self.projection * map_position.extend(0.0)).truncate() * self.tile_size
+    }
+}
+
+/// A very large map split into fixed-size chunks, each drawn by its own child map entity.
+///
+/// Only chunks in view of a 2d camera have a [`Map`] (and thus GPU data), the others are kept
+/// on the CPU and uploaded when they come into view. This keeps uploads small (editing a
+/// tile only re-uploads its chunk) and avoids huge buffers and quads.
+///
+/// The builder callback creates the [`MapBuilder`] for a chunk of the given size (chunks at the
+/// right and bottom border may be smaller), eg.
+/// `|size| Map::builder(size, atlas.clone(), vec2(16.0, 16.0))`. All chunks must use the same
+/// projection and tile size, variable grids are not supported.
+///
+/// Each chunk only draws its own tiles, so the parts of tiles that reach over the chunk border
+/// (overhangs of atlas tiles larger than the tile size, eg. for isometric maps) are clipped at the
+/// seams between chunks. Use a plain [`Map`] for atlases with overhangs.
+///
+/// Map position `(0, 0)` is placed at the origin of this entity, give it a `SpatialBundle`.
+#[derive(Component)]
+pub struct ChunkedMap<C: Customization = NoCustomization> {
+    size: UVec2,
+    chunk_size: UVec2,
+    builder: Arc<BuilderFn<C>>,
+    /// Tile value of chunks that were never written.
+    pub default_tile: u32,
+    /// Extra distance (in world units) around the camera view in which chunks are kept resident.
+    pub margin: f32,
+    layout: Option<ChunkLayout>,
+    resident: HashMap<UVec2, (Entity, Handle<Map<C>>)>,
+    /// Tiles of chunks that are not resident, row by row.
+    stored: HashMap<UVec2, Vec<u32>>,
+}
+
+impl<C: Customization> ChunkedMap<C> {
+    pub fn new(
+        size: UVec2,
+        chunk_size: UVec2,
+        builder: impl Fn(UVec2) -> MapBuilder<C> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            size,
+            chunk_size: chunk_size.max(UVec2::ONE),
+            builder: Arc::new(builder),
+            default_tile: 0,
+            margin: 0.0,
+            layout: None,
+            resident: default(),
+            stored: default(),
+        }
+    }
+
+    pub fn with_default_tile(self, default_tile: u32) -> Self {
+        Self {
+            default_tile,
+            ..self
+        }
+    }
+
+    pub fn with_margin(self, margin: f32) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Initialize all tiles with the given callback.
+    pub fn with_tiles(mut self, mut initializer: impl FnMut(UVec2) -> u32) -> Self {
+        let n_chunks = self.n_chunks();
+        for cy in 0..n_chunks.y {
+            for cx in 0..n_chunks.x {
+                let chunk = uvec2(cx, cy);
+                let rect = self.chunk_rect(chunk);
+                let tiles = (rect.min.y..rect.max.y)
+                    .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| uvec2(x, y)))
+                    .map(&mut initializer)
+                    .collect();
+                self.stored.insert(chunk, tiles);
+            }
+        }
+        self
+    }
+
+    /// Size of the whole map in tiles.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn n_chunks(&self) -> UVec2 {
+        (self.size + self.chunk_size - UVec2::ONE) / self.chunk_size
+    }
+
+    /// Tiles covered by the given chunk (`max` exclusive).
+    pub fn chunk_rect(&self, chunk: UVec2) -> URect {
+        let min = chunk * self.chunk_size;
+        URect::from_corners(min, (min + self.chunk_size).min(self.size))
+    }
+
+    /// Chunks that currently have a map entity, with that entity.
+    pub fn resident(&self) -> impl Iterator<Item = (UVec2, Entity)> + '_ {
+        self.resident
+            .iter()
+            .map(|(chunk, (entity, _))| (*chunk, *entity))
+    }
+
+    /// Tile at the given position, `None` outside of the map.
+    pub fn tile(&self, maps: &Assets<Map<C>>, pos: UVec2) -> Option<u32> {
+        if pos.x >= self.size.x || pos.y >= self.size.y {
+            return None;
+        }
+        let chunk = pos / self.chunk_size;
+        let rect = self.chunk_rect(chunk);
+        let local = pos - rect.min;
+        if let Some((_, handle)) = self.resident.get(&chunk) {
+            return maps.get(handle).map(|map| map.indexer().at_uvec(local));
+        }
+        Some(self.stored.get(&chunk).map_or(self.default_tile, |tiles| {
+            tiles[(local.y * rect.width() + local.x) as usize]
+        }))
+    }
+
+    /// Set the tile at the given position, ignored outside of the map.
+    /// Only the chunk holding the tile is re-uploaded.
+    pub fn set_tile(&mut self, maps: &mut Assets<Map<C>>, pos: UVec2, value: u32) {
+        if pos.x >= self.size.x || pos.y >= self.size.y {
+            return;
+        }
+        let chunk = pos / self.chunk_size;
+        let rect = self.chunk_rect(chunk);
+        let local = pos - rect.min;
+        if let Some((_, handle)) = self.resident.get(&chunk) {
+            if let Some(map) = maps.get_mut(handle) {
+                map.indexer_mut().set_uvec(local, value);
+            }
+            return;
+        }
+        let default_tile = self.default_tile;
+        let n = (rect.width() * rect.height()) as usize;
+        self.stored
+            .entry(chunk)
+            .or_insert_with(|| vec![default_tile; n])
+            [(local.y * rect.width() + local.x) as usize] = value;
+    }
+
+    /// Bounding box of the given chunk in the local space of this entity, grown by a tile
+    /// (for overhangs).
+    fn chunk_bounds(&self, layout: &ChunkLayout, chunk: UVec2) -> Rect {
+        let rect = self.chunk_rect(chunk);
+        let corners = [
+            rect.min,
+            uvec2(rect.max.x, rect.min.y),
+            uvec2(rect.min.x, rect.max.y),
+            rect.max,
+        ]
+        .map(|corner| layout.to_local(corner.as_vec2()));
+        bounding_rect(&corners).inflate(layout.tile_size.max_element())
+    }
+}
+
+fn bounding_rect(points: &[Vec2]) -> Rect {
+    points
+        .iter()
+        .fold(Rect::from_center_size(points[0], Vec2::ZERO), |r, p| {
+            r.union_point(*p)
+        })
+}
+
+/// Create map entities for chunks that came into view and store chunks that went out of view.
+pub(crate) fn update_chunked_maps<C: Customization>(
+    mut commands: Commands,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut chunked_maps: Query<(
+        Entity,
+        &mut ChunkedMap<C>,
+        &GlobalTransform,
+        Option<&RenderLayers>,
+    )>,
+    cameras: Query<ViewCamera>,
+) {
+    for (entity, mut chunked, transform, layers) in chunked_maps.iter_mut() {
+        let view_rects = camera_world_rects(cameras.iter(), layers);
+
+        let layout = match chunked.layout {
+            Some(layout) => layout,
+            None => {
+                let reference = (chunked.builder)(chunked.chunk_size).build();
+                let layout = ChunkLayout {
+                    projection: reference.map_uniform.projection,
+                    tile_size: reference.tile_size(),
+                };
+                chunked.layout = Some(layout);
+                layout
+            }
+        };
+
+        // Camera views in the local space of this entity
+        let inverse = transform.affine().inverse();
+        let views: Vec<Rect> = view_rects
+            .iter()
+            .map(|view| {
+                let corners = [
+                    view.min,
+                    vec2(view.min.x, view.max.y),
+                    vec2(view.max.x, view.min.y),
+                    view.max,
+                ]
+                .map(|corner| inverse.transform_point3(corner.extend(0.0)).truncate());
+                bounding_rect(&corners).inflate(chunked.margin)
+            })
+            .collect();
+
+        let n_chunks = chunked.n_chunks();
+        let mut visible = Vec::new();
+        for cy in 0..n_chunks.y {
+            for cx in 0..n_chunks.x {
+                let chunk = uvec2(cx, cy);
+                let bounds = chunked.chunk_bounds(&layout, chunk);
+                if views.iter().any(|view| !view.intersect(bounds).is_empty()) {
+                    visible.push(chunk);
+                }
+            }
+        }
+
+        // Store chunks that went out of view
+        let gone: Vec<_> = chunked
+            .resident
+            .keys()
+            .filter(|chunk| !visible.contains(chunk))
+            .copied()
+            .collect();
+        for chunk in gone {
+            let Some((chunk_entity, handle)) = chunked.resident.remove(&chunk) else {
+                continue;
+            };
+            commands.entity(chunk_entity).despawn_recursive();
+            if let Some(map) = map_materials.remove(&handle) {
+                chunked.stored.insert(chunk, map.map_texture);
+            }
+        }
+
+        // Upload chunks that came into view
+        for chunk in visible {
+            if chunked.resident.contains_key(&chunk) {
+                continue;
+            }
+            let rect = chunked.chunk_rect(chunk);
+            let tiles = chunked.stored.remove(&chunk);
+            let default_tile = chunked.default_tile;
+            let map = (chunked.builder)(rect.size()).build_and_set(|pos| match &tiles {
+                Some(tiles) => tiles[(pos.y * rect.width() + pos.x) as usize],
+                None => default_tile,
+            });
+
+            // Maps are centered on their transform, align tile `rect.min` with its position
+            let translation = layout.to_local(rect.min.as_vec2()) - map.map_to_local(Vec2::ZERO);
+            let handle = map_materials.add(map);
+            let mut chunk_entity = commands.spawn(MapBundleManaged {
+                material: handle.clone(),
+                transform: Transform::from_translation(translation.extend(0.0)),
+                ..default()
+            });
+            if let Some(layers) = layers {
+                chunk_entity.insert(layers.clone());
+            }
+            let chunk_entity = chunk_entity.set_parent(entity).id();
+            chunked.resident.insert(chunk, (chunk_entity, handle));
+        }
+    }
+}
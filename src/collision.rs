@@ -0,0 +1,421 @@
+//! Collision geometry generation from tile data.
+//!
+//! Tag atlas indices with [`TileShape`]s in a [`TileShapes`] table and generate colliders
+//! with [`Map::colliders`]. The output is plain polygons, so it can be fed into any physics
+//! engine.
+//!
+//! For fewer bodies, [`Map::merged_colliders`] merges runs of solid tiles into rectangles.
+//! A [`TileColliders`] component keeps merged colliders for the map of its entity up to date,
+//! rebuilding only the chunks with changed tiles. With the `avian` or `rapier` feature they are
+//! turned into static physics bodies by the `physics` module.
+//!
+//! ```ignore
+//! let shapes = TileShapes::new().with([1, 2, 3], TileShape::Full);
+//! commands.spawn((MapBundleManaged::new(map, materials.as_mut()), TileColliders::new(shapes)));
+//! ```
+
+use bevy::{
+    math::{uvec2, vec2, URect},
+    prelude::*,
+    utils::HashMap,
+};
+
+use super::{changes::MapTilesChanged, map::Map, plugin::Customization};
+
+/// Collision archetype of a tile.
+///
+/// Directions are in map coordinates, i.e. "top" is towards smaller y.
+/// Slopes are named by the side that is high, eg. [`TileShape::SlopeRight`] rises from bottom
+/// left to top right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum TileShape {
+    /// No collision.
+    #[default]
+    Empty,
+    /// Solid square.
+    Full,
+    /// Bottom half solid.
+    HalfBottom,
+    /// Top half solid.
+    HalfTop,
+    /// Left half solid.
+    HalfLeft,
+    /// Right half solid.
+    HalfRight,
+    /// 45° slope, high on the right (solid below the diagonal).
+    SlopeRight,
+    /// 45° slope, high on the left (solid below the diagonal).
+    SlopeLeft,
+    /// 45° ceiling slope, low on the right (solid above the diagonal).
+    CeilingRight,
+    /// 45° ceiling slope, low on the left (solid above the diagonal).
+    CeilingLeft,
+}
+
+impl TileShape {
+    /// Convex outline of the solid part in tile-relative map coordinates
+    /// (`(0, 0)` top left, `(1, 1)` bottom right), empty for [`TileShape::Empty`].
+    pub fn polygon(&self) -> Vec<Vec2> {
+        let (tl, tr, br, bl) = (
+            vec2(0.0, 0.0),
+            vec2(1.0, 0.0),
+            vec2(1.0, 1.0),
+            vec2(0.0, 1.0),
+        );
+        match self {
+            Self::Empty => vec![],
+            Self::Full => vec![tl, tr, br, bl],
+            Self::HalfBottom => vec![vec2(0.0, 0.5), vec2(1.0, 0.5), br, bl],
+            Self::HalfTop => vec![tl, tr, vec2(1.0, 0.5), vec2(0.0, 0.5)],
+            Self::HalfLeft => vec![tl, vec2(0.5, 0.0), vec2(0.5, 1.0), bl],
+            Self::HalfRight => vec![vec2(0.5, 0.0), tr, br, vec2(0.5, 1.0)],
+            Self::SlopeRight => vec![tr, br, bl],
+            Self::SlopeLeft => vec![tl, br, bl],
+            Self::CeilingRight => vec![tl, tr, bl],
+            Self::CeilingLeft => vec![tl, tr, br],
+        }
+    }
+
+    /// Height of the solid part (`0.0` to `1.0`, measured from the bottom of the tile) at the
+    /// given relative x position, for ground-following in platformers.
+    /// Ceiling shapes (and [`TileShape::HalfTop`]) return the height of their lower edge at `x`.
+    pub fn surface_height(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        match self {
+            Self::Empty => 0.0,
+            Self::Full => 1.0,
+            Self::HalfLeft => (x <= 0.5) as u8 as f32,
+            Self::HalfRight => (x >= 0.5) as u8 as f32,
+            Self::HalfBottom => 0.5,
+            Self::HalfTop => 0.5,
+            Self::SlopeRight => x,
+            Self::SlopeLeft => 1.0 - x,
+            Self::CeilingRight => x,
+            Self::CeilingLeft => 1.0 - x,
+        }
+    }
+}
+
+/// Lookup from atlas index to [`TileShape`], indices without entry are [`TileShape::Empty`].
+#[derive(Resource, Debug, Clone, Default, Reflect)]
+pub struct TileShapes {
+    shapes: HashMap<u32, TileShape>,
+}
+
+impl TileShapes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign `shape` to all the given atlas indices.
+    pub fn with(mut self, indices: impl IntoIterator<Item = u32>, shape: TileShape) -> Self {
+        for index in indices {
+            self.shapes.insert(index, shape);
+        }
+        self
+    }
+
+    pub fn set(&mut self, index: u32, shape: TileShape) {
+        self.shapes.insert(index, shape);
+    }
+
+    pub fn get(&self, index: u32) -> TileShape {
+        self.shapes.get(&index).copied().unwrap_or_default()
+    }
+}
+
+/// Collision polygon generated for a tile, see [`Map::colliders`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileCollider {
+    pub tile: UVec2,
+    pub shape: TileShape,
+    /// Convex polygon in map-local world coordinates
+    /// (apply the map entity's transform for global coordinates).
+    pub polygon: Vec<Vec2>,
+}
+
+impl<C: Customization> Map<C> {
+    /// Shape of the tile at `pos` according to `shapes`.
+    pub fn tile_shape(&self, pos: UVec2, shapes: &TileShapes) -> TileShape {
+        shapes.get(self.indexer().at_uvec(pos))
+    }
+
+    /// One collider per non-empty tile, following the map projection
+    /// (so slopes match the visuals also on non-rectangular maps).
+    pub fn colliders(&self, shapes: &TileShapes) -> Vec<TileCollider> {
+        let size = self.map_size();
+        let mut colliders = Vec::new();
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let tile = UVec2::new(x, y);
+                let shape = self.tile_shape(tile, shapes);
+                if shape == TileShape::Empty {
+                    continue;
+                }
+                colliders.push(TileCollider {
+                    tile,
+                    shape,
+                    polygon: shape
+                        .polygon()
+                        .into_iter()
+                        .map(|p| self.map_to_local(tile.as_vec2() + p))
+                        .collect(),
+                });
+            }
+        }
+        colliders
+    }
+
+    /// Colliders of the tiles of `layer` within `region` (`max` exclusive), with
+    /// [`TileShape::Full`] tiles greedily merged into rectangles (first along rows, then
+    /// downwards). Other shapes get one collider per tile.
+    pub fn merged_colliders(
+        &self,
+        shapes: &TileShapes,
+        layer: u32,
+        region: URect,
+    ) -> Vec<MergedCollider> {
+        let region = region.intersect(URect::from_corners(UVec2::ZERO, self.map_size()));
+        let size = region.size();
+        let shape_at = |x: u32, y: u32| {
+            shapes.get(self.tile_at_layer(layer, region.min.x + x, region.min.y + y))
+        };
+
+        let mut colliders = Vec::new();
+        let mut merged = vec![false; size.x as usize * size.y as usize];
+        let idx = |x: u32, y: u32| y as usize * size.x as usize + x as usize;
+        let solid = |merged: &[bool], x: u32, y: u32| {
+            !merged[idx(x, y)] && shape_at(x, y) == TileShape::Full
+        };
+
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let shape = shape_at(x, y);
+                match shape {
+                    TileShape::Empty => {}
+                    TileShape::Full if merged[idx(x, y)] => {}
+                    TileShape::Full => {
+                        let mut width = 1;
+                        while x + width < size.x && solid(&merged, x + width, y) {
+                            width += 1;
+                        }
+                        let mut height = 1;
+                        while y + height < size.y
+                            && (x..x + width).all(|x| solid(&merged, x, y + height))
+                        {
+                            height += 1;
+                        }
+                        for my in y..y + height {
+                            for mx in x..x + width {
+                                merged[idx(mx, my)] = true;
+                            }
+                        }
+                        let min = region.min + uvec2(x, y);
+                        colliders.push(self.merged_collider(
+                            URect::from_corners(min, min + uvec2(width, height)),
+                            shape,
+                        ));
+                    }
+                    _ => {
+                        let min = region.min + uvec2(x, y);
+                        colliders
+                            .push(self.merged_collider(URect::from_corners(min, min + 1), shape));
+                    }
+                }
+            }
+        }
+        colliders
+    }
+
+    fn merged_collider(&self, rect: URect, shape: TileShape) -> MergedCollider {
+        let size = rect.size().as_vec2();
+        MergedCollider {
+            rect,
+            shape,
+            polygon: shape
+                .polygon()
+                .into_iter()
+                .map(|p| self.map_to_local(rect.min.as_vec2() + p * size))
+                .collect(),
+        }
+    }
+}
+
+/// Collision polygon covering one or more tiles, see [`Map::merged_colliders`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergedCollider {
+    /// Tiles covered, `max` is exclusive. Only [`TileShape::Full`] colliders span multiple tiles.
+    pub rect: URect,
+    pub shape: TileShape,
+    /// Convex polygon in map-local world coordinates.
+    pub polygon: Vec<Vec2>,
+}
+
+/// Merged colliders of the map of this entity, kept up to date as tiles change,
+/// see the [module docs](self).
+///
+/// The map is split into chunks which are merged separately, so changing a tile only rebuilds
+/// its chunk.
+#[derive(Component, Debug, Clone)]
+pub struct TileColliders {
+    shapes: TileShapes,
+    layer: u32,
+    chunk_size: UVec2,
+    chunks: HashMap<UVec2, Vec<MergedCollider>>,
+    /// Map size the chunks were built for, `None` to rebuild all
+    built_for: Option<UVec2>,
+    rebuilt: Vec<UVec2>,
+}
+
+impl TileColliders {
+    pub fn new(shapes: TileShapes) -> Self {
+        Self {
+            shapes,
+            layer: 0,
+            chunk_size: UVec2::splat(32),
+            chunks: HashMap::default(),
+            built_for: None,
+            rebuilt: Vec::new(),
+        }
+    }
+
+    /// Generate colliders from the given layer instead of the base layer.
+    pub fn with_layer(self, layer: u32) -> Self {
+        Self {
+            layer,
+            built_for: None,
+            ..self
+        }
+    }
+
+    /// Size of the separately merged chunks in tiles, default is 32x32.
+    pub fn with_chunk_size(self, chunk_size: UVec2) -> Self {
+        Self {
+            chunk_size: chunk_size.max(UVec2::ONE),
+            built_for: None,
+            ..self
+        }
+    }
+
+    pub fn shapes(&self) -> &TileShapes {
+        &self.shapes
+    }
+
+    /// Change the shapes, which rebuilds all colliders.
+    pub fn set_shapes(&mut self, shapes: TileShapes) {
+        self.shapes = shapes;
+        self.rebuild();
+    }
+
+    /// Rebuild all colliders in the next update.
+    pub fn rebuild(&mut self) {
+        self.built_for = None;
+    }
+
+    pub fn chunk_size(&self) -> UVec2 {
+        self.chunk_size
+    }
+
+    /// All colliders of the map.
+    pub fn colliders(&self) -> impl Iterator<Item = &MergedCollider> {
+        self.chunks.values().flatten()
+    }
+
+    /// Colliders of the given chunk.
+    pub fn chunk(&self, chunk: UVec2) -> &[MergedCollider] {
+        self.chunks.get(&chunk).map_or(&[], |c| c.as_slice())
+    }
+
+    /// Chunks rebuilt (or removed) in the last update, for syncing derived physics bodies.
+    pub fn rebuilt_chunks(&self) -> &[UVec2] {
+        &self.rebuilt
+    }
+
+    fn rebuild_chunk<C: Customization>(&mut self, map: &Map<C>, chunk: UVec2) {
+        let min = chunk * self.chunk_size;
+        let colliders = map.merged_colliders(
+            &self.shapes,
+            self.layer,
+            URect::from_corners(min, min + self.chunk_size),
+        );
+        if colliders.is_empty() {
+            self.chunks.remove(&chunk);
+        } else {
+            self.chunks.insert(chunk, colliders);
+        }
+        if !self.rebuilt.contains(&chunk) {
+            self.rebuilt.push(chunk);
+        }
+    }
+}
+
+pub(crate) fn update_tile_colliders<C: Customization>(
+    map_materials: Res<Assets<Map<C>>>,
+    mut changes: EventReader<MapTilesChanged>,
+    mut maps: Query<(&Handle<Map<C>>, &mut TileColliders)>,
+) {
+    for (_, mut colliders) in maps.iter_mut() {
+        if !colliders.rebuilt.is_empty() {
+            colliders.rebuilt.clear();
+        }
+    }
+
+    for change in changes.read() {
+        let Ok((handle, mut colliders)) = maps.get_mut(change.map) else {
+            continue;
+        };
+        let Some(map) = map_materials.get(handle) else {
+            continue;
+        };
+        if colliders.built_for != Some(map.map_size()) || change.rect.is_empty() {
+            continue;
+        }
+        let (first, last) = (
+            change.rect.min / colliders.chunk_size,
+            (change.rect.max - 1) / colliders.chunk_size,
+        );
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                colliders.rebuild_chunk(map, uvec2(x, y));
+            }
+        }
+    }
+
+    for (handle, mut colliders) in maps.iter_mut() {
+        let Some(map) = map_materials.get(handle) else {
+            continue;
+        };
+        let size = map.map_size();
+        if colliders.built_for == Some(size) {
+            continue;
+        }
+        // Also report the previous chunks, some of them may be gone now
+        let old: Vec<_> = colliders.chunks.drain().map(|(chunk, _)| chunk).collect();
+        colliders.rebuilt.extend(old);
+        let n_chunks = (size + colliders.chunk_size - 1) / colliders.chunk_size;
+        for y in 0..n_chunks.y {
+            for x in 0..n_chunks.x {
+                colliders.rebuild_chunk(map, uvec2(x, y));
+            }
+        }
+        colliders.built_for = Some(size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TileShape;
+
+    #[test]
+    fn ceiling_surface_height_is_lower_edge() {
+        for (shape, expected) in [
+            (TileShape::HalfTop, [0.5, 0.5, 0.5]),
+            (TileShape::CeilingRight, [0.0, 0.5, 1.0]),
+            (TileShape::CeilingLeft, [1.0, 0.5, 0.0]),
+        ] {
+            for (x, expected) in [0.0, 0.5, 1.0].into_iter().zip(expected) {
+                assert_eq!(shape.surface_height(x), expected, "{shape:?} at x = {x}");
+            }
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::{math::URect, prelude::*};
+
+use super::{links::MapLinks, map::Map, plugin::Customization, settings::FastTileMapSettings};
+
+/// A single deferred map edit, see [`MapCommands`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapEdit {
+    /// Set a single tile.
+    Set { pos: UVec2, index: u32 },
+    /// Set all tiles in a rectangle (`max` is exclusive).
+    FillRect { rect: URect, index: u32 },
+    /// Replace the 4-connected region of equal tiles around `start`.
+    FloodFill { start: UVec2, index: u32 },
+}
+
+/// Edits queued for a map entity through [`MapCommands`],
+/// applied in the [`ApplyMapEdits`] system set.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapEditQueue {
+    edits: Vec<MapEdit>,
+}
+
+impl MapEditQueue {
+    pub fn push(&mut self, edit: MapEdit) {
+        self.edits.push(edit);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+}
+
+/// System set (in `PostUpdate`) in which queued [`MapEdit`]s are applied to their maps.
+/// Order your systems relative to this if they need to see the edits in the same frame.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ApplyMapEdits;
+
+/// Deferred edits for a single map, obtained from [`MapCommandsExt::map`].
+///
+/// ```ignore
+/// commands
+///     .map(entity)
+///     .set(uvec2(3, 4), 1)
+///     .fill_rect(URect::new(0, 0, 8, 2), 2);
+/// ```
+///
+/// Edits are applied in order in the [`ApplyMapEdits`] system set,
+/// so no system needs mutable access to the map assets to edit a map.
+/// Edits for maps that are not loaded yet are kept until the map is available.
+pub struct MapCommands<'a, 'w, 's> {
+    commands: &'a mut Commands<'w, 's>,
+    entity: Entity,
+}
+
+impl<'a, 'w, 's> MapCommands<'a, 'w, 's> {
+    /// The map entity these commands apply to.
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    /// Set the tile at `pos`.
+    pub fn set(&mut self, pos: UVec2, index: u32) -> &mut Self {
+        self.push(MapEdit::Set { pos, index })
+    }
+
+    /// Set all tiles in `rect` (`max` is exclusive).
+    pub fn fill_rect(&mut self, rect: URect, index: u32) -> &mut Self {
+        self.push(MapEdit::FillRect { rect, index })
+    }
+
+    /// Replace the 4-connected region of tiles equal to the one at `start`.
+    pub fn flood_fill(&mut self, start: UVec2, index: u32) -> &mut Self {
+        self.push(MapEdit::FloodFill { start, index })
+    }
+
+    /// Queue an arbitrary edit.
+    pub fn push(&mut self, edit: MapEdit) -> &mut Self {
+        let entity = self.entity;
+        self.commands.add(move |world: &mut World| {
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                return;
+            };
+            match entity.get_mut::<MapEditQueue>() {
+                Some(mut queue) => queue.push(edit),
+                None => {
+                    entity.insert(MapEditQueue { edits: vec![edit] });
+                }
+            }
+        });
+        self
+    }
+}
+
+/// Extension of [`Commands`] for deferred map edits, see [`MapCommands`].
+pub trait MapCommandsExt<'w, 's> {
+    fn map<'a>(&'a mut self, entity: Entity) -> MapCommands<'a, 'w, 's>;
+}
+
+impl<'w, 's> MapCommandsExt<'w, 's> for Commands<'w, 's> {
+    fn map<'a>(&'a mut self, entity: Entity) -> MapCommands<'a, 'w, 's> {
+        MapCommands {
+            commands: self,
+            entity,
+        }
+    }
+}
+
+/// Apply all queued [`MapEdit`]s to their maps (and maps linked to them, see [`MapLinks`]).
+/// Respects [`FastTileMapSettings::edit_budget`].
+pub fn apply_map_edits<C: Customization>(
+    settings: Res<FastTileMapSettings>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut queues: Query<(&Handle<Map<C>>, &mut MapEditQueue, Option<&MapLinks<C>>)>,
+) {
+    let budget = settings.edit_budget.unwrap_or(usize::MAX);
+    let mut spent = 0;
+
+    for (map_handle, mut queue, links) in queues.iter_mut() {
+        if queue.is_empty() {
+            continue;
+        }
+        // Budget exhausted, avoid marking further maps as modified
+        if spent >= budget && spent > 0 {
+            break;
+        }
+        let Some(map) = map_materials.get_mut(map_handle) else {
+            continue;
+        };
+
+        let size = map.map_size();
+        let mut touched = Vec::new();
+        let mut m = map.indexer_mut();
+        let mut applied = 0;
+        for edit in queue.edits.iter() {
+            // Always make progress, even if a single edit exceeds the budget
+            if spent >= budget && spent > 0 {
+                break;
+            }
+            spent += match *edit {
+                MapEdit::Set { pos, index } => {
+                    m.set_uvec(pos, index);
+                    touched.push(URect::from_corners(pos, pos + UVec2::ONE));
+                    1
+                }
+                MapEdit::FillRect { rect, index } => {
+                    m.fill_rect(rect, index);
+                    touched.push(rect);
+                    rect.size().element_product() as usize
+                }
+                MapEdit::FloodFill { start, index } => {
+                    touched.push(URect::from_corners(UVec2::ZERO, size));
+                    m.flood_fill(start, index)
+                }
+            };
+            applied += 1;
+        }
+        queue.edits.drain(..applied);
+
+        if let Some(links) = links {
+            for rect in touched {
+                links.propagate(map_handle.id(), rect, &mut map_materials);
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization};
+
+/// splitmix64 finalizer, platform independent so hashes can be compared across peers.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Hash contribution of the tile with value `index` at linear position `idx`.
+pub(crate) fn cell_hash(idx: usize, index: u32) -> u64 {
+    mix(((idx as u64) << 32) ^ index as u64 ^ 0x9e3779b97f4a7c15)
+}
+
+/// Content hash of a whole map texture, see [`Map::content_hash`].
+pub(crate) fn hash_tiles(tiles: &[u32]) -> u64 {
+    tiles
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (idx, index)| hash ^ cell_hash(idx, *index))
+}
+
+impl<C: Customization> Map<C> {
+    /// Hash over the size and all tiles of this map.
+    ///
+    /// The hash is updated incrementally on every edit, so it is cheap enough to compare
+    /// map state across peers every tick (eg. for desync detection in lockstep multiplayer).
+    /// It is deterministic across platforms and crate runs, but not cryptographically secure.
+    pub fn content_hash(&self) -> u64 {
+        let size = self.map_size();
+        self.content_hash ^ mix(((size.x as u64) << 32) | size.y as u64)
+    }
+}
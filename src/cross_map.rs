@@ -0,0 +1,104 @@
+//! Converting tiles between maps that overlap in world space but have different tile sizes or
+//! projections, eg. a coarse collision grid over a fine visual map.
+//!
+//! Conversions go through global world coordinates, so they take the global transforms of the
+//! entities showing both maps.
+
+use bevy::{math::URect, prelude::*};
+
+use super::{map::Map, plugin::Customization};
+
+/// Distance (in tiles of the target map) within which a position counts as lying exactly on a
+/// tile border, to absorb floating point error of the projections.
+const BORDER_EPSILON: f32 = 1e-4;
+
+/// Which tile a position lying exactly on a tile border belongs to, see [`Map::convert_tile`].
+///
+/// Positions inside a tile always belong to that tile. When one grid is a multiple of the other
+/// (eg. 2x2 fine tiles per coarse tile with centers on coarse borders) the choice matters and
+/// should be made explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum TileRounding {
+    /// Borders belong to the tile with the higher index, like `floor`.
+    #[default]
+    Floor,
+    /// Borders belong to the tile with the lower index.
+    Ceil,
+}
+
+impl TileRounding {
+    fn round(self, p: f32) -> i32 {
+        let nearest = p.round();
+        if (p - nearest).abs() >= BORDER_EPSILON {
+            return p.floor() as i32;
+        }
+        match self {
+            Self::Floor => nearest as i32,
+            Self::Ceil => nearest as i32 - 1,
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Map position in `target` of a map position in this map, with `transform` and
+    /// `target_transform` the global transforms of the entities showing the maps.
+    pub fn convert_map_position<D: Customization>(
+        &self,
+        transform: &GlobalTransform,
+        map_position: Vec2,
+        target: &Map<D>,
+        target_transform: &GlobalTransform,
+    ) -> Vec2 {
+        target.world_to_map(target_transform, self.map_to_world(transform, map_position))
+    }
+
+    /// Tile of `target` containing the center of `tile` of this map, with `rounding` deciding
+    /// for centers on tile borders of `target`. `None` if the center is outside of `target`.
+    ///
+    /// For hexagonal targets the hexagon containing the center is returned, `rounding` is
+    /// ignored.
+    pub fn convert_tile<D: Customization>(
+        &self,
+        transform: &GlobalTransform,
+        tile: UVec2,
+        target: &Map<D>,
+        target_transform: &GlobalTransform,
+        rounding: TileRounding,
+    ) -> Option<UVec2> {
+        let p =
+            self.convert_map_position(transform, tile.as_vec2() + 0.5, target, target_transform);
+        let converted = match target.hex_layout() {
+            Some(_) => target.map_position_to_tile(p),
+            None => IVec2::new(rounding.round(p.x), rounding.round(p.y)),
+        };
+        (converted.cmpge(IVec2::ZERO).all() && converted.cmplt(target.map_size().as_ivec2()).all())
+            .then(|| converted.as_uvec2())
+    }
+
+    /// Tiles of `target` (`max` exclusive) overlapping `tile` of this map, eg. all fine tiles
+    /// under a coarse tile. Tiles only touching `tile` along a border are not included.
+    /// Clamped to `target`, `None` if `tile` does not overlap `target`.
+    ///
+    /// For different projections (eg. isometric over orthogonal) this is the bounding rectangle
+    /// of the projected tile and may include tiles only overlapping that rectangle.
+    pub fn convert_tile_area<D: Customization>(
+        &self,
+        transform: &GlobalTransform,
+        tile: UVec2,
+        target: &Map<D>,
+        target_transform: &GlobalTransform,
+    ) -> Option<URect> {
+        let corners = [Vec2::ZERO, Vec2::X, Vec2::Y, Vec2::ONE].map(|corner| {
+            self.convert_map_position(transform, tile.as_vec2() + corner, target, target_transform)
+        });
+        let min = corners.iter().fold(Vec2::INFINITY, |m, c| m.min(*c));
+        let max = corners.iter().fold(Vec2::NEG_INFINITY, |m, c| m.max(*c));
+
+        let size = target.map_size().as_ivec2();
+        let min = (min + BORDER_EPSILON).floor().as_ivec2().max(IVec2::ZERO);
+        let max = (max - BORDER_EPSILON).ceil().as_ivec2().min(size);
+        min.cmplt(max)
+            .all()
+            .then(|| URect::from_corners(min.as_uvec2(), max.as_uvec2()))
+    }
+}
@@ -0,0 +1,251 @@
+use bevy::{math::ivec2, prelude::*};
+
+use super::{
+    highlight::{MapHighlights, TileHighlight},
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Highlight group used for drawing tile cursors, see [`MapHighlights`].
+pub const TILE_CURSOR_HIGHLIGHT: &str = "tile_cursor";
+
+/// Plugin moving [`TileCursor`]s with keyboard and gamepad input.
+pub type TileCursorPlugin = CustomTileCursorPlugin<NoCustomization>;
+
+/// Same as [`TileCursorPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomTileCursorPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomTileCursorPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileCursorBindings>()
+            .add_event::<TileCursorMoved>()
+            .add_systems(Update, update_tile_cursors::<C>);
+    }
+}
+
+/// A selection cursor on a map that can be moved tile by tile with keyboard or gamepad,
+/// eg. for tactics games or console-friendly UIs.
+/// Add this to a map entity (one cursor per map).
+///
+/// Directions are in map coordinates, i.e. "up" decreases the y coordinate.
+/// The cursor is drawn through [`MapHighlights`] (in group [`TILE_CURSOR_HIGHLIGHT`]).
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct TileCursor {
+    pub position: UVec2,
+    /// Wrap around at the map bounds instead of stopping there.
+    pub wrap: bool,
+    pub color: Color,
+    /// Time (in seconds) a direction has to be held before the cursor starts repeating.
+    pub repeat_delay: f32,
+    /// Time (in seconds) between repeated moves while a direction is held.
+    pub repeat_interval: f32,
+
+    #[reflect(ignore)]
+    held: Option<(IVec2, f32)>,
+}
+
+impl Default for TileCursor {
+    fn default() -> Self {
+        Self {
+            position: UVec2::ZERO,
+            wrap: false,
+            color: Color::WHITE,
+            repeat_delay: 0.4,
+            repeat_interval: 0.1,
+            held: None,
+        }
+    }
+}
+
+impl TileCursor {
+    pub fn new(position: UVec2) -> Self {
+        Self {
+            position,
+            ..default()
+        }
+    }
+
+    /// Move by the given offset, clamping or wrapping at the map bounds.
+    pub fn move_by(&mut self, offset: IVec2, map_size: UVec2) {
+        let size = map_size.as_ivec2().max(IVec2::ONE);
+        let p = self.position.as_ivec2() + offset;
+        self.position = if self.wrap {
+            p.rem_euclid(size)
+        } else {
+            p.clamp(IVec2::ZERO, size - IVec2::ONE)
+        }
+        .as_uvec2();
+    }
+}
+
+/// Input bindings for [`TileCursor`]s.
+/// Gamepads always use the D-pad and left stick.
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct TileCursorBindings {
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    /// Stick deflection above which the left stick counts as a direction.
+    pub stick_threshold: f32,
+}
+
+impl Default for TileCursorBindings {
+    fn default() -> Self {
+        Self {
+            up: vec![KeyCode::ArrowUp, KeyCode::KeyW],
+            down: vec![KeyCode::ArrowDown, KeyCode::KeyS],
+            left: vec![KeyCode::ArrowLeft, KeyCode::KeyA],
+            right: vec![KeyCode::ArrowRight, KeyCode::KeyD],
+            stick_threshold: 0.5,
+        }
+    }
+}
+
+/// A [`TileCursor`] moved.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileCursorMoved {
+    pub map: Entity,
+    pub pos: UVec2,
+}
+
+/// Direction currently requested by keyboard or any gamepad.
+fn input_direction(
+    bindings: &TileCursorBindings,
+    keys: &ButtonInput<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_buttons: &ButtonInput<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+) -> IVec2 {
+    let mut d = IVec2::ZERO;
+    let pressed = |codes: &[KeyCode]| keys.any_pressed(codes.iter().copied());
+    if pressed(&bindings.up) {
+        d.y -= 1;
+    }
+    if pressed(&bindings.down) {
+        d.y += 1;
+    }
+    if pressed(&bindings.left) {
+        d.x -= 1;
+    }
+    if pressed(&bindings.right) {
+        d.x += 1;
+    }
+
+    for gamepad in gamepads.iter() {
+        let button = |t| gamepad_buttons.pressed(GamepadButton::new(gamepad, t));
+        let axis = |t| axes.get(GamepadAxis::new(gamepad, t)).unwrap_or(0.0);
+        let stick = stick_direction(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+            bindings.stick_threshold,
+        );
+        d += stick;
+        if button(GamepadButtonType::DPadUp) {
+            d.y -= 1;
+        }
+        if button(GamepadButtonType::DPadDown) {
+            d.y += 1;
+        }
+        if button(GamepadButtonType::DPadLeft) {
+            d.x -= 1;
+        }
+        if button(GamepadButtonType::DPadRight) {
+            d.x += 1;
+        }
+    }
+
+    d.clamp(IVec2::NEG_ONE, IVec2::ONE)
+}
+
+/// Stick deflection to direction. Stick y points up, map y points down.
+fn stick_direction(x: f32, y: f32, threshold: f32) -> IVec2 {
+    let component = |v: f32| {
+        if v > threshold {
+            1
+        } else if v < -threshold {
+            -1
+        } else {
+            0
+        }
+    };
+    ivec2(component(x), -component(y))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_tile_cursors<C: Customization>(
+    time: Res<Time>,
+    bindings: Res<TileCursorBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    map_materials: Res<Assets<Map<C>>>,
+    mut cursors: Query<(
+        Entity,
+        &Handle<Map<C>>,
+        &mut TileCursor,
+        Option<&mut MapHighlights>,
+    )>,
+    mut moved: EventWriter<TileCursorMoved>,
+    mut commands: Commands,
+) {
+    let direction = input_direction(&bindings, &keys, &gamepads, &gamepad_buttons, &axes);
+    let now = time.elapsed_seconds();
+
+    for (entity, map_handle, mut cursor, highlights) in cursors.iter_mut() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+
+        // Move once on press, then repeat after `repeat_delay` every `repeat_interval`
+        let step = match cursor.held {
+            _ if direction == IVec2::ZERO => {
+                cursor.held = None;
+                false
+            }
+            Some((held, next)) if held == direction => {
+                if now >= next {
+                    cursor.held = Some((direction, now + cursor.repeat_interval));
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => {
+                cursor.held = Some((direction, now + cursor.repeat_delay));
+                true
+            }
+        };
+
+        if step {
+            let before = cursor.position;
+            cursor.move_by(direction, map.map_size());
+            if cursor.position != before {
+                moved.send(TileCursorMoved {
+                    map: entity,
+                    pos: cursor.position,
+                });
+            }
+        }
+
+        let highlight = vec![TileHighlight::tile(cursor.position, cursor.color)];
+        match highlights {
+            Some(mut highlights) => {
+                if highlights.get(TILE_CURSOR_HIGHLIGHT) != highlight.as_slice() {
+                    highlights.set(TILE_CURSOR_HIGHLIGHT, highlight);
+                }
+            }
+            None => {
+                let mut highlights = MapHighlights::default();
+                highlights.set(TILE_CURSOR_HIGHLIGHT, highlight);
+                commands.entity(entity).insert(highlights);
+            }
+        }
+    }
+}
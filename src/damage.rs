@@ -0,0 +1,113 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization};
+
+/// Damage state of an atlas index: the damage it takes to advance and the index it advances to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct DamageState {
+    pub health: u32,
+    pub next: u32,
+}
+
+/// Damage-state chains per atlas index (eg. intact → cracked → rubble) for destructible terrain,
+/// see [`Map::damage_tile`].
+///
+/// Insert as resource for easy access from systems.
+#[derive(Resource, Debug, Clone, Default, Reflect)]
+pub struct TileDamageStates {
+    states: HashMap<u32, DamageState>,
+}
+
+impl TileDamageStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a chain of atlas indices, each advancing to the next after taking `health`
+    /// damage. The last index of the chain is final and takes no more damage.
+    pub fn with_chain(mut self, chain: &[u32], health: u32) -> Self {
+        for pair in chain.windows(2) {
+            self.set(pair[0], health, pair[1]);
+        }
+        self
+    }
+
+    /// Let `index` advance to `next` after taking `health` damage.
+    pub fn set(&mut self, index: u32, health: u32, next: u32) {
+        self.states.insert(index, DamageState { health, next });
+    }
+
+    pub fn get(&self, index: u32) -> Option<DamageState> {
+        self.states.get(&index).copied()
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Damage accumulated by the tile at `pos` since it last changed.
+    pub fn tile_damage(&self, pos: UVec2) -> u32 {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return 0;
+        }
+        self.damage
+            .get((pos.y * size.x + pos.x) as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Add `amount` damage to the tile at `pos`, advancing it along its chain in `states`
+    /// as often as the accumulated damage allows.
+    /// Returns the new atlas index if the tile changed.
+    ///
+    /// Damage is kept per tile in an auxiliary buffer (not uploaded to the GPU) and reset
+    /// whenever the tile is set to a different index, including by damage.
+    pub fn damage_tile(
+        &mut self,
+        pos: UVec2,
+        amount: u32,
+        states: &TileDamageStates,
+    ) -> Option<u32> {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
+        }
+        let idx = (pos.y * size.x + pos.x) as usize;
+        let start = self.indexer().at_uvec(pos);
+        let mut index = start;
+        let mut damage = self.tile_damage(pos).saturating_add(amount);
+
+        // Guard against cyclic chains
+        let mut steps = 0;
+        while let Some(state) = states.get(index) {
+            if damage < state.health || steps > states.states.len() {
+                break;
+            }
+            damage -= state.health;
+            index = state.next;
+            steps += 1;
+        }
+        if states.get(index).is_none() {
+            damage = 0;
+        }
+
+        if index != start {
+            self.indexer_mut().set_uvec(pos, index);
+        }
+        if self.damage.len() != (size.x * size.y) as usize {
+            self.damage = vec![0; (size.x * size.y) as usize];
+        }
+        self.damage[idx] = damage;
+
+        (index != start).then_some(index)
+    }
+
+    /// Reset the accumulated damage of the tile at `pos`.
+    pub fn repair_tile(&mut self, pos: UVec2) {
+        let size = self.map_size();
+        if pos.x < size.x && pos.y < size.y {
+            if let Some(damage) = self.damage.get_mut((pos.y * size.x + pos.x) as usize) {
+                *damage = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+
+/// Debug visualization of the fragment cost of a map,
+/// see [`crate::map_builder::MapBuilder::with_overdraw_debug`].
+///
+/// Fragments are colored from blue (cheap) over green to red (expensive),
+/// which helps tuning overhang settings and map layering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum OverdrawDebugMode {
+    /// Color by number of tile samples taken from the atlas (including overhang lookups).
+    /// Red means 9 or more samples.
+    Samples,
+    /// Color by number of times a non-transparent sample was blended on top of an already
+    /// non-transparent color.
+    /// Red means 4 or more.
+    Overdraw,
+}
+
+impl OverdrawDebugMode {
+    pub(crate) fn shader_def(&self) -> &'static str {
+        match self {
+            Self::Samples => "DEBUG_OVERDRAW_SAMPLES",
+            Self::Overdraw => "DEBUG_OVERDRAW_BLENDS",
+        }
+    }
+}
+
+/// Color ramp for rendering raw tile values instead of the atlas,
+/// see [`crate::map::Map::set_color_ramp`].
+///
+/// Useful for debugging influence maps, pathfinding costs or other integer grids stored in maps.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct ColorRamp {
+    /// Colors evenly spaced between `min` and `max`, linearly interpolated in between.
+    pub stops: Vec<Color>,
+    /// Value mapped to the first stop, smaller values are clamped.
+    pub min: f32,
+    /// Value mapped to the last stop, larger values are clamped.
+    pub max: f32,
+}
+
+impl ColorRamp {
+    pub fn new(stops: Vec<Color>, min: f32, max: f32) -> Self {
+        Self { stops, min, max }
+    }
+
+    /// Perceptually uniform "viridis" ramp from dark purple over teal to yellow.
+    pub fn viridis(min: f32, max: f32) -> Self {
+        Self::new(
+            vec![
+                Color::srgb_u8(0x44, 0x01, 0x54),
+                Color::srgb_u8(0x3b, 0x52, 0x8b),
+                Color::srgb_u8(0x21, 0x91, 0x8c),
+                Color::srgb_u8(0x5e, 0xc9, 0x62),
+                Color::srgb_u8(0xfd, 0xe7, 0x25),
+            ],
+            min,
+            max,
+        )
+    }
+
+    pub(crate) fn shader_data(&self) -> Vec<Vec4> {
+        if self.stops.is_empty() {
+            return vec![Vec4::ZERO];
+        }
+        self.stops.iter().map(|c| c.to_linear().to_vec4()).collect()
+    }
+}
@@ -0,0 +1,209 @@
+use bevy::{
+    math::{vec2, URect},
+    prelude::*,
+};
+
+use super::{
+    chunk::MapChunks,
+    highlight::tile_rect_outline,
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Debug plugin drawing tile outlines, map bounds, chunk boundaries and coordinate labels
+/// for maps with a [`MapDebugDraw`] component, following the map's projection.
+/// Useful when authoring custom [`crate::tile_projection::TileProjection`]s.
+///
+/// Requires `GizmoPlugin` (part of `DefaultPlugins`), labels additionally require bevy's
+/// text rendering.
+pub type MapDebugDrawPlugin = CustomMapDebugDrawPlugin<NoCustomization>;
+
+/// Same as [`MapDebugDrawPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomMapDebugDrawPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomMapDebugDrawPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (draw_map_debug::<C>, update_map_debug_labels::<C>));
+    }
+}
+
+/// What to draw for a map, see [`MapDebugDrawPlugin`].
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MapDebugDraw {
+    /// Outline every tile.
+    pub tiles: bool,
+    /// Draw the (local) axis aligned bounding box of the map mesh.
+    pub aabb: bool,
+    /// Outline the chunks of [`MapChunks`] (if present on the map).
+    pub chunks: bool,
+    /// Label each tile in this region (`max` exclusive) with its coordinates.
+    /// Keep this small, every label is a text entity.
+    pub labels: Option<URect>,
+    pub tile_color: Color,
+    pub aabb_color: Color,
+    pub chunk_color: Color,
+    pub label_color: Color,
+}
+
+impl Default for MapDebugDraw {
+    fn default() -> Self {
+        Self {
+            tiles: true,
+            aabb: true,
+            chunks: true,
+            labels: None,
+            tile_color: Color::srgba(1.0, 1.0, 1.0, 0.3),
+            aabb_color: Color::srgb(1.0, 0.0, 1.0),
+            chunk_color: Color::srgb(1.0, 1.0, 0.0),
+            label_color: Color::WHITE,
+        }
+    }
+}
+
+/// Marker for the label entities spawned for [`MapDebugDraw::labels`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MapDebugLabel;
+
+/// Points along the map line from `from` to `to` (in map coordinates), one per tile,
+/// so lines follow non-linear layouts such as depth scaled rows.
+fn map_line<C: Customization>(
+    map: &Map<C>,
+    map_transform: &GlobalTransform,
+    from: Vec2,
+    to: Vec2,
+    steps: u32,
+) -> impl Iterator<Item = Vec2> + '_ {
+    let steps = steps.max(1);
+    let map_transform = *map_transform;
+    (0..=steps).map(move |i| {
+        let p = from.lerp(to, i as f32 / steps as f32);
+        map_transform
+            .transform_point(map.map_to_local(p).extend(0.0))
+            .truncate()
+    })
+}
+
+pub fn draw_map_debug<C: Customization>(
+    mut gizmos: Gizmos,
+    map_materials: Res<Assets<Map<C>>>,
+    maps: Query<(
+        &Handle<Map<C>>,
+        &GlobalTransform,
+        &MapDebugDraw,
+        Option<&MapChunks>,
+    )>,
+) {
+    for (map_handle, map_transform, debug, chunks) in maps.iter() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        let size = map.map_size();
+        let sizef = size.as_vec2();
+
+        if debug.tiles {
+            for x in 0..=size.x {
+                let (from, to) = (vec2(x as f32, 0.0), vec2(x as f32, sizef.y));
+                gizmos.linestrip_2d(
+                    map_line(map, map_transform, from, to, size.y),
+                    debug.tile_color,
+                );
+            }
+            for y in 0..=size.y {
+                let (from, to) = (vec2(0.0, y as f32), vec2(sizef.x, y as f32));
+                gizmos.linestrip_2d(
+                    map_line(map, map_transform, from, to, size.x),
+                    debug.tile_color,
+                );
+            }
+        }
+
+        if debug.aabb {
+            let half = map.world_size() / 2.0;
+            let corners = [
+                vec2(-half.x, -half.y),
+                vec2(half.x, -half.y),
+                vec2(half.x, half.y),
+                vec2(-half.x, half.y),
+                vec2(-half.x, -half.y),
+            ];
+            gizmos.linestrip_2d(
+                corners.map(|c| map_transform.transform_point(c.extend(0.0)).truncate()),
+                debug.aabb_color,
+            );
+        }
+
+        if let (true, Some(chunks)) = (debug.chunks, chunks) {
+            let n = chunks.n_chunks(size);
+            for y in 0..n.y {
+                for x in 0..n.x {
+                    let rect = chunks.chunk_rect(UVec2::new(x, y), size);
+                    let outline = tile_rect_outline(map, map_transform, rect);
+                    gizmos.linestrip_2d(
+                        outline.into_iter().chain(std::iter::once(outline[0])),
+                        debug.chunk_color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Respawn coordinate labels whenever [`MapDebugDraw`] changes.
+pub fn update_map_debug_labels<C: Customization>(
+    mut commands: Commands,
+    map_materials: Res<Assets<Map<C>>>,
+    maps: Query<(
+        Entity,
+        &Handle<Map<C>>,
+        Ref<MapDebugDraw>,
+        Option<&Children>,
+    )>,
+    labels: Query<(), With<MapDebugLabel>>,
+) {
+    for (entity, map_handle, debug, children) in maps.iter() {
+        if !debug.is_changed() {
+            continue;
+        }
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+
+        for child in children.into_iter().flatten() {
+            if labels.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+
+        let Some(region) = debug.labels else {
+            continue;
+        };
+        let region = region.intersect(URect::from_corners(UVec2::ZERO, map.map_size()));
+
+        commands.entity(entity).with_children(|parent| {
+            for y in region.min.y..region.max.y {
+                for x in region.min.x..region.max.x {
+                    let center = map.map_to_local(vec2(x as f32, y as f32) + 0.5);
+                    parent.spawn((
+                        Text2dBundle {
+                            text: Text::from_section(
+                                format!("{},{}", x, y),
+                                TextStyle {
+                                    font_size: 12.0,
+                                    color: debug.label_color,
+                                    ..default()
+                                },
+                            ),
+                            transform: Transform::from_translation(center.extend(1.0)),
+                            ..default()
+                        },
+                        MapDebugLabel,
+                    ));
+                }
+            }
+        });
+    }
+}
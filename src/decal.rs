@@ -0,0 +1,186 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+
+use super::{map::Map, plugin::Customization};
+
+/// Maximum number of decals per map, adding more replaces the oldest one.
+/// Every fragment of the map tests all active decals, so keep the count low.
+pub const MAX_DECALS: usize = 64;
+
+/// Lifetime used for decals that never expire (finite to keep the shader math well defined).
+const PERMANENT_LIFETIME: f32 = 1e30;
+
+/// Temporary image (eg. a scorch mark or blood splatter) projected onto a rectangle of a map,
+/// independent of the tile grid. See [`Map::add_decal`].
+///
+/// The decal is drawn on top of all tiles and layers of the map, but below the map's shadows
+/// and only where the map itself is visible.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Decal {
+    /// Covered area in map-local world coordinates (eg. from [`Map::tile_center`]).
+    pub rect: Rect,
+    /// Area of the decal texture to draw, in UV coordinates.
+    pub uv: Rect,
+    /// Multiplied onto the decal texture.
+    pub color: Color,
+    /// Seconds until the decal disappears, `f32::INFINITY` to keep it until
+    /// [`Map::clear_decals`].
+    pub lifetime: f32,
+    /// Seconds at the end of the lifetime during which the decal fades out.
+    pub fade: f32,
+}
+
+impl Decal {
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            uv: Rect::new(0.0, 0.0, 1.0, 1.0),
+            color: Color::WHITE,
+            lifetime: 10.0,
+            fade: 2.0,
+        }
+    }
+
+    /// Square decal of the given size centered at `center` (map-local world coordinates).
+    pub fn centered(center: Vec2, size: f32) -> Self {
+        Self::new(Rect::from_center_size(center, Vec2::splat(size)))
+    }
+
+    /// Draw only this part of the decal texture (eg. one cell of a decal atlas).
+    pub fn with_uv(self, uv: Rect) -> Self {
+        Self { uv, ..self }
+    }
+
+    pub fn with_color(self, color: Color) -> Self {
+        Self { color, ..self }
+    }
+
+    pub fn with_lifetime(self, lifetime: f32, fade: f32) -> Self {
+        Self {
+            lifetime,
+            fade,
+            ..self
+        }
+    }
+}
+
+/// Decal as seen by the shader.
+#[derive(ShaderType, Debug, Clone, Copy, Default, Reflect)]
+pub struct DecalShaderData {
+    /// min / max of the covered area in map-local world coordinates
+    pub rect: Vec4,
+    /// min / max in the decal texture
+    pub uv: Vec4,
+    pub color: Vec4,
+    /// start time (NaN until placed), lifetime, fade duration, unused
+    pub time: Vec4,
+}
+
+impl DecalShaderData {
+    /// Placeholder entry (the shader needs a non-empty array), never drawn.
+    pub(crate) fn placeholder() -> Self {
+        Self {
+            time: Vec4::new(f32::INFINITY, 0.0, 0.0, 0.0),
+            ..default()
+        }
+    }
+
+    fn start(&self) -> f32 {
+        self.time.x
+    }
+
+    fn is_expired(&self, now: f32) -> bool {
+        now - self.start() >= self.time.y
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Texture to take decals from, see [`Map::add_decal`].
+    /// Also available as [`crate::map_builder::MapBuilder::with_decal_texture`].
+    pub fn set_decal_texture(&mut self, texture: Handle<Image>) {
+        self.decal_texture = texture;
+        self.map_decals = true;
+    }
+
+    /// Project a decal onto this map, starting in the current frame.
+    /// Requires a decal texture, see [`Map::set_decal_texture`].
+    pub fn add_decal(&mut self, decal: Decal) {
+        let data = DecalShaderData {
+            rect: decal
+                .rect
+                .min
+                .extend(decal.rect.max.x)
+                .extend(decal.rect.max.y),
+            uv: decal.uv.min.extend(decal.uv.max.x).extend(decal.uv.max.y),
+            color: decal.color.to_linear().to_vec4(),
+            time: Vec4::new(
+                f32::NAN,
+                decal.lifetime.min(PERMANENT_LIFETIME),
+                decal.fade.max(0.0),
+                0.0,
+            ),
+        };
+
+        // Drop the dummy entry of maps without decals
+        self.decals.retain(|decal| !decal.start().is_infinite());
+        if self.decals.len() >= MAX_DECALS {
+            self.decals.remove(0);
+        }
+        self.decals.push(data);
+    }
+
+    /// Remove all decals of this map.
+    pub fn clear_decals(&mut self) {
+        self.decals = vec![DecalShaderData::placeholder()];
+    }
+
+    /// Number of decals currently projected onto this map.
+    pub fn n_decals(&self) -> usize {
+        self.decals
+            .iter()
+            .filter(|decal| !decal.start().is_infinite())
+            .count()
+    }
+}
+
+/// Start newly added decals and remove expired ones.
+/// Maps are only modified when a decal is added or expires.
+pub(crate) fn update_map_decals<C: Customization>(
+    time: Res<Time>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    // Same clock as `globals.time` in the shader
+    let now = time.elapsed_seconds_wrapped();
+    let period = time.wrap_period().as_secs_f32();
+
+    let ids: Vec<_> = maps
+        .iter()
+        .filter(|(_, map)| {
+            map.map_decals
+                && map.decals.iter().any(|decal| {
+                    decal.start().is_nan()
+                        || (decal.start().is_finite()
+                            && (decal.start() > now || decal.is_expired(now)))
+                })
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in ids {
+        let Some(map) = maps.get_mut(id) else {
+            continue;
+        };
+        for decal in map.decals.iter_mut() {
+            if decal.start().is_nan() {
+                decal.time.x = now;
+            } else if decal.start().is_finite() && decal.start() > now {
+                // The clock wrapped around
+                decal.time.x -= period;
+            }
+        }
+        map.decals
+            .retain(|decal| decal.start().is_infinite() || !decal.is_expired(now));
+        if map.decals.is_empty() {
+            map.clear_decals();
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Per tile depth output, so sprites can stand behind individual tiles of a map drawn as a single
+//! quad, eg. behind trees and walls overhanging from the tile in front on isometric maps.
+//!
+//! Each fragment writes the depth of the tile its color comes from (the frontmost sufficiently
+//! opaque one, including overhangs), that is the logical depth of the tile center as returned by
+//! [`Map::map_to_world_3d`] plus the `z` of the map entity. Give sprites the same kind of `z`:
+//!
+//! ```ignore
+//! let world = map.map_to_world_3d(map_position.extend(0.0));
+//! transform.translation = world.xy().extend(map_entity_z + world.z);
+//! ```
+//!
+//! The map must be drawn before the sprites, so keep the `z` of the map entity below the sprites.
+//! Fragments without opaque tiles are written as far away.
+
+use super::{map::Map, plugin::Customization};
+
+impl<C: Customization> Map<C> {
+    /// Write per tile depth, see the [module docs](self).
+    pub fn set_tile_depth(&mut self, enabled: bool) {
+        self.tile_depth = enabled;
+    }
+
+    pub fn tile_depth(&self) -> bool {
+        self.tile_depth
+    }
+}
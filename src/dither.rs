@@ -0,0 +1,67 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization, upload::Channel};
+
+/// Dithered blending at the borders between different terrain categories (eg. grass and sand),
+/// softening hard tile seams on natural terrain without authoring transition tiles.
+///
+/// Near the edge towards a neighbor of a different category, some pixels of a tile are drawn
+/// with the neighbor's atlas tile instead, thresholded by a (blue) noise texture. The noise is
+/// read per world unit and repeats, so a small tileable noise texture is sufficient.
+/// Atlas indices without a category are never dithered.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TerrainDither {
+    /// Noise texture, only the red channel is used.
+    pub noise: Handle<Image>,
+    /// Width of the dithered band on each side of a border, as fraction of a tile.
+    pub width: f32,
+    categories: HashMap<u32, u32>,
+}
+
+impl TerrainDither {
+    pub fn new(noise: Handle<Image>) -> Self {
+        Self {
+            noise,
+            width: 0.25,
+            categories: HashMap::default(),
+        }
+    }
+
+    /// Assign all the given atlas indices to `category` (must not be 0).
+    pub fn with_category(mut self, indices: impl IntoIterator<Item = u32>, category: u32) -> Self {
+        for index in indices {
+            self.categories.insert(index, category);
+        }
+        self
+    }
+
+    pub fn with_width(self, width: f32) -> Self {
+        Self { width, ..self }
+    }
+
+    /// Category per atlas index as it is uploaded to the shader, 0 for no category.
+    pub(crate) fn shader_data(&self) -> Vec<u32> {
+        let len = self.categories.keys().max().map_or(1, |i| *i as usize + 1);
+        let mut v = vec![0; len];
+        for (index, category) in self.categories.iter() {
+            v[*index as usize] = *category;
+        }
+        v
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Dither the borders between terrain categories, `None` to disable, see [`TerrainDither`].
+    pub fn set_terrain_dither(&mut self, dither: Option<&TerrainDither>) {
+        match dither {
+            Some(dither) => {
+                self.terrain_categories = dither.shader_data();
+                self.changed_tiles.add_channel(Channel::TerrainCategories);
+                self.dither_noise = dither.noise.clone();
+                self.map_uniform.dither_width = dither.width;
+                self.terrain_dither = true;
+            }
+            None => self.terrain_dither = false,
+        }
+    }
+}
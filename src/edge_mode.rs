@@ -0,0 +1,78 @@
+//! What is drawn outside of the map, see [`EdgeMode`].
+//!
+//! ```ignore
+//! // Endless scrolling background repeating a small map
+//! let map = MapBuilder::new(uvec2(16, 16), atlas, tile_size)
+//!     .with_edge_mode(EdgeMode::Wrap)
+//!     .build();
+//! ```
+//!
+//! With any mode other than [`EdgeMode::Empty`] the managed mesh covers the area seen by the
+//! cameras instead of only the map, the tile data is not duplicated.
+
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization};
+
+/// Tiles drawn at positions outside of the map, see [`Map::set_edge_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum EdgeMode {
+    /// Nothing is drawn outside of the map.
+    #[default]
+    Empty,
+    /// Repeat the tiles along the border of the map.
+    Clamp,
+    /// Repeat the whole map, for toroidal worlds and endless backgrounds.
+    Wrap,
+    /// Draw this atlas index everywhere outside of the map.
+    Fill(u32),
+}
+
+impl EdgeMode {
+    /// Mode and fill index as passed to the shader.
+    pub(crate) fn uniform(self) -> (u32, u32) {
+        match self {
+            Self::Empty => (0, 0),
+            Self::Clamp => (1, 0),
+            Self::Wrap => (2, 0),
+            Self::Fill(index) => (3, index),
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Choose what to draw outside of the map. Only applies to maps with managed meshes
+    /// and without variable row or column sizes.
+    pub fn set_edge_mode(&mut self, mode: EdgeMode) {
+        self.edge_mode = mode;
+        (self.map_uniform.edge_mode, self.map_uniform.edge_fill) = mode.uniform();
+    }
+
+    pub fn edge_mode(&self) -> EdgeMode {
+        self.edge_mode
+    }
+
+    pub(crate) fn edge_mode_active(&self) -> bool {
+        self.edge_mode != EdgeMode::Empty && self.variable_grid.is_none()
+    }
+
+    /// Whether the managed mesh covers the cameras' view rather than the map.
+    pub(crate) fn covers_view(&self) -> bool {
+        self.edge_mode_active() || self.relative_origin_active() || self.parallax
+    }
+
+    /// Map position of the tile drawn at `pos` according to the edge mode, `None` if no map tile
+    /// is drawn there (outside of the map with [`EdgeMode::Empty`] or [`EdgeMode::Fill`]).
+    pub fn edge_tile(&self, pos: IVec2) -> Option<UVec2> {
+        let size = self.map_size().as_ivec2();
+        let inside = pos.cmpge(IVec2::ZERO).all() && pos.cmplt(size).all();
+        match self.edge_mode {
+            _ if inside => Some(pos.as_uvec2()),
+            EdgeMode::Clamp if size.min_element() > 0 => {
+                Some(pos.clamp(IVec2::ZERO, size - 1).as_uvec2())
+            }
+            EdgeMode::Wrap if size.min_element() > 0 => Some(pos.rem_euclid(size).as_uvec2()),
+            _ => None,
+        }
+    }
+}
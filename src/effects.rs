@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization, query::MapQuery};
+
+/// Plugin for despawning (and fading out) [`TileEffect`]s.
+pub struct TileEffectPlugin;
+
+impl Plugin for TileEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_tile_effects);
+    }
+}
+
+/// Short-lived effect (eg. an explosion or sparkle sprite), despawned after its timer finishes.
+/// Place it with [`MapQuery::tile_transform`].
+#[derive(Component, Debug, Clone)]
+pub struct TileEffect {
+    pub timer: Timer,
+    /// Fade out the alpha of the entity's `Sprite` over the lifetime.
+    pub fade: bool,
+}
+
+impl TileEffect {
+    pub fn new(lifetime: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(lifetime, TimerMode::Once),
+            fade: false,
+        }
+    }
+
+    pub fn fading(self) -> Self {
+        Self { fade: true, ..self }
+    }
+}
+
+fn update_tile_effects(
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut TileEffect, Option<&mut Sprite>)>,
+    mut commands: Commands,
+) {
+    for (entity, mut effect, sprite) in effects.iter_mut() {
+        effect.timer.tick(time.delta());
+        if effect.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let (true, Some(mut sprite)) = (effect.fade, sprite) {
+            sprite.color.set_alpha(effect.timer.fraction_remaining());
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Center of the given tile in map-local world coordinates, following the map projection.
+    pub fn tile_center(&self, tile: UVec2) -> Vec2 {
+        self.map_to_local(tile.as_vec2() + Vec2::splat(0.5))
+    }
+}
+
+impl<'w, 's, C: Customization> MapQuery<'w, 's, C> {
+    /// Global transform for placing something (eg. a particle effect) at the center of `tile`,
+    /// `z_offset` in front of the map (see [`crate::stack::MapStack::sort_key`] for placing it
+    /// between the layers of a map stack).
+    pub fn tile_transform(&self, entity: Entity, tile: UVec2, z_offset: f32) -> Option<Transform> {
+        let map = self.get(entity)?;
+        let transform = self.transform(entity)?;
+        let world = transform.transform_point(map.tile_center(tile).extend(z_offset));
+        Some(Transform::from_translation(world))
+    }
+}
@@ -0,0 +1,84 @@
+//! Per tile elevation for fake 3D terrain, eg. cliffs and plateaus on isometric maps,
+//! see [`crate::map_builder::MapBuilder::with_height_layer`].
+//!
+//! Raised tiles are drawn shifted up on screen by their height, the area between a raised tile
+//! and the ground is filled with a cliff face (the tile's colors darkened by the cliff shade).
+//! Tiles lower on screen are in front, overhangs are rendered on the elevated surface.
+//! Heights have no effect on maps with variable row or column sizes.
+//!
+//! ```ignore
+//! let mut m = map.indexer_mut();
+//! // A plateau raised by a full tile height
+//! for y in 4..8 {
+//!     for x in 4..8 {
+//!         m.set_height(x, y, tile_size.y);
+//!     }
+//! }
+//! ```
+
+use bevy::prelude::*;
+
+use super::{
+    map::{Map, MapIndexer, MapIndexerMut},
+    plugin::Customization,
+    upload::Channel,
+};
+
+impl<C: Customization> Map<C> {
+    /// Height of the tile at the given position in world units, `0.0` if out of bounds
+    /// or without height layer.
+    pub fn height_at(&self, x: u32, y: u32) -> f32 {
+        let size = self.map_size();
+        if !self.height_layer || x >= size.x || y >= size.y {
+            return 0.0;
+        }
+        self.heights
+            .get(y as usize * size.x as usize + x as usize)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Brightness of cliff faces below raised tiles, from `0.0` (black) to `1.0` (same as the
+    /// tile). Default is `0.6`.
+    pub fn set_cliff_shade(&mut self, shade: f32) {
+        self.map_uniform.cliff_shade = shade.clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn heights_active(&self) -> bool {
+        self.height_layer && self.variable_grid.is_none()
+    }
+}
+
+impl<'a, C: Customization> MapIndexer<'a, C> {
+    /// Height of the tile at given position, see [`Map::height_at`].
+    pub fn height_at(&self, x: u32, y: u32) -> f32 {
+        self.map.height_at(x, y)
+    }
+}
+
+impl<'a, C: Customization> MapIndexerMut<'a, C> {
+    /// Height of the tile at given position, see [`Map::height_at`].
+    pub fn height_at(&self, x: u32, y: u32) -> f32 {
+        self.map.height_at(x, y)
+    }
+
+    /// Raise the tile at given position by `height` world units along the screen's up axis
+    /// (negative heights are clamped to `0.0`).
+    /// Has no effect for maps without height layer, see
+    /// [`MapBuilder::with_height_layer`](crate::map_builder::MapBuilder::with_height_layer).
+    pub fn set_height(&mut self, x: u32, y: u32, height: f32) {
+        let size = self.size();
+        if !self.map.height_layer || x >= size.x || y >= size.y {
+            return;
+        }
+        let height = height.max(0.0);
+        let idx = y as usize * size.x as usize + x as usize;
+        if let Some(h) = self.map.heights.get_mut(idx) {
+            *h = height;
+            self.map.changed_tiles.add_channel(Channel::Heights);
+        }
+        // Upper bound only, lowering tiles keeps it
+        let max_height = &mut self.map.map_uniform.max_height;
+        *max_height = max_height.max(height);
+    }
+}
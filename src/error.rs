@@ -0,0 +1,308 @@
+use bevy::prelude::*;
+use std::fmt;
+
+use super::format::MapFormatVersion;
+
+/// The number of tiles in the atlas could not be derived from the atlas size, tile size and
+/// padding, i.e. it did not come out as a near-integral number.
+///
+/// This usually means the padding given to [`crate::map_builder::MapBuilder::with_padding`] does
+/// not match the atlas. The suggested paddings are computed from the nearest integral tile count
+/// and are a good starting point for fixing the setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasTileCountError {
+    /// Size of the atlas texture in pixels.
+    pub atlas_size: Vec2,
+    /// Number of tiles per row/column as computed from the current settings.
+    pub n_tiles: Vec2,
+    /// Maximum allowed distance of `n_tiles` to the nearest integer.
+    pub tolerance: f32,
+    /// Inner padding that would produce `n_tiles.round()` tiles with the given outer padding.
+    /// `None` in dimensions with only a single tile, as inner padding is irrelevant there.
+    pub suggested_inner_padding: SuggestedPadding,
+    /// Bottom/right outer padding that would produce `n_tiles.round()` tiles with the given
+    /// top/left outer padding and inner padding.
+    pub suggested_outer_padding_bottomright: Vec2,
+}
+
+/// Suggested padding per dimension, `None` where no suggestion can be made.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SuggestedPadding {
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+}
+
+impl fmt::Display for AtlasTileCountError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Expected an integral number of tiles in your atlas (tolerance {}), \
+            but computes to be {:?} for atlas size {:?}. ",
+            self.tolerance, self.n_tiles, self.atlas_size
+        )?;
+        write!(
+            f,
+            "For {:?} tiles, try inner padding {:?} or bottom/right padding {:?}, \
+            or use `.with_n_tiles()` in MapBuilder if that is intentional.",
+            self.n_tiles.round().max(Vec2::ONE),
+            self.suggested_inner_padding,
+            self.suggested_outer_padding_bottomright,
+        )
+    }
+}
+
+impl std::error::Error for AtlasTileCountError {}
+
+/// A map can not be rendered with its settings, see
+/// [`crate::map_builder::MapBuilder::try_build`] and [`crate::map::MapFailed`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapBuildError {
+    /// The map size is zero in at least one dimension.
+    EmptyMap,
+    /// The tile size is zero, negative or not finite in at least one dimension.
+    InvalidTileSize(Vec2),
+    /// The tile data (in bytes) exceeds the maximum storage buffer size of the GPU.
+    MapTooLarge { size: u64, max: u64 },
+    /// More atlas pages than can be addressed, see [`crate::pages::MAX_ATLAS_PAGES`].
+    TooManyPages(usize),
+    /// The number of tiles in the atlas could not be derived, the map is still shown with a
+    /// truncated tile count.
+    Atlas(AtlasTileCountError),
+}
+
+impl fmt::Display for MapBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyMap => write!(f, "Map has no tiles"),
+            Self::InvalidTileSize(size) => write!(f, "Invalid tile size {:?}", size),
+            Self::MapTooLarge { size, max } => write!(
+                f,
+                "Map data of {} bytes exceeds the maximum buffer size of {} bytes, \
+                consider splitting the map into chunks",
+                size, max
+            ),
+            Self::TooManyPages(n) => write!(
+                f,
+                "Map has {} atlas pages, at most {} are supported",
+                n,
+                crate::pages::MAX_ATLAS_PAGES
+            ),
+            Self::Atlas(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for MapBuildError {}
+
+impl From<AtlasTileCountError> for MapBuildError {
+    fn from(e: AtlasTileCountError) -> Self {
+        Self::Atlas(e)
+    }
+}
+
+/// A tile position lies outside of the map, see
+/// [`MapIndexerMut::try_set`](crate::map::MapIndexerMut::try_set).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBoundsError {
+    pub position: UVec2,
+    /// Size of the map in tiles.
+    pub size: UVec2,
+}
+
+impl fmt::Display for OutOfBoundsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Tile position {} is outside of the map of size {}",
+            self.position, self.size
+        )
+    }
+}
+
+impl std::error::Error for OutOfBoundsError {}
+
+/// Map data could not be read from the native map format, see [`crate::format`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapFormatError {
+    /// The data does not start with the map format header.
+    BadMagic,
+    /// The data ended before all expected content was read.
+    Truncated,
+    /// The data was written by a newer format version than this crate supports.
+    UnsupportedVersion(MapFormatVersion),
+    /// No migration is registered to upgrade data from the given version.
+    MissingMigration(MapFormatVersion),
+    /// The map size in the data does not match the size of the map it is loaded into.
+    SizeMismatch { expected: UVec2, found: UVec2 },
+    /// The data only holds tiles (eg. it was written by format version 1),
+    /// but map settings are needed to create a map.
+    MissingSettings,
+    /// The data holds no atlas path, but the atlas is needed to create the map.
+    MissingAtlas,
+    /// The user data could not be decoded, eg. because the map's customization changed.
+    InvalidUserData,
+    /// The data holds a chunk outside of the map, see [`crate::persistence`].
+    ChunkOutOfRange(UVec2),
+}
+
+impl fmt::Display for MapFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "Not a map file (missing header)"),
+            Self::Truncated => write!(f, "Map data is truncated"),
+            Self::UnsupportedVersion(v) => write!(
+                f,
+                "Map format version {} is newer than the supported version {}",
+                v.0,
+                MapFormatVersion::CURRENT.0
+            ),
+            Self::MissingMigration(v) => {
+                write!(f, "No migration registered for map format version {}", v.0)
+            }
+            Self::SizeMismatch { expected, found } => write!(
+                f,
+                "Map data has size {:?} but the map has size {:?}",
+                found, expected
+            ),
+            Self::MissingSettings => write!(f, "Map data holds no map settings"),
+            Self::MissingAtlas => write!(f, "Map data holds no atlas path"),
+            Self::InvalidUserData => write!(f, "Map data holds invalid user data"),
+            Self::ChunkOutOfRange(chunk) => {
+                write!(f, "Map data holds chunk {:?} outside of the map", chunk)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapFormatError {}
+
+/// A map file could not be loaded, see [`crate::format::MapLoader`] and
+/// [`crate::map_asset::MapAssetLoader`].
+#[derive(Debug)]
+pub enum MapLoadError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file is not valid map data.
+    Format(MapFormatError),
+    /// The file is not a valid `.map.ron` file.
+    Ron(String),
+}
+
+impl fmt::Display for MapLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read map file: {}", e),
+            Self::Format(e) => write!(f, "Invalid map file: {}", e),
+            Self::Ron(e) => write!(f, "Invalid map RON: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MapLoadError {}
+
+impl From<std::io::Error> for MapLoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<MapFormatError> for MapLoadError {
+    fn from(e: MapFormatError) -> Self {
+        Self::Format(e)
+    }
+}
+
+/// A Tiled map could not be loaded, see [`crate::tmx::TmxLoader`].
+#[derive(Debug)]
+pub enum TmxError {
+    /// The file or a referenced tileset could not be read.
+    Io(std::io::Error),
+    /// The file is not a valid Tiled map, eg. a required attribute is missing.
+    Invalid(String),
+    /// The file uses a feature that is not supported, eg. compressed layer data.
+    Unsupported(String),
+}
+
+impl fmt::Display for TmxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read Tiled map: {}", e),
+            Self::Invalid(s) => write!(f, "Invalid Tiled map: {}", s),
+            Self::Unsupported(s) => write!(f, "Unsupported Tiled map feature: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for TmxError {}
+
+impl From<std::io::Error> for TmxError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A tileset metadata file could not be loaded, see [`crate::metadata::TilesetMetadataLoader`].
+#[derive(Debug)]
+pub enum TilesetMetadataError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The file is not valid tileset metadata.
+    Invalid(String),
+}
+
+impl fmt::Display for TilesetMetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read tileset metadata: {}", e),
+            Self::Invalid(s) => write!(f, "Invalid tileset metadata: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for TilesetMetadataError {}
+
+impl From<std::io::Error> for TilesetMetadataError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// An LDtk project could not be loaded, see [`crate::ldtk::LdtkLoader`].
+#[cfg(feature = "ldtk")]
+#[derive(Debug)]
+pub enum LdtkError {
+    /// The file or an external level could not be read.
+    Io(std::io::Error),
+    /// The file is not a valid LDtk project, eg. a required field is missing.
+    Invalid(String),
+    /// The project uses a feature that is not supported.
+    Unsupported(String),
+}
+
+#[cfg(feature = "ldtk")]
+impl fmt::Display for LdtkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read LDtk project: {}", e),
+            Self::Invalid(s) => write!(f, "Invalid LDtk project: {}", s),
+            Self::Unsupported(s) => write!(f, "Unsupported LDtk feature: {}", s),
+        }
+    }
+}
+
+#[cfg(feature = "ldtk")]
+impl std::error::Error for LdtkError {}
+
+#[cfg(feature = "ldtk")]
+impl From<std::io::Error> for LdtkError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+#[cfg(feature = "ldtk")]
+impl From<serde_json::Error> for LdtkError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Invalid(e.to_string())
+    }
+}
@@ -0,0 +1,46 @@
+//! Tiled-style flip flags packed into the high bits of tile values.
+//!
+//! The shader masks these bits off the atlas index and mirrors the tile accordingly,
+//! the diagonal flip (a transpose) is applied before the horizontal and vertical flips.
+
+use bevy::prelude::*;
+
+use super::{map::MapIndexerMut, plugin::Customization};
+
+/// Mirror the tile horizontally.
+pub const TILE_FLIP_X: u32 = 1 << 31;
+/// Mirror the tile vertically.
+pub const TILE_FLIP_Y: u32 = 1 << 30;
+/// Mirror the tile along its top-left to bottom-right diagonal,
+/// combine with [`TILE_FLIP_X`] or [`TILE_FLIP_Y`] for 90° rotations.
+pub const TILE_FLIP_DIAGONAL: u32 = 1 << 29;
+/// All flip bits.
+pub const TILE_FLIP_MASK: u32 = TILE_FLIP_X | TILE_FLIP_Y | TILE_FLIP_DIAGONAL;
+
+/// Atlas index of a tile value, without flip flags.
+pub fn tile_atlas_index(value: u32) -> u32 {
+    value & !TILE_FLIP_MASK
+}
+
+/// Flip flags (`TILE_FLIP_*` bits) of a tile value.
+pub fn tile_flip_flags(value: u32) -> u32 {
+    value & TILE_FLIP_MASK
+}
+
+impl<'a, C: Customization> MapIndexerMut<'a, C> {
+    /// Set tile at given position to atlas index `index` with the given `TILE_FLIP_*` flags.
+    pub fn set_flipped(&mut self, x: u32, y: u32, index: u32, flags: u32) {
+        self.set(x, y, tile_atlas_index(index) | tile_flip_flags(flags));
+    }
+
+    /// Set tile at given position to atlas index `index` with the given `TILE_FLIP_*` flags.
+    pub fn set_flipped_uvec(&mut self, i: UVec2, index: u32, flags: u32) {
+        self.set_flipped(i.x, i.y, index, flags);
+    }
+
+    /// Flip flags of the tile at given position, keeping its atlas index.
+    pub fn set_flags(&mut self, x: u32, y: u32, flags: u32) {
+        let index = tile_atlas_index(self.at(x, y));
+        self.set_flipped(x, y, index, flags);
+    }
+}
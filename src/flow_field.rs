@@ -0,0 +1,156 @@
+//! Dijkstra maps / flow fields over map data, eg. for steering many units towards common goals.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    math::ivec2,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+
+use super::{map::Map, plugin::Customization};
+
+const NEIGHBORS: [IVec2; 4] = [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y];
+
+/// Accumulated travel cost to the nearest goal and the direction to walk for every tile,
+/// see [`Map::flow_field`].
+///
+/// Movement is 4-connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowField {
+    size: UVec2,
+    costs: Vec<u32>,
+    directions: Vec<IVec2>,
+    content_hash: u64,
+}
+
+impl FlowField {
+    /// Compute a flow field on a grid of the given tiles (row by row).
+    /// `cost_fn` gives the cost of entering a tile by its atlas index, `None` for impassable tiles.
+    fn compute(
+        size: UVec2,
+        tiles: &[u32],
+        content_hash: u64,
+        goals: &[UVec2],
+        cost_fn: impl Fn(u32) -> Option<u32>,
+    ) -> Self {
+        let n = (size.x * size.y) as usize;
+        let mut costs = vec![u32::MAX; n];
+        let mut heap = BinaryHeap::new();
+
+        let index = |p: IVec2| {
+            (p.x >= 0 && p.y >= 0 && p.x < size.x as i32 && p.y < size.y as i32)
+                .then(|| (p.y as u32 * size.x + p.x as u32) as usize)
+        };
+
+        for goal in goals {
+            if let Some(i) = index(goal.as_ivec2()) {
+                costs[i] = 0;
+                heap.push(Reverse((0, i)));
+            }
+        }
+
+        while let Some(Reverse((cost, i))) = heap.pop() {
+            if cost > costs[i] {
+                continue;
+            }
+            let p = ivec2((i as u32 % size.x) as i32, (i as u32 / size.x) as i32);
+            for d in NEIGHBORS {
+                let Some(j) = index(p + d) else {
+                    continue;
+                };
+                let Some(step) = cost_fn(tiles[j]) else {
+                    continue;
+                };
+                let c = cost.saturating_add(step);
+                if c < costs[j] {
+                    costs[j] = c;
+                    heap.push(Reverse((c, j)));
+                }
+            }
+        }
+
+        let directions = (0..n)
+            .map(|i| {
+                let p = ivec2((i as u32 % size.x) as i32, (i as u32 / size.x) as i32);
+                let mut best = (costs[i], IVec2::ZERO);
+                for d in NEIGHBORS {
+                    if let Some(j) = index(p + d) {
+                        if costs[j] < best.0 {
+                            best = (costs[j], d);
+                        }
+                    }
+                }
+                best.1
+            })
+            .collect();
+
+        Self {
+            size,
+            costs,
+            directions,
+            content_hash,
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, pos: UVec2) -> Option<usize> {
+        (pos.x < self.size.x && pos.y < self.size.y).then(|| (pos.y * self.size.x + pos.x) as usize)
+    }
+
+    /// Accumulated cost from `pos` to the nearest goal,
+    /// `None` if no goal is reachable from `pos` (or `pos` is outside the map).
+    pub fn cost(&self, pos: UVec2) -> Option<u32> {
+        self.index(pos)
+            .map(|i| self.costs[i])
+            .filter(|c| *c != u32::MAX)
+    }
+
+    /// Step (one of the four unit vectors) that leads from `pos` towards the nearest goal.
+    /// Zero on goals, unreachable tiles and outside of the map.
+    pub fn direction(&self, pos: UVec2) -> IVec2 {
+        self.index(pos)
+            .map(|i| self.directions[i])
+            .unwrap_or(IVec2::ZERO)
+    }
+
+    /// True iff the map has been edited since this field was computed
+    /// (based on [`Map::content_hash`]), ie. the field should be recomputed.
+    pub fn is_stale<C: Customization>(&self, map: &Map<C>) -> bool {
+        map.content_hash() != self.content_hash
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Compute a flow field towards the given goal tiles.
+    /// `cost_fn` gives the cost of entering a tile by its atlas index, `None` for impassable tiles.
+    ///
+    /// Use [`FlowField::is_stale`] to find out when the map has changed under the field.
+    pub fn flow_field(&self, goals: &[UVec2], cost_fn: impl Fn(u32) -> Option<u32>) -> FlowField {
+        FlowField::compute(
+            self.map_size(),
+            &self.map_texture,
+            self.content_hash(),
+            goals,
+            cost_fn,
+        )
+    }
+
+    /// Like [`Self::flow_field`], but computed on the [`AsyncComputeTaskPool`] from a snapshot
+    /// of the current map data. Poll the returned task (eg. with
+    /// `block_on(futures_lite::future::poll_once(&mut task))` from `bevy::tasks`) from a system.
+    pub fn flow_field_task(
+        &self,
+        goals: Vec<UVec2>,
+        cost_fn: impl Fn(u32) -> Option<u32> + Send + 'static,
+    ) -> Task<FlowField> {
+        let size = self.map_size();
+        let tiles = self.map_texture.clone();
+        let content_hash = self.content_hash();
+        AsyncComputeTaskPool::get()
+            .spawn(async move { FlowField::compute(size, &tiles, content_hash, &goals, cost_fn) })
+    }
+}
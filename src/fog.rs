@@ -0,0 +1,175 @@
+//! Fog of war: per tile visibility, drawn by darkening or hiding tiles in the shader.
+//!
+//! Each tile has a visibility from `0` (never seen) to `255` (in view), stored one byte per tile
+//! separately from the tile data. Typically visibility is updated each turn or when units move:
+//!
+//! ```ignore
+//! let mut fog = map.fog_mut();
+//! fog.cover();
+//! for unit in units.iter() {
+//!     fog.reveal_circle(unit.tile, unit.sight);
+//! }
+//! ```
+
+use bevy::{math::URect, prelude::*};
+
+use super::{
+    fov::FieldOfView, map::Map, ownership::owner_words, plugin::Customization, upload::Channel,
+};
+
+/// Visibility of tiles that were never seen.
+pub const FOG_HIDDEN: u8 = 0;
+/// Visibility of tiles that are in view.
+pub const FOG_VISIBLE: u8 = 255;
+
+/// How the fog of war is drawn, see [`Map::set_fog_of_war`].
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct FogOfWar {
+    /// Color of the fog, its alpha is the fog opacity over hidden tiles.
+    /// An opaque color hides unseen tiles completely.
+    pub color: Color,
+    /// Visibility that tiles in view fall back to on [`MapFogMut::cover`],
+    /// ie. how much of explored terrain remains visible.
+    pub explored: u8,
+    /// Blend visibility between tile centers (`1.0`) instead of hard tile edges (`0.0`).
+    pub softness: f32,
+}
+
+impl Default for FogOfWar {
+    fn default() -> Self {
+        Self {
+            color: Color::BLACK,
+            explored: 128,
+            softness: 1.0,
+        }
+    }
+}
+
+impl FogOfWar {
+    pub fn new(color: Color) -> Self {
+        Self { color, ..default() }
+    }
+
+    pub fn with_explored(self, explored: u8) -> Self {
+        Self { explored, ..self }
+    }
+
+    pub fn with_softness(self, softness: f32) -> Self {
+        Self { softness, ..self }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Draw the fog of war with the given settings, `None` disables the fog.
+    /// Enabling the fog on a map without visibility data starts with all tiles hidden.
+    pub fn set_fog_of_war(&mut self, fog: Option<&FogOfWar>) {
+        match fog {
+            Some(fog) => {
+                let n_words = owner_words((self.map_size().x * self.map_size().y) as usize);
+                if self.fog.len() != n_words {
+                    self.fog = vec![0; n_words];
+                    self.changed_tiles.add_channel(Channel::Fog);
+                }
+                self.map_uniform.fog_color = fog.color.to_linear().to_vec4();
+                self.map_uniform.fog_softness = fog.softness.clamp(0.0, 1.0);
+                self.fog_explored = fog.explored;
+                self.map_fog = true;
+            }
+            None => self.map_fog = false,
+        }
+    }
+
+    /// Visibility of the given tile, [`FOG_HIDDEN`] for positions outside the map.
+    pub fn fog(&self, pos: UVec2) -> u8 {
+        self.fog_slot(pos)
+            .and_then(|(word, shift)| self.fog.get(word).map(|w| (w >> shift) as u8))
+            .unwrap_or(FOG_HIDDEN)
+    }
+
+    /// Access for changing the visibility of tiles.
+    pub fn fog_mut(&mut self) -> MapFogMut<'_, C> {
+        self.changed_tiles.add_channel(Channel::Fog);
+        MapFogMut { map: self }
+    }
+
+    fn fog_slot(&self, pos: UVec2) -> Option<(usize, u32)> {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
+        }
+        let i = (pos.y * size.x + pos.x) as usize;
+        Some((i / 4, (i % 4) as u32 * 8))
+    }
+}
+
+/// Changes the visibility of the tiles of a map, see [`Map::fog_mut`].
+pub struct MapFogMut<'a, C: Customization> {
+    map: &'a mut Map<C>,
+}
+
+impl<C: Customization> MapFogMut<'_, C> {
+    pub fn get(&self, pos: UVec2) -> u8 {
+        self.map.fog(pos)
+    }
+
+    /// Set the visibility of the given tile, positions outside the map are ignored.
+    pub fn set(&mut self, pos: UVec2, visibility: u8) {
+        let Some((word, shift)) = self.map.fog_slot(pos) else {
+            return;
+        };
+        if let Some(w) = self.map.fog.get_mut(word) {
+            *w = (*w & !(0xff << shift)) | ((visibility as u32) << shift);
+        }
+    }
+
+    /// Set the visibility of all tiles in `rect` (`max` exclusive).
+    pub fn fill(&mut self, rect: URect, visibility: u8) {
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, self.map.map_size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.set(UVec2::new(x, y), visibility);
+            }
+        }
+    }
+
+    /// Make all tiles within `radius` tiles (euclidean distance between tile centers)
+    /// of `center` visible.
+    pub fn reveal_circle(&mut self, center: UVec2, radius: f32) {
+        let r = radius.max(0.0).floor() as u32;
+        let rect = URect::from_corners(center.saturating_sub(UVec2::splat(r)), center + r + 1);
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, self.map.map_size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                let pos = UVec2::new(x, y);
+                if pos.as_vec2().distance_squared(center.as_vec2()) <= radius * radius {
+                    self.set(pos, FOG_VISIBLE);
+                }
+            }
+        }
+    }
+
+    /// Make all tiles of a field of view visible, eg. to respect line of sight.
+    pub fn reveal_fov(&mut self, fov: &FieldOfView) {
+        for pos in fov.iter() {
+            self.set(pos, FOG_VISIBLE);
+        }
+    }
+
+    /// Let tiles in view fall back to the explored visibility (see [`FogOfWar::explored`]),
+    /// eg. before revealing the current views of all units.
+    pub fn cover(&mut self) {
+        let explored = self.map.fog_explored as u32;
+        for w in self.map.fog.iter_mut() {
+            for shift in [0, 8, 16, 24] {
+                if (*w >> shift) & 0xff > explored {
+                    *w = (*w & !(0xff << shift)) | (explored << shift);
+                }
+            }
+        }
+    }
+
+    /// Hide all tiles again.
+    pub fn reset(&mut self) {
+        self.map.fog.fill(0);
+    }
+}
@@ -0,0 +1,361 @@
+//! Native binary map format.
+//!
+//! Layout: the magic bytes `BFTM`, the [`MapFormatVersion`] (`u32`), then the version-specific
+//! payload. All integers are little endian.
+//!
+//! Version 1 payload: map size (`u32` x, `u32` y) followed by one `u32` atlas index per tile,
+//! row by row.
+//!
+//! Version 2 payload: the version 1 payload, followed by a `u32` that is 1 if map settings follow
+//! (0 for data migrated from version 1). The settings are the tile size (2 `f32`), projection
+//! matrix (9 `f32`, column by column), tile anchor point (2 `f32`), atlas padding (inner,
+//! top/left and bottom/right, 2 `f32` each), atlas tile size factor (`i32`), the atlas asset path
+//! and the user data. The path and user data are each stored as their length in bytes (`u32`)
+//! followed by the bytes, the path as UTF-8 (empty if the atlas has no path) and the user data in
+//! its storage buffer layout.
+//!
+//! Data written by older versions is upgraded step by step through [`MapFormatMigrations`]
+//! before it is read, so saves stay loadable across crate updates.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    math::{uvec2, vec2, Mat3},
+    prelude::*,
+    render::render_resource::encase::{internal::CreateFrom, StorageBuffer},
+    utils::HashMap,
+};
+
+use super::{
+    error::{MapFormatError, MapLoadError},
+    map::Map,
+    map_builder::MapBuilder,
+    plugin::{Customization, NoCustomization},
+    tile_projection::TileProjection,
+};
+
+pub(crate) const MAGIC: &[u8; 4] = b"BFTM";
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+/// Version of the native map format.
+/// Embed this in your own save files if you store map data separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect)]
+pub struct MapFormatVersion(pub u32);
+
+impl MapFormatVersion {
+    /// Version written by this crate version.
+    pub const CURRENT: Self = Self(2);
+
+    /// Read the version from the header of `bytes` without decoding the rest.
+    pub fn of(bytes: &[u8]) -> Result<Self, MapFormatError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(if bytes.starts_with(&MAGIC[..bytes.len().min(4)]) {
+                MapFormatError::Truncated
+            } else {
+                MapFormatError::BadMagic
+            });
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(MapFormatError::BadMagic);
+        }
+        Ok(Self(read_u32(bytes, 4)?))
+    }
+}
+
+/// Upgrades the payload (without header) of one version to the payload of the next version.
+pub type MapFormatMigration = fn(&[u8]) -> Result<Vec<u8>, MapFormatError>;
+
+/// Registry of migrations between consecutive [`MapFormatVersion`]s.
+///
+/// [`MapFormatMigrations::default`] contains all migrations shipped with this crate.
+#[derive(Debug, Clone)]
+pub struct MapFormatMigrations {
+    migrations: HashMap<MapFormatVersion, MapFormatMigration>,
+}
+
+impl Default for MapFormatMigrations {
+    fn default() -> Self {
+        // Register migrations here when bumping `MapFormatVersion::CURRENT`
+        let mut migrations = Self {
+            migrations: HashMap::default(),
+        };
+        migrations.register(MapFormatVersion(1), migrate_v1_settings);
+        migrations
+    }
+}
+
+/// Version 1 has no map settings, mark them as missing.
+fn migrate_v1_settings(payload: &[u8]) -> Result<Vec<u8>, MapFormatError> {
+    let mut payload = payload.to_vec();
+    payload.extend_from_slice(&0u32.to_le_bytes());
+    Ok(payload)
+}
+
+impl MapFormatMigrations {
+    /// Register (or replace) the migration from version `from` to the next version.
+    pub fn register(&mut self, from: MapFormatVersion, migration: MapFormatMigration) {
+        self.migrations.insert(from, migration);
+    }
+
+    /// Upgrade `bytes` (including header) to a payload of [`MapFormatVersion::CURRENT`].
+    pub fn migrate(&self, bytes: &[u8]) -> Result<Vec<u8>, MapFormatError> {
+        let mut version = MapFormatVersion::of(bytes)?;
+        if version > MapFormatVersion::CURRENT {
+            return Err(MapFormatError::UnsupportedVersion(version));
+        }
+        let mut payload = bytes[HEADER_LEN..].to_vec();
+        while version < MapFormatVersion::CURRENT {
+            let migration = self
+                .migrations
+                .get(&version)
+                .ok_or(MapFormatError::MissingMigration(version))?;
+            payload = migration(&payload)?;
+            version.0 += 1;
+        }
+        Ok(payload)
+    }
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> Result<u32, MapFormatError> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(MapFormatError::Truncated)
+}
+
+/// Reads consecutive values from a payload.
+struct PayloadReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PayloadReader<'a> {
+    fn u32(&mut self) -> Result<u32, MapFormatError> {
+        let v = read_u32(self.bytes, self.offset)?;
+        self.offset += 4;
+        Ok(v)
+    }
+
+    fn f32(&mut self) -> Result<f32, MapFormatError> {
+        self.u32().map(f32::from_bits)
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, MapFormatError> {
+        Ok(vec2(self.f32()?, self.f32()?))
+    }
+
+    /// Bytes prefixed with their length.
+    fn bytes(&mut self) -> Result<&'a [u8], MapFormatError> {
+        let len = self.u32()? as usize;
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + len)
+            .ok_or(MapFormatError::Truncated)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+fn write_vec2(bytes: &mut Vec<u8>, v: Vec2) {
+    bytes.extend_from_slice(&v.x.to_le_bytes());
+    bytes.extend_from_slice(&v.y.to_le_bytes());
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+impl<C: Customization> Map<C> {
+    /// Encode the tiles and settings of this map in the native map format
+    /// (of version [`MapFormatVersion::CURRENT`]), see the [module docs](self) for what is stored.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = self.map_size();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + 8 + self.map_texture.len() * 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&MapFormatVersion::CURRENT.0.to_le_bytes());
+        bytes.extend_from_slice(&size.x.to_le_bytes());
+        bytes.extend_from_slice(&size.y.to_le_bytes());
+        for index in self.map_texture.iter() {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let u = &self.map_uniform;
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        write_vec2(&mut bytes, u.tile_size);
+        for v in u.projection.to_cols_array() {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        write_vec2(&mut bytes, u.tile_anchor_point);
+        write_vec2(&mut bytes, u.inner_padding);
+        write_vec2(&mut bytes, u.outer_padding_topleft);
+        write_vec2(&mut bytes, u.outer_padding_bottomright);
+        bytes.extend_from_slice(&u.atlas_tile_size_factor.to_le_bytes());
+
+        let atlas_path = self
+            .atlas_texture
+            .path()
+            .map(|path| path.to_string())
+            .unwrap_or_default();
+        write_bytes(&mut bytes, atlas_path.as_bytes());
+
+        let mut user_data = StorageBuffer::new(Vec::new());
+        if user_data.write(&self.user_data).is_err() {
+            warn!("Could not encode map user data");
+        }
+        write_bytes(&mut bytes, &user_data.into_inner());
+        bytes
+    }
+
+    /// Create a map from data in the native map format, eg. a level written with
+    /// [`Self::to_bytes`]. Only the settings listed in the [module docs](self) are restored,
+    /// the map is drawn with `atlas_texture` regardless of the atlas path stored in the data.
+    pub fn from_bytes(bytes: &[u8], atlas_texture: Handle<Image>) -> Result<Self, MapFormatError>
+    where
+        C::UserData: CreateFrom,
+    {
+        Self::decode(bytes, &MapFormatMigrations::default(), |_| {
+            Ok(atlas_texture)
+        })
+    }
+
+    /// Decode a map, `atlas` receives the atlas path stored in the data (if any).
+    fn decode(
+        bytes: &[u8],
+        migrations: &MapFormatMigrations,
+        atlas: impl FnOnce(Option<&str>) -> Result<Handle<Image>, MapFormatError>,
+    ) -> Result<Self, MapFormatError>
+    where
+        C::UserData: CreateFrom,
+    {
+        let payload = migrations.migrate(bytes)?;
+        let mut r = PayloadReader {
+            bytes: &payload,
+            offset: 0,
+        };
+
+        let size = uvec2(r.u32()?, r.u32()?);
+        let n = (size.x * size.y) as usize;
+        let tiles = (0..n).map(|_| r.u32()).collect::<Result<Vec<_>, _>>()?;
+
+        if r.u32()? == 0 {
+            return Err(MapFormatError::MissingSettings);
+        }
+        let tile_size = r.vec2()?;
+        let mut cols = [0.0; 9];
+        for v in cols.iter_mut() {
+            *v = r.f32()?;
+        }
+        let projection = TileProjection {
+            projection: Mat3::from_cols_array(&cols),
+            tile_anchor_point: r.vec2()?,
+        };
+        let (inner, topleft, bottomright) = (r.vec2()?, r.vec2()?, r.vec2()?);
+        let atlas_tile_size_factor = r.u32()? as i32;
+        let atlas_path = String::from_utf8_lossy(r.bytes()?).into_owned();
+        let user_data = StorageBuffer::new(r.bytes()?)
+            .create::<C::UserData>()
+            .map_err(|_| MapFormatError::InvalidUserData)?;
+
+        let atlas_texture = atlas((!atlas_path.is_empty()).then_some(atlas_path.as_str()))?;
+        Ok(MapBuilder::new(size, atlas_texture, tile_size)
+            .with_projection(projection)
+            .with_padding(inner, topleft, bottomright)
+            .with_atlas_tile_size_factor(atlas_tile_size_factor)
+            .with_user_data(user_data)
+            .build_and_set(|p| tiles[(p.y * size.x + p.x) as usize]))
+    }
+
+    /// Load tiles from data in the native map format, migrating older versions with the
+    /// migrations shipped with this crate.
+    /// The data must have been written for a map of the same size.
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), MapFormatError> {
+        self.load_bytes_with(bytes, &MapFormatMigrations::default())
+    }
+
+    /// Same as [`Self::load_bytes`] with a custom migration registry.
+    pub fn load_bytes_with(
+        &mut self,
+        bytes: &[u8],
+        migrations: &MapFormatMigrations,
+    ) -> Result<(), MapFormatError> {
+        let payload = migrations.migrate(bytes)?;
+
+        let found = uvec2(read_u32(&payload, 0)?, read_u32(&payload, 4)?);
+        let expected = self.map_size();
+        if found != expected {
+            return Err(MapFormatError::SizeMismatch { expected, found });
+        }
+
+        let n = (found.x * found.y) as usize;
+        if payload.len() < 8 + n * 4 {
+            return Err(MapFormatError::Truncated);
+        }
+
+        let mut m = self.indexer_mut();
+        for i in 0..n {
+            let index = read_u32(&payload, 8 + i * 4)?;
+            m.set(i as u32 % found.x, i as u32 / found.x, index);
+        }
+        Ok(())
+    }
+}
+
+/// Plugin for loading `.bftm` files written by [`Map::to_bytes`] as [`Map`] assets.
+/// The atlas is loaded from the atlas path stored in the file.
+pub type MapFormatPlugin = CustomMapFormatPlugin<NoCustomization>;
+
+/// Same as [`MapFormatPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomMapFormatPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomMapFormatPlugin<C>
+where
+    C::UserData: CreateFrom,
+{
+    fn build(&self, app: &mut App) {
+        app.register_asset_loader(MapLoader::<C>::default());
+    }
+}
+
+/// Loads `.bftm` files as [`Map`]s, see [`MapFormatPlugin`].
+pub struct MapLoader<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Default for MapLoader<C> {
+    fn default() -> Self {
+        Self {
+            _customization: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<C: Customization> AssetLoader for MapLoader<C>
+where
+    C::UserData: CreateFrom,
+{
+    type Asset = Map<C>;
+    type Settings = ();
+    type Error = MapLoadError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Map<C>, MapLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let map = Map::decode(&bytes, &MapFormatMigrations::default(), |path| {
+            path.map(|path| load_context.load(path.to_string()))
+                .ok_or(MapFormatError::MissingAtlas)
+        })?;
+        Ok(map)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bftm"]
+    }
+}
@@ -0,0 +1,181 @@
+//! Line of sight / field of view over the map grid.
+
+use bevy::{math::ivec2, prelude::*};
+
+use super::{map::Map, plugin::Customization};
+
+/// Octant transformations (xx, xy, yx, yy) for shadowcasting.
+const OCTANTS: [[i32; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+/// Set of tiles visible from an origin, computed with recursive shadowcasting.
+///
+/// Opaque tiles that are hit by light are visible themselves (walls are seen), tiles behind
+/// them are not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldOfView {
+    size: UVec2,
+    visible: Vec<bool>,
+}
+
+impl FieldOfView {
+    /// Compute the tiles visible from `origin` within `radius` tiles (euclidean distance) on a
+    /// grid of the given size. `is_opaque` tells whether a tile blocks sight.
+    pub fn compute(
+        size: UVec2,
+        origin: UVec2,
+        radius: u32,
+        is_opaque: impl Fn(UVec2) -> bool,
+    ) -> Self {
+        let mut fov = Self {
+            size,
+            visible: vec![false; (size.x * size.y) as usize],
+        };
+        if origin.x >= size.x || origin.y >= size.y {
+            return fov;
+        }
+        fov.mark(origin.as_ivec2());
+        for octant in OCTANTS.iter() {
+            fov.cast_light(
+                origin.as_ivec2(),
+                1,
+                1.0,
+                0.0,
+                radius as i32,
+                octant,
+                &is_opaque,
+            );
+        }
+        fov
+    }
+
+    fn index(&self, p: IVec2) -> Option<usize> {
+        (p.x >= 0 && p.y >= 0 && p.x < self.size.x as i32 && p.y < self.size.y as i32)
+            .then(|| (p.y as u32 * self.size.x + p.x as u32) as usize)
+    }
+
+    fn mark(&mut self, p: IVec2) {
+        if let Some(i) = self.index(p) {
+            self.visible[i] = true;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn cast_light(
+        &mut self,
+        origin: IVec2,
+        row: i32,
+        mut start: f32,
+        end: f32,
+        radius: i32,
+        octant: &[i32; 4],
+        is_opaque: &impl Fn(UVec2) -> bool,
+    ) {
+        if start < end {
+            return;
+        }
+        let [xx, xy, yx, yy] = *octant;
+        let mut new_start = 0.0;
+
+        for j in row..=radius {
+            let dy = -j;
+            let mut blocked = false;
+            for dx in -j..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                }
+                if end > left_slope {
+                    break;
+                }
+
+                let p = origin + ivec2(dx * xx + dy * xy, dx * yx + dy * yy);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.mark(p);
+                }
+                let opaque = self
+                    .index(p)
+                    .map(|_| is_opaque(p.as_uvec2()))
+                    .unwrap_or(true);
+
+                if blocked {
+                    if opaque {
+                        new_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = new_start;
+                    }
+                } else if opaque && j < radius {
+                    blocked = true;
+                    self.cast_light(origin, j + 1, start, left_slope, radius, octant, is_opaque);
+                    new_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn is_visible(&self, pos: UVec2) -> bool {
+        self.index(pos.as_ivec2())
+            .map(|i| self.visible[i])
+            .unwrap_or(false)
+    }
+
+    /// All visible tiles.
+    pub fn iter(&self) -> impl Iterator<Item = UVec2> + '_ {
+        let width = self.size.x;
+        self.visible
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v)
+            .map(move |(i, _)| UVec2::new(i as u32 % width, i as u32 / width))
+    }
+
+    /// One byte per tile (row by row), `255` for visible and `0` for hidden tiles,
+    /// eg. for uploading as fog of war texture.
+    pub fn to_mask(&self) -> Vec<u8> {
+        self.visible
+            .iter()
+            .map(|v| if *v { 255 } else { 0 })
+            .collect()
+    }
+
+    /// Mark all tiles visible in `other` as visible in `self`,
+    /// eg. for combining the views of several units.
+    pub fn merge(&mut self, other: &FieldOfView) {
+        for (v, o) in self.visible.iter_mut().zip(other.visible.iter()) {
+            *v |= *o;
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Field of view from `origin` on this map, where `is_opaque` decides by atlas index
+    /// which tiles block sight. See [`FieldOfView::compute`].
+    pub fn field_of_view(
+        &self,
+        origin: UVec2,
+        radius: u32,
+        is_opaque: impl Fn(u32) -> bool,
+    ) -> FieldOfView {
+        let indexer = self.indexer();
+        FieldOfView::compute(self.map_size(), origin, radius, |p| {
+            is_opaque(indexer.at_uvec(p))
+        })
+    }
+}
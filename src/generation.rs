@@ -0,0 +1,192 @@
+use std::sync::Arc;
+
+use bevy::{
+    math::URect,
+    prelude::*,
+    render::view::RenderLayers,
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    chunk::{camera_world_rects, MapChunks, ViewCamera},
+    map::Map,
+    plugin::Customization,
+};
+
+type GenerateFn = dyn Fn(UVec2, URect) -> Vec<u32> + Send + Sync;
+
+/// Procedurally generate the chunks of a map (see [`MapChunks`]) as they come into view.
+///
+/// The generator callback receives the chunk coordinate and the tiles it covers (`max`
+/// exclusive) and returns the tile values of that rectangle row by row. It runs on the
+/// [`AsyncComputeTaskPool`], at most `max_tasks` chunks at a time, closest to the camera first.
+/// Chunks that leave the view before their generation finished are cancelled and queued again
+/// when they come back. Finished chunks are written into the map and reported with
+/// [`ChunkGenerated`].
+#[derive(Component)]
+pub struct ChunkGenerator {
+    generate: Arc<GenerateFn>,
+    /// Maximum number of chunks generated concurrently.
+    pub max_tasks: usize,
+    generated: HashSet<UVec2>,
+    running: HashMap<UVec2, Task<Vec<u32>>>,
+}
+
+impl ChunkGenerator {
+    pub fn new(generate: impl Fn(UVec2, URect) -> Vec<u32> + Send + Sync + 'static) -> Self {
+        Self {
+            generate: Arc::new(generate),
+            max_tasks: 4,
+            generated: default(),
+            running: default(),
+        }
+    }
+
+    pub fn with_max_tasks(self, max_tasks: usize) -> Self {
+        Self {
+            max_tasks: max_tasks.max(1),
+            ..self
+        }
+    }
+
+    pub fn is_generated(&self, chunk: UVec2) -> bool {
+        self.generated.contains(&chunk)
+    }
+
+    /// Chunks currently being generated.
+    pub fn pending(&self) -> impl Iterator<Item = UVec2> + '_ {
+        self.running.keys().copied()
+    }
+
+    /// Generate the given chunk again the next time it is visible.
+    pub fn regenerate(&mut self, chunk: UVec2) {
+        self.generated.remove(&chunk);
+        self.running.remove(&chunk);
+    }
+
+    /// Generate all chunks again (eg. after changing the seed captured by the generator).
+    pub fn regenerate_all(&mut self) {
+        self.generated.clear();
+        self.running.clear();
+    }
+}
+
+/// A chunk of `map` was generated by its [`ChunkGenerator`] and written into the map.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkGenerated {
+    pub map: Entity,
+    pub chunk: UVec2,
+}
+
+/// Cancel generation of chunks out of view, collect finished chunks and start generating
+/// visible chunks closest to a camera first.
+pub(crate) fn update_chunk_generation<C: Customization>(
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut maps: Query<(
+        Entity,
+        &Handle<Map<C>>,
+        &GlobalTransform,
+        &MapChunks,
+        &mut ChunkGenerator,
+        Option<&RenderLayers>,
+    )>,
+    cameras: Query<ViewCamera>,
+    mut generated: EventWriter<ChunkGenerated>,
+) {
+    for (entity, map_handle, map_transform, chunks, mut generator, layers) in maps.iter_mut() {
+        let generator = generator.as_mut();
+        // Dropping a task cancels it
+        generator
+            .running
+            .retain(|chunk, _| chunks.is_visible(*chunk));
+
+        let finished: Vec<_> = generator
+            .running
+            .iter_mut()
+            .filter_map(|(&chunk, task)| {
+                block_on(future::poll_once(task)).map(|tiles| (chunk, tiles))
+            })
+            .collect();
+        for (chunk, _) in finished.iter() {
+            generator.running.remove(chunk);
+        }
+
+        if !finished.is_empty() {
+            let Some(map) = map_materials.get_mut(map_handle) else {
+                continue;
+            };
+            let map_size = map.map_size();
+            let mut m = map.indexer_mut();
+            for (chunk, tiles) in finished {
+                let rect = chunks.chunk_rect(chunk, map_size);
+                if tiles.len() != rect.width() as usize * rect.height() as usize {
+                    warn!(
+                        "Chunk generator returned {} tiles for chunk {} of {}x{} tiles",
+                        tiles.len(),
+                        chunk,
+                        rect.width(),
+                        rect.height()
+                    );
+                    continue;
+                }
+                for (i, value) in tiles.into_iter().enumerate() {
+                    let i = i as u32;
+                    m.set(
+                        rect.min.x + i % rect.width(),
+                        rect.min.y + i / rect.width(),
+                        value,
+                    );
+                }
+                generator.generated.insert(chunk);
+                generated.send(ChunkGenerated { map: entity, chunk });
+            }
+        }
+
+        let free = generator.max_tasks.saturating_sub(generator.running.len());
+        if free == 0 {
+            continue;
+        }
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+
+        // Distance of each chunk to the nearest camera, in tiles
+        let inverse = map_transform.affine().inverse();
+        let camera_positions: Vec<_> = camera_world_rects(cameras.iter(), layers)
+            .iter()
+            .map(|rect| {
+                let local = inverse
+                    .transform_point3(rect.center().extend(0.0))
+                    .truncate();
+                map.local_to_map(local)
+            })
+            .collect();
+        let map_size = map.map_size();
+        let distance = |chunk: UVec2| {
+            let rect = chunks.chunk_rect(chunk, map_size);
+            let center = (rect.min + rect.max).as_vec2() * 0.5;
+            camera_positions
+                .iter()
+                .map(|camera| camera.distance_squared(center))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut queue: Vec<_> = chunks
+            .visible()
+            .filter(|chunk| {
+                !generator.generated.contains(chunk) && !generator.running.contains_key(chunk)
+            })
+            .map(|chunk| (distance(chunk), chunk))
+            .collect();
+        queue.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let pool = AsyncComputeTaskPool::get();
+        for (_, chunk) in queue.into_iter().take(free) {
+            let rect = chunks.chunk_rect(chunk, map_size);
+            let generate = generator.generate.clone();
+            let task = pool.spawn(async move { generate(chunk, rect) });
+            generator.running.insert(chunk, task);
+        }
+    }
+}
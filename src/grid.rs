@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+/// Cumulative column widths / row heights of a map whose rows or columns are not all of the same
+/// size.
+///
+/// Sizes are relative to the tile size, i.e. a row height of `2.0` means that row is twice as high
+/// as the map's tile size, tiles in it will be stretched accordingly.
+///
+/// Internally, map positions are converted to "linear" positions before projection,
+/// that is the position a uniform grid with the same total extent would have.
+/// This keeps the projection (and thus vertex interpolation) linear, the shader only needs to
+/// look up which cell a linear position falls into.
+#[derive(Debug, Clone, Default, Reflect)]
+pub(crate) struct VariableGrid {
+    /// `column_offsets[i]` is the summed width of all columns before column `i`,
+    /// so there is one more entry than columns.
+    column_offsets: Vec<f32>,
+    /// Same as `column_offsets` for rows.
+    row_offsets: Vec<f32>,
+}
+
+impl VariableGrid {
+    /// Create from column widths and row heights (relative to tile size).
+    /// Missing entries are assumed to be `1.0`, so an empty vector means uniform size.
+    pub(crate) fn new(map_size: UVec2, column_widths: &[f32], row_heights: &[f32]) -> Self {
+        Self {
+            column_offsets: Self::prefix_sums(map_size.x, column_widths),
+            row_offsets: Self::prefix_sums(map_size.y, row_heights),
+        }
+    }
+
+    /// Column widths and row heights, as passed to [`Self::new`].
+    pub(crate) fn sizes(&self) -> (Vec<f32>, Vec<f32>) {
+        let sizes = |offsets: &[f32]| offsets.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+        (sizes(&self.column_offsets), sizes(&self.row_offsets))
+    }
+
+    fn prefix_sums(n: u32, sizes: &[f32]) -> Vec<f32> {
+        let mut offsets = Vec::with_capacity(n as usize + 1);
+        let mut sum = 0.0;
+        offsets.push(sum);
+        for i in 0..n as usize {
+            sum += sizes.get(i).copied().unwrap_or(1.0).max(f32::EPSILON);
+            offsets.push(sum);
+        }
+        offsets
+    }
+
+    /// Data as it is uploaded to the shader: column offsets followed by row offsets.
+    pub(crate) fn shader_data(&self) -> Vec<f32> {
+        let mut v = self.column_offsets.clone();
+        v.extend_from_slice(&self.row_offsets);
+        v
+    }
+
+    /// Total size of the grid in (uniform) tiles.
+    pub(crate) fn extent(&self) -> Vec2 {
+        Vec2::new(
+            *self.column_offsets.last().unwrap(),
+            *self.row_offsets.last().unwrap(),
+        )
+    }
+
+    /// Map position -> linear position
+    pub(crate) fn map_to_linear(&self, map_position: Vec2) -> Vec2 {
+        Vec2::new(
+            Self::axis_to_linear(&self.column_offsets, map_position.x),
+            Self::axis_to_linear(&self.row_offsets, map_position.y),
+        )
+    }
+
+    /// Linear position -> map position
+    pub(crate) fn linear_to_map(&self, linear: Vec2) -> Vec2 {
+        Vec2::new(
+            Self::linear_to_axis(&self.column_offsets, linear.x),
+            Self::linear_to_axis(&self.row_offsets, linear.y),
+        )
+    }
+
+    fn axis_to_linear(offsets: &[f32], t: f32) -> f32 {
+        let n = offsets.len() - 1;
+        if t < 0.0 {
+            return t;
+        }
+        if t >= n as f32 {
+            // Beyond the map all cells have uniform size
+            return offsets[n] + t - n as f32;
+        }
+        let i = t as usize;
+        offsets[i] + (t - i as f32) * (offsets[i + 1] - offsets[i])
+    }
+
+    fn linear_to_axis(offsets: &[f32], l: f32) -> f32 {
+        let n = offsets.len() - 1;
+        if l < 0.0 {
+            return l;
+        }
+        if l >= offsets[n] {
+            return n as f32 + l - offsets[n];
+        }
+        let i = offsets.partition_point(|&o| o <= l) - 1;
+        i as f32 + (l - offsets[i]) / (offsets[i + 1] - offsets[i])
+    }
+}
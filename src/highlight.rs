@@ -0,0 +1,91 @@
+use bevy::{math::URect, prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization};
+
+/// Highlighted tile regions of a map, drawn as outlines following the map's projection.
+///
+/// Highlights are grouped by a key so independent systems (eg. a tile cursor and a placement
+/// preview) can each manage their own highlights without overwriting each other.
+///
+/// Drawing uses bevy gizmos and thus requires `GizmoPlugin` (part of `DefaultPlugins`).
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapHighlights {
+    groups: HashMap<String, Vec<TileHighlight>>,
+}
+
+/// A rectangular region of tiles to highlight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileHighlight {
+    /// Tiles to highlight, `max` is exclusive.
+    pub rect: URect,
+    pub color: Color,
+}
+
+impl TileHighlight {
+    /// Highlight a single tile.
+    pub fn tile(tile: UVec2, color: Color) -> Self {
+        Self {
+            rect: URect::from_corners(tile, tile + UVec2::ONE),
+            color,
+        }
+    }
+}
+
+impl MapHighlights {
+    /// Replace all highlights of the given group.
+    pub fn set(&mut self, group: impl Into<String>, highlights: Vec<TileHighlight>) {
+        self.groups.insert(group.into(), highlights);
+    }
+
+    /// Remove all highlights of the given group.
+    pub fn clear(&mut self, group: &str) {
+        self.groups.remove(group);
+    }
+
+    pub fn get(&self, group: &str) -> &[TileHighlight] {
+        self.groups
+            .get(group)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// All highlights of all groups.
+    pub fn iter(&self) -> impl Iterator<Item = &TileHighlight> {
+        self.groups.values().flatten()
+    }
+}
+
+/// Corners of the given tile rectangle in world coordinates, in outline order.
+pub(crate) fn tile_rect_outline<C: Customization>(
+    map: &Map<C>,
+    map_transform: &GlobalTransform,
+    rect: URect,
+) -> [Vec2; 4] {
+    let min = rect.min.as_vec2();
+    let max = rect.max.as_vec2();
+    [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)].map(|corner| {
+        map_transform
+            .transform_point(map.map_to_local(corner).extend(0.0))
+            .truncate()
+    })
+}
+
+/// Draw outlines for all [`MapHighlights`].
+pub fn draw_map_highlights<C: Customization>(
+    mut gizmos: Gizmos,
+    map_materials: Res<Assets<Map<C>>>,
+    maps: Query<(&Handle<Map<C>>, &GlobalTransform, &MapHighlights)>,
+) {
+    for (map_handle, map_transform, highlights) in maps.iter() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        for highlight in highlights.iter() {
+            let outline = tile_rect_outline(map, map_transform, highlight.rect);
+            gizmos.linestrip_2d(
+                outline.into_iter().chain(std::iter::once(outline[0])),
+                highlight.color,
+            );
+        }
+    }
+}
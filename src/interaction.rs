@@ -0,0 +1,296 @@
+use bevy::{
+    prelude::*,
+    render::{camera::RenderTarget, view::RenderLayers},
+    utils::HashMap,
+    window::{PrimaryWindow, WindowRef},
+};
+
+use super::{
+    map::Map,
+    plugin::{Customization, NoCustomization},
+    render_layers::renders_layers,
+};
+
+/// Optional plugin emitting high-level tile interaction events:
+/// [`TileClicked`], [`TileHoverStarted`], [`TileHoverEnded`], [`TileDragged`] and
+/// [`TileDragEnded`].
+///
+/// Tiles are determined with [`Map::pick_tile`], so overhanging tiles are picked the way they are
+/// seen. Events are emitted for all visible maps under the cursor, filter by `map` if you only care
+/// about some of them. With several cameras, the cursor is converted through the topmost camera
+/// under the cursor that renders the map (see [`RenderLayers`]), so eg. maps shown in a minimap
+/// viewport are picked through the minimap camera.
+pub type TileInteractionPlugin = CustomTileInteractionPlugin<NoCustomization>;
+
+/// Same as [`TileInteractionPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomTileInteractionPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomTileInteractionPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileInteractionSettings>()
+            .add_event::<TileClicked>()
+            .add_event::<TileHoverStarted>()
+            .add_event::<TileHoverEnded>()
+            .add_event::<TileDragged>()
+            .add_event::<TileDragEnded>()
+            .add_systems(Update, update_tile_interaction::<C>);
+    }
+}
+
+/// Settings for [`TileInteractionPlugin`].
+#[derive(Resource, Debug, Clone, Reflect)]
+#[reflect(Resource)]
+pub struct TileInteractionSettings {
+    /// Time (in seconds) the cursor has to stay on a tile before hover events are sent.
+    /// Avoids a flood of hover events when moving the cursor quickly across the map.
+    pub hover_debounce: f32,
+    /// Distance (in logical pixels) the cursor has to move while a button is pressed
+    /// for the interaction to be considered a drag instead of a click.
+    pub drag_threshold: f32,
+}
+
+impl Default for TileInteractionSettings {
+    fn default() -> Self {
+        Self {
+            hover_debounce: 0.05,
+            drag_threshold: 4.0,
+        }
+    }
+}
+
+/// A tile was clicked (pressed and released on the same tile without dragging).
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct TileClicked {
+    pub map: Entity,
+    pub pos: UVec2,
+    pub button: MouseButton,
+    /// Position within the tile, see [`crate::picking::TilePick::uv`].
+    pub uv: Vec2,
+}
+
+/// The cursor started hovering over a tile.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileHoverStarted {
+    pub map: Entity,
+    pub pos: UVec2,
+}
+
+/// The cursor stopped hovering over a tile.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileHoverEnded {
+    pub map: Entity,
+    pub pos: UVec2,
+}
+
+/// The cursor was dragged onto a new tile with a button held down.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileDragged {
+    pub map: Entity,
+    pub button: MouseButton,
+    /// Tile the drag started on.
+    pub start: UVec2,
+    /// Tile the cursor is on now.
+    pub pos: UVec2,
+}
+
+/// A drag ended (the button was released).
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileDragEnded {
+    pub map: Entity,
+    pub button: MouseButton,
+    pub start: UVec2,
+    /// Last tile the cursor was on, if it is still on the map.
+    pub pos: Option<UVec2>,
+}
+
+#[derive(Default)]
+struct HoverState {
+    /// Tile the cursor is on right now
+    candidate: Option<UVec2>,
+    /// Time at which `candidate` was last changed
+    since: f32,
+    /// Tile for which the last hover event was sent
+    hovered: Option<UVec2>,
+}
+
+struct PressState {
+    tile: Option<UVec2>,
+    cursor: Vec2,
+    dragging: bool,
+    last: Option<UVec2>,
+}
+
+#[derive(Default)]
+pub(crate) struct InteractionState {
+    hover: HashMap<Entity, HoverState>,
+    presses: HashMap<(Entity, MouseButton), PressState>,
+}
+
+/// Convert a cursor position in the primary window to world coordinates,
+/// using the topmost active camera whose viewport contains the cursor.
+pub(crate) fn cursor_to_world<'a>(
+    cursor: Vec2,
+    cameras: impl Iterator<Item = (&'a Camera, &'a GlobalTransform)>,
+) -> Option<Vec2> {
+    let mut cameras: Vec<_> = cameras
+        .filter(|(camera, _)| {
+            camera.is_active && matches!(camera.target, RenderTarget::Window(WindowRef::Primary))
+        })
+        .collect();
+    cameras.sort_by_key(|(camera, _)| std::cmp::Reverse(camera.order));
+
+    cameras.into_iter().find_map(|(camera, transform)| {
+        let mut position = cursor;
+        if let Some(rect) = camera.logical_viewport_rect() {
+            if !rect.contains(cursor) {
+                return None;
+            }
+            position -= rect.min;
+        }
+        camera.viewport_to_world_2d(transform, position)
+    })
+}
+
+/// Same as [`cursor_to_world`], only considering cameras that render the given layers.
+fn cursor_to_world_on_layers<'a>(
+    cursor: Vec2,
+    cameras: impl Iterator<Item = (&'a Camera, &'a GlobalTransform, Option<&'a RenderLayers>)>,
+    layers: &RenderLayers,
+) -> Option<Vec2> {
+    cursor_to_world(
+        cursor,
+        cameras
+            .filter(|(_, _, camera_layers)| renders_layers(*camera_layers, Some(layers)))
+            .map(|(camera, transform, _)| (camera, transform)),
+    )
+}
+
+type InteractiveMap<'a, C> = (
+    Entity,
+    &'a Handle<Map<C>>,
+    &'a GlobalTransform,
+    &'a InheritedVisibility,
+    Option<&'a RenderLayers>,
+);
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_tile_interaction<C: Customization>(
+    mut state: Local<InteractionState>,
+    time: Res<Time>,
+    settings: Res<TileInteractionSettings>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform, Option<&RenderLayers>)>,
+    maps: Query<InteractiveMap<C>>,
+    map_materials: Res<Assets<Map<C>>>,
+    images: Res<Assets<Image>>,
+    mut clicked: EventWriter<TileClicked>,
+    mut hover_started: EventWriter<TileHoverStarted>,
+    mut hover_ended: EventWriter<TileHoverEnded>,
+    mut dragged: EventWriter<TileDragged>,
+    mut drag_ended: EventWriter<TileDragEnded>,
+) {
+    let now = time.elapsed_seconds();
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let default_layers = RenderLayers::default();
+
+    state.hover.retain(|entity, _| maps.contains(*entity));
+    state
+        .presses
+        .retain(|(entity, _), _| maps.contains(*entity));
+
+    for (entity, map_handle, map_transform, visibility, layers) in maps.iter() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+
+        // Hidden maps are treated like maps that are not under the cursor
+        let world = cursor.filter(|_| visibility.get()).and_then(|c| {
+            cursor_to_world_on_layers(c, cameras.iter(), layers.unwrap_or(&default_layers))
+        });
+        let pick = world.and_then(|world| map.pick_tile(map_transform, world, &images));
+        let current = pick.map(|p| p.tile);
+
+        // Hover, debounced
+
+        let hover = state.hover.entry(entity).or_default();
+        if current != hover.candidate {
+            hover.candidate = current;
+            hover.since = now;
+        }
+        if hover.candidate != hover.hovered && now - hover.since >= settings.hover_debounce {
+            if let Some(pos) = hover.hovered {
+                hover_ended.send(TileHoverEnded { map: entity, pos });
+            }
+            if let Some(pos) = hover.candidate {
+                hover_started.send(TileHoverStarted { map: entity, pos });
+            }
+            hover.hovered = hover.candidate;
+        }
+
+        // Clicks & drags
+
+        let Some(cursor) = cursor else {
+            continue;
+        };
+
+        for &button in buttons.get_just_pressed() {
+            state.presses.insert(
+                (entity, button),
+                PressState {
+                    tile: current,
+                    cursor,
+                    dragging: false,
+                    last: current,
+                },
+            );
+        }
+
+        for &button in buttons.get_pressed() {
+            let Some(press) = state.presses.get_mut(&(entity, button)) else {
+                continue;
+            };
+            if !press.dragging && cursor.distance(press.cursor) > settings.drag_threshold {
+                press.dragging = true;
+            }
+            if press.dragging && current != press.last {
+                if let (Some(start), Some(pos)) = (press.tile, current) {
+                    dragged.send(TileDragged {
+                        map: entity,
+                        button,
+                        start,
+                        pos,
+                    });
+                }
+                press.last = current;
+            }
+        }
+
+        for &button in buttons.get_just_released() {
+            let Some(press) = state.presses.remove(&(entity, button)) else {
+                continue;
+            };
+            let Some(start) = press.tile else {
+                continue;
+            };
+            if press.dragging {
+                drag_ended.send(TileDragEnded {
+                    map: entity,
+                    button,
+                    start,
+                    pos: current,
+                });
+            } else if let Some(pick) = pick.filter(|p| p.tile == start) {
+                clicked.send(TileClicked {
+                    map: entity,
+                    pos: pick.tile,
+                    button,
+                    uv: pick.uv,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+
+/// Parent component for grouping maps (eg. all layers of one building floor).
+///
+/// Opacity and tint of the group are multiplied into the mix color of all descendant maps
+/// (nested groups combine), so fading out a whole group is a single write.
+/// Transform and visibility cascade through bevy's regular hierarchy,
+/// so give the group entity a `SpatialBundle` and toggle its `Visibility`.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct MapLayerGroup {
+    /// Multiplied into the alpha of all maps in the group.
+    pub opacity: f32,
+    /// Multiplied into the color of all maps in the group.
+    pub tint: Color,
+}
+
+impl Default for MapLayerGroup {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            tint: Color::WHITE,
+        }
+    }
+}
+
+impl MapLayerGroup {
+    fn mix_color(&self) -> Vec4 {
+        let tint = self.tint.to_linear().to_vec4();
+        tint.with_w(tint.w * self.opacity)
+    }
+}
+
+/// Combined mix color of all [`MapLayerGroup`]s above `entity` in the hierarchy.
+pub(crate) fn layer_group_mix_color(
+    entity: Entity,
+    parents: &Query<&Parent>,
+    groups: &Query<&MapLayerGroup>,
+) -> Vec4 {
+    parents
+        .iter_ancestors(entity)
+        .filter_map(|ancestor| groups.get(ancestor).ok())
+        .fold(Vec4::ONE, |c, group| c * group.mix_color())
+}
@@ -0,0 +1,612 @@
+//! Import of [LDtk](https://ldtk.io) projects (requires the `ldtk` feature).
+//!
+//! `.ldtk` files are loaded as [`LdtkProject`] assets, spawn their levels with
+//! [`LdtkSpawner`]:
+//!
+//! ```ignore
+//! app.add_plugins(LdtkPlugin);
+//! commands.spawn((
+//!     SpatialBundle::default(),
+//!     LdtkSpawner::new(asset_server.load("world.ldtk")),
+//! ));
+//! ```
+//!
+//! Entities and custom fields are not spawned, look them up through the [`LdtkLevelInstance`]
+//! of each spawned level.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, LoadContext},
+    math::uvec2,
+    prelude::*,
+};
+use serde_json::Value;
+
+use super::{
+    bundle::MapBundleManaged,
+    error::LdtkError,
+    flip::{TILE_FLIP_MASK, TILE_FLIP_X, TILE_FLIP_Y},
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Plugin for loading `.ldtk` files as [`LdtkProject`] assets and spawning them via
+/// [`LdtkSpawner`].
+pub type LdtkPlugin = CustomLdtkPlugin<NoCustomization>;
+
+/// Plugin for loading `.ldtk` files as [`LdtkProject`] assets and spawning them via
+/// [`LdtkSpawner`].
+#[derive(Default)]
+pub struct CustomLdtkPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomLdtkPlugin<C> {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Assets<LdtkProject>>() {
+            app.init_asset::<LdtkProject>()
+                .register_asset_loader(LdtkLoader);
+        }
+        app.add_systems(Update, spawn_ldtk_levels::<C>);
+    }
+}
+
+/// Tileset of an LDtk project.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct LdtkTileset {
+    pub uid: i64,
+    pub image: Handle<Image>,
+    /// Size of the tileset image in pixels.
+    pub image_size: Vec2,
+    pub tile_size: f32,
+    /// Space between tiles in pixels (inner padding).
+    pub spacing: f32,
+    /// Space around the tiles in pixels (outer padding).
+    pub padding: f32,
+    /// Number of tiles per row and column.
+    pub n_tiles: UVec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum LdtkLayerKind {
+    IntGrid,
+    Tiles,
+    AutoLayer,
+}
+
+/// Grid layer of an LDtk level.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct LdtkLayer {
+    pub identifier: String,
+    pub kind: LdtkLayerKind,
+    /// Size in cells.
+    pub size: UVec2,
+    pub grid_size: f32,
+    /// Offset of the layer from the level's top left corner in pixels (y down).
+    pub offset: Vec2,
+    pub opacity: f32,
+    pub visible: bool,
+    /// Uid of the tileset of [`Self::tiles`].
+    pub tileset: Option<i64>,
+    /// IntGrid values row by row, `0` for empty cells. Empty for layers without IntGrid.
+    pub int_grid: Vec<i32>,
+    /// Tileset tile ids plus one (including flip flags, see [`crate::flip`]) row by row,
+    /// `0` for no tile. Holds the regular tiles as well as the auto-layer tiles of IntGrid
+    /// layers. Where several tiles are stacked in one cell, this holds the bottom one.
+    pub tiles: Vec<u32>,
+    /// Tiles stacked on top of [`Self::tiles`], bottom first, each like [`Self::tiles`].
+    /// Empty if no cell has more than one tile.
+    pub stacked_tiles: Vec<Vec<u32>>,
+}
+
+/// Value of a custom field of a level or entity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LdtkField {
+    pub identifier: String,
+    /// LDtk type name, eg. `Int`, `String` or `Array<Point>`.
+    pub kind: String,
+    pub value: Value,
+}
+
+/// Entity instance of an LDtk level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LdtkEntity {
+    pub identifier: String,
+    pub iid: String,
+    /// Position of the pivot in pixels from the level's top left corner (y down).
+    pub position: Vec2,
+    /// Cell of the pivot in the entity layer.
+    pub grid: IVec2,
+    pub size: Vec2,
+    pub tags: Vec<String>,
+    pub fields: Vec<LdtkField>,
+}
+
+/// Level of an LDtk project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LdtkLevel {
+    pub identifier: String,
+    pub iid: String,
+    /// Position of the level's top left corner in the LDtk world in pixels (y down).
+    pub world_position: Vec2,
+    /// Size in pixels.
+    pub size: Vec2,
+    /// Grid layers, bottom layer first.
+    pub layers: Vec<LdtkLayer>,
+    pub entities: Vec<LdtkEntity>,
+    pub fields: Vec<LdtkField>,
+}
+
+impl LdtkLevel {
+    pub fn layer(&self, identifier: &str) -> Option<&LdtkLayer> {
+        self.layers.iter().find(|l| l.identifier == identifier)
+    }
+
+    pub fn field(&self, identifier: &str) -> Option<&LdtkField> {
+        self.fields.iter().find(|f| f.identifier == identifier)
+    }
+
+    /// All entities with the given identifier.
+    pub fn entities<'a>(&'a self, identifier: &'a str) -> impl Iterator<Item = &'a LdtkEntity> {
+        self.entities
+            .iter()
+            .filter(move |e| e.identifier == identifier)
+    }
+
+    /// Convert a pixel position in this level (y down, eg. [`LdtkEntity::position`]) into
+    /// bevy world coordinates, matching the maps spawned by [`LdtkSpawner`].
+    pub fn to_world(&self, position: Vec2) -> Vec2 {
+        let p = self.world_position + position;
+        Vec2::new(p.x, -p.y)
+    }
+}
+
+/// An LDtk project loaded from an `.ldtk` file by [`LdtkLoader`].
+///
+/// Levels may be embedded or stored in separate files. IntGrid, Tiles and auto layers are
+/// converted, entity layers are available as [`LdtkLevel::entities`].
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct LdtkProject {
+    pub tilesets: Vec<LdtkTileset>,
+    pub levels: Vec<LdtkLevel>,
+}
+
+impl LdtkProject {
+    pub fn level(&self, identifier: &str) -> Option<&LdtkLevel> {
+        self.levels.iter().find(|l| l.identifier == identifier)
+    }
+
+    pub fn tileset(&self, uid: i64) -> Option<&LdtkTileset> {
+        self.tilesets.iter().find(|t| t.uid == uid)
+    }
+
+    /// Build a [`Map`] for the given layer of the given level. Cells without tile become atlas
+    /// index `empty_tile`, which should be transparent. `None` for layers without tiles
+    /// (eg. IntGrid layers without auto-layer rules).
+    /// Stacked tiles (see [`LdtkLayer::stacked_tiles`]) become upper map layers.
+    /// Tiles are drawn at the tileset's tile size, layers are assumed to use the same grid size.
+    pub fn layer_map<C: Customization>(
+        &self,
+        level: usize,
+        layer: usize,
+        empty_tile: u32,
+    ) -> Option<Map<C>> {
+        let layer = self.levels.get(level)?.layers.get(layer)?;
+        let tileset = self.tileset(layer.tileset?)?;
+        if layer.tiles.is_empty() {
+            return None;
+        }
+
+        let bottomright = tileset.image_size
+            - tileset.padding
+            - tileset.n_tiles.as_vec2() * tileset.tile_size
+            - (tileset.n_tiles.as_vec2() - Vec2::ONE) * tileset.spacing;
+        let tile_size = Vec2::splat(tileset.tile_size);
+        let width = layer.size.x as usize;
+        let atlas_index = |value: u32| match value {
+            0 => empty_tile,
+            value => ((value & !TILE_FLIP_MASK) - 1) | (value & TILE_FLIP_MASK),
+        };
+
+        let map = Map::<C>::builder(layer.size, tileset.image.clone(), tile_size)
+            .with_padding(
+                Vec2::splat(tileset.spacing),
+                Vec2::splat(tileset.padding),
+                bottomright.max(Vec2::ZERO),
+            )
+            .with_n_tiles(Some(tileset.n_tiles))
+            .with_layers(1 + layer.stacked_tiles.len() as u32)
+            .build_and_initialize(|m| {
+                let layers = std::iter::once(&layer.tiles).chain(layer.stacked_tiles.iter());
+                for (l, tiles) in layers.enumerate() {
+                    for (i, value) in tiles.iter().enumerate() {
+                        let (x, y) = ((i % width) as u32, (i / width) as u32);
+                        m.set_layer(l as u32, x, y, atlas_index(*value));
+                    }
+                }
+            });
+        Some(map)
+    }
+}
+
+/// Which levels of an [`LdtkProject`] to spawn.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LdtkLevelSelection {
+    #[default]
+    All,
+    Identifiers(Vec<String>),
+}
+
+/// Spawns the levels of an [`LdtkProject`] as child entities once it is loaded,
+/// then removes itself. Give the entity a `SpatialBundle`.
+///
+/// Each level becomes a child with an [`LdtkLevelInstance`] and a [`Name`] of the level
+/// identifier, placed at its world position (LDtk's y axis points down, so levels further down
+/// in LDtk get negative y). Its tile layers become children with a [`MapBundleManaged`] and the
+/// layer name, layer `i` (from the bottom) is placed at `z = i * layer_spacing`.
+#[derive(Component, Debug, Clone)]
+pub struct LdtkSpawner {
+    pub project: Handle<LdtkProject>,
+    pub levels: LdtkLevelSelection,
+    /// Atlas index used for cells without tile, see [`LdtkProject::layer_map`].
+    pub empty_tile: u32,
+    pub layer_spacing: f32,
+}
+
+impl LdtkSpawner {
+    pub fn new(project: Handle<LdtkProject>) -> Self {
+        Self {
+            project,
+            levels: default(),
+            empty_tile: 0,
+            layer_spacing: 1.0,
+        }
+    }
+
+    /// Only spawn the level with the given identifier (can be called multiple times).
+    pub fn with_level(mut self, identifier: impl Into<String>) -> Self {
+        if let LdtkLevelSelection::Identifiers(ids) = &mut self.levels {
+            ids.push(identifier.into());
+        } else {
+            self.levels = LdtkLevelSelection::Identifiers(vec![identifier.into()]);
+        }
+        self
+    }
+
+    pub fn with_empty_tile(self, empty_tile: u32) -> Self {
+        Self { empty_tile, ..self }
+    }
+
+    pub fn with_layer_spacing(self, layer_spacing: f32) -> Self {
+        Self {
+            layer_spacing,
+            ..self
+        }
+    }
+}
+
+/// A level spawned by [`LdtkSpawner`], gives access to its entities and fields.
+#[derive(Component, Debug, Clone)]
+pub struct LdtkLevelInstance {
+    pub project: Handle<LdtkProject>,
+    /// Index into [`LdtkProject::levels`].
+    pub level: usize,
+}
+
+impl LdtkLevelInstance {
+    pub fn get<'a>(&self, projects: &'a Assets<LdtkProject>) -> Option<&'a LdtkLevel> {
+        projects.get(&self.project)?.levels.get(self.level)
+    }
+}
+
+/// A tile layer spawned by [`LdtkSpawner`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LdtkLayerInstance {
+    /// Index into [`LdtkLevel::layers`].
+    pub layer: usize,
+}
+
+fn spawn_ldtk_levels<C: Customization>(
+    mut commands: Commands,
+    spawners: Query<(Entity, &LdtkSpawner)>,
+    projects: Res<Assets<LdtkProject>>,
+    mut materials: ResMut<Assets<Map<C>>>,
+) {
+    for (entity, spawner) in spawners.iter() {
+        let Some(project) = projects.get(&spawner.project) else {
+            continue;
+        };
+
+        let mut entity = commands.entity(entity);
+        entity.remove::<LdtkSpawner>();
+        entity.with_children(|parent| {
+            for (l, level) in project.levels.iter().enumerate() {
+                if let LdtkLevelSelection::Identifiers(ids) = &spawner.levels {
+                    if !ids.contains(&level.identifier) {
+                        continue;
+                    }
+                }
+
+                let origin = level.to_world(Vec2::ZERO);
+                parent
+                    .spawn((
+                        Name::new(level.identifier.clone()),
+                        LdtkLevelInstance {
+                            project: spawner.project.clone(),
+                            level: l,
+                        },
+                        SpatialBundle::from_transform(Transform::from_translation(
+                            origin.extend(0.0),
+                        )),
+                    ))
+                    .with_children(|parent| {
+                        for (i, layer) in level.layers.iter().enumerate() {
+                            let Some(map) = project.layer_map::<C>(l, i, spawner.empty_tile) else {
+                                continue;
+                            };
+                            // Maps are centered on their transform
+                            let extent = layer.size.as_vec2() * layer.grid_size;
+                            let center = layer.offset + extent / 2.0;
+                            parent.spawn((
+                                Name::new(layer.identifier.clone()),
+                                LdtkLayerInstance { layer: i },
+                                MapBundleManaged {
+                                    material: materials.add(map),
+                                    transform: Transform::from_xyz(
+                                        center.x,
+                                        -center.y,
+                                        i as f32 * spawner.layer_spacing,
+                                    ),
+                                    visibility: match layer.visible {
+                                        true => Visibility::Inherited,
+                                        false => Visibility::Hidden,
+                                    },
+                                    ..default()
+                                },
+                            ));
+                        }
+                    });
+            }
+        });
+    }
+}
+
+/// Loads `.ldtk` files as [`LdtkProject`]s.
+#[derive(Debug, Default)]
+pub struct LdtkLoader;
+
+impl AssetLoader for LdtkLoader {
+    type Asset = LdtkProject;
+    type Settings = ();
+    type Error = LdtkError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<LdtkProject, LdtkError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let json: Value = serde_json::from_slice(&bytes)?;
+        let path = load_context.asset_path().clone();
+
+        let mut tilesets = Vec::new();
+        for tileset in array(&json["defs"], "tilesets")? {
+            // Internal icon tilesets have no image
+            let Some(image) = tileset["relPath"].as_str() else {
+                continue;
+            };
+            tilesets.push(LdtkTileset {
+                uid: int(tileset, "uid")?,
+                image: load_context.load(resolve(&path, image)?),
+                image_size: Vec2::new(float(tileset, "pxWid")?, float(tileset, "pxHei")?),
+                tile_size: float(tileset, "tileGridSize")?,
+                spacing: float(tileset, "spacing")?,
+                padding: float(tileset, "padding")?,
+                n_tiles: uvec2(
+                    int(tileset, "__cWid")? as u32,
+                    int(tileset, "__cHei")? as u32,
+                ),
+            });
+        }
+
+        let mut levels = Vec::new();
+        for level in array(&json, "levels")? {
+            if level["layerInstances"].is_array() {
+                levels.push(parse_level(level)?);
+                continue;
+            }
+            // Level stored in a separate `.ldtkl` file
+            let source = level["externalRelPath"]
+                .as_str()
+                .ok_or_else(|| LdtkError::Invalid("level without layers".into()))?;
+            let bytes = load_context
+                .read_asset_bytes(resolve(&path, source)?)
+                .await
+                .map_err(|e| LdtkError::Invalid(e.to_string()))?;
+            levels.push(parse_level(&serde_json::from_slice(&bytes)?)?);
+        }
+
+        Ok(LdtkProject { tilesets, levels })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ldtk"]
+    }
+}
+
+/// Path of `source` relative to the directory of the file at `base`.
+fn resolve(base: &AssetPath, source: &str) -> Result<AssetPath<'static>, LdtkError> {
+    base.resolve_embed(source)
+        .map_err(|e| LdtkError::Invalid(e.to_string()))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>, LdtkError> {
+    value[key]
+        .as_array()
+        .ok_or_else(|| LdtkError::Invalid(format!("missing {}", key)))
+}
+
+fn int(value: &Value, key: &str) -> Result<i64, LdtkError> {
+    value[key]
+        .as_i64()
+        .ok_or_else(|| LdtkError::Invalid(format!("missing {}", key)))
+}
+
+fn float(value: &Value, key: &str) -> Result<f32, LdtkError> {
+    value[key]
+        .as_f64()
+        .map(|v| v as f32)
+        .ok_or_else(|| LdtkError::Invalid(format!("missing {}", key)))
+}
+
+fn string(value: &Value, key: &str) -> Result<String, LdtkError> {
+    value[key]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| LdtkError::Invalid(format!("missing {}", key)))
+}
+
+/// `[x, y]` pair of numbers.
+fn vec2_at(value: &Value, key: &str) -> Result<Vec2, LdtkError> {
+    let invalid = || LdtkError::Invalid(format!("missing {}", key));
+    let pair = value[key].as_array().ok_or_else(invalid)?;
+    match pair.as_slice() {
+        [x, y] => Ok(Vec2::new(
+            x.as_f64().ok_or_else(invalid)? as f32,
+            y.as_f64().ok_or_else(invalid)? as f32,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_fields(value: &Value) -> Result<Vec<LdtkField>, LdtkError> {
+    array(value, "fieldInstances")?
+        .iter()
+        .map(|field| {
+            Ok(LdtkField {
+                identifier: string(field, "__identifier")?,
+                kind: string(field, "__type")?,
+                value: field["__value"].clone(),
+            })
+        })
+        .collect()
+}
+
+fn parse_level(level: &Value) -> Result<LdtkLevel, LdtkError> {
+    let mut layers = Vec::new();
+    let mut entities = Vec::new();
+
+    // LDtk lists layers from top to bottom
+    for layer in array(level, "layerInstances")?.iter().rev() {
+        let kind = match layer["__type"].as_str() {
+            Some("IntGrid") => LdtkLayerKind::IntGrid,
+            Some("Tiles") => LdtkLayerKind::Tiles,
+            Some("AutoLayer") => LdtkLayerKind::AutoLayer,
+            Some("Entities") => {
+                for entity in array(layer, "entityInstances")? {
+                    entities.push(LdtkEntity {
+                        identifier: string(entity, "__identifier")?,
+                        iid: string(entity, "iid")?,
+                        position: vec2_at(entity, "px")?,
+                        grid: vec2_at(entity, "__grid")?.as_ivec2(),
+                        size: Vec2::new(float(entity, "width")?, float(entity, "height")?),
+                        tags: array(entity, "__tags")?
+                            .iter()
+                            .filter_map(|t| t.as_str().map(str::to_string))
+                            .collect(),
+                        fields: parse_fields(entity)?,
+                    });
+                }
+                continue;
+            }
+            Some(t) => return Err(LdtkError::Unsupported(format!("{} layers", t))),
+            None => return Err(LdtkError::Invalid("layer without __type".into())),
+        };
+
+        let size = uvec2(int(layer, "__cWid")? as u32, int(layer, "__cHei")? as u32);
+        let grid_size = float(layer, "__gridSize")?;
+        let n_cells = (size.x * size.y) as usize;
+
+        let int_grid: Vec<i32> = match kind {
+            LdtkLayerKind::IntGrid => array(layer, "intGridCsv")?
+                .iter()
+                .map(|v| v.as_i64().unwrap_or(0) as i32)
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !int_grid.is_empty() && int_grid.len() != n_cells {
+            return Err(LdtkError::Invalid(format!(
+                "layer has {} IntGrid cells, expected {}",
+                int_grid.len(),
+                n_cells
+            )));
+        }
+
+        let mut tiles = Vec::new();
+        let mut stacked_tiles: Vec<Vec<u32>> = Vec::new();
+        let tile_key = match kind {
+            LdtkLayerKind::Tiles => "gridTiles",
+            _ => "autoLayerTiles",
+        };
+        let layer_tiles = array(layer, tile_key)?;
+        if !layer_tiles.is_empty() {
+            tiles = vec![0; n_cells];
+            // Later tiles are drawn on top of earlier ones, tiles stacked in one cell spill
+            // into extra layers
+            let mut stack_heights = vec![0usize; n_cells];
+            for tile in layer_tiles {
+                let cell = (vec2_at(tile, "px")? / grid_size).floor().as_ivec2();
+                if cell.x < 0 || cell.y < 0 || cell.x >= size.x as i32 || cell.y >= size.y as i32 {
+                    continue;
+                }
+                let flips = int(tile, "f")?;
+                let mut value = int(tile, "t")? as u32 + 1;
+                if flips & 1 != 0 {
+                    value |= TILE_FLIP_X;
+                }
+                if flips & 2 != 0 {
+                    value |= TILE_FLIP_Y;
+                }
+                let i = cell.y as usize * size.x as usize + cell.x as usize;
+                match stack_heights[i] {
+                    0 => tiles[i] = value,
+                    height => {
+                        if stacked_tiles.len() < height {
+                            stacked_tiles.push(vec![0; n_cells]);
+                        }
+                        stacked_tiles[height - 1][i] = value;
+                    }
+                }
+                stack_heights[i] += 1;
+            }
+        }
+
+        layers.push(LdtkLayer {
+            identifier: string(layer, "__identifier")?,
+            kind,
+            size,
+            grid_size,
+            offset: Vec2::new(
+                float(layer, "__pxTotalOffsetX")?,
+                float(layer, "__pxTotalOffsetY")?,
+            ),
+            opacity: float(layer, "__opacity")?,
+            visible: layer["visible"].as_bool().unwrap_or(true),
+            tileset: layer["__tilesetDefUid"].as_i64(),
+            int_grid,
+            tiles,
+            stacked_tiles,
+        });
+    }
+
+    Ok(LdtkLevel {
+        identifier: string(level, "identifier")?,
+        iid: string(level, "iid")?,
+        world_position: Vec2::new(float(level, "worldX")?, float(level, "worldY")?),
+        size: Vec2::new(float(level, "pxWid")?, float(level, "pxHei")?),
+        layers,
+        entities,
+        fields: parse_fields(level)?,
+    })
+}
@@ -7,7 +7,7 @@
 //!
 //! - Very high rendering performance (hundreds of fps, largely independent of map size)
 //! - Tilemaps can be very large or have many "layers"
-//! - Rectangular and isometric (axonometric) tile maps.
+//! - Rectangular, isometric (axonometric) and hexagonal tile maps.
 //! - Tile overlaps either by "dominance" rule or by perspective
 //! - Optional custom mesh for which the map serves as a texture
 //!
@@ -17,20 +17,199 @@
 //! rendered as a single quad and a shader cares for rendering the correct tiles at the correct
 //! position.
 
+pub mod accessibility;
+pub mod animation;
+pub mod atlas_array;
+pub mod atlas_variants;
+pub mod automaton;
+pub mod autotile;
+pub mod bake;
+pub mod blend;
+pub mod bounds;
 pub mod bundle;
+pub mod changes;
+pub mod chunk;
+pub mod chunked;
+pub mod collision;
+pub mod commands;
+mod content_hash;
+pub mod cross_map;
+pub mod cursor;
+pub mod damage;
+pub mod debug;
+pub mod debug_draw;
+pub mod decal;
+pub mod depth;
+pub mod dither;
+pub mod edge_mode;
+pub mod effects;
+pub mod elevation;
+pub mod error;
+pub mod flip;
+pub mod flow_field;
+pub mod fog;
+pub mod format;
+pub mod fov;
+pub mod generation;
+mod grid;
+pub mod highlight;
+pub mod interaction;
+pub mod layer_group;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+mod light;
+pub mod links;
+pub mod lod;
 pub mod map;
+pub mod map_asset;
 pub mod map_builder;
 pub mod map_uniform;
+pub mod metadata;
+pub mod minimap;
+pub mod occlusion;
+pub mod ownership;
+pub mod pages;
+pub mod parallax;
+pub mod persistence;
+#[cfg(any(feature = "avian", feature = "rapier"))]
+pub mod physics;
+pub mod picking;
+pub mod placement;
 pub mod plugin;
+pub mod precision;
+pub mod query;
+pub mod readback;
+pub mod rebake;
+pub mod reflection;
+pub mod registry;
+pub mod render_layers;
+pub mod replay;
+pub mod reveal;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod sdf;
+pub mod settings;
 pub mod shader;
+pub mod shadow;
+pub mod stack;
+pub mod stats;
+pub mod streaming;
+pub mod surface;
+pub mod sway;
 pub mod tile_projection;
+pub mod timeline;
+pub mod tmx;
+pub mod tracking;
+pub mod transaction;
+pub mod triggers;
+mod upload;
+pub mod view;
 
 pub mod prelude {
+    pub use super::accessibility::{ContrastPattern, HighContrastPalette, HighContrastStyle};
+    pub use super::animation::{
+        MapAnimationClock, MapAnimationTime, TileAnimation, TileAnimations,
+    };
+    pub use super::automaton::{CustomMapAutomatonPlugin, MapAutomaton, MapAutomatonPlugin};
+    pub use super::autotile::{
+        AutotileIndexerMut, AutotileRules, CliffRules, TerrainLayer, TerrainTiles,
+    };
+    pub use super::blend::MapBlendMode;
     pub use super::bundle::*;
+    pub use super::changes::MapTilesChanged;
+    pub use super::chunk::{ChunkEntered, ChunkExited, MapChunks};
+    pub use super::chunked::ChunkedMap;
+    pub use super::collision::{
+        MergedCollider, TileCollider, TileColliders, TileShape, TileShapes,
+    };
+    pub use super::commands::{ApplyMapEdits, MapCommands, MapCommandsExt, MapEdit, MapEditQueue};
+    pub use super::cross_map::TileRounding;
+    pub use super::cursor::{
+        CustomTileCursorPlugin, TileCursor, TileCursorBindings, TileCursorMoved, TileCursorPlugin,
+    };
+    pub use super::damage::{DamageState, TileDamageStates};
+    pub use super::debug::*;
+    pub use super::debug_draw::{CustomMapDebugDrawPlugin, MapDebugDraw, MapDebugDrawPlugin};
+    pub use super::decal::Decal;
+    pub use super::dither::TerrainDither;
+    pub use super::edge_mode::EdgeMode;
+    pub use super::effects::{TileEffect, TileEffectPlugin};
+    pub use super::error::*;
+    pub use super::flip::{TILE_FLIP_DIAGONAL, TILE_FLIP_MASK, TILE_FLIP_X, TILE_FLIP_Y};
+    pub use super::flow_field::FlowField;
+    pub use super::fog::{FogOfWar, MapFogMut, FOG_HIDDEN, FOG_VISIBLE};
+    pub use super::format::{
+        CustomMapFormatPlugin, MapFormatMigration, MapFormatMigrations, MapFormatPlugin,
+        MapFormatVersion,
+    };
+    pub use super::fov::FieldOfView;
+    pub use super::generation::{ChunkGenerated, ChunkGenerator};
+    pub use super::highlight::{MapHighlights, TileHighlight};
+    pub use super::interaction::{
+        CustomTileInteractionPlugin, TileClicked, TileDragEnded, TileDragged, TileHoverEnded,
+        TileHoverStarted, TileInteractionPlugin, TileInteractionSettings,
+    };
+    pub use super::layer_group::MapLayerGroup;
+    #[cfg(feature = "ldtk")]
+    pub use super::ldtk::{
+        CustomLdtkPlugin, LdtkLevelInstance, LdtkPlugin, LdtkProject, LdtkSpawner,
+    };
+    pub use super::links::{LinkedMapIndexerMut, MapLinks};
+    pub use super::lod::LodSettings;
     pub use super::map::*;
+    pub use super::map_asset::{CustomMapAssetPlugin, MapAsset, MapAssetPlugin, MapSource};
     pub use super::map_builder::*;
     pub use super::map_uniform::*;
+    pub use super::metadata::{
+        TileProperties, TileProperty, TilesetMetadata, TilesetMetadataPlugin,
+    };
+    pub use super::minimap::MinimapCamera;
+    pub use super::occlusion::TileOcclusion;
+    pub use super::ownership::OwnershipOverlay;
+    pub use super::pages::{
+        paged_tile, tile_page, MAX_ATLAS_PAGES, TILE_PAGE_MASK, TILE_PAGE_SHIFT,
+    };
+    pub use super::parallax::ParallaxLayer;
+    pub use super::persistence::IncrementalSave;
+    #[cfg(any(feature = "avian", feature = "rapier"))]
+    pub use super::physics::{
+        CustomTileColliderPhysicsPlugin, TileColliderChunk, TileColliderPhysicsPlugin,
+    };
+    pub use super::picking::*;
+    pub use super::placement::{
+        CustomPlacementPreviewPlugin, Placement, PlacementPreview, PlacementPreviewPlugin,
+    };
     pub use super::plugin::*;
+    pub use super::query::MapQuery;
+    pub use super::readback::{CustomMapReadbackPlugin, MapReadbackPlugin};
+    pub use super::rebake::{RebakeRegion, RebakeScheduler};
+    pub use super::reflection::{MapReflection, MirrorAxis};
+    pub use super::registry::{MapName, MapRegistry, MapRegistryPlugin};
+    pub use super::replay::{
+        CustomEditReplayPlugin, EditPlayback, EditRecording, EditReplayPlugin, RecordedEdit,
+    };
+    pub use super::reveal::{MapReveal, RevealShape};
+    #[cfg(feature = "scripting")]
+    pub use super::scripting::register_map_api;
+    pub use super::sdf::SdfSettings;
+    pub use super::settings::{FastTileMapSettings, OverhangQuality, TileFiltering};
+    pub use super::shadow::MapShadow;
+    pub use super::stack::{MapRowSlice, MapStack};
+    pub use super::stats::TileStats;
+    pub use super::streaming::StreamedMap;
+    pub use super::surface::TileSurfaces;
+    pub use super::sway::{MapWind, TileSway};
     pub use super::tile_projection::*;
+    pub use super::timeline::{Interpolate, Keyframes, MapTimeline, ProjectionBlend};
+    pub use super::tmx::{CustomTmxPlugin, TmxMap, TmxMapSpawner, TmxPlugin, TmxTilesetRef};
+    pub use super::tracking::{
+        ChangedTile, CurrentTile, CustomTileTrackingPlugin, PreviousTile, TileTracked,
+        TileTrackingPlugin,
+    };
+    pub use super::transaction::{MapChange, MapTransaction};
+    pub use super::triggers::{
+        CustomTileTriggerPlugin, TileTriggerPlugin, TileTriggerSensor, TileTriggered, TileTriggers,
+    };
+    pub use super::view::{MapRowBands, MapRowsMut, MapView};
 
 }
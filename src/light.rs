@@ -0,0 +1,79 @@
+use bevy::{math::Vec4Swizzles, prelude::*};
+
+use super::{map::Map, plugin::Customization, query::MapQuery};
+
+impl<C: Customization> Map<C> {
+    /// Shadow coverage of the tile at `tile`, 0 outside of the map or without shadows.
+    fn shadow_coverage_at(&self, tile: IVec2) -> f32 {
+        let size = self.map_size().as_ivec2();
+        if tile.cmplt(IVec2::ZERO).any() || tile.cmpge(size).any() {
+            return 0.0;
+        }
+        self.shadow_coverage
+            .get((tile.y * size.x + tile.x) as usize)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Bilinearly filtered shadow coverage at a fractional map position.
+    fn sample_shadow_coverage(&self, p: Vec2) -> f32 {
+        let q = p - 0.5;
+        let t = q.floor().as_ivec2();
+        let f = q - q.floor();
+        let top =
+            self.shadow_coverage_at(t) * (1.0 - f.x) + self.shadow_coverage_at(t + IVec2::X) * f.x;
+        let bottom = self.shadow_coverage_at(t + IVec2::Y) * (1.0 - f.x)
+            + self.shadow_coverage_at(t + IVec2::ONE) * f.x;
+        top * (1.0 - f.y) + bottom * f.y
+    }
+
+    /// Color the map's lighting multiplies into its tiles at the given position in map-local
+    /// world coordinates (apply the inverse of the map entity's transform to global coordinates
+    /// first, or use [`MapQuery::light_at`]).
+    ///
+    /// Use this to tint sprites walking across the map consistently with the tiles beneath
+    /// them. Matches the shader, ie. the tint of the tile (see
+    /// [`crate::map_builder::MapBuilder::with_tint_layer`]) and the blurred shadows cast onto
+    /// this map by an upper layer (see [`crate::shadow::MapShadow`]). White for unlit maps.
+    pub fn light_at(&self, world: Vec2) -> Color {
+        let map_position = self.local_to_map(world);
+        let tint = match map_position.cmpge(Vec2::ZERO).all() {
+            true => self.tint_at(map_position.x as u32, map_position.y as u32),
+            false => Color::WHITE,
+        }
+        .to_linear()
+        .to_vec4();
+        if !self.shadows {
+            return Color::linear_rgb(tint.x, tint.y, tint.z);
+        }
+        let u = &self.map_uniform;
+        let p = map_position - u.shadow_params.xy();
+
+        // 3x3 tent filter, same as in the shader
+        let mut sum = 0.0;
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let w = ((2 - i32::abs(x)) * (2 - i32::abs(y))) as f32;
+                let offset = Vec2::new(x as f32, y as f32) * u.shadow_params.z;
+                sum += w * self.sample_shadow_coverage(p + offset);
+            }
+        }
+        let shadow = sum / 16.0 * u.shadow_params.w * u.shadow_color.w;
+
+        let light = tint.xyz().lerp(u.shadow_color.xyz(), shadow);
+        Color::linear_rgb(light.x, light.y, light.z)
+    }
+}
+
+impl<'w, 's, C: Customization> MapQuery<'w, 's, C> {
+    /// Lighting of the map of `entity` at the given world position, see [`Map::light_at`].
+    pub fn light_at(&self, entity: Entity, world: Vec2) -> Option<Color> {
+        let map = self.get(entity)?;
+        let local = self
+            .transform(entity)?
+            .affine()
+            .inverse()
+            .transform_point3(world.extend(0.0));
+        Some(map.light_at(local.truncate()))
+    }
+}
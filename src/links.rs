@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use bevy::{
+    math::{uvec2, URect},
+    prelude::*,
+};
+
+use super::{
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+type DeriveFn = dyn Fn(u32) -> Option<u32> + Send + Sync;
+
+/// Maps derived from the map of this entity (the "master"), eg. a collision or minimap layer
+/// that follows the painted terrain.
+///
+/// Each link has a rule deriving the dependent tile from the master tile at the same position,
+/// `None` leaves the dependent tile unchanged. Edits through [`Self::indexer_mut`] and
+/// [`crate::commands::MapCommands`] of the master entity are applied to the dependent maps as
+/// well. Links are not followed transitively.
+///
+/// ```ignore
+/// let links = MapLinks::new()
+///     .with_link(collision_map.clone(), |terrain| Some((terrain == WATER) as u32))
+///     .with_link(minimap.clone(), |terrain| Some(terrain / 4));
+/// ```
+#[derive(Component, Clone)]
+pub struct MapLinks<C: Customization = NoCustomization> {
+    links: Vec<(Handle<Map<C>>, Arc<DeriveFn>)>,
+}
+
+impl<C: Customization> Default for MapLinks<C> {
+    fn default() -> Self {
+        Self { links: Vec::new() }
+    }
+}
+
+impl<C: Customization> MapLinks<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derive the tiles of `dependent` from the master map with `derive`.
+    pub fn with_link(
+        mut self,
+        dependent: Handle<Map<C>>,
+        derive: impl Fn(u32) -> Option<u32> + Send + Sync + 'static,
+    ) -> Self {
+        self.links.push((dependent, Arc::new(derive)));
+        self
+    }
+
+    /// Indexer for editing `master` and its dependent maps in one go,
+    /// `None` if `master` is not loaded.
+    pub fn indexer_mut<'a>(
+        &'a self,
+        master: &Handle<Map<C>>,
+        maps: &'a mut Assets<Map<C>>,
+    ) -> Option<LinkedMapIndexerMut<'a, C>> {
+        maps.contains(master).then(|| LinkedMapIndexerMut {
+            links: self,
+            master: master.id(),
+            maps,
+        })
+    }
+
+    /// Derive all tiles of the dependent maps from `master`, eg. after loading.
+    pub fn sync(&self, master: &Handle<Map<C>>, maps: &mut Assets<Map<C>>) {
+        let Some(size) = maps.get(master).map(|map| map.map_size()) else {
+            return;
+        };
+        self.propagate(master.id(), URect::from_corners(UVec2::ZERO, size), maps);
+    }
+
+    /// Derive the tiles in `rect` (`max` exclusive) of the dependent maps from `master`.
+    pub(crate) fn propagate(
+        &self,
+        master: AssetId<Map<C>>,
+        rect: URect,
+        maps: &mut Assets<Map<C>>,
+    ) {
+        let Some(map) = maps.get(master) else {
+            return;
+        };
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, map.map_size()));
+        let m = map.indexer();
+        let tiles: Vec<_> = (rect.min.y..rect.max.y)
+            .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| uvec2(x, y)))
+            .map(|pos| (pos, m.at_uvec(pos)))
+            .collect();
+
+        for (dependent, derive) in self.links.iter() {
+            if dependent.id() == master {
+                continue;
+            }
+            let Some(map) = maps.get_mut(dependent) else {
+                continue;
+            };
+            let size = map.map_size();
+            let mut m = map.indexer_mut();
+            for &(pos, value) in tiles.iter() {
+                if pos.x >= size.x || pos.y >= size.y {
+                    continue;
+                }
+                if let Some(derived) = derive(value) {
+                    m.set_uvec(pos, derived);
+                }
+            }
+        }
+    }
+}
+
+/// Edits a master map and its dependent maps, see [`MapLinks::indexer_mut`].
+pub struct LinkedMapIndexerMut<'a, C: Customization> {
+    links: &'a MapLinks<C>,
+    master: AssetId<Map<C>>,
+    maps: &'a mut Assets<Map<C>>,
+}
+
+impl<'a, C: Customization> LinkedMapIndexerMut<'a, C> {
+    /// Tile of the master map at the given position.
+    pub fn at(&self, x: u32, y: u32) -> u32 {
+        self.at_uvec(uvec2(x, y))
+    }
+
+    pub fn at_uvec(&self, pos: UVec2) -> u32 {
+        self.maps
+            .get(self.master)
+            .map_or(0, |map| map.indexer().at_uvec(pos))
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, v: u32) {
+        self.set_uvec(uvec2(x, y), v);
+    }
+
+    /// Set the tile of the master map and derive the dependent tiles.
+    pub fn set_uvec(&mut self, pos: UVec2, v: u32) {
+        let Some(map) = self.maps.get_mut(self.master) else {
+            return;
+        };
+        map.indexer_mut().set_uvec(pos, v);
+        self.links.propagate(
+            self.master,
+            URect::from_corners(pos, pos + UVec2::ONE),
+            self.maps,
+        );
+    }
+
+    /// Set all tiles in `rect` (`max` exclusive) of the master map and derive the dependent
+    /// tiles.
+    pub fn fill_rect(&mut self, rect: URect, v: u32) {
+        let Some(map) = self.maps.get_mut(self.master) else {
+            return;
+        };
+        map.indexer_mut().fill_rect(rect, v);
+        self.links.propagate(self.master, rect, self.maps);
+    }
+}
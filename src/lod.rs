@@ -0,0 +1,189 @@
+//! Distance based level of detail, see [`LodSettings`].
+//!
+//! Each map gets a texture with one texel per tile in the average color of its atlas tile. Far
+//! away only this texture is sampled, so neither the tiles nor the atlas are read. The chunks of
+//! a [`crate::chunked::ChunkedMap`] are maps of their own, so each chunk has its own texture.
+//!
+//! The texture is re-baked whenever the tiles of the map change (for chunked maps only the edited
+//! chunk), the flat colors whenever the atlas changes or is switched. Only the base layer is
+//! baked, upper layers and tile animations are not shown in the far view.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Maximum number of samples per axis when averaging an atlas tile.
+const MAX_SAMPLES: u32 = 8;
+
+/// Distance based level of detail: when tiles become small on screen (the camera is far away),
+/// the map is drawn from a baked low resolution texture (see the [module docs](self)) instead of
+/// looking up each tile and sampling the atlas and its overhangs, keeping zoomed out views of
+/// huge maps cheap.
+///
+/// Between `near` and `far` both renderings are blended, so switching is seamless.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct LodSettings {
+    /// Size of a tile on screen (in pixels) below which only the flat colors are drawn.
+    pub far: f32,
+    /// Size of a tile on screen (in pixels) above which tiles are drawn in full detail.
+    pub near: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            far: 2.0,
+            near: 4.0,
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Enable or disable distance based level of detail, see [`LodSettings`].
+    /// The flat tile colors and the texture are baked once the atlas is loaded.
+    pub fn set_lod(&mut self, lod: Option<LodSettings>) {
+        match lod {
+            Some(lod) => {
+                self.map_uniform.lod_range = Vec2::new(lod.far, lod.near);
+                self.lod = true;
+            }
+            None => self.lod = false,
+        }
+    }
+
+    /// Average (alpha weighted) color of each tile in the atlas, indexed by atlas index.
+    pub(crate) fn bake_lod_colors(&mut self, atlas: &Image) {
+        let u = &self.map_uniform;
+        let n_tiles = u.n_tiles;
+        let tile_size = u.tile_size * u.atlas_tile_size_factor as f32;
+        let step = (tile_size / MAX_SAMPLES as f32).max(Vec2::ONE);
+
+        self.lod_colors = (0..n_tiles.x * n_tiles.y)
+            .map(|index| {
+                let cell = UVec2::new(index % n_tiles.x, index / n_tiles.x).as_vec2();
+                let start = cell * (tile_size + u.inner_padding) + u.outer_padding_topleft;
+
+                let mut rgb = Vec3::ZERO;
+                let mut alpha = 0.0;
+                let mut n = 0.0;
+                let mut y = 0.0;
+                while y < tile_size.y {
+                    let mut x = 0.0;
+                    while x < tile_size.x {
+                        let p = (start + Vec2::new(x, y)).as_uvec2();
+                        if let Ok(color) = atlas.get_color_at(p.x, p.y) {
+                            let c = color.to_linear();
+                            rgb += Vec3::new(c.red, c.green, c.blue) * c.alpha;
+                            alpha += c.alpha;
+                        }
+                        n += 1.0;
+                        x += step.x;
+                    }
+                    y += step.y;
+                }
+
+                if alpha <= 0.0 {
+                    return Vec4::ZERO;
+                }
+                (rgb / alpha).extend(alpha / n)
+            })
+            .collect();
+
+        if self.lod_colors.is_empty() {
+            self.lod_colors.push(Vec4::ZERO);
+        }
+        self.lod_baked = true;
+        self.lod_texture_source = None;
+    }
+
+    /// Bake the flat color of each tile into the LOD texture, one texel per tile.
+    pub(crate) fn bake_lod_texture(&mut self, images: &mut Assets<Image>) {
+        let size = self.map_size();
+        self.lod_texture_source = Some(self.content_hash());
+        if self.map_texture.is_empty() {
+            return;
+        }
+
+        let data: Vec<u8> = self
+            .map_texture
+            .iter()
+            .flat_map(|index| {
+                let color = self.lod_colors.get(*index as usize).copied();
+                Srgba::from(LinearRgba::from_vec4(color.unwrap_or(Vec4::ZERO))).to_u8_array()
+            })
+            .collect();
+
+        let current = self
+            .lod_texture
+            .as_ref()
+            .and_then(|texture| images.get_mut(texture));
+        if let Some(image) = current.filter(|image| image.size() == size) {
+            image.data = data;
+            return;
+        }
+        let mut image = Image::new(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        // Blend neighboring tiles, they are smaller than a pixel when the texture is shown
+        image.sampler = ImageSampler::linear();
+        self.lod_texture = Some(images.add(image));
+    }
+}
+
+/// Bake the flat tile colors and LOD textures of maps with LOD enabled once their atlas is
+/// available, and again when the atlas or the tiles change.
+pub(crate) fn bake_map_lod_colors<C: Customization>(
+    mut maps: ResMut<Assets<Map<C>>>,
+    mut images: ResMut<Assets<Image>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+) {
+    let modified_images: Vec<AssetId<Image>> = image_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let stale_colors =
+        |map: &Map<C>| !map.lod_baked || modified_images.contains(&map.atlas_texture.id());
+
+    // Only touch (and thereby modify) maps that actually need baking
+    let pending: Vec<_> = maps
+        .iter()
+        .filter(|(_, map)| {
+            map.lod
+                && map.map_uniform.n_tiles.cmpgt(UVec2::ZERO).all()
+                && images.contains(&map.atlas_texture)
+                && (stale_colors(map) || map.lod_texture_source != Some(map.content_hash()))
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    for id in pending {
+        let Some(map) = maps.get_mut(id) else {
+            continue;
+        };
+        if stale_colors(map) {
+            let Some(atlas) = images.get(&map.atlas_texture) else {
+                continue;
+            };
+            map.bake_lod_colors(atlas);
+        }
+        map.bake_lod_texture(&mut images);
+    }
+}
@@ -1,18 +1,41 @@
 use bevy::{
-    math::{dmat2, vec2, Vec3Swizzles},
+    color::ColorToPacked,
+    math::{dmat2, uvec2, vec2, URect, Vec3Swizzles},
     prelude::*,
     render::{
         mesh::MeshVertexAttribute,
         render_resource::{AsBindGroup, ShaderDefVal, ShaderRef, ShaderType, VertexFormat},
-        texture::{ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+        renderer::RenderDevice,
     },
     sprite::{Material2d, Mesh2dHandle},
 };
 
 use super::{
+    accessibility::{HighContrastEntry, HighContrastPalette},
+    animation::{map_animation_time, MapAnimationClock, MapAnimationTime, TileAnimations},
+    automaton::MapAutomaton,
+    blend::MapBlendMode,
+    changes::ChangedTiles,
+    content_hash::{cell_hash, hash_tiles},
+    debug::{ColorRamp, OverdrawDebugMode},
+    decal::DecalShaderData,
+    edge_mode::EdgeMode,
+    error::{AtlasTileCountError, MapBuildError, OutOfBoundsError},
+    grid::VariableGrid,
+    layer_group::{layer_group_mix_color, MapLayerGroup},
     map_builder::MapBuilder,
     map_uniform::MapUniform,
+    occlusion::OcclusionGrid,
+    ownership::owner_words,
+    pages::MAX_ATLAS_PAGES,
     plugin::{Customization, NoCustomization},
+    precision::RelativeView,
+    readback::ReadbackRequest,
+    replay::EditRecorder,
+    settings::{FastTileMapSettings, OverhangQuality},
+    stats::TileStats,
+    tile_projection::{HexLayout, TileProjection},
+    upload::Channel,
 };
 
 const ATTRIBUTE_MAP_POSITION: MeshVertexAttribute =
@@ -21,6 +44,8 @@ const ATTRIBUTE_MIX_COLOR: MeshVertexAttribute =
     MeshVertexAttribute::new("MixColor", 988779055, VertexFormat::Float32x4);
 const ATTRIBUTE_ANIMATION_STATE: MeshVertexAttribute =
     MeshVertexAttribute::new("AnimationState", 988779056, VertexFormat::Float32);
+const ATTRIBUTE_ROW_RANGE: MeshVertexAttribute =
+    MeshVertexAttribute::new("RowRange", 988779057, VertexFormat::Uint32x2);
 
 #[derive(Debug, Clone, Default, Reflect, AsBindGroup, ShaderType)]
 pub struct DefaultUserData {
@@ -40,21 +65,200 @@ pub struct Map<C: Customization = NoCustomization> {
     #[uniform(1)]
     pub user_data: C::UserData,
 
-    /// Texture containing the tile IDs (one per each pixel)
-    #[storage(100, read_only)]
+    /// Tile IDs (one per tile).
     pub(crate) map_texture: Vec<u32>,
 
+    /// Stands in for the GPU copy of `map_texture`, which is kept (and bound) separately so it
+    /// can be updated in place, see `upload.rs`.
+    #[storage(100, read_only)]
+    pub(crate) map_texture_placeholder: Vec<u32>,
+
+    /// Stand in for the per-tile and per-atlas-index data of the optional features below
+    /// (`grid_offsets`, `owners`, `ramp_colors`, ..), which is packed into one buffer of `u32`
+    /// and one of colors to stay within the limit of 8 storage buffers per shader stage,
+    /// see `upload.rs`.
+    #[storage(103, read_only)]
+    pub(crate) channels_placeholder: Vec<u32>,
+    #[storage(105, read_only)]
+    pub(crate) colors_placeholder: Vec<Vec4>,
+
+    /// Tile histogram, kept in sync with `map_texture`.
+    pub(crate) stats: TileStats,
+
+    /// XOR of the per-tile hashes of `map_texture`, see [`Self::content_hash`].
+    pub(crate) content_hash: u64,
+
+    /// Tiles changed since the last [`crate::changes::MapTilesChanged`] report.
+    #[reflect(ignore)]
+    pub(crate) changed_tiles: ChangedTiles,
+
+    /// Accumulated damage per tile, see [`Self::damage_tile`].
+    /// Empty until the first tile is damaged.
+    pub(crate) damage: Vec<u32>,
+
+    /// Occlusion per tile, see [`Self::set_tile_occlusion`].
+    #[reflect(ignore)]
+    pub(crate) occlusion: Option<OcclusionGrid>,
+
+    /// Failed validation when loading, its tiles are not uploaded and it gets no mesh,
+    /// see [`MapFailed`].
+    pub(crate) invalid: bool,
+
+    /// Recording of tile edits in progress, see [`Self::start_recording`].
+    #[reflect(ignore)]
+    pub(crate) recorder: Option<EditRecorder>,
+
     /// Atlas texture with the individual tiles
     #[texture(101)]
     #[sampler(102)]
     pub(crate) atlas_texture: Handle<Image>,
 
+    /// Cumulative column/row sizes for maps with variable row heights / column widths,
+    /// see [`VariableGrid::shader_data`].
+    /// Contains a single dummy value for uniform grids.
+    pub(crate) grid_offsets: Vec<f32>,
+
+    pub(crate) variable_grid: Option<VariableGrid>,
+
+    /// Replacement colors/patterns per atlas index for high-contrast mode.
+    #[storage(104, read_only)]
+    pub(crate) high_contrast_lut: Vec<HighContrastEntry>,
+    pub(crate) high_contrast: bool,
+
+    /// Color stops for rendering tile values through a color ramp.
+    pub(crate) ramp_colors: Vec<Vec4>,
+    pub(crate) color_ramp: bool,
+
+    /// Owner per tile (packed four per `u32`), see [`Self::set_owner`].
+    pub(crate) owners: Vec<u32>,
+
+    /// Color per owner for the ownership overlay.
+    pub(crate) team_colors: Vec<Vec4>,
+    pub(crate) ownership: bool,
+
+    /// Flat color per atlas index for distance based level of detail.
+    pub(crate) lod_colors: Vec<Vec4>,
+    pub(crate) lod: bool,
+    pub(crate) lod_baked: bool,
+    /// One texel per tile in its flat color, drawn instead of the tiles when far away,
+    /// see [`crate::lod`].
+    #[texture(126)]
+    #[sampler(127)]
+    pub(crate) lod_texture: Option<Handle<Image>>,
+    /// [`Self::content_hash`] of the tiles `lod_texture` was baked from.
+    pub(crate) lod_texture_source: Option<u64>,
+
+    pub(crate) reveal: bool,
+
+    /// Shadow coverage per tile, cast by a map above this one, see [`crate::shadow::MapShadow`].
+    pub(crate) shadow_coverage: Vec<f32>,
+    /// Content hash of the caster the coverage was computed from.
+    pub(crate) shadow_source: u64,
+    pub(crate) shadows: bool,
+
+    /// Tiles of the layers above the base layer (`map_texture`), one layer after the other.
+    /// Contains a single dummy value for single layer maps.
+    pub(crate) layer_texture: Vec<u32>,
+
+    /// Shader driven tile animations, see [`TileAnimations::shader_data`].
+    pub(crate) tile_animations: Vec<u32>,
+    pub(crate) animated_tiles: bool,
+
+    /// Noise texture for dithered terrain borders, see [`crate::dither::TerrainDither`].
+    #[texture(112)]
+    pub(crate) dither_noise: Handle<Image>,
+    /// Terrain category per atlas index for dithered terrain borders.
+    pub(crate) terrain_categories: Vec<u32>,
+    pub(crate) terrain_dither: bool,
+
+    /// Tint per tile (linear RGBA, 8 bit per channel), see [`MapBuilder::with_tint_layer`].
+    /// Contains a single dummy value for maps without tint layer.
+    pub(crate) tints: Vec<u32>,
+    pub(crate) tint_layer: bool,
+
+    /// Height per tile in world units, see [`MapBuilder::with_height_layer`].
+    /// Contains a single dummy value for maps without height layer.
+    pub(crate) heights: Vec<f32>,
+    pub(crate) height_layer: bool,
+
+    /// Texture decals are taken from, see [`Map::add_decal`].
+    #[texture(115)]
+    #[sampler(116)]
+    pub(crate) decal_texture: Handle<Image>,
+    /// Decals projected onto the map, at least one (placeholder) entry.
+    #[storage(117, read_only)]
+    pub(crate) decals: Vec<DecalShaderData>,
+    pub(crate) map_decals: bool,
+
+    /// One bit per atlas index of the tiles swaying in the wind, see [`Map::set_tile_sway`].
+    pub(crate) sway_tiles: Vec<u32>,
+    pub(crate) tile_sway: bool,
+
+    /// Visibility per tile (packed four per `u32`), see [`Map::fog_mut`].
+    pub(crate) fog: Vec<u32>,
+    /// Visibility tiles in view fall back to, see [`crate::fog::FogOfWar::explored`].
+    pub(crate) fog_explored: u8,
+    pub(crate) map_fog: bool,
+
+    /// Atlas split into one layer per atlas index, see [`crate::atlas_array`].
+    #[texture(120, dimension = "2d_array")]
+    #[sampler(121)]
+    pub(crate) atlas_array: Option<Handle<Image>>,
+    pub(crate) texture_array: bool,
+
+    /// Named atlases with the layout of `atlas_texture`, see [`Map::switch_atlas`].
+    pub(crate) atlas_variants: Vec<(String, Handle<Image>)>,
+    pub(crate) atlas_variant: Option<String>,
+    /// Previous atlas while cross-fading to the current one.
+    #[texture(122)]
+    #[sampler(123)]
+    pub(crate) fade_atlas: Option<Handle<Image>>,
+    pub(crate) atlas_fade_duration: f32,
+    /// The atlas changed and its sampler (and texture array) still need to be updated.
+    pub(crate) atlas_switched: bool,
+
+    /// Atlas textures selected by the high bits of tile values, see [`crate::pages`].
+    pub(crate) atlas_pages: Vec<Handle<Image>>,
+    /// Atlas pages stacked into a texture array.
+    #[texture(124, dimension = "2d_array")]
+    #[sampler(125)]
+    pub(crate) page_array: Option<Handle<Image>>,
+
+    pub(crate) edge_antialiasing: bool,
+    pub(crate) sdf: bool,
+    pub(crate) index_labels: bool,
+    /// Drawn in row slices by a [`crate::stack::MapStack`].
+    pub(crate) row_slices: bool,
+    pub(crate) depth_scaled_rows: bool,
+    /// Map positions are passed relative to the origin, see [`Map::set_relative_origin`].
+    pub(crate) relative_origin: bool,
+    /// See [`Map::set_edge_mode`].
+    pub(crate) edge_mode: EdgeMode,
+    /// Scrolled by a [`crate::parallax::ParallaxLayer`].
+    pub(crate) parallax: bool,
+    /// See [`Map::set_tile_depth`].
+    pub(crate) tile_depth: bool,
+    /// See [`Map::set_blend_mode`].
+    pub(crate) blend_mode: MapBlendMode,
+    pub(crate) overdraw_debug: Option<OverdrawDebugMode>,
+
     pub(crate) perspective_defs: Vec<String>,
     pub(crate) perspective_underhangs: bool,
     pub(crate) perspective_overhangs: bool,
     pub(crate) dominance_overhangs: bool,
+    pub(crate) overhang_quality: OverhangQuality,
+    pub(crate) overhang_reach: u32,
     pub(crate) force_underhangs: Vec<Vec2>,
     pub(crate) force_n_tiles: Option<UVec2>,
+    pub(crate) n_tiles_tolerance: f32,
+
+    /// See [`Self::set_automaton`].
+    #[reflect(ignore)]
+    pub(crate) automaton: Option<MapAutomaton>,
+
+    /// Set by [`Self::read_back`].
+    #[reflect(ignore)]
+    pub(crate) readback: ReadbackRequest,
 
     pub(crate) _customization: std::marker::PhantomData<C>,
 }
@@ -65,13 +269,84 @@ impl<C: Customization> Default for Map<C> {
             map_uniform: Default::default(),
             user_data: Default::default(),
             map_texture: Vec::new(),
+            map_texture_placeholder: vec![0],
+            channels_placeholder: vec![0],
+            colors_placeholder: vec![Vec4::ZERO],
+            stats: Default::default(),
+            content_hash: 0,
+            changed_tiles: default(),
+            damage: Vec::new(),
+            occlusion: None,
+            invalid: false,
+            recorder: None,
             atlas_texture: Default::default(),
+            grid_offsets: vec![0.0],
+            variable_grid: None,
+            high_contrast_lut: vec![HighContrastEntry::default()],
+            high_contrast: false,
+            ramp_colors: vec![Vec4::ZERO],
+            color_ramp: false,
+            owners: vec![0],
+            team_colors: vec![Vec4::ZERO],
+            ownership: false,
+            lod_colors: vec![Vec4::ZERO],
+            lod: false,
+            lod_baked: false,
+            lod_texture: None,
+            lod_texture_source: None,
+            reveal: false,
+            shadow_coverage: vec![0.0],
+            shadow_source: 0,
+            shadows: false,
+            layer_texture: vec![0],
+            tile_animations: vec![0],
+            animated_tiles: false,
+            dither_noise: Default::default(),
+            terrain_categories: vec![0],
+            terrain_dither: false,
+            tints: vec![u32::MAX],
+            tint_layer: false,
+            heights: vec![0.0],
+            height_layer: false,
+            decal_texture: Default::default(),
+            decals: vec![DecalShaderData::placeholder()],
+            map_decals: false,
+            sway_tiles: vec![0],
+            tile_sway: false,
+            fog: vec![0],
+            fog_explored: 0,
+            map_fog: false,
+            atlas_array: None,
+            texture_array: false,
+            atlas_variants: Vec::new(),
+            atlas_variant: None,
+            fade_atlas: None,
+            atlas_fade_duration: 0.0,
+            atlas_switched: false,
+            atlas_pages: Vec::new(),
+            page_array: None,
+            edge_antialiasing: false,
+            sdf: false,
+            index_labels: false,
+            row_slices: false,
+            depth_scaled_rows: false,
+            relative_origin: false,
+            edge_mode: EdgeMode::Empty,
+            parallax: false,
+            tile_depth: false,
+            blend_mode: MapBlendMode::AlphaBlend,
+            overdraw_debug: None,
             perspective_defs: Vec::new(),
             perspective_underhangs: true,
             perspective_overhangs: true,
             dominance_overhangs: false,
+            overhang_quality: OverhangQuality::Full,
+            overhang_reach: 1,
             force_underhangs: Vec::new(),
             force_n_tiles: None,
+            n_tiles_tolerance: 0.01,
+            automaton: None,
+            readback: default(),
             _customization: std::marker::PhantomData,
         }
     }
@@ -83,15 +358,76 @@ pub struct MapKey {
     pub(crate) perspective_underhangs: bool,
     pub(crate) perspective_overhangs: bool,
     pub(crate) dominance_overhangs: bool,
+    pub(crate) overhang_reach: u32,
+    pub(crate) variable_grid: bool,
+    pub(crate) depth_scaled_rows: bool,
+    pub(crate) overdraw_debug: Option<OverdrawDebugMode>,
+    pub(crate) high_contrast: bool,
+    pub(crate) color_ramp: bool,
+    pub(crate) ownership: bool,
+    pub(crate) lod: bool,
+    pub(crate) reveal: bool,
+    pub(crate) shadows: bool,
+    pub(crate) layers: bool,
+    pub(crate) animated_tiles: bool,
+    pub(crate) terrain_dither: bool,
+    pub(crate) tint_layer: bool,
+    pub(crate) tile_heights: bool,
+    pub(crate) map_decals: bool,
+    pub(crate) tile_sway: bool,
+    pub(crate) map_fog: bool,
+    pub(crate) atlas_array: bool,
+    pub(crate) atlas_fade: bool,
+    pub(crate) atlas_pages: bool,
+    pub(crate) edge_antialiasing: bool,
+    pub(crate) sdf: bool,
+    pub(crate) index_labels: bool,
+    pub(crate) row_slices: bool,
+    pub(crate) relative_origin: bool,
+    pub(crate) edge_mode: bool,
+    pub(crate) tile_depth: bool,
+    pub(crate) blend_mode: MapBlendMode,
 }
 
 impl<C: Customization> From<&Map<C>> for MapKey {
     fn from(map: &Map<C>) -> Self {
+        let (perspective_underhangs, perspective_overhangs, dominance_overhangs) =
+            map.overhang_flags();
         MapKey {
             perspective_defs: map.perspective_defs.clone(),
-            perspective_underhangs: map.perspective_underhangs,
-            perspective_overhangs: map.perspective_overhangs,
-            dominance_overhangs: map.dominance_overhangs,
+            perspective_underhangs,
+            perspective_overhangs,
+            dominance_overhangs,
+            overhang_reach: map.overhang_reach,
+            variable_grid: map.variable_grid.is_some(),
+            depth_scaled_rows: map.depth_scaled_rows,
+            overdraw_debug: map.overdraw_debug,
+            high_contrast: map.high_contrast,
+            color_ramp: map.color_ramp,
+            ownership: map.ownership,
+            lod: map.lod,
+            reveal: map.reveal,
+            shadows: map.shadows,
+            layers: map.n_layers() > 1,
+            animated_tiles: map.animated_tiles,
+            terrain_dither: map.terrain_dither,
+            tint_layer: map.tint_layer,
+            tile_heights: map.heights_active(),
+            map_decals: map.map_decals,
+            tile_sway: map.tile_sway,
+            map_fog: map.map_fog,
+            atlas_array: map.atlas_array.is_some(),
+            atlas_fade: map.fade_atlas.is_some(),
+            atlas_pages: !map.atlas_pages.is_empty()
+                && (map.page_array.is_some() || map.atlas_array.is_some()),
+            edge_antialiasing: map.edge_antialiasing,
+            sdf: map.sdf,
+            index_labels: map.index_labels,
+            row_slices: map.row_slices,
+            relative_origin: map.relative_origin_active(),
+            edge_mode: map.edge_mode_active(),
+            tile_depth: map.tile_depth,
+            blend_mode: map.blend_mode,
         }
     }
 }
@@ -100,10 +436,21 @@ impl<C: Customization> From<&Map<C>> for MapKey {
 #[derive(Component, Default, Clone, Debug)]
 pub struct MapAttributes {
     pub mix_color: Vec<Vec4>,
+
+    /// Only draw tiles of these rows, set for the row slices of a [`crate::stack::MapStack`].
+    pub rows: Option<std::ops::Range<u32>>,
+
+    /// Subtracted from the map positions, kept up to date for entities with a
+    /// [`crate::parallax::ParallaxLayer`].
+    pub parallax_offset: Vec2,
 }
 
 impl MapAttributes {
-    fn set_mix_color(attributes: Option<&MapAttributes>, mesh: &mut Mesh) {
+    pub(crate) fn set_mix_color(
+        attributes: Option<&MapAttributes>,
+        group_color: Vec4,
+        mesh: &mut Mesh,
+    ) {
         let l = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
 
         let mut v = vec![Vec4::ONE; l];
@@ -115,31 +462,52 @@ impl MapAttributes {
                 v[i] = *c;
             }
         }
+        for c in v.iter_mut() {
+            *c *= group_color;
+        }
 
         mesh.insert_attribute(ATTRIBUTE_MIX_COLOR, v);
     }
 
-    fn set_map_position<C: Customization>(
-        _attributes: Option<&MapAttributes>,
+    pub(crate) fn set_map_position<C: Customization>(
+        attributes: Option<&MapAttributes>,
         mesh: &mut Mesh,
         map: &Map<C>,
     ) {
+        let relative = map.relative_origin_active();
+        let offset = attributes.map_or(Vec2::ZERO, |attr| attr.parallax_offset);
         let v: Vec<_> = mesh
             .attribute(Mesh::ATTRIBUTE_POSITION)
             .unwrap()
             .as_float3()
             .unwrap()
             .iter()
-            .map(|p| map.world_to_map(Vec2::new(p[0], p[1])))
+            .map(|p| match relative {
+                true => map.local_to_relative_linear(Vec2::new(p[0], p[1])) - offset,
+                false => map.world_to_linear(Vec2::new(p[0], p[1])) - offset,
+            })
             .collect();
         mesh.insert_attribute(ATTRIBUTE_MAP_POSITION, v);
     }
 
-    fn set_animation_state(_attributes: Option<&MapAttributes>, mesh: &mut Mesh, time: &Time) {
+    pub(crate) fn set_animation_state(
+        _attributes: Option<&MapAttributes>,
+        mesh: &mut Mesh,
+        time: f32,
+    ) {
         let l = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
-        let v = vec![time.elapsed_seconds_wrapped(); l];
+        let v = vec![time; l];
         mesh.insert_attribute(ATTRIBUTE_ANIMATION_STATE, v);
     }
+
+    pub(crate) fn set_row_range(attributes: Option<&MapAttributes>, mesh: &mut Mesh) {
+        let Some(rows) = attributes.and_then(|attr| attr.rows.clone()) else {
+            return;
+        };
+        let l = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().len();
+        let v = vec![[rows.start, rows.end]; l];
+        mesh.insert_attribute(ATTRIBUTE_ROW_RANGE, v);
+    }
 }
 
 impl<C: Customization> Material2d for Map<C> {
@@ -156,15 +524,36 @@ impl<C: Customization> Material2d for Map<C> {
         layout: &bevy::render::mesh::MeshVertexBufferLayoutRef,
         key: bevy::sprite::Material2dKey<Self>,
     ) -> Result<(), bevy::render::render_resource::SpecializedMeshPipelineError> {
-        let vertex_layout = layout.0.get_layout(&[
+        let mut attributes = vec![
             Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
             ATTRIBUTE_MAP_POSITION.at_shader_location(1),
             ATTRIBUTE_MIX_COLOR.at_shader_location(2),
             ATTRIBUTE_ANIMATION_STATE.at_shader_location(3),
-        ])?;
+        ];
+        if key.bind_group_data.row_slices {
+            attributes.push(ATTRIBUTE_ROW_RANGE.at_shader_location(4));
+        }
+        let vertex_layout = layout.0.get_layout(&attributes)?;
         descriptor.vertex.buffers = vec![vertex_layout];
 
+        // Used in module-level constants, so needs to be known to both stages
+        let reach = ShaderDefVal::UInt(
+            "OVERHANG_REACH".to_string(),
+            key.bind_group_data.overhang_reach,
+        );
+        descriptor.vertex.shader_defs.push(reach.clone());
+
+        let row_slices = ShaderDefVal::Bool("ROW_SLICES".to_string(), true);
+        if key.bind_group_data.row_slices {
+            descriptor.vertex.shader_defs.push(row_slices.clone());
+        }
+
         let fragment = descriptor.fragment.as_mut().unwrap();
+        fragment.shader_defs.push(reach);
+
+        if key.bind_group_data.row_slices {
+            fragment.shader_defs.push(row_slices);
+        }
 
         if key.bind_group_data.perspective_underhangs {
             fragment.shader_defs.push(ShaderDefVal::Bool(
@@ -186,6 +575,178 @@ impl<C: Customization> Material2d for Map<C> {
                 .push(ShaderDefVal::Bool("DOMINANCE_OVERHANGS".to_string(), true));
         }
 
+        if key.bind_group_data.variable_grid {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("VARIABLE_GRID".to_string(), true));
+        }
+
+        if key.bind_group_data.depth_scaled_rows {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("DEPTH_SCALED_ROWS".to_string(), true));
+        }
+
+        if key.bind_group_data.high_contrast {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("HIGH_CONTRAST".to_string(), true));
+        }
+
+        if key.bind_group_data.edge_antialiasing {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("EDGE_ANTIALIAS".to_string(), true));
+        }
+
+        if key.bind_group_data.sdf {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("SDF_ATLAS".to_string(), true));
+        }
+
+        if key.bind_group_data.index_labels {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("DEBUG_INDEX_LABELS".to_string(), true));
+        }
+
+        if key.bind_group_data.ownership {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_OWNERSHIP".to_string(), true));
+        }
+
+        if key.bind_group_data.lod {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("DISTANCE_LOD".to_string(), true));
+        }
+
+        if key.bind_group_data.reveal {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("REVEAL_HOLE".to_string(), true));
+        }
+
+        if key.bind_group_data.shadows {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("LAYER_SHADOWS".to_string(), true));
+        }
+
+        if key.bind_group_data.layers {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("MAP_LAYERS".to_string(), true));
+        }
+
+        if key.bind_group_data.animated_tiles {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_ANIMATIONS".to_string(), true));
+        }
+
+        if key.bind_group_data.terrain_dither {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TERRAIN_DITHER".to_string(), true));
+        }
+
+        if key.bind_group_data.tint_layer {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_TINTS".to_string(), true));
+        }
+
+        if key.bind_group_data.tile_heights {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_HEIGHTS".to_string(), true));
+        }
+
+        if key.bind_group_data.map_decals {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("MAP_DECALS".to_string(), true));
+        }
+
+        if key.bind_group_data.tile_sway {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_SWAY".to_string(), true));
+        }
+
+        if key.bind_group_data.map_fog {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("FOG_OF_WAR".to_string(), true));
+        }
+
+        if key.bind_group_data.atlas_array {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("ATLAS_ARRAY".to_string(), true));
+        }
+
+        if key.bind_group_data.atlas_pages {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("ATLAS_PAGES".to_string(), true));
+        }
+
+        if key.bind_group_data.atlas_fade {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("ATLAS_FADE".to_string(), true));
+        }
+
+        if key.bind_group_data.relative_origin {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("RELATIVE_ORIGIN".to_string(), true));
+        }
+
+        if key.bind_group_data.edge_mode {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("EDGE_MODE".to_string(), true));
+        }
+
+        if key.bind_group_data.tile_depth {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("TILE_DEPTH".to_string(), true));
+            if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+                depth_stencil.depth_write_enabled = true;
+            }
+        }
+
+        let blend_mode = key.bind_group_data.blend_mode;
+        if let Some(def) = blend_mode.shader_def() {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool(def.to_string(), true));
+        }
+        for target in fragment.targets.iter_mut().flatten() {
+            target.blend = blend_mode.blend_state();
+        }
+
+        if key.bind_group_data.color_ramp {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("DATA_RAMP".to_string(), true));
+        }
+
+        if let Some(mode) = key.bind_group_data.overdraw_debug {
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool("DEBUG_OVERDRAW".to_string(), true));
+            fragment
+                .shader_defs
+                .push(ShaderDefVal::Bool(mode.shader_def().to_string(), true));
+        }
+
         for def in key.bind_group_data.perspective_defs.iter() {
             fragment
                 .shader_defs
@@ -217,6 +778,25 @@ pub struct MeshManagedByMap;
 #[reflect(Component)]
 pub struct MapLoading;
 
+/// Default maximum size of a storage buffer (in bytes) of wgpu,
+/// used for validating maps when the actual GPU limits are not known.
+pub const DEFAULT_MAX_BUFFER_SIZE: u64 = 128 << 20;
+
+/// The map of `map` finished loading and is shown.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapReady {
+    pub map: Entity,
+}
+
+/// The map of `map` finished loading with an error.
+/// For [`MapBuildError::Atlas`] the map is still shown. For all other errors its tiles are not
+/// uploaded to the GPU, it gets no mesh and the entity is hidden.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct MapFailed {
+    pub map: Entity,
+    pub error: MapBuildError,
+}
+
 impl<C: Customization> Map<C> {
     /// Create a [`MapBuilder`] for configuring your map.
     pub fn builder(
@@ -228,13 +808,50 @@ impl<C: Customization> Map<C> {
     }
 
     pub fn indexer_mut(&mut self) -> MapIndexerMut<C> {
-        MapIndexerMut::<C> { map: self }
+        MapIndexerMut::new(self)
     }
 
     pub fn indexer(&self) -> MapIndexer<C> {
         MapIndexer::<C> { map: self }
     }
 
+    /// Number of tile layers of this map, see [`MapBuilder::with_layers`].
+    pub fn n_layers(&self) -> u32 {
+        self.map_uniform.n_layers
+    }
+
+    /// Index into `layer_texture` of the given position of an upper layer (`layer >= 1`).
+    pub(crate) fn layer_idx(&self, layer: u32, x: u32, y: u32) -> Option<usize> {
+        let size = self.map_size();
+        if layer == 0 || layer >= self.n_layers() || x >= size.x || y >= size.y {
+            return None;
+        }
+        let n = size.x as usize * size.y as usize;
+        Some((layer as usize - 1) * n + y as usize * size.x as usize + x as usize)
+    }
+
+    /// Tint of the tile at the given position, white if out of bounds or without tint layer.
+    pub(crate) fn tint_at(&self, x: u32, y: u32) -> Color {
+        let size = self.map_size();
+        if !self.tint_layer || x >= size.x || y >= size.y {
+            return Color::WHITE;
+        }
+        self.tints
+            .get(y as usize * size.x as usize + x as usize)
+            .map_or(Color::WHITE, |tint| {
+                LinearRgba::from_u8_array(tint.to_le_bytes()).into()
+            })
+    }
+
+    /// Tile at the given position of the given layer, 0 if out of bounds.
+    pub(crate) fn tile_at_layer(&self, layer: u32, x: u32, y: u32) -> u32 {
+        if layer == 0 {
+            return self.indexer().at(x, y);
+        }
+        self.layer_idx(layer, x, y)
+            .map_or(0, |idx| self.layer_texture[idx])
+    }
+
     /// Dimensions of this map in tiles.
     pub fn map_size(&self) -> UVec2 {
         self.map_uniform.map_size()
@@ -254,27 +871,202 @@ impl<C: Customization> Map<C> {
     /// E.g. map position `(0.5, 0.5)` is in the center of the tile
     /// at index `(0, 0)`.
     pub fn map_to_local(&self, map_position: Vec2) -> Vec2 {
-        self.map_uniform.map_to_local(map_position.extend(0.0)).xy()
+        self.map_uniform
+            .map_to_local(self.map_to_linear(map_position).extend(0.0))
+            .xy()
     }
 
     /// Same as [`Self::map_to_local`], but return a 3d coordinate,
     /// z-value is the logical "depth" of the map position (for eg axonometric projection).
     /// Not generally consistent with actual z-position of the mesh.
     pub fn map_to_local_3d(&self, map_position: Vec3) -> Vec3 {
-        self.map_uniform.map_to_local(map_position)
+        self.map_uniform
+            .map_to_local(self.map_to_linear(map_position.xy()).extend(map_position.z))
+    }
+
+    /// Convert map position to global world position, taking the projection, tile anchor
+    /// point and `transform` (the global transform of the entity showing the map) into account.
+    pub fn map_to_world(&self, transform: &GlobalTransform, map_position: Vec2) -> Vec2 {
+        transform
+            .transform_point(self.map_to_local(map_position).extend(0.0))
+            .xy()
+    }
+
+    /// Same as [`Self::map_to_world`], but return a 3d coordinate with the logical "depth"
+    /// of the map position, see [`Self::map_to_local_3d`].
+    pub fn map_to_world_3d(&self, transform: &GlobalTransform, map_position: Vec3) -> Vec3 {
+        let local = self.map_to_local_3d(map_position);
+        let depth = local.z;
+        transform
+            .transform_point(local.xy().extend(0.0))
+            .xy()
+            .extend(depth)
+    }
+
+    /// Convert local world position (before this entities transform) to map position,
+    /// the inverse of [`Self::map_to_local`].
+    pub fn local_to_map(&self, local: Vec2) -> Vec2 {
+        self.linear_to_map(self.world_to_linear(local))
+    }
+
+    /// Convert global world position (eg. of the cursor) to map position, taking `transform`
+    /// (the global transform of the entity showing the map) into account.
+    /// The inverse of [`Self::map_to_world`].
+    pub fn world_to_map(&self, transform: &GlobalTransform, world: Vec2) -> Vec2 {
+        self.local_to_map(world_to_local(transform, world))
     }
 
-    pub fn map_to_world_3d(&self, map_position: Vec3) -> Vec3 {
-        self.map_uniform.map_to_world(map_position)
+    pub fn world_to_map_3d(&self, transform: &GlobalTransform, world: Vec3) -> Vec3 {
+        let local = world_to_local(transform, world.xy());
+        let linear = self.map_uniform.local_to_map(local.extend(world.z));
+        self.linear_to_map(linear.xy()).extend(linear.z)
     }
 
-    /// Convert world position to map position.
-    pub fn world_to_map(&self, world: Vec2) -> Vec2 {
+    /// Tile at the given global world position, `None` outside of the map.
+    /// For hexagonal projections this is the hexagon containing the position.
+    pub fn world_to_tile(&self, transform: &GlobalTransform, world: Vec2) -> Option<UVec2> {
+        self.local_to_tile(world_to_local(transform, world))
+    }
+
+    /// Same as [`Self::world_to_tile`] for a local world position.
+    pub fn local_to_tile(&self, local: Vec2) -> Option<UVec2> {
+        let tile = self.map_position_to_tile(self.local_to_map(local));
+        (tile.cmpge(IVec2::ZERO).all() && tile.cmplt(self.map_size().as_ivec2()).all())
+            .then(|| tile.as_uvec2())
+    }
+
+    /// Tile containing the given map position, may be outside of the map.
+    pub(crate) fn map_position_to_tile(&self, map_position: Vec2) -> IVec2 {
+        match self.hex_layout() {
+            Some(layout) => layout.round(map_position),
+            None => map_position.floor().as_ivec2(),
+        }
+    }
+
+    /// Hexagon layout of the map projection, `None` for non-hexagonal maps.
+    pub fn hex_layout(&self) -> Option<HexLayout> {
+        TileProjection {
+            projection: self.map_uniform.projection,
+            tile_anchor_point: self.map_uniform.tile_anchor_point,
+        }
+        .hex_layout()
+    }
+
+    /// Convert local world position to the map position of a uniform grid with the same extent.
+    /// Same as [`Self::local_to_map`] unless rows/columns have variable sizes.
+    /// This is what the shader expects in the vertex attributes, as it is linear in `world`.
+    pub(crate) fn world_to_linear(&self, world: Vec2) -> Vec2 {
         self.map_uniform.world_to_map(world.extend(0.0)).xy()
     }
 
-    pub fn world_to_map_3d(&self, world: Vec3) -> Vec3 {
-        self.map_uniform.world_to_map(world)
+    pub(crate) fn map_to_linear(&self, map_position: Vec2) -> Vec2 {
+        let mut linear = match &self.variable_grid {
+            Some(grid) => grid.map_to_linear(map_position),
+            None => map_position,
+        };
+        if self.depth_scaled_rows {
+            let center = self.linear_extent().x / 2.0;
+            linear.x = center + (linear.x - center) * self.row_scale(map_position.y);
+        }
+        linear
+    }
+
+    pub(crate) fn linear_to_map(&self, linear: Vec2) -> Vec2 {
+        let Some(grid) = &self.variable_grid else {
+            return linear;
+        };
+        let mut linear = linear;
+        if self.depth_scaled_rows {
+            let center = self.linear_extent().x / 2.0;
+            let row = grid.linear_to_map(linear).y;
+            linear.x = center + (linear.x - center) / self.row_scale(row);
+        }
+        grid.linear_to_map(linear)
+    }
+
+    /// Horizontal scale of the given (fractional) row for depth-scaled rows.
+    pub(crate) fn row_scale(&self, map_y: f32) -> f32 {
+        let t = (map_y / self.map_size().y as f32).clamp(0.0, 1.0);
+        let scale = self.map_uniform.depth_scale;
+        scale.x + (scale.y - scale.x) * t
+    }
+
+    /// Extent of the map in (uniform) tiles, that is the map size
+    /// unless rows/columns have variable sizes.
+    pub(crate) fn linear_extent(&self) -> Vec2 {
+        match &self.variable_grid {
+            Some(grid) => grid.extent(),
+            None => self.map_size().as_vec2(),
+        }
+    }
+
+    /// Set the replacement colors/patterns used in high-contrast mode.
+    pub fn set_high_contrast_palette(&mut self, palette: &HighContrastPalette) {
+        self.high_contrast_lut = palette.shader_data();
+    }
+
+    /// Enable or disable high-contrast rendering using the palette given by
+    /// [`Self::set_high_contrast_palette`].
+    pub fn set_high_contrast(&mut self, enabled: bool) {
+        self.high_contrast = enabled;
+    }
+
+    pub fn is_high_contrast(&self) -> bool {
+        self.high_contrast
+    }
+
+    /// Overlay every tile with its atlas index (for development only),
+    /// eg. for verifying loaders or autotiling output.
+    /// Labels are drawn by the shader with a built-in font, so this works at any map size.
+    pub fn set_index_labels(&mut self, enabled: bool) {
+        self.index_labels = enabled;
+    }
+
+    /// Multiply the output of the whole map with `color`, like `Sprite::color`,
+    /// eg. for day/night tinting or fading the map in and out. Default is white.
+    pub fn set_color(&mut self, color: Color) {
+        self.map_uniform.color = color.to_linear().to_vec4();
+    }
+
+    pub fn color(&self) -> Color {
+        Color::LinearRgba(LinearRgba::from_vec4(self.map_uniform.color))
+    }
+
+    /// Set the opacity of the whole map, keeping the color of [`Self::set_color`].
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.map_uniform.color.w = alpha.clamp(0.0, 1.0);
+    }
+
+    pub fn alpha(&self) -> f32 {
+        self.map_uniform.color.w
+    }
+
+    /// Render the raw tile values through the given color ramp instead of the atlas
+    /// (`None` to render the atlas again).
+    /// Overhangs and custom shader code do not apply in this mode.
+    pub fn set_color_ramp(&mut self, ramp: Option<&ColorRamp>) {
+        match ramp {
+            Some(ramp) => {
+                self.ramp_colors = ramp.shader_data();
+                self.changed_tiles.add_channel(Channel::RampColors);
+                self.map_uniform.ramp_range = Vec2::new(ramp.min, ramp.max);
+                self.color_ramp = true;
+            }
+            None => self.color_ramp = false,
+        }
+    }
+
+    /// Play the given frame animations for atlas indices in the shader
+    /// (`None` to stop all tile animations), see [`TileAnimations`].
+    pub fn set_tile_animations(&mut self, animations: Option<&TileAnimations>) {
+        match animations {
+            Some(animations) => {
+                self.tile_animations = animations.shader_data();
+                self.changed_tiles.add_channel(Channel::TileAnimations);
+                self.animated_tiles = true;
+            }
+            None => self.animated_tiles = false,
+        }
     }
 
     pub fn is_loaded(&self, images: &Assets<Image>) -> bool {
@@ -284,13 +1076,151 @@ impl<C: Customization> Map<C> {
     /// Update internal state.
     /// Call this when map size changed or assets may have become available.
     /// Should not be necessary to call this if only map contents changed.
+    ///
+    /// Problems with deriving the atlas layout are logged as warning,
+    /// use [`Self::try_update`] to handle them yourself.
     pub fn update(&mut self, images: &Assets<Image>) -> bool {
+        match self.try_update(images) {
+            Ok(updated) => updated,
+            Err(e) => {
+                warn!("{}", e);
+                true
+            }
+        }
+    }
+
+    /// Same as [`Self::update`], but return an error if the number of tiles in the atlas could
+    /// not be derived from atlas size, tile size and padding.
+    /// The map is still updated (with a truncated tile count) in that case.
+    pub fn try_update(&mut self, images: &Assets<Image>) -> Result<bool, AtlasTileCountError> {
         let Some(atlas_texture) = images.get(&self.atlas_texture) else {
-            return false;
+            return Ok(false);
         };
 
-        self.map_uniform
-            .update_atlas_size(atlas_texture.size().as_vec2(), self.force_n_tiles)
+        self.map_uniform.update_atlas_size(
+            atlas_texture.size().as_vec2(),
+            self.force_n_tiles,
+            self.n_tiles_tolerance,
+        )
+    }
+
+    /// Check that the map can be rendered on a GPU with the given maximum storage buffer size
+    /// (see [`DEFAULT_MAX_BUFFER_SIZE`]). Does not check the atlas, see [`Self::try_update`].
+    pub fn validate(&self, max_buffer_size: u64) -> Result<(), MapBuildError> {
+        let map_size = self.map_size();
+        if map_size.x == 0 || map_size.y == 0 {
+            return Err(MapBuildError::EmptyMap);
+        }
+        let tile_size = self.map_uniform.tile_size;
+        if !tile_size.is_finite() || tile_size.cmple(Vec2::ZERO).any() {
+            return Err(MapBuildError::InvalidTileSize(tile_size));
+        }
+        if self.atlas_pages.len() > MAX_ATLAS_PAGES {
+            return Err(MapBuildError::TooManyPages(self.atlas_pages.len()));
+        }
+        let n_tiles = map_size.x as u64 * map_size.y as u64;
+        let n_layer_tiles = n_tiles * self.n_layers().saturating_sub(1) as u64;
+        // The per-tile data of the optional features shares one buffer, see `upload.rs`
+        let n_tile_channels = [self.tint_layer, self.height_layer, self.shadows]
+            .iter()
+            .filter(|enabled| **enabled)
+            .count() as u64;
+        let n_channels = n_layer_tiles + n_tiles * n_tile_channels + 2 * n_tiles.div_ceil(4);
+        let size = n_tiles.max(n_channels) * std::mem::size_of::<u32>() as u64;
+        if size > max_buffer_size {
+            return Err(MapBuildError::MapTooLarge {
+                size,
+                max: max_buffer_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Change the size of a built map (in tiles), keeping the tiles of the region both sizes
+    /// have in common and setting new tiles (of all layers) to `fill`.
+    /// Per tile data (owners, fog, tints, damage, ..) is kept the same way,
+    /// new rows and columns of maps with variable row/column sizes get size `1.0`.
+    pub fn resize(&mut self, new_size: UVec2, fill: u32) {
+        let old_size = self.map_size();
+        if new_size == old_size {
+            return;
+        }
+        let old_n = old_size.x as usize * old_size.y as usize;
+        let new_n = new_size.x as usize * new_size.y as usize;
+
+        self.map_texture = resize_grid(&self.map_texture, old_size, new_size, fill);
+        if self.n_layers() > 1 {
+            self.layer_texture = self
+                .layer_texture
+                .chunks(old_n.max(1))
+                .flat_map(|layer| resize_grid(layer, old_size, new_size, fill))
+                .collect();
+        }
+        self.owners = resize_packed_grid(&self.owners, old_size, new_size);
+        if self.fog.len() == owner_words(old_n) {
+            self.fog = resize_packed_grid(&self.fog, old_size, new_size);
+        }
+        if self.tint_layer {
+            self.tints = resize_grid(&self.tints, old_size, new_size, u32::MAX);
+        }
+        if self.height_layer {
+            self.heights = resize_grid(&self.heights, old_size, new_size, 0.0);
+        }
+        if self.damage.len() == old_n {
+            self.damage = resize_grid(&self.damage, old_size, new_size, 0);
+        }
+        if self.shadow_coverage.len() == old_n {
+            self.shadow_coverage = resize_grid(&self.shadow_coverage, old_size, new_size, 0.0);
+        }
+
+        // Depth-scaled row heights include the scale, which depends on the map height
+        let grid_sizes = self.variable_grid.as_ref().map(|grid| {
+            let (column_widths, mut row_heights) = grid.sizes();
+            if self.depth_scaled_rows {
+                for (y, height) in row_heights.iter_mut().enumerate() {
+                    *height /= self.row_scale(y as f32 + 0.5);
+                }
+            }
+            (column_widths, row_heights)
+        });
+
+        self.map_uniform.map_size = new_size;
+        self.stats = TileStats::from_tiles(self.map_texture.iter().copied());
+        self.content_hash = hash_tiles(&self.map_texture);
+        if let Some(occlusion) = self.occlusion.as_mut() {
+            occlusion.rebuild(&self.map_texture);
+        }
+        if let Some((column_widths, mut row_heights)) = grid_sizes {
+            if self.depth_scaled_rows {
+                row_heights.resize(new_size.y as usize, 1.0);
+                for (y, height) in row_heights.iter_mut().enumerate() {
+                    *height *= self.row_scale(y as f32 + 0.5);
+                }
+            }
+            let grid = VariableGrid::new(new_size, &column_widths, &row_heights);
+            self.grid_offsets = grid.shader_data();
+            self.variable_grid = Some(grid);
+        }
+        if new_n > 0 {
+            self.changed_tiles
+                .add(URect::from_corners(UVec2::ZERO, new_size));
+        }
+        for channel in Channel::ALL {
+            self.changed_tiles.add_channel(channel);
+        }
+
+        self.update_inverse_projection();
+        let extent = self.linear_extent();
+        self.map_uniform.update_world_size(extent);
+    }
+
+    /// Change the projection of a built map, see [`MapBuilder::with_projection`].
+    pub fn set_projection(&mut self, projection: TileProjection) {
+        self.map_uniform.projection = projection.projection;
+        self.map_uniform.tile_anchor_point = projection.tile_anchor_point;
+        self.update_inverse_projection();
+        let extent = self.linear_extent();
+        self.map_uniform.update_world_size(extent);
     }
 
     pub(crate) fn update_inverse_projection(&mut self) {
@@ -374,16 +1304,26 @@ impl<'a, C: Customization> MapIndexer<'a, C> {
         self.map.map_texture[idx]
     }
 
+    /// Get tile at given position of the given layer, see [`MapBuilder::with_layers`].
+    pub fn at_layer(&self, layer: u32, x: u32, y: u32) -> u32 {
+        self.map.tile_at_layer(layer, x, y)
+    }
+
+    /// Tint of the tile at given position, see [`MapBuilder::with_tint_layer`].
+    pub fn tint_at(&self, x: u32, y: u32) -> Color {
+        self.map.tint_at(x, y)
+    }
+
     pub fn map_texture(&self) -> &Vec<u32> {
         &self.map.map_texture
     }
 
-    pub fn world_to_map(&self, world: Vec2) -> Vec2 {
-        self.map.world_to_map(world)
+    pub fn world_to_map(&self, transform: &GlobalTransform, world: Vec2) -> Vec2 {
+        self.map.world_to_map(transform, world)
     }
 
-    pub fn map_to_world_3d(&self, map_position: Vec3) -> Vec3 {
-        self.map.map_to_world_3d(map_position)
+    pub fn map_to_world_3d(&self, transform: &GlobalTransform, map_position: Vec3) -> Vec3 {
+        self.map.map_to_world_3d(transform, map_position)
     }
 
     pub fn map_to_local_3d(&self, map_position: Vec3) -> Vec3 {
@@ -394,8 +1334,8 @@ impl<'a, C: Customization> MapIndexer<'a, C> {
         self.map.map_to_local(map_position)
     }
 
-    pub fn world_to_map_3d(&self, world: Vec3) -> Vec3 {
-        self.map.world_to_map_3d(world)
+    pub fn world_to_map_3d(&self, transform: &GlobalTransform, world: Vec3) -> Vec3 {
+        self.map.world_to_map_3d(transform, world)
     }
 }
 // Internally holds a mutable reference to the underlying texture.
@@ -403,9 +1343,32 @@ impl<'a, C: Customization> MapIndexer<'a, C> {
 // #[derive(Debug)]
 pub struct MapIndexerMut<'a, C: Customization = NoCustomization> {
     pub(crate) map: &'a mut Map<C>,
+    /// Tiles before the first [`Self::iter_mut`], to account for its writes when dropped.
+    snapshot: Option<Vec<u32>>,
+}
+
+impl<'a, C: Customization> Drop for MapIndexerMut<'a, C> {
+    fn drop(&mut self) {
+        let Some(old) = self.snapshot.take() else {
+            return;
+        };
+        for (idx, &old) in old.iter().enumerate() {
+            let v = self.map.map_texture[idx];
+            if old != v {
+                self.tile_changed(idx, old, v);
+            }
+        }
+    }
 }
 
 impl<'a, C: Customization> MapIndexerMut<'a, C> {
+    pub(crate) fn new(map: &'a mut Map<C>) -> Self {
+        Self {
+            map,
+            snapshot: None,
+        }
+    }
+
     /// Size of the map being indexed.
     pub fn size(&self) -> UVec2 {
         self.map.map_size()
@@ -431,6 +1394,46 @@ impl<'a, C: Customization> MapIndexerMut<'a, C> {
         self.map.map_texture[idx]
     }
 
+    /// Get tile at given position of the given layer, see [`MapBuilder::with_layers`].
+    pub fn at_layer(&self, layer: u32, x: u32, y: u32) -> u32 {
+        self.map.tile_at_layer(layer, x, y)
+    }
+
+    /// Tint of the tile at given position, see [`MapBuilder::with_tint_layer`].
+    pub fn tint_at(&self, x: u32, y: u32) -> Color {
+        self.map.tint_at(x, y)
+    }
+
+    /// Set the tint of the tile at given position, which is multiplied onto the tile's colors.
+    /// Has no effect for maps without tint layer, see [`MapBuilder::with_tint_layer`].
+    pub fn set_tint(&mut self, x: u32, y: u32, color: Color) {
+        let size = self.size();
+        if x >= size.x || y >= size.y {
+            return;
+        }
+        let idx = y as usize * size.x as usize + x as usize;
+        if let Some(tint) = self.map.tints.get_mut(idx) {
+            *tint = u32::from_le_bytes(color.to_linear().to_u8_array());
+            self.map.changed_tiles.add_channel(Channel::Tints);
+        }
+    }
+
+    /// Set tile at given position of the given layer, see [`MapBuilder::with_layers`].
+    /// Layer 0 is the base layer, ie. the same as [`Self::set`].
+    ///
+    /// Only the base layer is reflected in [`Map::stats`] and [`Map::content_hash`].
+    pub fn set_layer(&mut self, layer: u32, x: u32, y: u32, v: u32) {
+        if layer == 0 {
+            return self.set(x, y, v);
+        }
+        if let Some(idx) = self.map.layer_idx(layer, x, y) {
+            let old = std::mem::replace(&mut self.map.layer_texture[idx], v);
+            if old != v {
+                self.report_change(layer, uvec2(x, y), v);
+            }
+        }
+    }
+
     /// Set tile at given position.
     pub fn set_uvec(&mut self, i: UVec2, v: u32) {
         self.set(i.x, i.y, v)
@@ -443,15 +1446,150 @@ impl<'a, C: Customization> MapIndexerMut<'a, C> {
             return;
         }
         let idx = y as usize * self.size().x as usize + x as usize;
-        self.map.map_texture[idx] = v;
+        let old = std::mem::replace(&mut self.map.map_texture[idx], v);
+        if old != v {
+            self.tile_changed(idx, old, v);
+        }
+    }
+
+    /// Update the data derived from the base layer after the tile at `idx` changed.
+    fn tile_changed(&mut self, idx: usize, old: u32, v: u32) {
+        // After `iter_mut` the snapshot holds the tiles accounted for so far
+        let old = match self.snapshot.as_mut() {
+            Some(snapshot) => std::mem::replace(&mut snapshot[idx], v),
+            None => old,
+        };
+        if old == v {
+            return;
+        }
+        self.map.stats.remove(old);
+        self.map.stats.add(v);
+        self.map.content_hash ^= cell_hash(idx, old) ^ cell_hash(idx, v);
+        if let Some(damage) = self.map.damage.get_mut(idx) {
+            *damage = 0;
+        }
+        if let Some(occlusion) = self.map.occlusion.as_mut() {
+            occlusion.update(idx, v);
+        }
+        let width = self.size().x as usize;
+        self.report_change(0, uvec2((idx % width) as u32, (idx / width) as u32), v);
+    }
+
+    /// Report the tile at `pos` of `layer` as changed and record the edit.
+    fn report_change(&mut self, layer: u32, pos: UVec2, v: u32) {
+        let rect = URect::from_corners(pos, pos + UVec2::ONE);
+        match layer {
+            0 => self.map.changed_tiles.add(rect),
+            _ => self.map.changed_tiles.add_layers(rect),
+        }
+        if let Some(recorder) = self.map.recorder.as_mut() {
+            recorder.record(layer, pos, v);
+        }
+    }
+
+    /// Tile at given position, `None` if out of bounds.
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        let size = self.size();
+        (x < size.x && y < size.y)
+            .then(|| self.map.map_texture[y as usize * size.x as usize + x as usize])
+    }
+
+    /// Tile at given position.
+    pub fn try_get(&self, x: u32, y: u32) -> Result<u32, OutOfBoundsError> {
+        self.get(x, y).ok_or(OutOfBoundsError {
+            position: uvec2(x, y),
+            size: self.size(),
+        })
+    }
+
+    /// Set tile at given position, unlike [`Self::set`] out of bounds positions are an error.
+    pub fn try_set(&mut self, x: u32, y: u32, v: u32) -> Result<(), OutOfBoundsError> {
+        let size = self.size();
+        if x >= size.x || y >= size.y {
+            return Err(OutOfBoundsError {
+                position: uvec2(x, y),
+                size,
+            });
+        }
+        self.set(x, y, v);
+        Ok(())
+    }
+
+    /// All tiles of the base layer with their positions, row by row, eg. for procedural
+    /// generation. Stats, content hash and changed tiles are updated when the indexer is dropped.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (UVec2, &mut u32)> + '_ {
+        if self.snapshot.is_none() {
+            self.snapshot = Some(self.map.map_texture.clone());
+        }
+        let width = self.size().x.max(1);
+        self.map
+            .map_texture
+            .iter_mut()
+            .enumerate()
+            .map(move |(i, tile)| (uvec2(i as u32 % width, i as u32 / width), tile))
+    }
+
+    /// Set all tiles in `rect` (`max` is exclusive), clamped to the map.
+    pub fn fill_rect(&mut self, rect: URect, v: u32) {
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, self.size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.set(x, y, v);
+            }
+        }
+    }
+
+    /// Replace the 4-connected region of tiles that have the same value as `start` with `v`.
+    /// Returns the number of tiles in the region.
+    pub fn flood_fill(&mut self, start: UVec2, v: u32) -> usize {
+        let region = flood_region(self.size(), start, |p| self.at_uvec(p));
+        for pos in region.iter() {
+            self.set_uvec(*pos, v);
+        }
+        region.len()
+    }
+
+    /// Set all tiles within `radius` tiles (euclidean distance between tile centers) of
+    /// `center`, clamped to the map.
+    pub fn fill_circle(&mut self, center: UVec2, radius: f32, v: u32) {
+        let r = radius.max(0.0).floor() as u32;
+        let rect = URect::from_corners(center.saturating_sub(UVec2::splat(r)), center + r + 1)
+            .intersect(URect::from_corners(UVec2::ZERO, self.size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                if uvec2(x, y).as_vec2().distance_squared(center.as_vec2()) <= radius * radius {
+                    self.set(x, y, v);
+                }
+            }
+        }
+    }
+
+    /// Set the 8-connected line of tiles from `from` to `to` (both inclusive),
+    /// tiles outside the map are skipped.
+    pub fn draw_line(&mut self, from: UVec2, to: UVec2, v: u32) {
+        for pos in line_tiles(from.as_ivec2(), to.as_ivec2()) {
+            self.set_uvec(pos.as_uvec2(), v);
+        }
+    }
+
+    /// Copy the tiles of `rect` (`max` exclusive) of `source` to this map, with `rect.min` placed
+    /// at `dest`. Parts outside of either map are skipped.
+    pub fn blit<D: Customization>(&mut self, source: &MapIndexer<D>, rect: URect, dest: UVec2) {
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, source.size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                let pos = uvec2(x, y);
+                self.set_uvec(dest + pos - rect.min, source.at_uvec(pos));
+            }
+        }
     }
 
-    pub fn world_to_map(&self, world: Vec2) -> Vec2 {
-        self.map.world_to_map(world)
+    pub fn world_to_map(&self, transform: &GlobalTransform, world: Vec2) -> Vec2 {
+        self.map.world_to_map(transform, world)
     }
 
-    pub fn map_to_world_3d(&self, map_position: Vec3) -> Vec3 {
-        self.map.map_to_world_3d(map_position)
+    pub fn map_to_world_3d(&self, transform: &GlobalTransform, map_position: Vec3) -> Vec3 {
+        self.map.map_to_world_3d(transform, map_position)
     }
 
     pub fn map_to_local_3d(&self, map_position: Vec3) -> Vec3 {
@@ -462,11 +1600,103 @@ impl<'a, C: Customization> MapIndexerMut<'a, C> {
         self.map.map_to_local(map_position)
     }
 
-    pub fn world_to_map_3d(&self, world: Vec3) -> Vec3 {
-        self.map.world_to_map_3d(world)
+    pub fn world_to_map_3d(&self, transform: &GlobalTransform, world: Vec3) -> Vec3 {
+        self.map.world_to_map_3d(transform, world)
     }
 }
 
+/// Global world position relative to the entity with `transform`.
+fn world_to_local(transform: &GlobalTransform, world: Vec2) -> Vec2 {
+    transform
+        .affine()
+        .inverse()
+        .transform_point3(world.extend(0.0))
+        .xy()
+}
+
+/// Copy the region two row-major grids have in common, new cells are set to `fill`.
+fn resize_grid<T: Copy>(data: &[T], old_size: UVec2, new_size: UVec2, fill: T) -> Vec<T> {
+    let mut resized = vec![fill; new_size.x as usize * new_size.y as usize];
+    let width = old_size.x.min(new_size.x) as usize;
+    for y in 0..old_size.y.min(new_size.y) as usize {
+        let src = y * old_size.x as usize;
+        let dst = y * new_size.x as usize;
+        if let Some(row) = data.get(src..src + width) {
+            resized[dst..dst + width].copy_from_slice(row);
+        }
+    }
+    resized
+}
+
+/// Same as [`resize_grid`] for byte grids packed four per `u32` (owners, fog), filled with `0`.
+fn resize_packed_grid(words: &[u32], old_size: UVec2, new_size: UVec2) -> Vec<u32> {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let resized = resize_grid(&bytes, old_size, new_size, 0);
+    let mut words: Vec<u32> = resized
+        .chunks(4)
+        .map(|c| c.iter().rev().fold(0, |w, b| (w << 8) | *b as u32))
+        .collect();
+    words.resize(owner_words(resized.len()), 0);
+    words
+}
+
+/// Positions of the 8-connected (Bresenham) line from `from` to `to`, both inclusive.
+pub(crate) fn line_tiles(from: IVec2, to: IVec2) -> Vec<IVec2> {
+    let d = (to - from).abs();
+    let step = (to - from).signum();
+    let mut err = d.x - d.y;
+    let mut pos = from;
+    let mut tiles = Vec::with_capacity((d.x.max(d.y) + 1) as usize);
+    loop {
+        tiles.push(pos);
+        if pos == to {
+            return tiles;
+        }
+        let e2 = 2 * err;
+        if e2 > -d.y {
+            err -= d.y;
+            pos.x += step.x;
+        }
+        if e2 < d.x {
+            err += d.x;
+            pos.y += step.y;
+        }
+    }
+}
+
+/// Positions of the 4-connected region of tiles with the same value as `start`.
+pub(crate) fn flood_region(size: UVec2, start: UVec2, at: impl Fn(UVec2) -> u32) -> Vec<UVec2> {
+    if start.x >= size.x || start.y >= size.y {
+        return Vec::new();
+    }
+    let value = at(start);
+    let mut visited = vec![false; (size.x * size.y) as usize];
+    let mut stack = vec![start];
+    let mut region = Vec::new();
+    visited[(start.y * size.x + start.x) as usize] = true;
+
+    while let Some(pos) = stack.pop() {
+        region.push(pos);
+        let neighbors = [
+            pos.x.checked_sub(1).map(|x| uvec2(x, pos.y)),
+            pos.y.checked_sub(1).map(|y| uvec2(pos.x, y)),
+            Some(uvec2(pos.x + 1, pos.y)),
+            Some(uvec2(pos.x, pos.y + 1)),
+        ];
+        for n in neighbors.into_iter().flatten() {
+            if n.x >= size.x || n.y >= size.y {
+                continue;
+            }
+            let i = (n.y * size.x + n.x) as usize;
+            if !visited[i] && at(n) == value {
+                visited[i] = true;
+                stack.push(n);
+            }
+        }
+    }
+    region
+}
+
 pub fn log_map_events<C: Customization>(
     mut ev_asset: EventReader<AssetEvent<Map<C>>>,
     map_handles: Query<&Handle<Map<C>>>,
@@ -485,6 +1715,7 @@ pub fn log_map_events<C: Customization>(
 
 /// Check to see if any maps' assets became available
 /// if so.
+#[allow(clippy::too_many_arguments)]
 pub fn update_loading_maps<C: Customization>(
     mut images: ResMut<Assets<Image>>,
     mut map_materials: ResMut<Assets<Map<C>>>,
@@ -494,48 +1725,78 @@ pub fn update_loading_maps<C: Customization>(
             Option<&MapAttributes>,
             &Handle<Map<C>>,
             Option<&MeshManagedByMap>,
+            Option<&MapAnimationClock>,
         ),
         With<MapLoading>,
     >,
     mut meshes: ResMut<Assets<Mesh>>,
     mut commands: Commands,
-    time: Res<Time>,
+    animation_time: Res<MapAnimationTime>,
+    settings: Res<FastTileMapSettings>,
+    render_device: Option<Res<RenderDevice>>,
+    mut ready: EventWriter<MapReady>,
+    mut failed: EventWriter<MapFailed>,
 ) {
-    for (entity, attributes, map_handle, manage_mesh) in maps.iter_mut() {
+    let max_buffer_size = render_device.map_or(DEFAULT_MAX_BUFFER_SIZE, |device| {
+        device.limits().max_storage_buffer_binding_size as u64
+    });
+
+    for (entity, attributes, map_handle, manage_mesh, clock) in maps.iter_mut() {
         let Some(map) = map_materials.get_mut(map_handle) else {
             continue;
         };
+        if !map.pages_loaded(&images) {
+            continue;
+        }
         let Some(atlas) = images.get_mut(&map.atlas_texture) else {
             continue;
         };
 
-        atlas.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
-            // min_filter of linear gives undesired grid lines when zooming out
-            min_filter: ImageFilterMode::Nearest,
-            // mag_filter of linear gives mushy edges on tiles in closeup which is
-            // usually not what we want
-            mag_filter: ImageFilterMode::Nearest,
-            mipmap_filter: ImageFilterMode::Linear,
-            ..default()
-        });
+        atlas.sampler = settings.filtering.atlas_sampler();
 
         commands.entity(entity).remove::<MapLoading>();
-        map.update(images.as_ref());
+        if let Err(error) = map.validate(max_buffer_size) {
+            error!("{}", error);
+            map.invalid = true;
+            commands.entity(entity).insert(Visibility::Hidden);
+            failed.send(MapFailed { map: entity, error });
+            continue;
+        }
+        let result = map.try_update(images.as_ref());
+        map.update_atlas_array(&mut images, settings.filtering.atlas_array_sampler());
+        if map.atlas_array.is_none() {
+            map.update_atlas_pages(&mut images, settings.filtering.atlas_sampler());
+        }
 
         if manage_mesh.is_some() {
             let mut mesh = Mesh::from(Rectangle {
                 half_size: map.world_size() / 2.0,
             });
 
-            MapAttributes::set_mix_color(attributes, &mut mesh);
+            // Layer groups are applied on the next vertex attribute update
+            MapAttributes::set_mix_color(attributes, Vec4::ONE, &mut mesh);
             MapAttributes::set_map_position(attributes, &mut mesh, &map);
-            MapAttributes::set_animation_state(attributes, &mut mesh, &time);
+            let time = map_animation_time(clock, &animation_time);
+            MapAttributes::set_animation_state(attributes, &mut mesh, time);
+            MapAttributes::set_row_range(attributes, &mut mesh);
 
             let mesh = Mesh2dHandle(meshes.add(mesh));
             commands.entity(entity).insert(mesh);
         }
 
         debug!("Map loaded: {:?}", map.map_size());
+        match result {
+            Ok(_) => {
+                ready.send(MapReady { map: entity });
+            }
+            Err(e) => {
+                warn!("{}", e);
+                failed.send(MapFailed {
+                    map: entity,
+                    error: e.into(),
+                });
+            }
+        }
     }
 }
 
@@ -548,31 +1809,46 @@ pub fn update_map_vertex_attributes<C: Customization>(
         &MapAttributes,
         Option<&Mesh2dHandle>,
         Option<&MeshManagedByMap>,
+        Option<&MapAnimationClock>,
+        Option<&RelativeView>,
     )>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut commands: Commands,
-    time: Res<Time>,
+    animation_time: Res<MapAnimationTime>,
+    parents: Query<&Parent>,
+    groups: Query<&MapLayerGroup>,
 ) {
-    for (entity, map_handle, attr, mesh_handle, manage_mesh) in maps.iter() {
+    for (entity, map_handle, attr, mesh_handle, manage_mesh, clock, view) in maps.iter() {
         let Some(map) = map_materials.get(map_handle) else {
             warn!("No map material");
             continue;
         };
+        if map.invalid {
+            continue;
+        }
 
         let mut mesh = if manage_mesh.is_some() {
-            let p = map.world_size() / 2.0;
+            // With relative origin, edge modes or parallax cover what the cameras see, see `precision`
+            let rect = match view.filter(|_| map.covers_view()) {
+                Some(view) => view.rect,
+                None => map.local_bounds(),
+            };
+            let (center, p) = (rect.center(), rect.half_size());
             Mesh::from(Triangle2d::new(
-                vec2(-p.x, p.y),
-                vec2(-p.x, -3.0 * p.y),
-                vec2(3.0 * p.x, p.y),
+                center + vec2(-p.x, p.y),
+                center + vec2(-p.x, -3.0 * p.y),
+                center + vec2(3.0 * p.x, p.y),
             ))
         } else {
             meshes.get(&mesh_handle.unwrap().0).unwrap().clone()
         };
 
-        MapAttributes::set_mix_color(Some(attr), &mut mesh);
+        let group_color = layer_group_mix_color(entity, &parents, &groups);
+        MapAttributes::set_mix_color(Some(attr), group_color, &mut mesh);
         MapAttributes::set_map_position(Some(attr), &mut mesh, &map);
-        MapAttributes::set_animation_state(Some(attr), &mut mesh, &time);
+        let time = map_animation_time(clock, &animation_time);
+        MapAttributes::set_animation_state(Some(attr), &mut mesh, time);
+        MapAttributes::set_row_range(Some(attr), &mut mesh);
 
         let mesh = Mesh2dHandle(meshes.add(mesh));
         commands.entity(entity).insert(mesh);
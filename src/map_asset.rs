@@ -0,0 +1,253 @@
+//! Map tiles as an asset loaded from `.map.ron` or `.bftiles` files, so levels can be edited on disk
+//! and (with bevy's `file_watcher` feature) hot reloaded into the running game.
+//!
+//! ```ron
+//! (
+//!     width: 3,
+//!     height: 2,
+//!     tiles: [
+//!         1, 1, 2,
+//!         0, 4, 2,
+//!     ],
+//!     // Optional, one entry per upper layer (see `MapBuilder::with_layers`)
+//!     layers: [],
+//! )
+//! ```
+//!
+//! Only the tiles are stored, the map is set up in code and keeps its settings:
+//!
+//! ```ignore
+//! app.add_plugins(MapAssetPlugin);
+//! commands.spawn((
+//!     MapBundleManaged::new(map, materials.as_mut()),
+//!     MapSource(asset_server.load("levels/level1.map.ron")),
+//! ));
+//! ```
+//!
+//! Each time the asset is (re)loaded its tiles are written to the map, which is resized if the
+//! sizes differ. Binary files use the native map format (see [`crate::format`]) without settings,
+//! followed by the number of upper layers (`u32`) and their tiles, row by row. They have an
+//! extension of their own as `.bftm` files are loaded as whole maps by [`crate::format::MapLoader`].
+//! Data written by [`Map::to_bytes`] can still be read, only its base layer is used.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    math::uvec2,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    error::{MapFormatError, MapLoadError},
+    format::{read_u32, MapFormatMigrations, MapFormatVersion, MAGIC},
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Tiles of a map, see the [module docs](self).
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MapAsset {
+    pub width: u32,
+    pub height: u32,
+    /// Tiles of the base layer, row by row.
+    pub tiles: Vec<u32>,
+    /// Tiles of the upper layers, each row by row.
+    #[serde(default)]
+    pub layers: Vec<Vec<u32>>,
+}
+
+impl MapAsset {
+    pub fn size(&self) -> UVec2 {
+        uvec2(self.width, self.height)
+    }
+
+    /// Tiles of all layers of `map`.
+    pub fn from_map<C: Customization>(map: &Map<C>) -> Self {
+        let size = map.map_size();
+        let m = map.indexer();
+        let layers = (1..map.n_layers())
+            .map(|layer| {
+                (0..size.y)
+                    .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+                    .map(|(x, y)| m.at_layer(layer, x, y))
+                    .collect()
+            })
+            .collect();
+        Self {
+            width: size.x,
+            height: size.y,
+            tiles: map.map_texture.clone(),
+            layers,
+        }
+    }
+
+    /// Write the tiles to `map`, resizing it first if its size differs.
+    /// Layers the map does not have are skipped.
+    pub fn apply<C: Customization>(&self, map: &mut Map<C>) {
+        let size = self.size();
+        if map.map_size() != size {
+            map.resize(size, 0);
+        }
+        let n = (size.x * size.y) as usize;
+        let mut m = map.indexer_mut();
+        for (i, tile) in self.tiles.iter().take(n).enumerate() {
+            m.set(i as u32 % size.x, i as u32 / size.x, *tile);
+        }
+        for (layer, tiles) in self.layers.iter().enumerate() {
+            for (i, tile) in tiles.iter().take(n).enumerate() {
+                m.set_layer(
+                    layer as u32 + 1,
+                    i as u32 % size.x,
+                    i as u32 / size.x,
+                    *tile,
+                );
+            }
+        }
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, MapLoadError> {
+        let asset: Self = ron::from_str(ron).map_err(|e| MapLoadError::Ron(e.to_string()))?;
+        let n = asset.width as usize * asset.height as usize;
+        if asset.tiles.len() != n || asset.layers.iter().any(|layer| layer.len() != n) {
+            return Err(MapLoadError::Ron(format!(
+                "expected {} tiles per layer for a {}x{} map",
+                n, asset.width, asset.height
+            )));
+        }
+        Ok(asset)
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, default()).unwrap_or_default()
+    }
+
+    /// Read the tiles from data in the native map format, migrating older versions, see the
+    /// [module docs](self).
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MapFormatError> {
+        let payload = MapFormatMigrations::default().migrate(bytes)?;
+        let (width, height) = (read_u32(&payload, 0)?, read_u32(&payload, 4)?);
+        let n = width as usize * height as usize;
+        let read_tiles = |offset: usize| -> Result<Vec<u32>, MapFormatError> {
+            (0..n).map(|i| read_u32(&payload, offset + i * 4)).collect()
+        };
+        let tiles = read_tiles(8)?;
+
+        // Upper layers follow if no map settings are stored
+        let mut offset = 8 + n * 4;
+        let mut layers = Vec::new();
+        if read_u32(&payload, offset)? == 0 && payload.len() > offset + 4 {
+            let n_layers = read_u32(&payload, offset + 4)?;
+            offset += 8;
+            for _ in 0..n_layers {
+                layers.push(read_tiles(offset)?);
+                offset += n * 4;
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles,
+            layers,
+        })
+    }
+
+    /// Encode the tiles of all layers in the native map format, without map settings.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&MapFormatVersion::CURRENT.0.to_le_bytes());
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        for tile in self.tiles.iter() {
+            bytes.extend_from_slice(&tile.to_le_bytes());
+        }
+        // No settings follow
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+        for tile in self.layers.iter().flatten() {
+            bytes.extend_from_slice(&tile.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// The map of this entity takes its tiles from a [`MapAsset`], whenever it is (re)loaded.
+/// Requires [`MapAssetPlugin`] (or [`CustomMapAssetPlugin`]).
+#[derive(Component, Debug, Clone, Default)]
+pub struct MapSource(pub Handle<MapAsset>);
+
+/// Plugin for loading [`MapAsset`]s and applying them to maps with [`MapSource`].
+pub type MapAssetPlugin = CustomMapAssetPlugin<NoCustomization>;
+
+/// Same as [`MapAssetPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomMapAssetPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomMapAssetPlugin<C> {
+    fn build(&self, app: &mut App) {
+        // Shared by all customizations
+        if !app.world().contains_resource::<Assets<MapAsset>>() {
+            app.init_asset::<MapAsset>()
+                .register_asset_loader(MapAssetLoader);
+        }
+        app.add_systems(Update, apply_map_sources::<C>);
+    }
+}
+
+fn apply_map_sources<C: Customization>(
+    mut events: EventReader<AssetEvent<MapAsset>>,
+    sources: Query<(Ref<MapSource>, &Handle<Map<C>>)>,
+    map_assets: Res<Assets<MapAsset>>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    let loaded: Vec<_> = events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    for (source, map_handle) in sources.iter() {
+        if !source.is_changed() && !loaded.contains(&source.0.id()) {
+            continue;
+        }
+        let (Some(asset), Some(map)) = (map_assets.get(&source.0), maps.get_mut(map_handle)) else {
+            continue;
+        };
+        asset.apply(map);
+    }
+}
+
+/// Loads `.map.ron` and `.bftiles` files as [`MapAsset`]s.
+#[derive(Debug, Default)]
+pub struct MapAssetLoader;
+
+impl AssetLoader for MapAssetLoader {
+    type Asset = MapAsset;
+    type Settings = ();
+    type Error = MapLoadError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<MapAsset, MapLoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        if load_context
+            .path()
+            .extension()
+            .is_some_and(|ext| ext == "bftiles")
+        {
+            return Ok(MapAsset::from_bytes(&bytes)?);
+        }
+        MapAsset::from_ron(&String::from_utf8_lossy(&bytes))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["map.ron", "bftiles"]
+    }
+}
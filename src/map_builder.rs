@@ -1,11 +1,19 @@
 use super::prelude::*;
-use bevy::{math::uvec2, prelude::*};
+use bevy::{
+    math::{uvec2, vec2},
+    prelude::*,
+};
 
-use super::tile_projection::TileProjection;
+use super::{
+    content_hash::hash_tiles, grid::VariableGrid, ownership::owner_words, stats::TileStats,
+    tile_projection::TileProjection,
+};
 
 /// Builder for constructing a map component. This is usually the preferred way of constructing.
 pub struct MapBuilder<C: Customization = NoCustomization> {
     map: Map<C>,
+    column_widths: Vec<f32>,
+    row_heights: Vec<f32>,
 }
 
 impl<C: Customization> MapBuilder<C> {
@@ -25,6 +33,8 @@ impl<C: Customization> MapBuilder<C> {
                 dominance_overhangs: false,
                 ..default()
             },
+            column_widths: Vec::new(),
+            row_heights: Vec::new(),
         }
     } // fn new
 
@@ -50,9 +60,51 @@ impl<C: Customization> MapBuilder<C> {
                 user_data,
                 ..default()
             },
+            column_widths: Vec::new(),
+            row_heights: Vec::new(),
         }
     } // fn new
 
+    /// Create a builder for an isometric map (diamond-shaped tiles).
+    /// Uses [`crate::tile_projection::AXONOMETRIC`] projection with perspective overhangs,
+    /// so tiles further down on screen are drawn on top.
+    pub fn isometric(map_size: UVec2, atlas_texture: Handle<Image>, tile_size: Vec2) -> Self {
+        Self::new(map_size, atlas_texture, tile_size)
+            .with_projection(AXONOMETRIC)
+            .with_perspective_overhang()
+    }
+
+    /// Create a builder for a map of pointy-top hexagons in axial coordinates.
+    /// Uses [`crate::tile_projection::HEX_POINTY`] projection.
+    /// As hexagons do not line up with the (rectangular) tile cells, neighbors are sampled in
+    /// all directions so each hexagon is fully drawn.
+    pub fn hex_pointy(map_size: UVec2, atlas_texture: Handle<Image>, tile_size: Vec2) -> Self {
+        Self::new(map_size, atlas_texture, tile_size)
+            .with_projection(HEX_POINTY)
+            .with_forced_underhangs(Self::hex_underhangs())
+    }
+
+    /// Create a builder for a map of flat-top hexagons in axial coordinates.
+    /// Uses [`crate::tile_projection::HEX_FLAT`] projection.
+    /// As hexagons do not line up with the (rectangular) tile cells, neighbors are sampled in
+    /// all directions so each hexagon is fully drawn.
+    pub fn hex_flat(map_size: UVec2, atlas_texture: Handle<Image>, tile_size: Vec2) -> Self {
+        Self::new(map_size, atlas_texture, tile_size)
+            .with_projection(HEX_FLAT)
+            .with_forced_underhangs(Self::hex_underhangs())
+    }
+
+    /// Underhangs in four directions, overhangs are implicitly the other four.
+    /// Hex tiles never overlap, so the order in which neighbors are drawn does not matter.
+    fn hex_underhangs() -> Vec<Vec2> {
+        vec![
+            vec2(-1.0, 0.0),
+            vec2(-1.0, -1.0),
+            vec2(0.0, -1.0),
+            vec2(1.0, -1.0),
+        ]
+    }
+
     pub fn with_atlas_tile_size_factor(mut self, factor: i32) -> Self {
         self.map.map_uniform.atlas_tile_size_factor = factor;
         self
@@ -81,6 +133,15 @@ impl<C: Customization> MapBuilder<C> {
         self
     }
 
+    /// Maximum distance to the nearest integer the number of tiles per atlas row/column
+    /// may have when derived from atlas size, tile size and padding.
+    /// Default is `0.01`.
+    /// See [`crate::error::AtlasTileCountError`] for what happens if this is exceeded.
+    pub fn with_n_tiles_tolerance(mut self, tolerance: f32) -> Self {
+        self.map.n_tiles_tolerance = tolerance;
+        self
+    }
+
     /// Specify the padding in the `atlas_texture`.
     /// `inner`: Padding between the tiles,
     /// `topleft`: Padding to top and left of the tile atlas,
@@ -89,7 +150,8 @@ impl<C: Customization> MapBuilder<C> {
     /// Note that it is crucial that these values are precisely correct,
     /// we use them internally to determine how many tiles there are in the atlas in each
     /// direction, if that does not produce a number close to an integer,
-    /// you will get a `panic` when the tile atlas is loaded.
+    /// you will get a warning with suggested paddings when the tile atlas is loaded
+    /// (or an [`crate::error::AtlasTileCountError`] from [`Map::try_update`]).
     pub fn with_padding(mut self, inner: Vec2, topleft: Vec2, bottomright: Vec2) -> Self {
         self.map.map_uniform.inner_padding = inner;
         self.map.map_uniform.outer_padding_topleft = topleft;
@@ -97,6 +159,44 @@ impl<C: Customization> MapBuilder<C> {
         self
     }
 
+    /// Give columns individual widths, relative to the tile size
+    /// (i.e. `1.0` means the column is `tile_size.x` wide).
+    /// Tiles are stretched to fill their column.
+    /// Missing entries default to `1.0`.
+    ///
+    /// Note that overhangs assume uniform tile sizes and will only line up exactly
+    /// for neighbors of the same size.
+    pub fn with_column_widths(mut self, column_widths: Vec<f32>) -> Self {
+        self.column_widths = column_widths;
+        self
+    }
+
+    /// Give rows individual heights, relative to the tile size
+    /// (i.e. `1.0` means the row is `tile_size.y` high).
+    /// Tiles are stretched to fill their row.
+    /// Missing entries default to `1.0`.
+    ///
+    /// Note that overhangs assume uniform tile sizes and will only line up exactly
+    /// for neighbors of the same size.
+    pub fn with_row_heights(mut self, row_heights: Vec<f32>) -> Self {
+        self.row_heights = row_heights;
+        self
+    }
+
+    /// Scale tiles per row, interpolating linearly from `top` (row 0) to `bottom`
+    /// (last row), e.g. smaller towards the top for fake depth (roads, stadium-style playfields).
+    /// Rows are scaled around the horizontal center of the map and row heights are scaled
+    /// accordingly (on top of [`Self::with_row_heights`], if given).
+    ///
+    /// Scales should be in `(0.0, 1.0]`, the map bounding box is computed for a scale of `1.0`.
+    /// Coordinate conversions (eg. [`Map::world_to_map`]) take the scaling into account
+    /// so picking keeps working.
+    pub fn with_depth_scale(mut self, top: f32, bottom: f32) -> Self {
+        self.map.map_uniform.depth_scale = vec2(top, bottom);
+        self.map.depth_scaled_rows = true;
+        self
+    }
+
     /// Render this map in "dominance" overhang mode.
     /// "Dominance" overhang draws the overlap of tiles depending on their index in the tile atlas.
     /// Tiles with higher index will be drawn on top of tiles with lower index.
@@ -144,11 +244,214 @@ impl<C: Customization> MapBuilder<C> {
         self
     }
 
+    /// Smooth the outline of the map with analytically computed edge coverage,
+    /// instead of the stair-stepped alpha edges of the tile art.
+    /// Mostly useful for non-rectangular layouts (isometric, hexagonal) where the map border runs
+    /// diagonally. Requires the tile art to fill its cell (as given by the projection).
+    ///
+    /// Only the outer border of the map is smoothed, edges between tiles inside the map (eg. next
+    /// to transparent tiles) keep the alpha of the tile art. For hexagonal maps the cells are the
+    /// parallelograms spanned by the projection, which hexagonal tile art does not fill, so the
+    /// smoothed border follows the parallelograms rather than the hexagon outlines.
+    pub fn with_edge_antialiasing(mut self, enabled: bool) -> Self {
+        self.map.edge_antialiasing = enabled;
+        self
+    }
+
+    /// Split the atlas into a texture array with one layer per atlas index once it is loaded,
+    /// so tiles do not bleed into each other when filtered, see [`crate::atlas_array`].
+    /// Tiles can not overhang into the atlas padding then.
+    /// Falls back to the packed atlas for compressed texture formats.
+    pub fn with_texture_array(mut self, enabled: bool) -> Self {
+        self.map.texture_array = enabled;
+        self
+    }
+
+    /// Use several atlas textures (pages) with the same size and layout, the page of a tile is
+    /// selected by the high bits of its value, see [`crate::pages`].
+    /// The first page replaces the atlas given to [`Self::new`].
+    pub fn with_atlas_pages(mut self, pages: Vec<Handle<Image>>) -> Self {
+        if let Some(first) = pages.first() {
+            self.map.atlas_texture = first.clone();
+        }
+        self.map.atlas_pages = pages;
+        self
+    }
+
+    /// Register an atlas with the same layout that can be switched to at runtime,
+    /// see [`Map::switch_atlas`].
+    pub fn with_atlas_variant(mut self, name: impl Into<String>, atlas: Handle<Image>) -> Self {
+        self.map.add_atlas_variant(name, atlas);
+        self
+    }
+
+    /// Pass map positions relative to a tile near the cameras for maps too large for `f32`
+    /// precision, see [`Map::set_relative_origin`].
+    pub fn with_relative_origin(mut self, enabled: bool) -> Self {
+        self.map.set_relative_origin(enabled);
+        self
+    }
+
+    /// Run a cellular automaton over the tiles on the GPU every frame,
+    /// see [`Map::set_automaton`].
+    pub fn with_automaton(mut self, automaton: MapAutomaton) -> Self {
+        self.map.set_automaton(Some(automaton));
+        self
+    }
+
+    /// Write per tile depth so sprites can stand behind individual tiles,
+    /// see [`Map::set_tile_depth`].
+    pub fn with_tile_depth(mut self, enabled: bool) -> Self {
+        self.map.set_tile_depth(enabled);
+        self
+    }
+
+    /// Multiply the output of the map with `color`, see [`Map::set_color`].
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.map.set_color(color);
+        self
+    }
+
+    /// Blend the map additively, multiplicatively or not at all, see [`Map::set_blend_mode`].
+    pub fn with_blend_mode(mut self, mode: MapBlendMode) -> Self {
+        self.map.set_blend_mode(mode);
+        self
+    }
+
+    /// Choose what to draw outside of the map, eg. wrap around for toroidal worlds,
+    /// see [`Map::set_edge_mode`].
+    pub fn with_edge_mode(mut self, mode: EdgeMode) -> Self {
+        self.map.set_edge_mode(mode);
+        self
+    }
+
+    /// Interpret the atlas as signed distance field, see [`Map::set_sdf`].
+    pub fn with_sdf(mut self, sdf: SdfSettings) -> Self {
+        self.map.set_sdf(Some(&sdf));
+        self
+    }
+
+    /// Maximum distance (in tiles) of neighbors that are considered for overhangs and underhangs.
+    /// Default is `1`. Increase this for tiles whose graphics reach further than their direct
+    /// neighbors (eg. very tall isometric objects), at the cost of more work per fragment.
+    pub fn with_overhang_reach(mut self, reach: u32) -> Self {
+        self.map.overhang_reach = reach.max(1);
+        self
+    }
+
+    /// Set the replacement colors/patterns for high-contrast (accessibility) rendering.
+    /// High-contrast mode itself is toggled with [`Map::set_high_contrast`].
+    pub fn with_high_contrast_palette(mut self, palette: &HighContrastPalette) -> Self {
+        self.map.set_high_contrast_palette(palette);
+        self
+    }
+
+    /// Render the raw tile values through a color ramp instead of the atlas,
+    /// see [`Map::set_color_ramp`].
+    pub fn with_color_ramp(mut self, ramp: Option<&ColorRamp>) -> Self {
+        self.map.set_color_ramp(ramp);
+        self
+    }
+
+    /// Draw tile owners as a team colored overlay, see [`Map::set_ownership_overlay`].
+    pub fn with_ownership_overlay(mut self, overlay: Option<&OwnershipOverlay>) -> Self {
+        self.map.set_ownership_overlay(overlay);
+        self
+    }
+
+    /// Animate atlas indices in the shader, see [`TileAnimations`].
+    pub fn with_tile_animations(mut self, animations: Option<&TileAnimations>) -> Self {
+        self.map.set_tile_animations(animations);
+        self
+    }
+
+    /// Dither the borders between terrain categories, see [`TerrainDither`].
+    pub fn with_terrain_dither(mut self, dither: Option<&TerrainDither>) -> Self {
+        self.map.set_terrain_dither(dither);
+        self
+    }
+
+    /// Draw tiles in flat colors when they are small on screen, see [`LodSettings`].
+    pub fn with_lod(mut self, lod: Option<LodSettings>) -> Self {
+        self.map.set_lod(lod);
+        self
+    }
+
+    /// Give the map `n` tile layers (at least 1) that are composited in order in a single draw,
+    /// eg. ground, decoration and overlay. All layers share size and projection.
+    /// Set tiles of the upper layers with [`MapIndexerMut::set_layer`], they are initialized
+    /// with atlas index 0, so use a transparent atlas tile for empty cells.
+    pub fn with_layers(mut self, n: u32) -> Self {
+        self.map.map_uniform.n_layers = n.max(1);
+        self
+    }
+
+    /// Give every tile an RGBA tint (initially white) that is multiplied onto its colors,
+    /// eg. for fog of war darkening, team colors or damage flashes.
+    /// Set tints with [`MapIndexerMut::set_tint`].
+    pub fn with_tint_layer(mut self) -> Self {
+        self.map.tint_layer = true;
+        self
+    }
+
+    /// Allocate a height per tile for raising tiles (eg. cliffs on isometric maps),
+    /// see [`crate::elevation`]. Set heights with [`MapIndexerMut::set_height`].
+    pub fn with_height_layer(mut self) -> Self {
+        self.map.height_layer = true;
+        self
+    }
+
+    /// Allow projecting decals from the given texture onto the map, see [`Map::add_decal`].
+    pub fn with_decal_texture(mut self, texture: Handle<Image>) -> Self {
+        self.map.set_decal_texture(texture);
+        self
+    }
+
+    /// Let the given atlas indices sway in the wind, see [`Map::set_tile_sway`].
+    pub fn with_tile_sway(mut self, sway: Option<&TileSway>) -> Self {
+        self.map.set_tile_sway(sway);
+        self
+    }
+
+    /// Derive occlusion from the tiles, see [`Map::set_tile_occlusion`].
+    pub fn with_tile_occlusion(mut self, occlusion: Option<&TileOcclusion>) -> Self {
+        self.map.set_tile_occlusion(occlusion);
+        self
+    }
+
+    /// Draw a fog of war, see [`Map::set_fog_of_war`].
+    pub fn with_fog_of_war(mut self, fog: Option<&FogOfWar>) -> Self {
+        self.map.set_fog_of_war(fog);
+        self
+    }
+
+    /// Render a debug visualization of the fragment cost instead of the map,
+    /// see [`OverdrawDebugMode`]. `None` (the default) renders the map normally.
+    pub fn with_overdraw_debug(mut self, mode: Option<OverdrawDebugMode>) -> Self {
+        self.map.overdraw_debug = mode;
+        self
+    }
+
     /// Build the map component.
+    /// Invalid settings are only reported once the map is loaded, see [`Self::try_build`].
     pub fn build(self) -> Map<C> {
         self.build_and_initialize(|_| {})
     }
 
+    /// Check the map settings without building, assuming the default GPU limits
+    /// (see [`DEFAULT_MAX_BUFFER_SIZE`]). The atlas can only be checked once it is loaded,
+    /// which is reported by [`MapReady`] / [`MapFailed`].
+    pub fn validate(&self) -> Result<(), MapBuildError> {
+        self.map.validate(DEFAULT_MAX_BUFFER_SIZE)
+    }
+
+    /// Build the map component if its settings are valid, see [`Self::validate`].
+    /// Unlike [`Self::build`] no tile data is allocated for invalid settings.
+    pub fn try_build(self) -> Result<Map<C>, MapBuildError> {
+        self.validate()?;
+        Ok(self.build())
+    }
+
     /// Build the map component and immediately initialize the map
     /// data with the given initializer callback.
     /// The callback will receive a mutable reference to a `MapIndexer`.
@@ -160,11 +463,44 @@ impl<C: Customization> MapBuilder<C> {
             (self.map.map_size().x * self.map.map_size().y) as usize,
             0u32,
         );
+        self.map.stats = TileStats::from_tiles(self.map.map_texture.iter().copied());
+        self.map.content_hash = hash_tiles(&self.map.map_texture);
+        self.map.owners = vec![0; owner_words(self.map.map_texture.len())];
+        if let Some(occlusion) = self.map.occlusion.as_mut() {
+            occlusion.rebuild(&self.map.map_texture);
+        }
+        if self.map.map_fog {
+            self.map.fog = vec![0; owner_words(self.map.map_texture.len())];
+        }
+        if self.map.tint_layer {
+            self.map.tints = vec![u32::MAX; self.map.map_texture.len()];
+        }
+        if self.map.height_layer {
+            self.map.heights = vec![0.0; self.map.map_texture.len()];
+        }
+        self.map.layer_texture =
+            vec![0; (self.map.map_texture.len() * (self.map.n_layers() as usize - 1)).max(1)];
 
-        initializer(&mut MapIndexerMut::<C> { map: &mut self.map });
+        initializer(&mut MapIndexerMut::new(&mut self.map));
+
+        if self.map.depth_scaled_rows {
+            let n = self.map.map_size().y;
+            self.row_heights.resize(n as usize, 1.0);
+            for (y, height) in self.row_heights.iter_mut().enumerate() {
+                *height *= self.map.row_scale(y as f32 + 0.5);
+            }
+        }
+
+        if !self.column_widths.is_empty() || !self.row_heights.is_empty() {
+            let grid =
+                VariableGrid::new(self.map.map_size(), &self.column_widths, &self.row_heights);
+            self.map.grid_offsets = grid.shader_data();
+            self.map.variable_grid = Some(grid);
+        }
 
         self.map.update_inverse_projection();
-        self.map.map_uniform.update_world_size();
+        let extent = self.map.linear_extent();
+        self.map.map_uniform.update_world_size(extent);
 
         self.map
     } // fn build_and_initialize
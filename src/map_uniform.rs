@@ -4,7 +4,10 @@ use bevy::{
     render::render_resource::{AsBindGroup, ShaderType},
 };
 
-use super::prelude::*;
+use super::{
+    error::{AtlasTileCountError, SuggestedPadding},
+    prelude::*,
+};
 
 #[derive(ShaderType, Clone, Debug, Reflect, AsBindGroup)]
 pub struct MapUniform {
@@ -37,6 +40,67 @@ pub struct MapUniform {
     pub(crate) global_transform_matrix: Mat3,
     pub(crate) global_transform_translation: Vec3,
 
+    /// Horizontal scale of the first (x) and last (y) row for depth-scaled rows,
+    /// linearly interpolated in between.
+    pub(crate) depth_scale: Vec2,
+
+    /// Tile values mapped to the first and last color of the color ramp.
+    pub(crate) ramp_range: Vec2,
+
+    /// Signed distance field atlas: spread, outline width and glow width (in atlas pixels)
+    pub(crate) sdf_params: Vec4,
+    pub(crate) sdf_outline_color: Vec4,
+    pub(crate) sdf_glow_color: Vec4,
+
+    /// Ownership overlay: tint opacity and border width (as fraction of a tile)
+    pub(crate) owner_params: Vec2,
+
+    /// Screen size of a tile (in pixels) below which only LOD colors are drawn (x)
+    /// and above which tiles are drawn in full detail (y)
+    pub(crate) lod_range: Vec2,
+
+    /// Hole cut into the map: center (xy) and radii (zw) in world coordinates
+    pub(crate) reveal_shape: Vec4,
+    /// Hole cut into the map: feather width (x) and opacity in the center (y)
+    pub(crate) reveal_params: Vec2,
+
+    /// Shadow cast by an upper layer: offset (xy) and blur radius (z) in tiles, strength (w)
+    pub(crate) shadow_params: Vec4,
+    pub(crate) shadow_color: Vec4,
+
+    /// Number of tile layers, including the base layer
+    pub(crate) n_layers: u32,
+
+    /// Width of dithered terrain borders, as fraction of a tile
+    pub(crate) dither_width: f32,
+
+    /// Wind for swaying tiles: direction (xy), strength in pixels (z) and frequency (w)
+    pub(crate) wind: Vec4,
+
+    /// Fog of war color, alpha is the opacity over hidden tiles
+    pub(crate) fog_color: Vec4,
+    /// Fog of war blending between tile centers (1) or hard tile edges (0)
+    pub(crate) fog_softness: f32,
+
+    /// Tile the vertex map positions are relative to, see [`Map::set_relative_origin`]
+    pub(crate) origin: IVec2,
+
+    /// Progress of the cross-fade from the previous atlas, see [`Map::switch_atlas`]
+    pub(crate) atlas_fade: f32,
+
+    /// What is drawn outside of the map (see [`crate::edge_mode::EdgeMode`])
+    /// and the atlas index for [`crate::edge_mode::EdgeMode::Fill`]
+    pub(crate) edge_mode: u32,
+    pub(crate) edge_fill: u32,
+
+    /// Upper bound of the tile heights, see [`crate::elevation`]
+    pub(crate) max_height: f32,
+    /// Brightness of cliff faces below raised tiles
+    pub(crate) cliff_shade: f32,
+
+    /// Multiplied with the final output color, see [`Map::set_color`]
+    pub(crate) color: Vec4,
+
     /// (derived) Size of the map in world units necessary to display
     /// all tiles according to projection.
     pub(crate) world_size: Vec2,
@@ -76,6 +140,29 @@ impl Default for MapUniform {
             projection: IDENTITY.projection,
             global_transform_matrix: default(),
             global_transform_translation: default(),
+            depth_scale: Vec2::ONE,
+            ramp_range: Vec2::new(0.0, 1.0),
+            sdf_params: Vec4::new(8.0, 0.0, 0.0, 0.0),
+            sdf_outline_color: Vec4::ZERO,
+            sdf_glow_color: Vec4::ZERO,
+            owner_params: Vec2::ZERO,
+            lod_range: Vec2::new(2.0, 4.0),
+            reveal_shape: Vec4::ZERO,
+            reveal_params: Vec2::ZERO,
+            shadow_params: Vec4::ZERO,
+            shadow_color: Vec4::ZERO,
+            n_layers: 1,
+            dither_width: 0.0,
+            wind: Vec4::ZERO,
+            fog_color: Vec4::ZERO,
+            fog_softness: 0.0,
+            origin: IVec2::ZERO,
+            atlas_fade: 1.0,
+            edge_mode: 0,
+            edge_fill: 0,
+            max_height: 0.0,
+            cliff_shade: 0.6,
+            color: Vec4::ONE,
             world_size: default(),
             world_offset: default(),
             n_tiles: default(),
@@ -100,11 +187,6 @@ impl MapUniform {
             + self.world_offset.extend(0.0)
     }
 
-    pub(crate) fn map_to_world(&self, map_position: Vec3) -> Vec3 {
-        let local = self.map_to_local(map_position);
-        self.global_transform_matrix * local + self.global_inverse_transform_translation
-    }
-
     /// As of now, this will ignore `world`s z coordinate
     /// and always project to z=0 on the map.
     /// This behavior might change in the future
@@ -118,7 +200,8 @@ impl MapUniform {
         self.local_to_map(local)
     }
 
-    pub(crate) fn update_world_size(&mut self) {
+    /// `extent`: Size of the map in (uniform) tiles, usually `map_size`.
+    pub(crate) fn update_world_size(&mut self, extent: Vec2) {
         // World Size
         //
         // Determine the bounding rectangle of the projected map (in order to construct the quad
@@ -130,11 +213,7 @@ impl MapUniform {
         // 2. take maximum x- and y distances
         let mut low = self.map_to_local(vec3(0.0, 0.0, 0.0)).xy();
         let mut high = low;
-        for corner in [
-            vec2(self.map_size().x as f32, 0.0),
-            vec2(0.0, self.map_size().y as f32),
-            vec2(self.map_size().x as f32, self.map_size().y as f32),
-        ] {
+        for corner in [vec2(extent.x, 0.0), vec2(0.0, extent.y), extent] {
             let pos = self.map_to_local(corner.extend(0.0)).xy();
             low = low.min(pos);
             high = high.max(pos);
@@ -158,17 +237,26 @@ impl MapUniform {
 
     /// Return true iff this update made the uniform ready
     /// (ie. it was not ready before and is ready now).
-    pub(crate) fn update_atlas_size(&mut self, atlas_size: Vec2, force_n_tiles: Option<UVec2>) -> bool {
+    ///
+    /// If the number of tiles could not be derived reliably (see `tolerance`),
+    /// the (truncated) tile count is still applied, but an error describing the mismatch
+    /// is returned.
+    pub(crate) fn update_atlas_size(
+        &mut self,
+        atlas_size: Vec2,
+        force_n_tiles: Option<UVec2>,
+        tolerance: f32,
+    ) -> Result<bool, AtlasTileCountError> {
         if self.atlas_size == atlas_size {
-            return false;
+            return Ok(false);
         }
 
         self.atlas_size = atlas_size;
         match force_n_tiles {
             Some(n_tiles) => self.n_tiles = n_tiles,
-            None => self.update_n_tiles(),
+            None => self.update_n_tiles(tolerance)?,
         }
-        true
+        Ok(true)
     }
 
     pub(crate) fn _apply_transform(&mut self, transform: GlobalTransform) {
@@ -181,22 +269,41 @@ impl MapUniform {
         self.global_inverse_transform_translation = inverse.translation.into();
     }
 
-    fn update_n_tiles(&mut self) {
+    fn update_n_tiles(&mut self, tolerance: f32) -> Result<(), AtlasTileCountError> {
         // area after removing outer padding
         let inner = self.atlas_size - self.outer_padding_topleft - self.outer_padding_bottomright;
+        let tile_size = self.tile_size * self.atlas_tile_size_factor as f32;
 
-        let n_tiles = (inner + self.inner_padding)
-            / (self.inner_padding + self.tile_size * self.atlas_tile_size_factor as f32);
+        let n_tiles = (inner + self.inner_padding) / (self.inner_padding + tile_size);
+        self.n_tiles = n_tiles.as_uvec2();
 
-        let eps = 0.01;
-        if (n_tiles.x - n_tiles.x.round()).abs() > eps
-            || (n_tiles.y - n_tiles.y.round()).abs() > eps
+        if (n_tiles.x - n_tiles.x.round()).abs() <= tolerance
+            && (n_tiles.y - n_tiles.y.round()).abs() <= tolerance
         {
-            warn!(
-                "Expected an integral number of tiles in your atlas, but computes to be {:?}, use `.with_n_tiles()` in MapBuilder if that is intentional.",
-                n_tiles
-            );
+            return Ok(());
         }
-        self.n_tiles = n_tiles.as_uvec2();
+
+        // Derive paddings that would produce the nearest integral number of tiles,
+        // solving `(inner + p) / (p + tile_size) = n` for `p`, and
+        // `atlas_size - topleft - bottomright = n * tile_size + (n - 1) * inner_padding`
+        // for `bottomright` respectively.
+        let n = n_tiles.round().max(Vec2::ONE);
+        let suggest_inner = |inner: f32, n: f32, tile_size: f32| {
+            (n > 1.0).then(|| (inner - n * tile_size) / (n - 1.0))
+        };
+
+        Err(AtlasTileCountError {
+            atlas_size: self.atlas_size,
+            n_tiles,
+            tolerance,
+            suggested_inner_padding: SuggestedPadding {
+                x: suggest_inner(inner.x, n.x, tile_size.x),
+                y: suggest_inner(inner.y, n.y, tile_size.y),
+            },
+            suggested_outer_padding_bottomright: self.atlas_size
+                - self.outer_padding_topleft
+                - n * tile_size
+                - (n - Vec2::ONE) * self.inner_padding,
+        })
     }
 }
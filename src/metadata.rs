@@ -0,0 +1,200 @@
+//! Gameplay properties per atlas index (eg. `solid`, `move_cost`, `footstep_sound`),
+//! kept next to the art definition instead of in game code.
+//!
+//! Load a [`TilesetMetadata`] from a `.tiles.ron` file (requires [`TilesetMetadataPlugin`]),
+//! import it from a Tiled tileset (see [`crate::tmx::TmxTileset::metadata`]) or build it in code:
+//!
+//! ```ron
+//! (
+//!     tiles: {
+//!         3: { "solid": Bool(true) },
+//!         4: { "move_cost": Int(3), "footstep_sound": String("sounds/mud.ogg") },
+//!     },
+//! )
+//! ```
+//!
+//! ```ignore
+//! let metadata = metadatas.get(&tileset_metadata).unwrap();
+//! if map.property_at(pos, "solid", metadata).and_then(TileProperty::as_bool) == Some(true) {
+//!     ..
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{error::TilesetMetadataError, flip::TILE_FLIP_MASK, map::Map, plugin::Customization};
+
+/// Plugin for loading `.tiles.ron` files as [`TilesetMetadata`] assets.
+#[derive(Default)]
+pub struct TilesetMetadataPlugin;
+
+impl Plugin for TilesetMetadataPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TilesetMetadata>()
+            .register_asset_loader(TilesetMetadataLoader);
+    }
+}
+
+/// Value of a tile property, the property types of Tiled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TileProperty {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    /// sRGBA color, each channel from `0.0` to `1.0`.
+    Color([f32; 4]),
+}
+
+impl TileProperty {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Float value, integers are converted.
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Float(v) => Some(*v),
+            Self::Int(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            Self::Color([r, g, b, a]) => Some(Color::srgba(*r, *g, *b, *a)),
+            _ => None,
+        }
+    }
+}
+
+/// Named properties of a single atlas index.
+pub type TileProperties = BTreeMap<String, TileProperty>;
+
+/// Properties per atlas index, see the [module docs](self).
+#[derive(Asset, TypePath, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TilesetMetadata {
+    #[serde(default)]
+    tiles: BTreeMap<u32, TileProperties>,
+}
+
+impl TilesetMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, index: u32, name: impl Into<String>, value: TileProperty) -> Self {
+        self.set(index, name, value);
+        self
+    }
+
+    /// Set property `name` of atlas index `index`.
+    pub fn set(&mut self, index: u32, name: impl Into<String>, value: TileProperty) {
+        self.tiles
+            .entry(index)
+            .or_default()
+            .insert(name.into(), value);
+    }
+
+    /// All properties of atlas index `index`, `None` if it has none.
+    pub fn properties(&self, index: u32) -> Option<&TileProperties> {
+        self.tiles.get(&index)
+    }
+
+    /// Property `name` of atlas index `index`.
+    pub fn get(&self, index: u32, name: &str) -> Option<&TileProperty> {
+        self.properties(index).and_then(|p| p.get(name))
+    }
+
+    /// Atlas indices that have property `name`, with its value.
+    pub fn indices_with<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = (u32, &'a TileProperty)> + 'a {
+        self.tiles
+            .iter()
+            .filter_map(move |(index, p)| p.get(name).map(|v| (*index, v)))
+    }
+
+    pub fn from_ron(ron: &str) -> Result<Self, TilesetMetadataError> {
+        ron::from_str(ron).map_err(|e| TilesetMetadataError::Invalid(e.to_string()))
+    }
+
+    pub fn to_ron(&self) -> String {
+        ron::ser::to_string_pretty(self, default()).unwrap_or_default()
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Properties of the tile at `pos` (ignoring flip flags, see [`crate::flip`]),
+    /// `None` outside of the map or if the tile has no properties.
+    pub fn properties_at<'a>(
+        &self,
+        pos: UVec2,
+        metadata: &'a TilesetMetadata,
+    ) -> Option<&'a TileProperties> {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
+        }
+        metadata.properties(self.indexer().at_uvec(pos) & !TILE_FLIP_MASK)
+    }
+
+    /// Property `name` of the tile at `pos`, see [`Self::properties_at`].
+    pub fn property_at<'a>(
+        &self,
+        pos: UVec2,
+        name: &str,
+        metadata: &'a TilesetMetadata,
+    ) -> Option<&'a TileProperty> {
+        self.properties_at(pos, metadata).and_then(|p| p.get(name))
+    }
+}
+
+/// Loads `.tiles.ron` files as [`TilesetMetadata`].
+#[derive(Debug, Default)]
+pub struct TilesetMetadataLoader;
+
+impl AssetLoader for TilesetMetadataLoader {
+    type Asset = TilesetMetadata;
+    type Settings = ();
+    type Error = TilesetMetadataError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TilesetMetadata, TilesetMetadataError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await?;
+        TilesetMetadata::from_ron(&text)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tiles.ron"]
+    }
+}
@@ -0,0 +1,142 @@
+//! Rendering maps with a dedicated camera into an image, eg. for a minimap UI node,
+//! see [`MinimapCamera`].
+//!
+//! ```ignore
+//! // Shown by the main camera (layer 0) and the minimap camera (layer 1)
+//! commands.spawn((
+//!     MapBundleManaged::new(map, materials.as_mut()),
+//!     RenderLayers::from_layers(&[0, 1]),
+//! ));
+//! let (_, image) = MinimapCamera::new(uvec2(256, 256))
+//!     .with_layers(RenderLayers::layer(1))
+//!     .fit(&map_material)
+//!     .spawn(&mut commands, &mut images);
+//! commands.spawn(ImageBundle {
+//!     image: UiImage::new(image),
+//!     ..default()
+//! });
+//! ```
+//!
+//! Maps are drawn by every camera sharing a render layer with them (see [`crate::render_layers`]),
+//! so give the minimap camera its own layer to keep it from drawing everything else.
+
+use bevy::{
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::RenderLayers,
+    },
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Settings for an offscreen 2d camera rendering into an image, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct MinimapCamera {
+    /// Size of the image in pixels.
+    pub size: UVec2,
+    /// World units per pixel, larger values zoom out.
+    pub scale: f32,
+    /// World position shown at the center of the image.
+    pub center: Vec2,
+    pub layers: RenderLayers,
+    pub clear_color: Color,
+    /// Render order, negative values render before the main camera so the image is up to date
+    /// in the same frame.
+    pub order: isize,
+}
+
+impl MinimapCamera {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size: size.max(UVec2::ONE),
+            scale: 1.0,
+            center: Vec2::ZERO,
+            layers: RenderLayers::default(),
+            clear_color: Color::NONE,
+            order: -1,
+        }
+    }
+
+    pub fn with_scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub fn with_center(self, center: Vec2) -> Self {
+        Self { center, ..self }
+    }
+
+    pub fn with_layers(self, layers: RenderLayers) -> Self {
+        Self { layers, ..self }
+    }
+
+    pub fn with_clear_color(self, clear_color: Color) -> Self {
+        Self {
+            clear_color,
+            ..self
+        }
+    }
+
+    pub fn with_order(self, order: isize) -> Self {
+        Self { order, ..self }
+    }
+
+    /// Zoom out so the whole `map` fits into the image, for a map entity at the origin.
+    pub fn fit<C: Customization>(self, map: &Map<C>) -> Self {
+        let scale = (map.world_size() / self.size.as_vec2()).max_element();
+        Self {
+            scale: scale.max(f32::EPSILON),
+            center: Vec2::ZERO,
+            ..self
+        }
+    }
+
+    /// Empty image of [`Self::size`] usable as render target.
+    pub fn target_image(&self) -> Image {
+        let mut image = Image::new_fill(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            &[0, 0, 0, 0],
+            TextureFormat::Bgra8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::RENDER_ATTACHMENT;
+        image
+    }
+
+    /// Camera rendering into `image`.
+    pub fn bundle(&self, image: Handle<Image>) -> (Camera2dBundle, RenderLayers) {
+        let mut bundle = Camera2dBundle {
+            camera: Camera {
+                target: RenderTarget::Image(image),
+                order: self.order,
+                clear_color: ClearColorConfig::Custom(self.clear_color),
+                ..default()
+            },
+            transform: Transform::from_translation(self.center.extend(999.9)),
+            ..default()
+        };
+        bundle.projection.scale = self.scale;
+        (bundle, self.layers.clone())
+    }
+
+    /// Create the target image and spawn the camera, returns the camera entity and the image
+    /// (eg. for a `UiImage`).
+    pub fn spawn(
+        &self,
+        commands: &mut Commands,
+        images: &mut Assets<Image>,
+    ) -> (Entity, Handle<Image>) {
+        let image = images.add(self.target_image());
+        let camera = commands.spawn(self.bundle(image.clone())).id();
+        (camera, image)
+    }
+}
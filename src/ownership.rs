@@ -0,0 +1,123 @@
+use bevy::{math::URect, prelude::*};
+
+use super::{map::Map, plugin::Customization, upload::Channel};
+
+/// Territory overlay, drawing each tile's owner (see [`Map::set_owner`]) as a team colored tint
+/// and/or border along edges to tiles of other owners.
+///
+/// Owners are stored as one byte per tile separately from the tile data,
+/// so territory can change without touching the tiles themselves.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct OwnershipOverlay {
+    /// Color per owner, owner `0` means "unowned" and is never drawn.
+    pub colors: Vec<Color>,
+    /// Opacity of the team color over owned tiles.
+    pub tint: f32,
+    /// Width of the borders as fraction of a tile, zero for no borders.
+    pub border_width: f32,
+}
+
+impl Default for OwnershipOverlay {
+    fn default() -> Self {
+        Self {
+            colors: Vec::new(),
+            tint: 0.3,
+            border_width: 0.1,
+        }
+    }
+}
+
+impl OwnershipOverlay {
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self {
+            colors,
+            ..default()
+        }
+    }
+
+    pub fn with_tint(self, tint: f32) -> Self {
+        Self { tint, ..self }
+    }
+
+    pub fn with_border_width(self, border_width: f32) -> Self {
+        Self {
+            border_width,
+            ..self
+        }
+    }
+
+    pub(crate) fn shader_data(&self) -> Vec<Vec4> {
+        if self.colors.is_empty() {
+            return vec![Vec4::ZERO];
+        }
+        self.colors
+            .iter()
+            .map(|c| c.to_linear().to_vec4())
+            .collect()
+    }
+}
+
+/// Owners packed four per `u32` as uploaded to the shader.
+pub(crate) fn owner_words(n_tiles: usize) -> usize {
+    n_tiles.div_ceil(4).max(1)
+}
+
+impl<C: Customization> Map<C> {
+    /// Draw tile owners with the given overlay, `None` disables the overlay.
+    pub fn set_ownership_overlay(&mut self, overlay: Option<&OwnershipOverlay>) {
+        match overlay {
+            Some(overlay) => {
+                self.team_colors = overlay.shader_data();
+                self.changed_tiles.add_channel(Channel::TeamColors);
+                self.map_uniform.owner_params = Vec2::new(overlay.tint, overlay.border_width);
+                self.ownership = true;
+            }
+            None => self.ownership = false,
+        }
+    }
+
+    fn owner_slot(&self, pos: UVec2) -> Option<(usize, u32)> {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
+        }
+        let i = (pos.y * size.x + pos.x) as usize;
+        Some((i / 4, (i % 4) as u32 * 8))
+    }
+
+    /// Owner of the given tile, `0` for unowned tiles and positions outside the map.
+    pub fn owner(&self, pos: UVec2) -> u8 {
+        self.owner_slot(pos)
+            .and_then(|(word, shift)| self.owners.get(word).map(|w| (w >> shift) as u8))
+            .unwrap_or(0)
+    }
+
+    /// Set the owner of the given tile, `0` for unowned.
+    /// Positions outside the map are ignored.
+    pub fn set_owner(&mut self, pos: UVec2, owner: u8) {
+        let Some((word, shift)) = self.owner_slot(pos) else {
+            return;
+        };
+        if let Some(w) = self.owners.get_mut(word) {
+            *w = (*w & !(0xff << shift)) | ((owner as u32) << shift);
+            self.changed_tiles.add_channel(Channel::Owners);
+        }
+    }
+
+    /// Set the owners of many tiles at once.
+    pub fn set_owners(&mut self, owners: impl IntoIterator<Item = (UVec2, u8)>) {
+        for (pos, owner) in owners {
+            self.set_owner(pos, owner);
+        }
+    }
+
+    /// Set the owner of all tiles in `rect` (`max` exclusive).
+    pub fn fill_owner(&mut self, rect: URect, owner: u8) {
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, self.map_size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.set_owner(UVec2::new(x, y), owner);
+            }
+        }
+    }
+}
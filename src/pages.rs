@@ -0,0 +1,113 @@
+//! Several atlas textures (pages) per map, for tilesets that do not fit into a single texture.
+//!
+//! The page of a tile is stored in bits 24 to 27 of its value, below the flip flags
+//! (see [`crate::flip`]). Bit 28 is left free as Tiled uses it for rotating hex tiles. All pages must have the same size, format and layout, they are stacked
+//! into a texture array once loaded, see [`crate::map_builder::MapBuilder::with_atlas_pages`].
+//!
+//! Per atlas index data (eg. animations, LOD colors or tile sway) is looked up with the full
+//! index including the page bits. Atlas variants (see [`crate::atlas_variants`]) are not
+//! supported for maps with pages.
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension},
+        texture::ImageSampler,
+    },
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Position of the page in tile values.
+pub const TILE_PAGE_SHIFT: u32 = 24;
+/// All page bits.
+pub const TILE_PAGE_MASK: u32 = 0xf << TILE_PAGE_SHIFT;
+/// Maximum number of atlas pages of a map.
+pub const MAX_ATLAS_PAGES: usize = 16;
+
+/// Tile value of atlas index `index` on page `page`.
+pub fn paged_tile(page: u32, index: u32) -> u32 {
+    ((page << TILE_PAGE_SHIFT) & TILE_PAGE_MASK) | (index & !TILE_PAGE_MASK)
+}
+
+/// Page of a tile value.
+pub fn tile_page(value: u32) -> u32 {
+    (value & TILE_PAGE_MASK) >> TILE_PAGE_SHIFT
+}
+
+/// Stack equally sized pages into a texture array, one layer per page.
+/// `None` for compressed formats, pages without CPU side data or differing pages.
+fn page_array_image(pages: &[&Image]) -> Option<Image> {
+    let first = pages.first()?;
+    let descriptor = &first.texture_descriptor;
+    if descriptor.format.block_dimensions() != (1, 1)
+        || descriptor.mip_level_count != 1
+        || descriptor.size.depth_or_array_layers != 1
+    {
+        return None;
+    }
+    let same = |page: &&Image| {
+        page.texture_descriptor.size == descriptor.size
+            && page.texture_descriptor.format == descriptor.format
+            && page.data.len() == first.data.len()
+    };
+    if !pages.iter().all(same) {
+        return None;
+    }
+
+    let n_pages = pages.len() as u32;
+    let mut image = Image::new(
+        Extent3d {
+            width: descriptor.size.width,
+            height: descriptor.size.height * n_pages,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pages
+            .iter()
+            .flat_map(|page| page.data.iter().copied())
+            .collect(),
+        descriptor.format,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.reinterpret_stacked_2d_as_array(n_pages);
+    Some(image)
+}
+
+impl<C: Customization> Map<C> {
+    /// Atlas pages of this map, empty for maps with a single atlas.
+    pub fn atlas_pages(&self) -> &[Handle<Image>] {
+        &self.atlas_pages
+    }
+
+    /// Whether all atlas pages (or the atlas) are loaded.
+    pub(crate) fn pages_loaded(&self, images: &Assets<Image>) -> bool {
+        self.atlas_pages.iter().all(|page| images.contains(page))
+    }
+
+    /// Stack the loaded pages into a texture array, if the map has pages and it was not done yet.
+    pub(crate) fn update_atlas_pages(&mut self, images: &mut Assets<Image>, sampler: ImageSampler) {
+        if self.atlas_pages.is_empty() || self.page_array.is_some() {
+            return;
+        }
+        let Some(pages) = self
+            .atlas_pages
+            .iter()
+            .map(|page| images.get(page))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        match page_array_image(&pages) {
+            Some(mut image) => {
+                image.sampler = sampler;
+                self.page_array = Some(images.add(image));
+            }
+            None => warn!(
+                "Atlas pages differ in size or format, are compressed or have no CPU side data, \
+                only the first page is used"
+            ),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Background maps scrolling slower (or faster) than the camera, see [`ParallaxLayer`].
+//!
+//! ```ignore
+//! // Far mountains, scrolling at a fifth of the camera speed and repeating endlessly
+//! let map = MapBuilder::new(uvec2(64, 16), mountains, tile_size)
+//!     .with_edge_mode(EdgeMode::Wrap)
+//!     .build();
+//! commands.spawn((MapBundleManaged::new(map, materials.as_mut()), ParallaxLayer::new(0.2)));
+//! ```
+
+use bevy::{math::Vec3Swizzles, prelude::*, render::view::RenderLayers};
+
+use super::{
+    map::{Map, MapAttributes},
+    plugin::Customization,
+    render_layers::renders_layers,
+};
+
+/// Scroll the map of this entity at a fraction of the camera movement. The map entity stays in
+/// place, the map positions of its mesh are offset (see [`MapAttributes::parallax_offset`]),
+/// so the managed mesh covers the whole view.
+/// Follows the active camera with the lowest order among those drawing the map.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct ParallaxLayer {
+    /// Fraction of the camera movement the map follows per axis, `1.0` scrolls like an ordinary
+    /// map, `0.0` stays fixed on screen.
+    pub factor: Vec2,
+}
+
+impl Default for ParallaxLayer {
+    fn default() -> Self {
+        Self { factor: Vec2::ONE }
+    }
+}
+
+impl ParallaxLayer {
+    pub fn new(factor: f32) -> Self {
+        Self {
+            factor: Vec2::splat(factor),
+        }
+    }
+
+    pub fn with_factor(self, factor: Vec2) -> Self {
+        Self { factor }
+    }
+}
+
+/// Offset the map positions of maps with [`ParallaxLayer`] by the camera movement they do not
+/// follow.
+pub(crate) fn update_parallax_layers<C: Customization>(
+    mut maps: Query<(
+        &Handle<Map<C>>,
+        &GlobalTransform,
+        &mut MapAttributes,
+        Option<&ParallaxLayer>,
+        Option<&RenderLayers>,
+    )>,
+    cameras: Query<(&Camera, &GlobalTransform, Option<&RenderLayers>)>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+) {
+    for (map_handle, transform, mut attributes, layer, layers) in maps.iter_mut() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        let camera = cameras
+            .iter()
+            .filter(|(camera, _, camera_layers)| {
+                camera.is_active && renders_layers(*camera_layers, layers)
+            })
+            .min_by_key(|(camera, _, _)| camera.order)
+            .map_or(Vec2::ZERO, |(_, transform, _)| transform.translation().xy());
+
+        let offset = match layer {
+            Some(layer) => {
+                // World units the map lags behind the camera, in local and then map coordinates
+                let lag = camera * (Vec2::ONE - layer.factor);
+                let local = transform
+                    .affine()
+                    .inverse()
+                    .transform_vector3(lag.extend(0.0));
+                map.world_to_linear(local.xy()) - map.world_to_linear(Vec2::ZERO)
+            }
+            None => Vec2::ZERO,
+        };
+
+        if attributes.parallax_offset != offset {
+            attributes.parallax_offset = offset;
+        }
+        // Only touch the map when a layer is added or removed, as that re-uploads it
+        if map.parallax != layer.is_some() {
+            if let Some(map) = map_materials.get_mut(map_handle) {
+                map.parallax = layer.is_some();
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Incremental saving of large maps: only chunks that changed since the last save are written.
+//!
+//! Layout: the magic bytes `BFTI`, the format version (`u32`, currently 1), map size
+//! (`u32` x, `u32` y) and chunk size (`u32` x, `u32` y), followed by an append-only log of chunk
+//! records. Each record is the chunk coordinate (`u32` x, `u32` y) followed by one `u32` atlas
+//! index per tile of the chunk, row by row. Later records of a chunk replace earlier ones.
+//! All integers are little endian.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use bevy::{math::uvec2, prelude::*, utils::HashMap};
+
+use super::{
+    chunk::MapChunks, content_hash::cell_hash, error::MapFormatError, format::MapFormatVersion,
+    map::Map, plugin::Customization,
+};
+
+const MAGIC: &[u8; 4] = b"BFTI";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = MAGIC.len() + 5 * 4;
+
+/// Saves a map to a file incrementally, see the [module docs](self) for the file layout.
+///
+/// The first save writes all chunks, later saves only append the chunks whose tiles changed.
+/// Changes are tracked by the map and consumed by saving, so use one `IncrementalSave` per map.
+/// Once the log holds more than `compact_ratio` records per chunk, or the map was resized, the
+/// file is rewritten with a single record per chunk.
+#[derive(Debug, Clone)]
+pub struct IncrementalSave {
+    path: PathBuf,
+    chunks: MapChunks,
+    /// Map size the file was written with.
+    size: UVec2,
+    /// Hash of each chunk as last written.
+    saved: HashMap<UVec2, u64>,
+    records: usize,
+    pub compact_ratio: usize,
+}
+
+impl IncrementalSave {
+    pub fn new(path: impl Into<PathBuf>, chunk_size: UVec2) -> Self {
+        Self {
+            path: path.into(),
+            chunks: MapChunks::new(chunk_size),
+            size: UVec2::ZERO,
+            saved: HashMap::default(),
+            records: 0,
+            compact_ratio: 4,
+        }
+    }
+
+    fn chunk_hash<C: Customization>(&self, map: &Map<C>, chunk: UVec2) -> u64 {
+        let size = map.map_size();
+        let rect = self.chunks.chunk_rect(chunk, size);
+        let indexer = map.indexer();
+        (rect.min.y..rect.max.y)
+            .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| uvec2(x, y)))
+            .fold(0, |hash, p| {
+                hash ^ cell_hash((p.y * size.x + p.x) as usize, indexer.at_uvec(p))
+            })
+    }
+
+    fn write_chunk<C: Customization>(
+        &self,
+        out: &mut impl Write,
+        map: &Map<C>,
+        chunk: UVec2,
+    ) -> io::Result<()> {
+        let rect = self.chunks.chunk_rect(chunk, map.map_size());
+        let indexer = map.indexer();
+        out.write_all(&chunk.x.to_le_bytes())?;
+        out.write_all(&chunk.y.to_le_bytes())?;
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                out.write_all(&indexer.at(x, y).to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the chunks of `map` that changed since the last save (all chunks on the first save),
+    /// returns the number of chunks written.
+    pub fn save<C: Customization>(&mut self, map: &Map<C>) -> io::Result<usize> {
+        let size = map.map_size();
+        let n_chunks = self.chunks.n_chunks(size);
+        let total = (n_chunks.x * n_chunks.y) as usize;
+        // The chunks of a resized map are at other places, the log can not be continued
+        if self.saved.is_empty()
+            || size != self.size
+            || self.records > total * self.compact_ratio.max(1)
+        {
+            self.compact(map)?;
+            return Ok(total);
+        }
+
+        // Only chunks touched by edits since the last save can differ from the file
+        let Some(changed) = map.changed_tiles.take_saved() else {
+            return Ok(0);
+        };
+        let first = changed.min / self.chunks.chunk_size;
+        let last = ((changed.max - UVec2::ONE) / self.chunks.chunk_size).min(n_chunks - UVec2::ONE);
+        let dirty: Vec<(UVec2, u64)> = (first.y..=last.y)
+            .flat_map(|y| (first.x..=last.x).map(move |x| uvec2(x, y)))
+            .map(|chunk| (chunk, self.chunk_hash(map, chunk)))
+            .filter(|(chunk, hash)| self.saved.get(chunk) != Some(hash))
+            .collect();
+
+        let mut out = BufWriter::new(OpenOptions::new().append(true).open(&self.path)?);
+        for (chunk, _) in dirty.iter() {
+            self.write_chunk(&mut out, map, *chunk)?;
+        }
+        out.flush()?;
+
+        self.records += dirty.len();
+        self.saved.extend(dirty.iter().copied());
+        Ok(dirty.len())
+    }
+
+    /// Rewrite the file with exactly one record per chunk.
+    pub fn compact<C: Customization>(&mut self, map: &Map<C>) -> io::Result<()> {
+        let size = map.map_size();
+        let chunk_size = self.chunks.chunk_size;
+        let n_chunks = self.chunks.n_chunks(size);
+
+        let mut out = BufWriter::new(File::create(&self.path)?);
+        out.write_all(MAGIC)?;
+        for v in [VERSION, size.x, size.y, chunk_size.x, chunk_size.y] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+
+        self.size = size;
+        map.changed_tiles.take_saved();
+        self.saved.clear();
+        for chunk in (0..n_chunks.y).flat_map(|y| (0..n_chunks.x).map(move |x| uvec2(x, y))) {
+            self.write_chunk(&mut out, map, chunk)?;
+            self.saved.insert(chunk, self.chunk_hash(map, chunk));
+        }
+        out.flush()?;
+
+        self.records = self.saved.len();
+        Ok(())
+    }
+
+    /// Replay the file into `map`, which must have the size the file was written with.
+    /// Subsequent saves append to the loaded file.
+    pub fn load<C: Customization>(&mut self, map: &mut Map<C>) -> io::Result<()> {
+        let invalid = |e: MapFormatError| io::Error::new(io::ErrorKind::InvalidData, e);
+
+        let mut bytes = Vec::new();
+        File::open(&self.path)?.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid(MapFormatError::Truncated));
+        }
+        if &bytes[..4] != MAGIC {
+            return Err(invalid(MapFormatError::BadMagic));
+        }
+        let header: Vec<u32> = bytes[4..HEADER_LEN]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        if header[0] != VERSION {
+            return Err(invalid(MapFormatError::UnsupportedVersion(
+                MapFormatVersion(header[0]),
+            )));
+        }
+        let found = uvec2(header[1], header[2]);
+        let expected = map.map_size();
+        if found != expected {
+            return Err(invalid(MapFormatError::SizeMismatch { expected, found }));
+        }
+        self.chunks = MapChunks::new(uvec2(header[3], header[4]).max(UVec2::ONE));
+
+        let mut words = bytes[HEADER_LEN..]
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+        let n_chunks = self.chunks.n_chunks(found);
+        let mut records = 0;
+        let mut m = map.indexer_mut();
+        while let Some(x) = words.next() {
+            let y = words.next().ok_or(invalid(MapFormatError::Truncated))?;
+            let chunk = uvec2(x, y);
+            if chunk.cmpge(n_chunks).any() {
+                return Err(invalid(MapFormatError::ChunkOutOfRange(chunk)));
+            }
+            let rect = self.chunks.chunk_rect(chunk, found);
+            for ty in rect.min.y..rect.max.y {
+                for tx in rect.min.x..rect.max.x {
+                    let index = words.next().ok_or(invalid(MapFormatError::Truncated))?;
+                    m.set(tx, ty, index);
+                }
+            }
+            records += 1;
+        }
+        // The file already holds the loaded tiles
+        map.changed_tiles.take_saved();
+
+        self.size = found;
+        self.saved = (0..n_chunks.y)
+            .flat_map(|y| (0..n_chunks.x).map(move |x| uvec2(x, y)))
+            .map(|chunk| (chunk, self.chunk_hash(map, chunk)))
+            .collect();
+        self.records = records;
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+//! Static physics bodies for [`TileColliders`], for [avian](https://github.com/Jondolf/avian)
+//! (`avian` feature) or [rapier](https://rapier.rs) (`rapier` feature).
+//!
+//! Each chunk of merged colliders becomes a static child entity of the map entity, marked with
+//! [`TileColliderChunk`] and holding a compound collider of convex polygons. When tiles change
+//! only the children of the rebuilt chunks are replaced.
+//!
+//! ```ignore
+//! app.add_plugins((PhysicsPlugins::default(), TileColliderPhysicsPlugin));
+//! commands.spawn((MapBundleManaged::new(map, materials.as_mut()), TileColliders::new(shapes)));
+//! ```
+
+use bevy::prelude::*;
+
+use super::{
+    collision::{update_tile_colliders, MergedCollider, TileColliders},
+    plugin::{Customization, NoCustomization},
+};
+
+/// Child entity holding the physics body for a chunk of [`TileColliders`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileColliderChunk {
+    pub chunk: UVec2,
+}
+
+/// Plugin spawning physics bodies for [`TileColliders`], see the [module docs](self).
+pub type TileColliderPhysicsPlugin = CustomTileColliderPhysicsPlugin<NoCustomization>;
+
+/// Same as [`TileColliderPhysicsPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomTileColliderPhysicsPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomTileColliderPhysicsPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            sync_tile_collider_chunks.after(update_tile_colliders::<C>),
+        );
+    }
+}
+
+fn sync_tile_collider_chunks(
+    mut commands: Commands,
+    maps: Query<(Entity, &TileColliders, Option<&Children>)>,
+    chunks: Query<&TileColliderChunk>,
+) {
+    for (entity, colliders, children) in maps.iter() {
+        let rebuilt = colliders.rebuilt_chunks();
+        if rebuilt.is_empty() {
+            continue;
+        }
+
+        for &child in children.into_iter().flatten() {
+            if chunks
+                .get(child)
+                .is_ok_and(|chunk| rebuilt.contains(&chunk.chunk))
+            {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            for &chunk in rebuilt {
+                let chunk_colliders = colliders.chunk(chunk);
+                if chunk_colliders.is_empty() {
+                    continue;
+                }
+                let mut child = parent.spawn((
+                    TileColliderChunk { chunk },
+                    TransformBundle::default(),
+                    Name::new(format!("Tile colliders {}", chunk)),
+                ));
+                #[cfg(feature = "avian")]
+                child.insert(avian::body(chunk_colliders));
+                #[cfg(feature = "rapier")]
+                child.insert(rapier::body(chunk_colliders));
+            }
+        });
+    }
+}
+
+#[cfg(feature = "avian")]
+mod avian {
+    use avian2d::prelude::{Collider, Position, RigidBody, Rotation};
+
+    use super::MergedCollider;
+
+    pub(super) fn body(colliders: &[MergedCollider]) -> (RigidBody, Collider) {
+        let shapes = colliders
+            .iter()
+            .filter_map(|c| Collider::convex_hull(c.polygon.clone()))
+            .map(|c| (Position::default(), Rotation::default(), c))
+            .collect();
+        (RigidBody::Static, Collider::compound(shapes))
+    }
+}
+
+#[cfg(feature = "rapier")]
+mod rapier {
+    use bevy::math::Vec2;
+    use bevy_rapier2d::prelude::{Collider, RigidBody};
+
+    use super::MergedCollider;
+
+    pub(super) fn body(colliders: &[MergedCollider]) -> (RigidBody, Collider) {
+        let shapes = colliders
+            .iter()
+            .filter_map(|c| Collider::convex_hull(&c.polygon))
+            .map(|c| (Vec2::ZERO, 0.0, c))
+            .collect();
+        (RigidBody::Fixed, Collider::compound(shapes))
+    }
+}
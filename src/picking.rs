@@ -0,0 +1,243 @@
+use bevy::{
+    math::{ivec2, vec2, Vec3Swizzles},
+    prelude::*,
+    render::render_resource::TextureFormat,
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Order in which the shader draws perspective underhangs, see `render_perspective_underhangs`.
+const UNDERHANG_ORDER: [&str; 8] = ["NN", "NP", "PN", "PP", "ZN", "NZ", "ZP", "PZ"];
+
+/// Order in which the shader draws perspective overhangs, see `render_perspective_overhangs`.
+/// Directions are the opposite of the respective underhang.
+const OVERHANG_ORDER: [&str; 8] = ["ZN", "NZ", "ZP", "PZ", "NN", "NP", "PN", "PP"];
+
+/// Order in which the shader collects neighbors for dominance overhangs.
+const DOMINANCE_NEIGHBORS: [IVec2; 8] = [
+    ivec2(-1, -1),
+    ivec2(-1, 0),
+    ivec2(-1, 1),
+    ivec2(0, 1),
+    ivec2(1, 1),
+    ivec2(1, 0),
+    ivec2(1, -1),
+    ivec2(0, -1),
+];
+
+/// Result of picking a tile on a map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TilePick {
+    /// Position of the picked tile on the map.
+    pub tile: UVec2,
+    /// Atlas index of the picked tile.
+    pub index: u32,
+    /// Position within the tile graphic, `(0.0, 0.0)` is top left, `(1.0, 1.0)` bottom right
+    /// of the `tile_size` rectangle in the atlas.
+    /// May be outside of `[0.0, 1.0]` if the tile was picked in its overhang (padding) area.
+    pub uv: Vec2,
+}
+
+impl<C: Customization> Map<C> {
+    /// Pick the tile that is visually on top at the given global world position,
+    /// `transform` is the [`GlobalTransform`] of the map entity.
+    ///
+    /// When overhangs are enabled, a neighboring tile may cover part of a cell.
+    /// This replicates the draw order of the shader and returns the topmost tile that has a
+    /// non-transparent atlas pixel at that position,
+    /// i.e. what the player actually sees rather than the geometric cell.
+    /// If no tile is visible there, falls back to the geometric cell (if within the map).
+    ///
+    /// Only the atlas alpha channel is considered, effects of custom shader code are not.
+    /// If the atlas is not loaded (or has a format that can not be read),
+    /// this returns the geometric cell.
+    pub fn pick_tile(
+        &self,
+        transform: &GlobalTransform,
+        world: Vec2,
+        images: &Assets<Image>,
+    ) -> Option<TilePick> {
+        let map_position = self.world_to_map(transform, world);
+        let tile = map_position.floor();
+
+        // Offset within the tile in (unscaled) local units, as the atlas is sampled
+        let u = &self.map_uniform;
+        let local_offset = (u.projection * (map_position - tile).extend(0.0)).xy() * u.tile_size;
+        let offset = vec2(1.0, -1.0) * local_offset;
+
+        // Hexagons do not fill their cell, so the geometric tile may be a neighbor of `tile`
+        let geometric_tile = self.map_position_to_tile(map_position);
+        let geometric = self.tile_pick(geometric_tile).map(|pick| {
+            let d = (geometric_tile - tile.as_ivec2()).as_vec2();
+            let overhang = (u.projection * d.extend(0.0)).xy() * u.tile_size;
+            TilePick {
+                uv: self.offset_to_uv(offset - vec2(1.0, -1.0) * overhang),
+                ..pick
+            }
+        });
+        let Some(atlas) = images.get(&self.atlas_texture) else {
+            return geometric;
+        };
+
+        self.draw_order(tile.as_ivec2())
+            .into_iter()
+            .rev()
+            .find_map(|(pick, direction)| {
+                let overhang =
+                    (u.projection * (-direction.as_vec2()).extend(0.0)).xy() * u.tile_size;
+                let offset = offset + vec2(1.0, -1.0) * overhang;
+                (self.sample_alpha(atlas, pick.index, pick.tile.as_ivec2(), offset) > 0.0).then(
+                    || TilePick {
+                        uv: self.offset_to_uv(offset),
+                        ..pick
+                    },
+                )
+            })
+            .or(geometric)
+    }
+
+    /// Convert an offset from the tile anchor point (in pixels) to relative position
+    /// in the tile rectangle.
+    fn offset_to_uv(&self, offset: Vec2) -> Vec2 {
+        let u = &self.map_uniform;
+        (offset + u.tile_anchor_point * u.tile_size) / u.tile_size
+    }
+
+    /// Tiles (with their direction relative to `tile`) that the shader would draw at a fragment
+    /// in `tile`, bottom-most first.
+    fn draw_order(&self, tile: IVec2) -> Vec<(TilePick, IVec2)> {
+        let mut order = Vec::new();
+        let mut push = |direction: IVec2| {
+            if let Some(pick) = self.tile_pick(tile + direction) {
+                order.push((pick, direction));
+            }
+        };
+
+        let under: Vec<IVec2> = UNDERHANG_ORDER
+            .iter()
+            .filter(|def| {
+                self.perspective_defs
+                    .contains(&format!("PERSPECTIVE_UNDER_{}", def))
+            })
+            .map(|def| Self::def_direction(def))
+            .collect();
+
+        let (perspective_underhangs, perspective_overhangs, dominance_overhangs) =
+            self.overhang_flags();
+
+        let reach = self.overhang_reach as i32;
+
+        if perspective_underhangs {
+            for direction in under.iter() {
+                for k in (1..=reach).rev() {
+                    push(*direction * k);
+                }
+            }
+        }
+
+        push(IVec2::ZERO);
+
+        if dominance_overhangs {
+            let index = self.tile_pick(tile).map(|p| p.index).unwrap_or(0);
+            let mut neighbors: Vec<(u32, IVec2)> = (1..=reach)
+                .flat_map(|k| DOMINANCE_NEIGHBORS.iter().map(move |d| *d * k))
+                .filter_map(|d| self.tile_pick(tile + d).map(|p| (p.index, d)))
+                .filter(|(i, _)| *i > index)
+                .collect();
+            neighbors.sort_by_key(|(i, _)| *i);
+            for (_, direction) in neighbors {
+                push(direction);
+            }
+        }
+
+        if perspective_overhangs {
+            for def in OVERHANG_ORDER.iter() {
+                if self
+                    .perspective_defs
+                    .contains(&format!("PERSPECTIVE_UNDER_{}", def))
+                {
+                    for k in 1..=reach {
+                        push(-Self::def_direction(def) * k);
+                    }
+                }
+            }
+        }
+
+        order
+    }
+
+    /// Direction encoded in a perspective shader def suffix such as `"NZ"`.
+    fn def_direction(def: &str) -> IVec2 {
+        let component = |c: u8| match c {
+            b'N' => -1,
+            b'P' => 1,
+            _ => 0,
+        };
+        let bytes = def.as_bytes();
+        ivec2(component(bytes[0]), component(bytes[1]))
+    }
+
+    fn tile_pick(&self, tile: IVec2) -> Option<TilePick> {
+        let size = self.map_size().as_ivec2();
+        if tile.x < 0 || tile.y < 0 || tile.x >= size.x || tile.y >= size.y {
+            return None;
+        }
+        let tile = tile.as_uvec2();
+        Some(TilePick {
+            tile,
+            index: self.indexer().at_uvec(tile),
+            uv: Vec2::ZERO,
+        })
+    }
+
+    /// Alpha of the atlas for tile `index` at `offset` from the anchor point,
+    /// mirroring `sample_tile_at` in the shader.
+    fn sample_alpha(&self, atlas: &Image, index: u32, tile: IVec2, offset: Vec2) -> f32 {
+        let u = &self.map_uniform;
+        let n_tiles_x = u.n_tiles.x.max(1);
+        let index2d = vec2((index % n_tiles_x) as f32, (index / n_tiles_x) as f32);
+
+        let factor = u.atlas_tile_size_factor;
+        let tile_start = if factor > 1 {
+            index2d * (u.tile_size * factor as f32 + u.inner_padding)
+                + u.outer_padding_topleft
+                + u.tile_size * (tile % factor).as_vec2()
+        } else {
+            index2d * (u.tile_size + u.inner_padding) + u.outer_padding_topleft
+        };
+
+        let rect_offset = offset + u.tile_anchor_point * u.tile_size;
+        let max_overhang = u.inner_padding / 2.0;
+        if rect_offset.cmplt(-max_overhang).any()
+            || rect_offset.cmpge(u.tile_size + max_overhang).any()
+        {
+            return 0.0;
+        }
+
+        atlas_alpha(atlas, tile_start + rect_offset)
+    }
+}
+
+/// Alpha value of the atlas pixel at the given pixel position.
+/// Formats other than 8-bit RGBA/BGRA are assumed to be opaque.
+pub(crate) fn atlas_alpha(atlas: &Image, pixel: Vec2) -> f32 {
+    let size = atlas.size();
+    if pixel.x < 0.0 || pixel.y < 0.0 {
+        return 0.0;
+    }
+    let pixel = pixel.as_uvec2();
+    if pixel.x >= size.x || pixel.y >= size.y {
+        return 0.0;
+    }
+
+    match atlas.texture_descriptor.format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => {
+            let i = (pixel.y as usize * size.x as usize + pixel.x as usize) * 4 + 3;
+            atlas.data.get(i).map(|a| *a as f32 / 255.0).unwrap_or(0.0)
+        }
+        _ => 1.0,
+    }
+}
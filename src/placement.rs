@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use bevy::{math::URect, prelude::*, window::PrimaryWindow};
+
+use super::{
+    highlight::{MapHighlights, TileHighlight},
+    interaction::cursor_to_world,
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Highlight group used for drawing placement previews, see [`MapHighlights`].
+pub const PLACEMENT_PREVIEW_HIGHLIGHT: &str = "placement_preview";
+
+/// Plugin moving [`PlacementPreview`]s with the mouse cursor.
+pub type PlacementPreviewPlugin = CustomPlacementPreviewPlugin<NoCustomization>;
+
+/// Same as [`PlacementPreviewPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomPlacementPreviewPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomPlacementPreviewPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_placement_previews::<C>);
+    }
+}
+
+/// A footprint snapped to the tile grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// Tiles covered by the footprint, `max` is exclusive.
+    pub rect: URect,
+    /// Whether all tiles of `rect` passed the placement rule.
+    pub valid: bool,
+}
+
+/// Snap a footprint of `footprint` tiles to the tile under the global world position `world`,
+/// for `map` shown by an entity with `transform`.
+///
+/// The tile under the cursor is the center of the footprint (for even sizes the tile right/below
+/// of the center). The footprint is moved to stay within the map, `None` if `world` is not over
+/// the map or the footprint is larger than the map.
+pub fn snap_footprint<C: Customization>(
+    map: &Map<C>,
+    transform: &GlobalTransform,
+    world: Vec2,
+    footprint: UVec2,
+) -> Option<URect> {
+    let map_size = map.map_size();
+    let footprint = footprint.max(UVec2::ONE);
+    if footprint.cmpgt(map_size).any() {
+        return None;
+    }
+    let tile = map.world_to_tile(transform, world)?;
+    let min = (tile.as_ivec2() - (footprint / 2).as_ivec2())
+        .max(IVec2::ZERO)
+        .as_uvec2()
+        .min(map_size - footprint);
+    Some(URect::from_corners(min, min + footprint))
+}
+
+/// Snap a footprint (see [`snap_footprint`]) and check every covered tile with `rule`,
+/// which receives the tile position and value.
+pub fn check_placement<C: Customization>(
+    map: &Map<C>,
+    transform: &GlobalTransform,
+    world: Vec2,
+    footprint: UVec2,
+    mut rule: impl FnMut(UVec2, u32) -> bool,
+) -> Option<Placement> {
+    let rect = snap_footprint(map, transform, world, footprint)?;
+    let m = map.indexer();
+    let valid = (rect.min.y..rect.max.y)
+        .flat_map(|y| (rect.min.x..rect.max.x).map(move |x| UVec2::new(x, y)))
+        .all(|pos| rule(pos, m.at_uvec(pos)));
+    Some(Placement { rect, valid })
+}
+
+type RuleFn = dyn Fn(UVec2, u32) -> bool + Send + Sync;
+
+/// Preview of placing something (eg. a building) of `footprint` tiles at the mouse cursor.
+/// Add this to a map entity.
+///
+/// [`PlacementPreviewPlugin`] snaps the footprint to the tile under the cursor (see
+/// [`snap_footprint`]), checks each covered tile with the placement rule and draws the footprint
+/// through [`MapHighlights`] (in group [`PLACEMENT_PREVIEW_HIGHLIGHT`]) in `valid_color` or
+/// `invalid_color`. Read the result with [`Self::placement`], eg. when the player clicks.
+///
+/// ```ignore
+/// PlacementPreview::new(uvec2(2, 2), |_, tile| tile == GRASS)
+/// ```
+#[derive(Component, Clone)]
+pub struct PlacementPreview {
+    pub footprint: UVec2,
+    pub valid_color: Color,
+    pub invalid_color: Color,
+    rule: Arc<RuleFn>,
+    placement: Option<Placement>,
+}
+
+impl PlacementPreview {
+    pub fn new(
+        footprint: UVec2,
+        rule: impl Fn(UVec2, u32) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            footprint,
+            valid_color: Color::srgb(0.2, 0.9, 0.2),
+            invalid_color: Color::srgb(0.9, 0.2, 0.2),
+            rule: Arc::new(rule),
+            placement: None,
+        }
+    }
+
+    pub fn with_colors(self, valid_color: Color, invalid_color: Color) -> Self {
+        Self {
+            valid_color,
+            invalid_color,
+            ..self
+        }
+    }
+
+    /// Replace the placement rule, eg. when selecting a different building.
+    pub fn set_rule(&mut self, rule: impl Fn(UVec2, u32) -> bool + Send + Sync + 'static) {
+        self.rule = Arc::new(rule);
+    }
+
+    /// Current snapped footprint, `None` if the cursor is not over the map.
+    pub fn placement(&self) -> Option<Placement> {
+        self.placement
+    }
+}
+
+pub(crate) fn update_placement_previews<C: Customization>(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    map_materials: Res<Assets<Map<C>>>,
+    mut previews: Query<(
+        Entity,
+        &Handle<Map<C>>,
+        &GlobalTransform,
+        &mut PlacementPreview,
+        Option<&mut MapHighlights>,
+    )>,
+    mut commands: Commands,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let world = cursor.and_then(|c| cursor_to_world(c, cameras.iter()));
+
+    for (entity, map_handle, transform, mut preview, highlights) in previews.iter_mut() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+
+        let placement = world.and_then(|world| {
+            check_placement(map, transform, world, preview.footprint, |pos, tile| {
+                (preview.rule)(pos, tile)
+            })
+        });
+        if preview.placement != placement {
+            preview.placement = placement;
+        }
+
+        let highlight: Vec<_> = placement
+            .map(|placement| TileHighlight {
+                rect: placement.rect,
+                color: if placement.valid {
+                    preview.valid_color
+                } else {
+                    preview.invalid_color
+                },
+            })
+            .into_iter()
+            .collect();
+        match highlights {
+            Some(mut highlights) => {
+                if highlights.get(PLACEMENT_PREVIEW_HIGHLIGHT) != highlight.as_slice() {
+                    highlights.set(PLACEMENT_PREVIEW_HIGHLIGHT, highlight);
+                }
+            }
+            None => {
+                let mut highlights = MapHighlights::default();
+                highlights.set(PLACEMENT_PREVIEW_HIGHLIGHT, highlight);
+                commands.entity(entity).insert(highlights);
+            }
+        }
+    }
+}
@@ -1,5 +1,31 @@
-use super::map::{log_map_events, update_loading_maps, update_map_vertex_attributes};
+use super::{
+    animation::MapAnimationPlugin,
+    atlas_variants::update_atlas_variants,
+    bounds::update_map_aabbs,
+    changes::{report_changed_tiles, MapTilesChanged},
+    chunk::{update_chunk_visibility, ChunkEntered, ChunkExited},
+    chunked::update_chunked_maps,
+    collision::update_tile_colliders,
+    commands::{apply_map_edits, ApplyMapEdits},
+    decal::update_map_decals,
+    generation::{update_chunk_generation, ChunkGenerated},
+    highlight::draw_map_highlights,
+    lod::bake_map_lod_colors,
+    map::{log_map_events, update_loading_maps, update_map_vertex_attributes, MapFailed, MapReady},
+    rebake::{schedule_rebakes, RebakeRegion},
+    reflection::update_map_reflections,
+    render_layers::propagate_map_render_layers,
+    reveal::update_map_reveals,
+    settings::{apply_tilemap_settings, FastTileMapSettings},
+    shadow::update_map_shadows,
+    stack::update_map_stacks,
+    streaming::update_streamed_maps,
+    sway::{apply_map_wind, MapWind},
+    timeline::advance_map_timelines,
+    upload::MapUploadPlugin,
+};
 use bevy::{
+    gizmos::GizmoPlugin,
     prelude::*,
     render::render_resource::{encase::internal::WriteInto, AsBindGroup, ShaderSize, ShaderType},
     sprite::Material2dPlugin,
@@ -7,6 +33,8 @@ use bevy::{
 
 use super::{
     map::{DefaultUserData, Map},
+    parallax::update_parallax_layers,
+    precision::update_relative_origins,
     shader::SHADER_CODE,
 };
 
@@ -23,6 +51,24 @@ pub trait Customization: Sync + Send + 'static + TypePath + Clone
         + ShaderSize
         + Default;
     fn custom_shader_code() -> String;
+
+    /// Custom code for the vertex stage, inserted right before the vertex shader.
+    /// It must define `fn displace_vertex(in: DisplaceIn) -> vec3<f32>` which returns the
+    /// world position to draw a vertex of the map mesh at, eg. for screen shake or wrapping the
+    /// map around a sphere. `map` and `user_data` are available as in [`Self::custom_shader_code`].
+    ///
+    /// Only the drawn position changes, tiles are still looked up from the undisplaced map
+    /// position, so projections, overhangs and picking keep working on the flat map.
+    /// Vertices are only those of the map mesh (4 for the default quad),
+    /// use a subdivided custom mesh for curved displacements.
+    fn custom_vertex_code() -> String {
+        r#"
+        fn displace_vertex(in: DisplaceIn) -> vec3<f32> {
+            return in.world_position;
+        }
+        "#
+        .to_string()
+    }
 }
 
 /// Default custumization that will use the default user data and shader code.
@@ -57,21 +103,82 @@ pub struct CustomFastTileMapPlugin<C: Customization = NoCustomization> {
 
 impl<C: Customization> Plugin for CustomFastTileMapPlugin<C> {
     fn build(&self, app: &mut App) {
-        app.add_plugins(Material2dPlugin::<Map<C>>::default());
+        app.add_plugins((
+            Material2dPlugin::<Map<C>>::default(),
+            MapUploadPlugin::<C>::default(),
+        ));
         let mut shaders = app.world_mut().resource_mut::<Assets<Shader>>();
 
         let mut code = SHADER_CODE.to_string();
 
         code = code.replace("#[user_code]", C::custom_shader_code().as_str());
+        code = code.replace("#[user_vertex_code]", C::custom_vertex_code().as_str());
 
         shaders.insert(&C::SHADER_HANDLE, Shader::from_wgsl(code, file!()));
 
+        app.add_event::<ChunkEntered>()
+            .add_event::<ChunkExited>()
+            .add_event::<ChunkGenerated>()
+            .add_event::<MapFailed>()
+            .add_event::<MapReady>()
+            .add_event::<MapTilesChanged>()
+            .add_event::<RebakeRegion>();
+
         app.add_systems(
             Update,
             (
-                (update_loading_maps::<C>, log_map_events::<C>).chain(),
+                (
+                    apply_tilemap_settings::<C>,
+                    update_loading_maps::<C>,
+                    log_map_events::<C>,
+                )
+                    .chain(),
+                update_map_stacks::<C>.before(update_map_vertex_attributes::<C>),
+                update_map_reflections::<C>.before(update_map_vertex_attributes::<C>),
+                advance_map_timelines::<C>.before(update_map_vertex_attributes::<C>),
+                update_map_reveals::<C>,
+                update_map_shadows::<C>.after(update_loading_maps::<C>),
+                update_map_decals::<C>,
+                update_streamed_maps::<C>.after(update_loading_maps::<C>),
+                update_relative_origins::<C>.before(update_map_vertex_attributes::<C>),
+                update_atlas_variants::<C>.after(update_loading_maps::<C>),
+                update_parallax_layers::<C>.before(update_relative_origins::<C>),
+                update_map_aabbs::<C>.after(update_loading_maps::<C>),
                 update_map_vertex_attributes::<C>,
+                bake_map_lod_colors::<C>.after(update_loading_maps::<C>),
+                update_chunk_visibility::<C>,
+                update_chunk_generation::<C>.after(update_chunk_visibility::<C>),
+                update_chunked_maps::<C>,
+                apply_map_wind::<C>,
+                propagate_map_render_layers::<C>
+                    .after(update_map_stacks::<C>)
+                    .after(update_chunked_maps::<C>),
+            ),
+        );
+
+        app.add_systems(PostUpdate, apply_map_edits::<C>.in_set(ApplyMapEdits));
+        app.add_systems(
+            PostUpdate,
+            (
+                report_changed_tiles::<C>,
+                schedule_rebakes::<C>,
+                update_tile_colliders::<C>,
             )
+                .chain()
+                .after(ApplyMapEdits),
         );
+
+        app.init_resource::<FastTileMapSettings>();
+        app.init_resource::<MapWind>();
+
+        // Shared by all customizations, only advance it once per frame
+        if !app.is_plugin_added::<MapAnimationPlugin>() {
+            app.add_plugins(MapAnimationPlugin);
+        }
+
+        // Highlights are drawn with gizmos, which might not be available (eg. `MinimalPlugins`)
+        if app.is_plugin_added::<GizmoPlugin>() {
+            app.add_systems(Update, draw_map_highlights::<C>);
+        }
     }
 }
@@ -0,0 +1,124 @@
+//! Rendering maps whose world extents exceed `f32` precision (hundreds of thousands of pixels).
+//!
+//! Normally the map mesh spans the whole map and the shader receives absolute map positions,
+//! which far away from the map origin only have a few bits left for the position within a tile,
+//! so tiles jitter and seams appear. With a relative origin (see [`Map::set_relative_origin`])
+//! the managed mesh only covers the area seen by the cameras and map positions are passed
+//! relative to a tile near the cameras (computed in double precision), the shader only adds back
+//! whole tiles.
+
+use bevy::{
+    math::{dmat2, Vec3Swizzles},
+    prelude::*,
+    render::view::{NoFrustumCulling, RenderLayers},
+};
+
+use super::{
+    chunk::{camera_world_rects, ViewCamera},
+    map::{Map, MeshManagedByMap},
+    plugin::Customization,
+};
+
+/// Local rectangle (in the coordinates of the map entity) seen by the cameras,
+/// the managed mesh of a map with relative origin, an edge mode or parallax covers it.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RelativeView {
+    pub(crate) rect: Rect,
+}
+
+impl<C: Customization> Map<C> {
+    /// Pass map positions to the shader relative to a tile near the cameras, for maps too large
+    /// for `f32` precision, see the [module docs](self). Only applies to maps with managed meshes
+    /// and without variable row or column sizes.
+    pub fn set_relative_origin(&mut self, enabled: bool) {
+        self.relative_origin = enabled;
+        if !enabled {
+            self.map_uniform.origin = IVec2::ZERO;
+        }
+    }
+
+    /// Tile the shader map positions are currently relative to, `(0, 0)` without
+    /// relative origin.
+    pub fn origin(&self) -> IVec2 {
+        self.map_uniform.origin
+    }
+
+    pub(crate) fn relative_origin_active(&self) -> bool {
+        self.relative_origin && self.variable_grid.is_none()
+    }
+
+    /// Same as `world_to_linear` for a local position, relative to [`Self::origin`] and
+    /// computed in double precision.
+    pub(crate) fn local_to_relative_linear(&self, local: Vec2) -> Vec2 {
+        let u = &self.map_uniform;
+        let projection2d = dmat2(
+            u.projection.x_axis.xy().as_dvec2(),
+            u.projection.y_axis.xy().as_dvec2(),
+        );
+        let linear = projection2d.inverse()
+            * ((local.as_dvec2() - u.world_offset.as_dvec2()) / u.tile_size.as_dvec2());
+        (linear - u.origin.as_dvec2()).as_vec2()
+    }
+}
+
+/// Move the origin of maps with relative origin to the tile at the center of the cameras' view
+/// and track the view of maps whose meshes cover it.
+pub fn update_relative_origins<C: Customization>(
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    maps: Query<
+        (
+            Entity,
+            &Handle<Map<C>>,
+            &GlobalTransform,
+            Option<&RelativeView>,
+            Option<&RenderLayers>,
+        ),
+        With<MeshManagedByMap>,
+    >,
+    cameras: Query<ViewCamera>,
+    mut commands: Commands,
+) {
+    for (entity, map_handle, transform, view, layers) in maps.iter() {
+        let Some(map) = map_materials.get(map_handle) else {
+            continue;
+        };
+        if !map.covers_view() {
+            if view.is_some() {
+                commands
+                    .entity(entity)
+                    .remove::<(RelativeView, NoFrustumCulling)>();
+            }
+            continue;
+        }
+
+        let inverse = transform.affine().inverse();
+        let mut rect = Rect::EMPTY;
+        for view_rect in camera_world_rects(cameras.iter(), layers).iter() {
+            for corner in [
+                view_rect.min,
+                Vec2::new(view_rect.min.x, view_rect.max.y),
+                Vec2::new(view_rect.max.x, view_rect.min.y),
+                view_rect.max,
+            ] {
+                rect = rect.union_point(inverse.transform_point3(corner.extend(0.0)).xy());
+            }
+        }
+        if rect.is_empty() {
+            continue;
+        }
+
+        let origin = map.local_to_map(rect.center()).floor().as_ivec2();
+        if map.relative_origin_active() && origin != map.map_uniform.origin {
+            if let Some(map) = map_materials.get_mut(map_handle) {
+                map.map_uniform.origin = origin;
+            }
+        }
+
+        if view.map_or(true, |view| view.rect != rect) {
+            // The mesh follows the cameras, so its bounds computed once by bevy would be stale
+            commands
+                .entity(entity)
+                .insert((RelativeView { rect }, NoFrustumCulling));
+        }
+    }
+}
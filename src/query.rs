@@ -0,0 +1,119 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::{
+    map::{Map, MapLoading},
+    plugin::{Customization, NoCustomization},
+};
+
+/// System parameter bundling access to all maps (of one customization) with their transforms.
+///
+/// Only maps that are ready (their asset is available and loading has finished) are returned,
+/// all helpers return `None` (or `false`) for other entities.
+///
+/// ```ignore
+/// fn dig(mut maps: MapQuery, cursor: Res<CursorWorld>, ground: Res<Ground>) {
+///     if let Some(tile) = maps.tile_at_world(ground.0, cursor.0) {
+///         maps.set(ground.0, tile, DIRT);
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct MapQuery<'w, 's, C: Customization = NoCustomization> {
+    maps: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Handle<Map<C>>,
+            &'static GlobalTransform,
+            Has<MapLoading>,
+        ),
+    >,
+    map_materials: ResMut<'w, Assets<Map<C>>>,
+}
+
+impl<'w, 's, C: Customization> MapQuery<'w, 's, C> {
+    fn ready_handle(&self, entity: Entity) -> Option<(&Handle<Map<C>>, &GlobalTransform)> {
+        let (_, handle, transform, loading) = self.maps.get(entity).ok()?;
+        (!loading && self.map_materials.contains(handle)).then_some((handle, transform))
+    }
+
+    /// Global transform of the map entity, if ready.
+    pub fn transform(&self, entity: Entity) -> Option<&GlobalTransform> {
+        self.ready_handle(entity).map(|(_, transform)| transform)
+    }
+
+    /// Whether the map of `entity` is ready to be used.
+    pub fn is_ready(&self, entity: Entity) -> bool {
+        self.ready_handle(entity).is_some()
+    }
+
+    /// All map entities that are ready.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.maps
+            .iter()
+            .filter(|(_, handle, _, loading)| !loading && self.map_materials.contains(*handle))
+            .map(|(entity, ..)| entity)
+    }
+
+    /// The map of `entity`, if ready.
+    pub fn get(&self, entity: Entity) -> Option<&Map<C>> {
+        let (handle, _) = self.ready_handle(entity)?;
+        self.map_materials.get(handle)
+    }
+
+    /// The map of `entity` for editing, if ready.
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut Map<C>> {
+        let handle = self.ready_handle(entity)?.0.clone();
+        self.map_materials.get_mut(&handle)
+    }
+
+    /// Map coordinates of the given world position, taking the map entity transform into account.
+    pub fn world_to_map(&self, entity: Entity, world: Vec2) -> Option<Vec2> {
+        let (handle, transform) = self.ready_handle(entity)?;
+        Some(
+            self.map_materials
+                .get(handle)?
+                .world_to_map(transform, world),
+        )
+    }
+
+    /// World position of the given map coordinates, taking the map entity transform into account.
+    pub fn map_to_world(&self, entity: Entity, map_position: Vec2) -> Option<Vec2> {
+        let (handle, transform) = self.ready_handle(entity)?;
+        Some(
+            self.map_materials
+                .get(handle)?
+                .map_to_world(transform, map_position),
+        )
+    }
+
+    /// Tile at the given world position, `None` if outside of the map.
+    /// For hexagonal projections this is the hexagon containing the position.
+    pub fn tile_at_world(&self, entity: Entity, world: Vec2) -> Option<UVec2> {
+        let (handle, transform) = self.ready_handle(entity)?;
+        self.map_materials
+            .get(handle)?
+            .world_to_tile(transform, world)
+    }
+
+    /// Atlas index of the tile at `pos`, `None` if outside of the map.
+    pub fn tile(&self, entity: Entity, pos: UVec2) -> Option<u32> {
+        let map = self.get(entity)?;
+        pos.cmplt(map.map_size())
+            .all()
+            .then(|| map.indexer().at_uvec(pos))
+    }
+
+    /// Set the tile at `pos`, returns whether the map was ready and `pos` within it.
+    pub fn set(&mut self, entity: Entity, pos: UVec2, index: u32) -> bool {
+        let Some(map) = self.get_mut(entity) else {
+            return false;
+        };
+        if !pos.cmplt(map.map_size()).all() {
+            return false;
+        }
+        map.indexer_mut().set_uvec(pos, index);
+        true
+    }
+}
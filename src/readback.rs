@@ -0,0 +1,289 @@
+//! Copying map data that has been modified on the GPU back into the CPU-side [`Map`].
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            binding_types::{storage_buffer_read_only_sized, storage_buffer_sized},
+            BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferDescriptor,
+            BufferUsages, CommandEncoderDescriptor, ComputePassDescriptor, ComputePipeline,
+            Maintain, MapMode, OwnedBindingResource, PipelineLayoutDescriptor,
+            RawComputePipelineDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    sprite::PreparedMaterial2d,
+};
+
+use super::{
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+/// Binding of the map data in [`Map`]'s bind group.
+pub(crate) const MAP_DATA_BINDING: u32 = 100;
+
+const WORKGROUP_SIZE: u32 = 64;
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// Copy the map data with a minimal compute pass, which unlike a buffer copy works with any
+/// storage buffer bound as map data.
+const COPY_SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read> src: array<u32>;
+@group(0) @binding(1) var<storage, read_write> dst: array<u32>;
+
+@compute @workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) n: vec3<u32>,
+) {
+    let i = id.y * n.x * 64u + id.x;
+    if i < arrayLength(&dst) {
+        dst[i] = src[i];
+    }
+}
+"#;
+
+/// Flag set by [`Map::read_back`] and picked up during extraction.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReadbackRequest(Arc<AtomicBool>);
+
+/// Plugin for [`Map::read_back`].
+pub type MapReadbackPlugin = CustomMapReadbackPlugin<NoCustomization>;
+
+/// Plugin for [`Map::read_back`].
+#[derive(Default)]
+pub struct CustomMapReadbackPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomMapReadbackPlugin<C> {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+        app.insert_resource(MapReadbackReceiver::<C>(Mutex::new(receiver)))
+            .add_systems(First, apply_map_readbacks::<C>);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(MapReadbackSender::<C>(sender))
+            .init_resource::<PendingMapReadbacks<C>>()
+            .add_systems(ExtractSchedule, extract_map_readbacks::<C>)
+            // After the render graph has been submitted, so this sees all GPU-side changes
+            // of the frame
+            .add_systems(Render, copy_map_readbacks::<C>.in_set(RenderSet::Cleanup));
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<MapReadbackPipeline>();
+        }
+    }
+}
+
+#[derive(Resource)]
+struct MapReadbackReceiver<C: Customization>(Mutex<Receiver<(AssetId<Map<C>>, Vec<u32>)>>);
+
+#[derive(Resource)]
+struct MapReadbackSender<C: Customization>(Sender<(AssetId<Map<C>>, Vec<u32>)>);
+
+#[derive(Resource)]
+struct PendingMapReadbacks<C: Customization>(Vec<AssetId<Map<C>>>);
+
+impl<C: Customization> Default for PendingMapReadbacks<C> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+#[derive(Resource)]
+struct MapReadbackPipeline {
+    layout: BindGroupLayout,
+    pipeline: ComputePipeline,
+}
+
+impl FromWorld for MapReadbackPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout(
+            "map_readback_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_sized(false, None),
+                ),
+            ),
+        );
+        let module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("map_readback_shader"),
+            source: ShaderSource::Wgsl(COPY_SHADER.into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("map_readback_pipeline_layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&RawComputePipelineDescriptor {
+            label: Some("map_readback_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "main",
+            compilation_options: default(),
+            cache: None,
+        });
+        Self { layout, pipeline }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Copy the map data back from the GPU, for when it has been modified there
+    /// (eg. by compute kernels or external render passes).
+    ///
+    /// The copy happens asynchronously, the map is updated a few frames later
+    /// (which triggers an `AssetEvent::Modified` and updates [`Self::stats`] and
+    /// [`Self::content_hash`]).
+    /// Note that modifying the map on the CPU side re-uploads it, discarding GPU-side changes that
+    /// have not been read back yet.
+    ///
+    /// This does not modify the map itself, so it can be called on `Assets::get`.
+    /// Requires [`MapReadbackPlugin`] (or [`CustomMapReadbackPlugin`]).
+    pub fn read_back(&self) {
+        self.readback.0.store(true, Ordering::Relaxed);
+    }
+}
+
+fn extract_map_readbacks<C: Customization>(
+    maps: Extract<Res<Assets<Map<C>>>>,
+    mut pending: ResMut<PendingMapReadbacks<C>>,
+) {
+    for (id, map) in maps.iter() {
+        if map.readback.0.swap(false, Ordering::Relaxed) && !pending.0.contains(&id) {
+            pending.0.push(id);
+        }
+    }
+}
+
+fn copy_map_readbacks<C: Customization>(
+    mut pending: ResMut<PendingMapReadbacks<C>>,
+    materials: Res<RenderAssets<PreparedMaterial2d<Map<C>>>>,
+    pipeline: Option<Res<MapReadbackPipeline>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+    sender: Res<MapReadbackSender<C>>,
+) {
+    let Some(pipeline) = pipeline else {
+        return;
+    };
+
+    // Maps that have not been prepared yet stay pending
+    pending.0.retain(|id| {
+        let Some(material) = materials.get(*id) else {
+            return true;
+        };
+        let Some(OwnedBindingResource::Buffer(source)) = material
+            .bindings
+            .iter()
+            .find(|(binding, _)| *binding == MAP_DATA_BINDING)
+            .map(|(_, resource)| resource)
+        else {
+            return false;
+        };
+
+        let size = source.size();
+        let target = device.create_buffer(&BufferDescriptor {
+            label: Some("map_readback_target"),
+            size,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = device.create_buffer(&BufferDescriptor {
+            label: Some("map_readback_staging"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(
+            "map_readback_bind_group",
+            &pipeline.layout,
+            &BindGroupEntries::sequential((source.as_entire_binding(), target.as_entire_binding())),
+        );
+
+        let workgroups = ((size / 4) as u32).div_ceil(WORKGROUP_SIZE);
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("map_readback_encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("map_readback_pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&pipeline.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                workgroups.min(MAX_WORKGROUPS),
+                workgroups.div_ceil(MAX_WORKGROUPS),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&target, 0, &staging, 0, size);
+        queue.submit([encoder.finish()]);
+
+        let id = *id;
+        let sender = sender.0.clone();
+        let buffer = staging.clone();
+        staging.slice(..).map_async(MapMode::Read, move |result| {
+            if result.is_err() {
+                warn!("Failed to read back map data of {:?}", id);
+                return;
+            }
+            let data = buffer
+                .slice(..)
+                .get_mapped_range()
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            buffer.unmap();
+            let _ = sender.send((id, data));
+        });
+        false
+    });
+
+    device.poll(Maintain::Poll);
+}
+
+fn apply_map_readbacks<C: Customization>(
+    receiver: Res<MapReadbackReceiver<C>>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    let Ok(receiver) = receiver.0.lock() else {
+        return;
+    };
+    for (id, data) in receiver.try_iter() {
+        let Some(map) = maps.get_mut(id) else {
+            continue;
+        };
+        let size = map.map_size();
+        if data.len() < (size.x * size.y) as usize {
+            warn!("Read back map data of {:?} is too short", id);
+            continue;
+        }
+        let mut m = map.indexer_mut();
+        for (i, index) in data
+            .into_iter()
+            .take((size.x * size.y) as usize)
+            .enumerate()
+        {
+            m.set(i as u32 % size.x, i as u32 / size.x, index);
+        }
+    }
+}
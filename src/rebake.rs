@@ -0,0 +1,146 @@
+//! Spreading updates of data derived from the tiles over multiple frames.
+//!
+//! Data derived from the tiles of a map (autotiles, baked lighting, minimap pixels, ..) has to be
+//! updated when tiles change. For large edits (eg. a flood fill over the whole map) doing that in
+//! one go causes a frame spike. A [`RebakeScheduler`] collects the changed regions of its map
+//! (see [`MapTilesChanged`]) and hands them out as [`RebakeRegion`] events of at most
+//! [`RebakeScheduler::budget`] tiles per frame. Read these events in the systems maintaining
+//! the derived data, eg.
+//!
+//! ```ignore
+//! fn rebake_cliffs(mut regions: EventReader<RebakeRegion>, ..) {
+//!     for region in regions.read() {
+//!         map.apply_cliffs_in(region.rect, |p| elevation(p), &rules);
+//!     }
+//! }
+//! ```
+//!
+//! Tiles changed while rebaking are reported again, so derived tiles written back into the same
+//! map should only be set when they differ (as [`Map::apply_cliffs_in`] does).
+
+use std::collections::VecDeque;
+
+use bevy::{math::URect, prelude::*};
+
+use super::{
+    autotile::coalesce_regions, changes::MapTilesChanged, map::Map, plugin::Customization,
+};
+
+/// Hands out changed regions of the map of this entity as [`RebakeRegion`] events,
+/// see the [module docs](self).
+#[derive(Component, Debug, Clone)]
+pub struct RebakeScheduler {
+    /// Maximum number of tiles handed out per frame.
+    /// At least one row of a region is handed out per frame, even if it is wider.
+    pub budget: u32,
+    /// Number of tiles changed regions are grown by, for derived data that depends on
+    /// neighboring tiles.
+    pub margin: u32,
+    pending: VecDeque<URect>,
+}
+
+impl RebakeScheduler {
+    pub fn new(budget: u32) -> Self {
+        Self {
+            budget: budget.max(1),
+            margin: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn with_margin(self, margin: u32) -> Self {
+        Self { margin, ..self }
+    }
+
+    /// Queue a region (`max` exclusive) for rebaking, eg. everything after loading a map.
+    pub fn queue(&mut self, rect: URect) {
+        if !rect.is_empty() {
+            self.pending.push_back(rect);
+        }
+    }
+
+    /// Number of tiles still to be handed out.
+    pub fn pending_tiles(&self) -> u32 {
+        self.pending.iter().map(|r| r.width() * r.height()).sum()
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Take the regions to rebake this frame.
+    fn next_regions(&mut self) -> Vec<URect> {
+        let mut remaining = self.budget;
+        let mut regions = Vec::new();
+        while let Some(rect) = self.pending.front_mut() {
+            let width = rect.width().max(1);
+            let tiles = width * rect.height();
+            if tiles <= remaining {
+                remaining -= tiles;
+                regions.push(*rect);
+                self.pending.pop_front();
+                continue;
+            }
+            // Split off as many rows as fit into the budget
+            let rows = remaining / width;
+            if rows == 0 && !regions.is_empty() {
+                break;
+            }
+            let rows = rows.max(1);
+            regions.push(URect::new(
+                rect.min.x,
+                rect.min.y,
+                rect.max.x,
+                rect.min.y + rows,
+            ));
+            rect.min.y += rows;
+            break;
+        }
+        regions
+    }
+}
+
+/// Tiles of `rect` (`max` exclusive) of the map of `map` changed and derived data should be
+/// updated, see [`RebakeScheduler`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RebakeRegion {
+    pub map: Entity,
+    pub rect: URect,
+}
+
+pub(crate) fn schedule_rebakes<C: Customization>(
+    map_materials: Res<Assets<Map<C>>>,
+    mut changes: EventReader<MapTilesChanged>,
+    mut schedulers: Query<(Entity, &Handle<Map<C>>, &mut RebakeScheduler)>,
+    mut rebake: EventWriter<RebakeRegion>,
+) {
+    for change in changes.read() {
+        let Ok((_, handle, mut scheduler)) = schedulers.get_mut(change.map) else {
+            continue;
+        };
+        let Some(map) = map_materials.get(handle) else {
+            continue;
+        };
+        let margin = UVec2::splat(scheduler.margin);
+        let rect = URect::from_corners(
+            change.rect.min.saturating_sub(margin),
+            change.rect.max + margin,
+        )
+        .intersect(URect::from_corners(UVec2::ZERO, map.map_size()));
+        scheduler.queue(rect);
+    }
+
+    for (entity, _, mut scheduler) in schedulers.iter_mut() {
+        if scheduler.is_idle() {
+            continue;
+        }
+        // Repeated edits of the same area are only rebaked once
+        if scheduler.pending.len() > 1 {
+            let regions = coalesce_regions(scheduler.pending.drain(..));
+            scheduler.pending = regions.into();
+        }
+        for rect in scheduler.next_regions() {
+            rebake.send(RebakeRegion { map: entity, rect });
+        }
+    }
+}
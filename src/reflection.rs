@@ -0,0 +1,115 @@
+use bevy::{math::URect, prelude::*};
+
+use super::{
+    map::{Map, MapAttributes},
+    plugin::Customization,
+};
+
+/// Axis along which a [`MapReflection`] mirrors its source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum MirrorAxis {
+    /// Reverse the order of rows, eg. for reflections in a floor below the source.
+    #[default]
+    Rows,
+    /// Reverse the order of columns.
+    Columns,
+}
+
+/// Keep the map of this entity a mirrored, tinted copy of a region of another map,
+/// eg. for floor reflections in interiors.
+///
+/// The copy is refreshed whenever the source map's [`Map::content_hash`] changes.
+/// Only the arrangement of tiles is mirrored, not the tile images themselves,
+/// so use an atlas with mirrored tiles for the reflection map.
+/// The tint is applied as mix color (see [`MapAttributes`]).
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MapReflection {
+    /// Map entity to mirror.
+    pub source: Entity,
+    /// Region of the source map to mirror (`max` exclusive), copied to the origin of this map.
+    pub region: URect,
+    pub axis: MirrorAxis,
+    pub tint: Color,
+
+    #[reflect(ignore)]
+    pub(crate) synced_hash: Option<u64>,
+}
+
+impl MapReflection {
+    pub fn new(source: Entity, region: URect) -> Self {
+        Self {
+            source,
+            region,
+            axis: default(),
+            tint: Color::srgba(1.0, 1.0, 1.0, 0.4),
+            synced_hash: None,
+        }
+    }
+
+    pub fn with_axis(self, axis: MirrorAxis) -> Self {
+        Self { axis, ..self }
+    }
+
+    pub fn with_tint(self, tint: Color) -> Self {
+        Self { tint, ..self }
+    }
+}
+
+pub(crate) fn update_map_reflections<C: Customization>(
+    mut reflections: Query<(
+        &mut MapReflection,
+        &Handle<Map<C>>,
+        Option<&mut MapAttributes>,
+    )>,
+    sources: Query<&Handle<Map<C>>>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    for (mut reflection, handle, attributes) in reflections.iter_mut() {
+        let Some(source) = sources
+            .get(reflection.source)
+            .ok()
+            .and_then(|source| maps.get(source))
+        else {
+            continue;
+        };
+
+        let hash = source.content_hash();
+        if !reflection.is_changed() && reflection.synced_hash == Some(hash) {
+            continue;
+        }
+
+        let region = reflection
+            .region
+            .intersect(URect::from_corners(UVec2::ZERO, source.map_size()));
+        let size = region.size();
+        let indexer = source.indexer();
+        let tiles: Vec<u32> = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| UVec2::new(x, y)))
+            .map(|p| indexer.at_uvec(region.min + p))
+            .collect();
+
+        let Some(target) = maps.get_mut(handle) else {
+            continue;
+        };
+        let mut m = target.indexer_mut();
+        let target_size = m.size();
+        for (i, index) in tiles.into_iter().enumerate() {
+            let p = UVec2::new(i as u32 % size.x, i as u32 / size.x);
+            let p = match reflection.axis {
+                MirrorAxis::Rows => UVec2::new(p.x, size.y - 1 - p.y),
+                MirrorAxis::Columns => UVec2::new(size.x - 1 - p.x, p.y),
+            };
+            if p.x < target_size.x && p.y < target_size.y {
+                m.set_uvec(p, index);
+            }
+        }
+
+        if reflection.is_changed() {
+            if let Some(mut attributes) = attributes {
+                attributes.mix_color = vec![reflection.tint.to_linear().to_vec4(); 4];
+            }
+        }
+        reflection.bypass_change_detection().synced_hash = Some(hash);
+    }
+}
@@ -0,0 +1,77 @@
+use bevy::{prelude::*, utils::HashMap};
+
+/// Optional plugin maintaining the [`MapRegistry`] resource.
+#[derive(Default)]
+pub struct MapRegistryPlugin;
+
+impl Plugin for MapRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MapName>()
+            .init_resource::<MapRegistry>()
+            .add_systems(PostUpdate, update_map_registry);
+    }
+}
+
+/// Identifier of a map entity (eg. `"ground"`, `"props"`, `"collision"`),
+/// see [`MapRegistry`].
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash, Reflect)]
+#[reflect(Component)]
+pub struct MapName(pub String);
+
+impl MapName {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+}
+
+/// Lookup of map entities by their [`MapName`], maintained by [`MapRegistryPlugin`].
+///
+/// Lets systems and scripts address maps (eg. layers) by name instead of passing entities
+/// around. Names should be unique, if several maps share a name the most recently named one
+/// is registered. Changes are picked up in `PostUpdate`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MapRegistry {
+    entities: HashMap<String, Entity>,
+}
+
+impl MapRegistry {
+    /// Map entity with the given name.
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.entities.get(name).copied()
+    }
+
+    /// Name under which `entity` is registered.
+    pub fn name_of(&self, entity: Entity) -> Option<&str> {
+        self.entities
+            .iter()
+            .find(|(_, e)| **e == entity)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// All registered names with their map entities.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.entities.iter().map(|(name, e)| (name.as_str(), *e))
+    }
+
+    fn unregister(&mut self, entity: Entity) {
+        self.entities.retain(|_, e| *e != entity);
+    }
+}
+
+pub fn update_map_registry(
+    mut registry: ResMut<MapRegistry>,
+    names: Query<(Entity, Ref<MapName>)>,
+    mut removed: RemovedComponents<MapName>,
+) {
+    for entity in removed.read() {
+        registry.unregister(entity);
+    }
+
+    let rebuild = registry.is_added();
+    for (entity, name) in names.iter() {
+        if rebuild || name.is_changed() {
+            registry.unregister(entity);
+            registry.entities.insert(name.0.clone(), entity);
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Maps are drawn by the cameras sharing a [`RenderLayers`] layer with the map entity (both
+//! default to layer `0`), eg. for a minimap camera showing its own map entities.
+//!
+//! Everything following the cameras' views (relative origins, edge modes, parallax, chunks,
+//! chunked and streamed maps) only considers the cameras drawing the map. Entities spawned to
+//! draw parts of a map (row slices of a [`crate::stack::MapStack`], chunks of a
+//! [`crate::chunked::ChunkedMap`]) take over the layers of the map entity.
+
+use bevy::{prelude::*, render::view::RenderLayers};
+
+use super::{chunked::ChunkedMap, plugin::Customization, stack::MapRowSlice};
+
+/// Whether a camera with `camera_layers` draws an entity with `layers`.
+pub(crate) fn renders_layers(
+    camera_layers: Option<&RenderLayers>,
+    layers: Option<&RenderLayers>,
+) -> bool {
+    let default_layers = RenderLayers::default();
+    camera_layers
+        .unwrap_or(&default_layers)
+        .intersects(layers.unwrap_or(&default_layers))
+}
+
+/// Copy the render layers of map entities to the entities drawing parts of them.
+pub(crate) fn propagate_map_render_layers<C: Customization>(
+    mut commands: Commands,
+    slices: Query<(Entity, &Parent), With<MapRowSlice>>,
+    chunked_maps: Query<(&ChunkedMap<C>, Option<&RenderLayers>)>,
+    layers: Query<Option<&RenderLayers>>,
+) {
+    let mut sync = |entity: Entity, parent_layers: Option<&RenderLayers>| {
+        let Ok(current) = layers.get(entity) else {
+            return;
+        };
+        if current == parent_layers {
+            return;
+        }
+        match parent_layers {
+            Some(parent_layers) => {
+                commands.entity(entity).insert(parent_layers.clone());
+            }
+            None => {
+                commands.entity(entity).remove::<RenderLayers>();
+            }
+        }
+    };
+
+    for (entity, parent) in slices.iter() {
+        if let Ok(parent_layers) = layers.get(parent.get()) {
+            sync(entity, parent_layers);
+        }
+    }
+    for (chunked, chunked_layers) in chunked_maps.iter() {
+        for (_, entity) in chunked.resident() {
+            sync(entity, chunked_layers);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+//! Recording tile edits with timestamps and replaying them, eg. for debugging desyncs, demos or
+//! timelapses of building games.
+//!
+//! ```ignore
+//! map.start_recording();
+//! // .. edits through `map.indexer_mut()`, `MapCommands`, transactions, ..
+//! let recording = map.stop_recording().unwrap();
+//! std::fs::write("build.bftr", recording.to_bytes())?;
+//!
+//! // Later, replay at 10x speed on a fresh map (requires `EditReplayPlugin`)
+//! let recording = EditRecording::from_bytes(&std::fs::read("build.bftr")?)?;
+//! commands.entity(map_entity).insert(EditPlayback::new(recording).with_speed(10.0));
+//! ```
+
+use bevy::{prelude::*, utils::Instant};
+
+use super::{
+    error::MapFormatError,
+    map::Map,
+    plugin::{Customization, NoCustomization},
+};
+
+const MAGIC: &[u8; 4] = b"BFTR";
+
+/// Plugin playing back [`EditPlayback`]s.
+pub type EditReplayPlugin = CustomEditReplayPlugin<NoCustomization>;
+
+/// Same as [`EditReplayPlugin`] for maps with custom shader code.
+#[derive(Default)]
+pub struct CustomEditReplayPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomEditReplayPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, play_edits::<C>);
+    }
+}
+
+/// A single recorded tile change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedEdit {
+    /// Seconds since the start of the recording, in millisecond resolution.
+    pub time: f32,
+    /// Layer of the tile, `0` for the base layer.
+    pub layer: u32,
+    pub pos: UVec2,
+    pub value: u32,
+}
+
+/// Tile changes in the order they happened, see [`Map::start_recording`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditRecording {
+    edits: Vec<RecordedEdit>,
+}
+
+impl EditRecording {
+    pub fn edits(&self) -> &[RecordedEdit] {
+        &self.edits
+    }
+
+    pub fn len(&self) -> usize {
+        self.edits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    /// Time of the last edit, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.edits.last().map_or(0.0, |edit| edit.time)
+    }
+
+    /// Apply all edits to `map` at once.
+    pub fn apply<C: Customization>(&self, map: &mut Map<C>) {
+        let mut m = map.indexer_mut();
+        for edit in self.edits.iter() {
+            m.set_layer(edit.layer, edit.pos.x, edit.pos.y, edit.value);
+        }
+    }
+
+    /// Compact binary form: a header followed by the time delta (in milliseconds), layer,
+    /// position and value of each edit as variable length integers.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let mut last_ms = 0;
+        for edit in self.edits.iter() {
+            let ms = (edit.time * 1000.0).round().max(0.0) as u32;
+            write_varint(&mut bytes, ms.saturating_sub(last_ms));
+            write_varint(&mut bytes, edit.layer);
+            write_varint(&mut bytes, edit.pos.x);
+            write_varint(&mut bytes, edit.pos.y);
+            write_varint(&mut bytes, edit.value);
+            last_ms = ms.max(last_ms);
+        }
+        bytes
+    }
+
+    /// Read a recording written by [`Self::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MapFormatError> {
+        let Some(mut rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+            return Err(MapFormatError::BadMagic);
+        };
+        let mut edits = Vec::new();
+        let mut ms = 0u32;
+        while !rest.is_empty() {
+            ms = ms.saturating_add(read_varint(&mut rest)?);
+            let layer = read_varint(&mut rest)?;
+            let x = read_varint(&mut rest)?;
+            let y = read_varint(&mut rest)?;
+            let value = read_varint(&mut rest)?;
+            edits.push(RecordedEdit {
+                time: ms as f32 / 1000.0,
+                layer,
+                pos: UVec2::new(x, y),
+                value,
+            });
+        }
+        Ok(Self { edits })
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut v: u32) {
+    while v >= 0x80 {
+        bytes.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    bytes.push(v as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u32, MapFormatError> {
+    let mut v = 0u32;
+    for shift in (0..35).step_by(7) {
+        let (&byte, rest) = bytes.split_first().ok_or(MapFormatError::Truncated)?;
+        *bytes = rest;
+        v |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err(MapFormatError::Truncated)
+}
+
+/// Recording in progress on a map.
+#[derive(Debug, Clone)]
+pub(crate) struct EditRecorder {
+    start: Instant,
+    recording: EditRecording,
+}
+
+impl EditRecorder {
+    pub(crate) fn record(&mut self, layer: u32, pos: UVec2, value: u32) {
+        self.recording.edits.push(RecordedEdit {
+            time: self.start.elapsed().as_secs_f32(),
+            layer,
+            pos,
+            value,
+        });
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Start recording all tile changes of this map (of all layers, unchanged writes are
+    /// skipped), discarding a recording in progress. Timestamps are taken from the wall clock.
+    pub fn start_recording(&mut self) {
+        self.recorder = Some(EditRecorder {
+            start: Instant::now(),
+            recording: default(),
+        });
+    }
+
+    /// Stop recording and return the recorded edits, `None` if no recording was in progress.
+    pub fn stop_recording(&mut self) -> Option<EditRecording> {
+        self.recorder.take().map(|recorder| recorder.recording)
+    }
+
+    /// Edits recorded so far, `None` if no recording is in progress.
+    pub fn recording(&self) -> Option<&EditRecording> {
+        self.recorder.as_ref().map(|recorder| &recorder.recording)
+    }
+}
+
+/// Replays an [`EditRecording`] on the map of this entity in real time, scaled by `speed`.
+/// Requires [`EditReplayPlugin`].
+#[derive(Component, Debug, Clone)]
+pub struct EditPlayback {
+    recording: EditRecording,
+    /// Playback speed, `1.0` for real time.
+    pub speed: f32,
+    time: f32,
+    next: usize,
+}
+
+impl EditPlayback {
+    pub fn new(recording: EditRecording) -> Self {
+        Self {
+            recording,
+            speed: 1.0,
+            time: 0.0,
+            next: 0,
+        }
+    }
+
+    pub fn with_speed(self, speed: f32) -> Self {
+        Self { speed, ..self }
+    }
+
+    /// Playback position in seconds of the recording.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.edits.len()
+    }
+}
+
+fn play_edits<C: Customization>(
+    time: Res<Time>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut playbacks: Query<(&Handle<Map<C>>, &mut EditPlayback)>,
+) {
+    for (map_handle, mut playback) in playbacks.iter_mut() {
+        if playback.is_finished() || !map_materials.contains(map_handle) {
+            continue;
+        }
+        playback.time += time.delta_seconds() * playback.speed;
+
+        let playback = &mut *playback;
+        let pending = &playback.recording.edits[playback.next..];
+        let n = pending.partition_point(|edit| edit.time <= playback.time);
+        if n == 0 {
+            continue;
+        }
+        // Only touch the map when there is something to apply, as that re-uploads it
+        if let Some(map) = map_materials.get_mut(map_handle) {
+            let mut m = map.indexer_mut();
+            for edit in pending[..n].iter() {
+                m.set_layer(edit.layer, edit.pos.x, edit.pos.y, edit.value);
+            }
+        }
+        playback.next += n;
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization};
+
+/// Elliptic hole cut into a map around a world position, eg. to reveal the interior below
+/// a roof layer around the player. See [`Map::set_reveal`] and [`MapReveal`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct RevealShape {
+    /// Center of the hole in world coordinates.
+    pub center: Vec2,
+    /// Radii of the hole in world units.
+    pub radius: Vec2,
+    /// Width of the soft edge (inside of `radius`) in world units.
+    pub feather: f32,
+    /// Opacity of the map in the center of the hole.
+    pub min_opacity: f32,
+}
+
+impl Default for RevealShape {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            radius: Vec2::splat(64.0),
+            feather: 16.0,
+            min_opacity: 0.0,
+        }
+    }
+}
+
+impl RevealShape {
+    pub fn circle(radius: f32) -> Self {
+        Self {
+            radius: Vec2::splat(radius),
+            ..default()
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Cut a (feathered) hole into this map, `None` for no hole.
+    pub fn set_reveal(&mut self, shape: Option<RevealShape>) {
+        match shape {
+            Some(shape) => {
+                self.map_uniform.reveal_shape =
+                    shape.center.extend(shape.radius.x).extend(shape.radius.y);
+                self.map_uniform.reveal_params = Vec2::new(shape.feather, shape.min_opacity);
+                self.reveal = true;
+            }
+            None => self.reveal = false,
+        }
+    }
+}
+
+/// Keep a hole of the given shape in the map of this entity centered on the `target` entity
+/// (eg. the player), see [`RevealShape`].
+///
+/// The map is only modified when the target moves or the shape changes.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct MapReveal {
+    pub target: Entity,
+    /// Shape of the hole, `center` is ignored.
+    pub shape: RevealShape,
+}
+
+impl MapReveal {
+    pub fn new(target: Entity, shape: RevealShape) -> Self {
+        Self { target, shape }
+    }
+}
+
+pub(crate) fn update_map_reveals<C: Customization>(
+    reveals: Query<(&MapReveal, &Handle<Map<C>>)>,
+    targets: Query<&GlobalTransform>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    for (reveal, handle) in reveals.iter() {
+        let Ok(target) = targets.get(reveal.target) else {
+            continue;
+        };
+        let shape = RevealShape {
+            center: target.translation().truncate(),
+            ..reveal.shape
+        };
+
+        let Some(map) = maps.get(handle) else {
+            continue;
+        };
+        let u = &map.map_uniform;
+        if map.reveal
+            && u.reveal_shape == shape.center.extend(shape.radius.x).extend(shape.radius.y)
+            && u.reveal_params == Vec2::new(shape.feather, shape.min_opacity)
+        {
+            continue;
+        }
+
+        if let Some(map) = maps.get_mut(handle) {
+            map.set_reveal(Some(shape));
+        }
+    }
+}
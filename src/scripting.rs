@@ -0,0 +1,152 @@
+//! Map editing from [rhai](https://rhai.rs) scripts (requires the `scripting` feature).
+//!
+//! Register the map API with [`register_map_api`], then run scripts with [`Map::run_script`].
+//! The script sees the map as a variable `map`:
+//!
+//! ```rhai
+//! for x in 0..map.width {
+//!     if map.get(x, 0) == 0 {
+//!         map.set(x, 0, 3);
+//!     }
+//! }
+//! map.fill(2, 2, 4, 4, 1);   // x, y, width, height, index
+//! map.flood_fill(10, 10, 7);
+//! ```
+//!
+//! All positions are bounds checked, accessing a tile outside of the map raises a script error.
+//! Areas passed to `fill` only need to start inside of the map, they are clipped to it.
+//! Edits are applied to the map only when the script finishes successfully.
+
+use bevy::{math::uvec2, prelude::*};
+use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+
+use super::{
+    map::{flood_region, Map},
+    plugin::Customization,
+};
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Copy of the map tiles as seen by scripts.
+#[derive(Debug, Clone)]
+pub struct ScriptMap {
+    size: UVec2,
+    tiles: Vec<u32>,
+}
+
+impl ScriptMap {
+    fn pos(&self, x: i64, y: i64) -> ScriptResult<UVec2> {
+        if x < 0 || y < 0 || x >= self.size.x as i64 || y >= self.size.y as i64 {
+            return Err(format!(
+                "Tile position ({}, {}) is outside of the map (size {} x {})",
+                x, y, self.size.x, self.size.y
+            )
+            .into());
+        }
+        Ok(uvec2(x as u32, y as u32))
+    }
+
+    fn index(v: i64) -> ScriptResult<u32> {
+        u32::try_from(v).map_err(|_| format!("Invalid tile index {}", v).into())
+    }
+
+    fn at(&self, pos: UVec2) -> u32 {
+        self.tiles[(pos.y * self.size.x + pos.x) as usize]
+    }
+
+    fn put(&mut self, pos: UVec2, v: u32) {
+        self.tiles[(pos.y * self.size.x + pos.x) as usize] = v;
+    }
+
+    fn width(&mut self) -> i64 {
+        self.size.x as i64
+    }
+
+    fn height(&mut self) -> i64 {
+        self.size.y as i64
+    }
+
+    fn get(&mut self, x: i64, y: i64) -> ScriptResult<i64> {
+        let pos = self.pos(x, y)?;
+        Ok(self.at(pos) as i64)
+    }
+
+    fn set(&mut self, x: i64, y: i64, v: i64) -> ScriptResult<()> {
+        let pos = self.pos(x, y)?;
+        self.put(pos, Self::index(v)?);
+        Ok(())
+    }
+
+    fn fill(&mut self, x: i64, y: i64, w: i64, h: i64, v: i64) -> ScriptResult<()> {
+        let v = Self::index(v)?;
+        if w <= 0 || h <= 0 {
+            return Ok(());
+        }
+        let min = self.pos(x, y)?;
+        // The area is clipped to the map
+        let max = uvec2(
+            x.saturating_add(w - 1).min(self.size.x as i64 - 1) as u32,
+            y.saturating_add(h - 1).min(self.size.y as i64 - 1) as u32,
+        );
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                self.put(uvec2(x, y), v);
+            }
+        }
+        Ok(())
+    }
+
+    fn flood_fill(&mut self, x: i64, y: i64, v: i64) -> ScriptResult<()> {
+        let start = self.pos(x, y)?;
+        let v = Self::index(v)?;
+        for pos in flood_region(self.size, start, |p| self.at(p)) {
+            self.put(pos, v);
+        }
+        Ok(())
+    }
+}
+
+/// Register the map type and its functions with a rhai engine.
+pub fn register_map_api(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptMap>("Map")
+        .register_get("width", ScriptMap::width)
+        .register_get("height", ScriptMap::height)
+        .register_fn("get", ScriptMap::get)
+        .register_fn("set", ScriptMap::set)
+        .register_fn("fill", ScriptMap::fill)
+        .register_fn("flood_fill", ScriptMap::flood_fill);
+}
+
+impl<C: Customization> Map<C> {
+    /// Run a compiled script against this map, see [`crate::scripting`].
+    /// `engine` must have the map API registered with [`register_map_api`].
+    ///
+    /// Returns the value of the script. On error, the map is left unchanged.
+    pub fn run_script(&mut self, engine: &Engine, ast: &AST) -> ScriptResult<Dynamic> {
+        let mut scope = Scope::new();
+        scope.push(
+            "map",
+            ScriptMap {
+                size: self.map_size(),
+                tiles: self.map_texture.clone(),
+            },
+        );
+
+        let result = engine.eval_ast_with_scope::<Dynamic>(&mut scope, ast)?;
+
+        let script_map = scope
+            .get_value::<ScriptMap>("map")
+            .ok_or("Script replaced the `map` variable")?;
+
+        let size = self.map_size();
+        let mut m = self.indexer_mut();
+        for (i, v) in script_map.tiles.iter().enumerate() {
+            let pos = uvec2(i as u32 % size.x, i as u32 / size.x);
+            if m.at_uvec(pos) != *v {
+                m.set_uvec(pos, *v);
+            }
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization};
+
+/// Parameters for atlases authored as signed distance fields, see [`Map::set_sdf`].
+///
+/// The alpha channel of the atlas holds the distance to the shape edge,
+/// `0.5` being on the edge and larger values inside. The color channels are used as fill color.
+/// Shapes are rendered with smooth edges at any zoom level.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct SdfSettings {
+    /// Distance (in atlas pixels) that the alpha range `0.0..1.0` spans.
+    pub spread: f32,
+    /// Width (in atlas pixels) of an outline drawn around the shape, `0.0` for none.
+    pub outline_width: f32,
+    pub outline_color: Color,
+    /// Width (in atlas pixels) of a glow fading out around the shape (and outline),
+    /// `0.0` for none.
+    pub glow_width: f32,
+    pub glow_color: Color,
+}
+
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self {
+            spread: 8.0,
+            outline_width: 0.0,
+            outline_color: Color::BLACK,
+            glow_width: 0.0,
+            glow_color: Color::WHITE,
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Interpret the atlas as signed distance field with the given settings
+    /// (`None` to render the atlas as regular image again).
+    pub fn set_sdf(&mut self, sdf: Option<&SdfSettings>) {
+        self.sdf = sdf.is_some();
+        if let Some(sdf) = sdf {
+            let u = &mut self.map_uniform;
+            u.sdf_params = Vec4::new(sdf.spread, sdf.outline_width, sdf.glow_width, 0.0);
+            u.sdf_outline_color = sdf.outline_color.to_linear().to_vec4();
+            u.sdf_glow_color = sdf.glow_color.to_linear().to_vec4();
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::{
+    prelude::*,
+    render::texture::{ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+};
+
+use super::{map::Map, plugin::Customization};
+
+/// Global quality settings for all maps, eg. to be exposed in a graphics options menu.
+///
+/// Changes are picked up every frame; changes to `overhangs` re-specialize the map pipelines.
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct FastTileMapSettings {
+    /// Upper limit for the overhang rendering configured per map.
+    pub overhangs: OverhangQuality,
+    /// Whether shader animations run, when `false` all maps show a still image.
+    pub animations: bool,
+    /// Maximum number of tiles changed by queued [`crate::commands::MapEdit`]s per frame
+    /// (over all maps), remaining edits are deferred to the next frames.
+    /// At least one edit is applied per frame. `None` for no limit.
+    pub edit_budget: Option<usize>,
+    /// Texture filtering used when magnifying the atlas.
+    pub filtering: TileFiltering,
+}
+
+impl Default for FastTileMapSettings {
+    fn default() -> Self {
+        Self {
+            overhangs: OverhangQuality::Full,
+            animations: true,
+            edit_budget: None,
+            filtering: TileFiltering::Nearest,
+        }
+    }
+}
+
+/// See [`FastTileMapSettings::overhangs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Reflect)]
+pub enum OverhangQuality {
+    /// Render overhangs as configured per map.
+    #[default]
+    Full,
+    /// Skip perspective underhangs, the most expensive part for maps with transparent tiles.
+    NoUnderhangs,
+    /// Render no overhangs at all, tiles are clipped to their cell.
+    Off,
+}
+
+/// See [`FastTileMapSettings::filtering`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Reflect)]
+pub enum TileFiltering {
+    /// Crisp pixels, best for pixel art.
+    #[default]
+    Nearest,
+    /// Smooth magnification, for high resolution tile art.
+    Linear,
+}
+
+impl TileFiltering {
+    pub(crate) fn atlas_sampler(self) -> ImageSampler {
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            // min_filter of linear gives undesired grid lines when zooming out
+            min_filter: ImageFilterMode::Nearest,
+            mag_filter: match self {
+                Self::Nearest => ImageFilterMode::Nearest,
+                Self::Linear => ImageFilterMode::Linear,
+            },
+            mipmap_filter: ImageFilterMode::Linear,
+            ..default()
+        })
+    }
+
+    /// Array layers do not bleed into each other, so minification can be filtered too.
+    pub(crate) fn atlas_array_sampler(self) -> ImageSampler {
+        let filter = match self {
+            Self::Nearest => ImageFilterMode::Nearest,
+            Self::Linear => ImageFilterMode::Linear,
+        };
+        ImageSampler::Descriptor(ImageSamplerDescriptor {
+            min_filter: filter,
+            mag_filter: filter,
+            mipmap_filter: ImageFilterMode::Linear,
+            ..default()
+        })
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Perspective underhangs, perspective overhangs and dominance overhangs as actually
+    /// rendered, i.e. limited by [`FastTileMapSettings::overhangs`].
+    pub(crate) fn overhang_flags(&self) -> (bool, bool, bool) {
+        match self.overhang_quality {
+            OverhangQuality::Full => (
+                self.perspective_underhangs,
+                self.perspective_overhangs,
+                self.dominance_overhangs,
+            ),
+            OverhangQuality::NoUnderhangs => {
+                (false, self.perspective_overhangs, self.dominance_overhangs)
+            }
+            OverhangQuality::Off => (false, false, false),
+        }
+    }
+}
+
+/// Copy [`FastTileMapSettings`] into all maps and atlases.
+pub fn apply_tilemap_settings<C: Customization>(
+    settings: Res<FastTileMapSettings>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // Only touch maps that differ so unchanged maps are not re-prepared
+    let outdated: Vec<_> = map_materials
+        .iter()
+        .filter(|(_, map)| map.overhang_quality != settings.overhangs)
+        .map(|(id, _)| id)
+        .collect();
+    for id in outdated {
+        if let Some(map) = map_materials.get_mut(id) {
+            map.overhang_quality = settings.overhangs;
+        }
+    }
+
+    if settings.is_changed() && !settings.is_added() {
+        for (_, map) in map_materials.iter() {
+            if let Some(atlas) = images.get_mut(&map.atlas_texture) {
+                atlas.sampler = settings.filtering.atlas_sampler();
+            }
+            if let Some(atlas_array) = map.atlas_array.as_ref().and_then(|a| images.get_mut(a)) {
+                atlas_array.sampler = settings.filtering.atlas_array_sampler();
+            }
+            if let Some(page_array) = map.page_array.as_ref().and_then(|a| images.get_mut(a)) {
+                page_array.sampler = settings.filtering.atlas_sampler();
+            }
+        }
+    }
+}
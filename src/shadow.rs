@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+
+use super::{layer_group::MapLayerGroup, map::Map, plugin::Customization, upload::Channel};
+
+/// Blurred shadow that the map of this entity casts onto the maps below it
+/// (by `z` translation) in the same [`MapLayerGroup`], eg. for bridges or tree canopies.
+///
+/// The shadow is derived from the alpha of the caster's tiles, so transparent tiles cast
+/// no shadow. Maps in a group should share size and projection with the caster; maps with a
+/// different size receive no shadow. A map only receives the shadow of the nearest caster
+/// above it. Only direct children of the group entity take part.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component)]
+pub struct MapShadow {
+    /// Displacement of the shadow relative to the caster, in tiles.
+    pub offset: Vec2,
+    /// Blur radius, in tiles.
+    pub blur: f32,
+    /// Opacity of the shadow below fully opaque caster tiles.
+    pub strength: f32,
+    pub color: Color,
+}
+
+impl Default for MapShadow {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::new(0.25, 0.25),
+            blur: 0.5,
+            strength: 0.5,
+            color: Color::BLACK,
+        }
+    }
+}
+
+impl MapShadow {
+    pub fn with_offset(self, offset: Vec2) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn with_blur(self, blur: f32) -> Self {
+        Self { blur, ..self }
+    }
+
+    pub fn with_strength(self, strength: f32) -> Self {
+        Self { strength, ..self }
+    }
+
+    pub fn with_color(self, color: Color) -> Self {
+        Self { color, ..self }
+    }
+
+    fn params(&self) -> Vec4 {
+        self.offset.extend(self.blur).extend(self.strength)
+    }
+}
+
+/// Update the shadow coverage of all maps in layer groups whenever a caster's tiles
+/// or shadow settings change.
+pub(crate) fn update_map_shadows<C: Customization>(
+    groups: Query<&Children, With<MapLayerGroup>>,
+    layers: Query<(&Handle<Map<C>>, &GlobalTransform, Option<&MapShadow>)>,
+    images: Res<Assets<Image>>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    for children in groups.iter() {
+        let mut group: Vec<_> = children
+            .iter()
+            .filter_map(|c| layers.get(*c).ok())
+            .collect();
+        group.sort_by(|a, b| a.1.translation().z.total_cmp(&b.1.translation().z));
+
+        for (i, (handle, _, _)) in group.iter().enumerate() {
+            let caster = group[i + 1..]
+                .iter()
+                .find_map(|(h, _, shadow)| shadow.map(|s| (*h, *s)));
+
+            let Some((caster_handle, shadow)) = caster else {
+                if maps.get(*handle).is_some_and(|map| map.shadows) {
+                    maps.get_mut(*handle).unwrap().shadows = false;
+                }
+                continue;
+            };
+
+            // Coverage comes from the flat (LOD) colors of the caster's atlas
+            if maps.get(caster_handle).is_some_and(|map| {
+                !map.lod_baked && map.map_uniform.n_tiles.cmpgt(UVec2::ZERO).all()
+            }) {
+                let map = maps.get_mut(caster_handle).unwrap();
+                let Some(atlas) = images.get(&map.atlas_texture) else {
+                    continue;
+                };
+                map.bake_lod_colors(atlas);
+            }
+
+            let (Some(caster), Some(map)) = (maps.get(caster_handle), maps.get(*handle)) else {
+                continue;
+            };
+            if !caster.lod_baked || caster.map_size() != map.map_size() {
+                continue;
+            }
+
+            let source = caster.content_hash();
+            let params = shadow.params();
+            let color = shadow.color.to_linear().to_vec4();
+            let u = &map.map_uniform;
+            if map.shadows
+                && map.shadow_source == source
+                && u.shadow_params == params
+                && u.shadow_color == color
+            {
+                continue;
+            }
+
+            let coverage: Vec<f32> = caster
+                .map_texture
+                .iter()
+                .map(|index| caster.lod_colors.get(*index as usize).map_or(0.0, |c| c.w))
+                .collect();
+
+            let map = maps.get_mut(*handle).unwrap();
+            map.shadow_coverage = coverage;
+            map.changed_tiles.add_channel(Channel::ShadowCoverage);
+            map.map_uniform.shadow_params = params;
+            map.map_uniform.shadow_color = color;
+            map.shadow_source = source;
+            map.shadows = true;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Interleaving the rows of several maps, so entities can be sandwiched between map layers.
+
+use bevy::{math::vec2, prelude::*, render::view::RenderLayers, sprite::Mesh2dHandle};
+
+use super::{
+    bundle::MapBundleUnmanaged,
+    map::{Map, MapAttributes, MapLoading},
+    plugin::Customization,
+};
+
+/// Render several maps (eg. "ground" and "walls" of an isometric scene) row by row interleaved:
+/// row 0 of every map, from bottom to top, then row 1 of every map and so on.
+///
+/// This way an entity placed at [`Self::sort_key`] is drawn in front of the rows behind it but
+/// behind the rows in front of it, on every layer.
+/// Each map is drawn as one child entity per row (marked with [`MapRowSlice`]), which are
+/// re-created whenever this component changes.
+///
+/// All maps should share the same projection and z coordinate.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct MapStack {
+    /// Map entities, bottom to top.
+    pub maps: Vec<Entity>,
+
+    /// Difference in z between subsequent rows.
+    /// Negative if rows with higher index are further away from the camera.
+    pub row_depth: f32,
+}
+
+impl MapStack {
+    pub fn new(maps: Vec<Entity>, row_depth: f32) -> Self {
+        Self { maps, row_depth }
+    }
+
+    /// z offset (relative to the maps) for the given (fractional) map row and layer,
+    /// eg. `layer = 0.5` for something standing on the first map, in front of the second.
+    pub fn sort_key(&self, row: f32, layer: f32) -> f32 {
+        let n = self.maps.len().max(1) as f32;
+        (row.floor() + layer / n) * self.row_depth
+    }
+}
+
+/// Child entity drawing a single row of a map in a [`MapStack`].
+/// It holds the map handle like the map itself, so exclude it from map queries where needed.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct MapRowSlice {
+    pub row: u32,
+}
+
+/// Mesh covering the given row of `map`, including the overhangs into and from
+/// neighboring rows.
+fn row_mesh<C: Customization>(map: &Map<C>, row: u32) -> Mesh {
+    let pad = map.overhang_reach as f32 + 1.0;
+    let width = map.map_size().x as f32;
+    let corners = [
+        vec2(-pad, row as f32 - pad),
+        vec2(width + pad, row as f32 - pad),
+        vec2(-pad, row as f32 + 1.0 + pad),
+        vec2(width + pad, row as f32 + 1.0 + pad),
+    ]
+    .map(|p| map.map_to_local(p));
+
+    let low = corners.iter().fold(corners[0], |a, b| a.min(*b));
+    let high = corners.iter().fold(corners[0], |a, b| a.max(*b));
+
+    Mesh::from(Rectangle::from_corners(low, high)).translated_by(((low + high) / 2.0).extend(0.0))
+}
+
+/// (Re-)create the row slices of all maps in changed [`MapStack`]s,
+/// or of maps that finished loading since.
+pub(crate) fn update_map_stacks<C: Customization>(
+    mut commands: Commands,
+    stacks: Query<Ref<MapStack>>,
+    mut maps: Query<
+        (
+            &Handle<Map<C>>,
+            &mut MapAttributes,
+            Option<&Children>,
+            Option<&RenderLayers>,
+        ),
+        Without<MapLoading>,
+    >,
+    slices: Query<(), With<MapRowSlice>>,
+    mut materials: ResMut<Assets<Map<C>>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for stack in stacks.iter() {
+        for (layer, entity) in stack.maps.iter().enumerate() {
+            let Ok((handle, mut attributes, children, layers)) = maps.get_mut(*entity) else {
+                continue;
+            };
+            if !stack.is_changed() && attributes.rows == Some(0..0) {
+                continue;
+            }
+
+            if !materials.get(handle).map_or(true, |map| map.row_slices) {
+                materials.get_mut(handle).unwrap().row_slices = true;
+            }
+            let Some(map) = materials.get(handle) else {
+                continue;
+            };
+
+            for child in children.into_iter().flatten() {
+                if slices.contains(*child) {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+
+            // The map itself only serves as parent for the slices from now on
+            attributes.rows = Some(0..0);
+
+            commands.entity(*entity).with_children(|parent| {
+                for row in 0..map.map_size().y {
+                    let slice_attributes = MapAttributes {
+                        mix_color: attributes.mix_color.clone(),
+                        rows: Some(row..row + 1),
+                        parallax_offset: attributes.parallax_offset,
+                    };
+
+                    // Vertex attributes are needed right away as the material now expects
+                    // the row range
+                    let mut mesh = row_mesh(map, row);
+                    MapAttributes::set_mix_color(Some(&slice_attributes), Vec4::ONE, &mut mesh);
+                    MapAttributes::set_map_position(Some(&slice_attributes), &mut mesh, map);
+                    MapAttributes::set_animation_state(Some(&slice_attributes), &mut mesh, 0.0);
+                    MapAttributes::set_row_range(Some(&slice_attributes), &mut mesh);
+
+                    let mut slice = parent.spawn((
+                        MapBundleUnmanaged::<C> {
+                            attributes: slice_attributes,
+                            material: handle.clone(),
+                            mesh: Mesh2dHandle(meshes.add(mesh)),
+                            transform: Transform::from_xyz(
+                                0.0,
+                                0.0,
+                                stack.sort_key(row as f32, layer as f32),
+                            ),
+                            ..default()
+                        },
+                        MapRowSlice { row },
+                    ));
+                    if let Some(layers) = layers {
+                        slice.insert(layers.clone());
+                    }
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use bevy::{math::URect, prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization};
+
+/// Number of tiles per atlas index, see [`Map::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect)]
+pub struct TileStats {
+    counts: HashMap<u32, usize>,
+}
+
+impl TileStats {
+    pub(crate) fn from_tiles(tiles: impl IntoIterator<Item = u32>) -> Self {
+        let mut stats = Self::default();
+        for index in tiles {
+            stats.add(index);
+        }
+        stats
+    }
+
+    pub(crate) fn add(&mut self, index: u32) {
+        *self.counts.entry(index).or_default() += 1;
+    }
+
+    pub(crate) fn remove(&mut self, index: u32) {
+        if let Some(count) = self.counts.get_mut(&index) {
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(&index);
+            }
+        }
+    }
+
+    pub(crate) fn apply_delta(&mut self, index: u32, delta: isize) {
+        let count = self.counts.entry(index).or_default();
+        *count = count.saturating_add_signed(delta);
+        if *count == 0 {
+            self.counts.remove(&index);
+        }
+    }
+
+    /// Number of tiles with the given atlas index.
+    pub fn count(&self, index: u32) -> usize {
+        self.counts.get(&index).copied().unwrap_or(0)
+    }
+
+    /// Number of tiles with any of the given atlas indices.
+    pub fn count_any(&self, indices: impl IntoIterator<Item = u32>) -> usize {
+        indices.into_iter().map(|i| self.count(i)).sum()
+    }
+
+    /// Total number of tiles counted.
+    pub fn total(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Fraction of tiles with the given atlas index, `0.0` if no tiles were counted.
+    pub fn fraction(&self, index: u32) -> f32 {
+        match self.total() {
+            0 => 0.0,
+            total => self.count(index) as f32 / total as f32,
+        }
+    }
+
+    /// All atlas indices that occur at least once, with their count (in no particular order).
+    pub fn iter(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.counts.iter().map(|(i, c)| (*i, *c))
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Histogram of the atlas indices of all tiles on this map.
+    ///
+    /// This is maintained incrementally on every edit and thus cheap to query,
+    /// eg. every frame for checking win conditions.
+    pub fn stats(&self) -> &TileStats {
+        &self.stats
+    }
+
+    /// Histogram of the atlas indices of the tiles in `region` (`max` is exclusive).
+    /// The region is clamped to the map.
+    ///
+    /// Unlike [`Self::stats`] this iterates over the region on every call.
+    pub fn stats_in(&self, region: URect) -> TileStats {
+        let region = region.intersect(URect::from_corners(UVec2::ZERO, self.map_size()));
+        let indexer = self.indexer();
+        TileStats::from_tiles(
+            (region.min.y..region.max.y)
+                .flat_map(|y| (region.min.x..region.max.x).map(move |x| (x, y)))
+                .map(|(x, y)| indexer.at(x, y)),
+        )
+    }
+}
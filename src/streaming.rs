@@ -0,0 +1,314 @@
+//! Worlds too large to keep on the GPU as a whole.
+//!
+//! A [`StreamedMap`] holds the tiles of the whole world as run-length compressed chunks on the
+//! CPU, while the [`Map`] of the same entity only covers a window of chunks around the camera.
+//! When the camera moves, chunks leaving the window are compressed (demoted) and chunks entering
+//! it are written into the map (promoted), so VRAM stays bounded by the window size.
+//! Gameplay queries ([`StreamedMap::tile`]) work for the whole world either way.
+
+use bevy::{
+    math::{uvec2, URect},
+    prelude::*,
+    render::view::RenderLayers,
+    utils::HashMap,
+};
+
+use super::{map::Map, plugin::Customization, render_layers::renders_layers};
+
+/// Run-length encoded tiles of a chunk, row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct CompressedChunk {
+    /// (run length, tile value)
+    runs: Vec<(u32, u32)>,
+}
+
+impl CompressedChunk {
+    fn compress(tiles: &[u32]) -> Self {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for &tile in tiles {
+            match runs.last_mut() {
+                Some((n, value)) if *value == tile => *n += 1,
+                _ => runs.push((1, tile)),
+            }
+        }
+        Self { runs }
+    }
+
+    fn decompress(&self) -> Vec<u32> {
+        self.runs
+            .iter()
+            .flat_map(|&(n, value)| std::iter::repeat(value).take(n as usize))
+            .collect()
+    }
+
+    fn get(&self, index: u32) -> Option<u32> {
+        let mut start = 0;
+        for &(n, value) in self.runs.iter() {
+            if index < start + n {
+                return Some(value);
+            }
+            start += n;
+        }
+        None
+    }
+}
+
+/// Tile data of a world of `size` tiles of which only a window of chunks around the camera is
+/// resident in the [`Map`] of this entity, see the [module docs](self).
+///
+/// Build the map with [`Self::window_size`] tiles. The system keeps the window centered on the
+/// first active 2d camera and moves the entity's `Transform` by the window offset, so world
+/// tiles stay in place. Chunks that were never written hold [`Self::default_tile`].
+#[derive(Component, Debug, Clone)]
+pub struct StreamedMap {
+    size: UVec2,
+    chunk_size: UVec2,
+    window_chunks: UVec2,
+    /// Tile value of chunks without data.
+    pub default_tile: u32,
+    /// Chunk coordinate of the window's first chunk.
+    origin: UVec2,
+    /// Translation applied to the entity for the current window.
+    offset: Vec2,
+    initialized: bool,
+    /// Chunks outside of the window.
+    store: HashMap<UVec2, CompressedChunk>,
+}
+
+impl StreamedMap {
+    /// World of `size` tiles in chunks of `chunk_size`, with a window of `window_chunks` chunks
+    /// resident on the GPU.
+    pub fn new(size: UVec2, chunk_size: UVec2, window_chunks: UVec2) -> Self {
+        let n_chunks = (size + chunk_size - UVec2::ONE) / chunk_size;
+        Self {
+            size,
+            chunk_size,
+            window_chunks: window_chunks.min(n_chunks).max(UVec2::ONE),
+            default_tile: 0,
+            origin: UVec2::ZERO,
+            offset: Vec2::ZERO,
+            initialized: false,
+            store: default(),
+        }
+    }
+
+    pub fn with_default_tile(self, default_tile: u32) -> Self {
+        Self {
+            default_tile,
+            ..self
+        }
+    }
+
+    /// Size of the world in tiles.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// Size of the map holding the window in tiles.
+    pub fn window_size(&self) -> UVec2 {
+        self.window_chunks * self.chunk_size
+    }
+
+    /// Tiles currently resident in the map (`max` exclusive).
+    pub fn window(&self) -> URect {
+        let min = self.origin * self.chunk_size;
+        URect::from_corners(min, min + self.window_size())
+    }
+
+    pub fn is_resident(&self, tile: UVec2) -> bool {
+        let window = self.window();
+        self.initialized && tile.cmpge(window.min).all() && tile.cmplt(window.max).all()
+    }
+
+    /// Number of chunks held in compressed form.
+    pub fn n_compressed_chunks(&self) -> usize {
+        self.store.len()
+    }
+
+    fn n_chunks(&self) -> UVec2 {
+        (self.size + self.chunk_size - UVec2::ONE) / self.chunk_size
+    }
+
+    /// Tile value at the given world position, `None` outside of the world.
+    pub fn tile<C: Customization>(&self, map: &Map<C>, tile: UVec2) -> Option<u32> {
+        if tile.x >= self.size.x || tile.y >= self.size.y {
+            return None;
+        }
+        if self.is_resident(tile) {
+            let local = tile - self.window().min;
+            return Some(map.indexer().at(local.x, local.y));
+        }
+        let chunk = tile / self.chunk_size;
+        let within = tile - chunk * self.chunk_size;
+        Some(
+            self.store
+                .get(&chunk)
+                .and_then(|c| c.get(within.y * self.chunk_size.x + within.x))
+                .unwrap_or(self.default_tile),
+        )
+    }
+
+    /// Set the tile value at the given world position, ignored outside of the world.
+    pub fn set_tile<C: Customization>(&mut self, map: &mut Map<C>, tile: UVec2, value: u32) {
+        if tile.x >= self.size.x || tile.y >= self.size.y {
+            return;
+        }
+        if self.is_resident(tile) {
+            let local = tile - self.window().min;
+            map.indexer_mut().set(local.x, local.y, value);
+            return;
+        }
+        let chunk = tile / self.chunk_size;
+        let within = tile - chunk * self.chunk_size;
+        let mut tiles = self.chunk_tiles(chunk);
+        tiles[(within.y * self.chunk_size.x + within.x) as usize] = value;
+        self.store.insert(chunk, CompressedChunk::compress(&tiles));
+    }
+
+    /// Replace all tiles of a chunk (eg. from a generator or save file), row by row.
+    /// Written into `map` if the chunk is resident.
+    pub fn set_chunk<C: Customization>(&mut self, map: &mut Map<C>, chunk: UVec2, tiles: &[u32]) {
+        let n = (self.chunk_size.x * self.chunk_size.y) as usize;
+        if tiles.len() != n {
+            warn!(
+                "Expected {} tiles for chunk {}, got {}",
+                n,
+                chunk,
+                tiles.len()
+            );
+            return;
+        }
+        let min = chunk * self.chunk_size;
+        if self.is_resident(min) {
+            let local = min - self.window().min;
+            let mut m = map.indexer_mut();
+            for (i, &value) in tiles.iter().enumerate() {
+                let i = i as u32;
+                m.set(
+                    local.x + i % self.chunk_size.x,
+                    local.y + i / self.chunk_size.x,
+                    value,
+                );
+            }
+            return;
+        }
+        self.store.insert(chunk, CompressedChunk::compress(tiles));
+    }
+
+    /// Tiles of a non-resident chunk, row by row.
+    fn chunk_tiles(&self, chunk: UVec2) -> Vec<u32> {
+        match self.store.get(&chunk) {
+            Some(c) => c.decompress(),
+            None => vec![self.default_tile; (self.chunk_size.x * self.chunk_size.y) as usize],
+        }
+    }
+
+    /// Move the window to start at chunk `origin`, demoting and promoting chunks.
+    fn move_window<C: Customization>(&mut self, map: &mut Map<C>, origin: UVec2) {
+        let chunk_size = self.chunk_size;
+        let chunk_len = (chunk_size.x * chunk_size.y) as usize;
+        let window_size = self.window_size();
+
+        // Demote all resident chunks, then promote the new window
+        let old = map.map_texture.clone();
+        if self.initialized {
+            for cy in 0..self.window_chunks.y {
+                for cx in 0..self.window_chunks.x {
+                    let mut tiles = Vec::with_capacity(chunk_len);
+                    for y in 0..chunk_size.y {
+                        let row =
+                            ((cy * chunk_size.y + y) * window_size.x + cx * chunk_size.x) as usize;
+                        tiles.extend_from_slice(&old[row..row + chunk_size.x as usize]);
+                    }
+                    let chunk = self.origin + uvec2(cx, cy);
+                    self.store.insert(chunk, CompressedChunk::compress(&tiles));
+                }
+            }
+        }
+
+        self.origin = origin;
+        self.initialized = true;
+        let mut m = map.indexer_mut();
+        for cy in 0..self.window_chunks.y {
+            for cx in 0..self.window_chunks.x {
+                let chunk = origin + uvec2(cx, cy);
+                let tiles = self.chunk_tiles(chunk);
+                self.store.remove(&chunk);
+                for (i, value) in tiles.into_iter().enumerate() {
+                    let i = i as u32;
+                    m.set(
+                        cx * chunk_size.x + i % chunk_size.x,
+                        cy * chunk_size.y + i / chunk_size.x,
+                        value,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Keep the window of each [`StreamedMap`] centered on the camera.
+pub(crate) fn update_streamed_maps<C: Customization>(
+    mut map_materials: ResMut<Assets<Map<C>>>,
+    mut maps: Query<(
+        &Handle<Map<C>>,
+        &mut StreamedMap,
+        &mut Transform,
+        &GlobalTransform,
+        Option<&RenderLayers>,
+    )>,
+    cameras: Query<(&Camera, &GlobalTransform, Option<&RenderLayers>)>,
+) {
+    for (handle, mut streamed, mut transform, global, layers) in maps.iter_mut() {
+        let Some(camera) = cameras
+            .iter()
+            .find(|(camera, _, camera_layers)| {
+                camera.is_active && renders_layers(*camera_layers, layers)
+            })
+            .map(|(_, transform, _)| transform.translation().truncate())
+        else {
+            continue;
+        };
+        let Some(map) = map_materials.get(handle) else {
+            continue;
+        };
+        if map.map_size() != streamed.window_size() {
+            warn_once!(
+                "StreamedMap window of {} tiles does not match the map size {}",
+                streamed.window_size(),
+                map.map_size()
+            );
+            continue;
+        }
+
+        // Camera position in world tiles
+        let local = global
+            .affine()
+            .inverse()
+            .transform_point3(camera.extend(0.0));
+        let window_position = map.local_to_map(local.truncate());
+        let tile = window_position + (streamed.origin * streamed.chunk_size).as_vec2();
+        let max_origin = streamed.n_chunks() - streamed.window_chunks;
+        let center = (tile.max(Vec2::ZERO).as_uvec2() / streamed.chunk_size).as_ivec2();
+        let origin = (center - (streamed.window_chunks / 2).as_ivec2())
+            .max(IVec2::ZERO)
+            .as_uvec2()
+            .min(max_origin);
+
+        if streamed.initialized && origin == streamed.origin {
+            continue;
+        }
+
+        let Some(map) = map_materials.get_mut(handle) else {
+            continue;
+        };
+        streamed.move_window(map, origin);
+
+        // Shift the map so world tiles stay in place
+        let window_min = (origin * streamed.chunk_size).as_vec2();
+        let offset = map.map_to_local(window_min) - map.map_to_local(Vec2::ZERO);
+        let delta = (offset - streamed.offset).extend(0.0);
+        transform.translation += transform.rotation * (transform.scale * delta);
+        streamed.offset = offset;
+    }
+}
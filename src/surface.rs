@@ -0,0 +1,74 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{map::Map, plugin::Customization};
+
+/// Lookup from atlas index to a user defined surface type `S` (eg. an enum of grass, stone,
+/// water), giving audio and particle systems a standard query for footsteps and impacts,
+/// see [`Map::surface_at`].
+///
+/// Insert as resource for easy access from systems.
+#[derive(Resource, Debug, Clone)]
+pub struct TileSurfaces<S> {
+    surfaces: HashMap<u32, S>,
+}
+
+impl<S> Default for TileSurfaces<S> {
+    fn default() -> Self {
+        Self {
+            surfaces: HashMap::default(),
+        }
+    }
+}
+
+impl<S: Clone> TileSurfaces<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign `surface` to all the given atlas indices.
+    pub fn with(mut self, indices: impl IntoIterator<Item = u32>, surface: S) -> Self {
+        for index in indices {
+            self.surfaces.insert(index, surface.clone());
+        }
+        self
+    }
+
+    pub fn set(&mut self, index: u32, surface: S) {
+        self.surfaces.insert(index, surface);
+    }
+
+    pub fn get(&self, index: u32) -> Option<&S> {
+        self.surfaces.get(&index)
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Surface of the tile at `pos` according to `surfaces`.
+    pub fn surface<'a, S: Clone>(
+        &self,
+        pos: UVec2,
+        surfaces: &'a TileSurfaces<S>,
+    ) -> Option<&'a S> {
+        let size = self.map_size();
+        if pos.x >= size.x || pos.y >= size.y {
+            return None;
+        }
+        surfaces.get(self.indexer().at_uvec(pos))
+    }
+
+    /// Surface of the tile at the given position in map-local world coordinates
+    /// (apply the inverse of the map entity's transform to global coordinates first,
+    /// or use [`crate::query::MapQuery::tile_at_world`]).
+    /// `None` outside of the map and for tiles without a surface.
+    pub fn surface_at<'a, S: Clone>(
+        &self,
+        world: Vec2,
+        surfaces: &'a TileSurfaces<S>,
+    ) -> Option<&'a S> {
+        let map_position = self.local_to_map(world);
+        if map_position.cmplt(Vec2::ZERO).any() {
+            return None;
+        }
+        self.surface(map_position.as_uvec2(), surfaces)
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use super::{map::Map, plugin::Customization, upload::Channel};
+
+/// Atlas indices of tiles that sway in the wind (eg. grass, reeds, tree tops),
+/// see [`Map::set_tile_sway`].
+///
+/// The sway is computed entirely in the shader from the map animation time and [`MapWind`],
+/// so it costs no CPU work or uploads per frame. Tiles bend from their bottom edge,
+/// parts bending out of the tile's cell are cut off, so leave some transparent margin at the
+/// sides of swaying tiles in the atlas.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct TileSway {
+    indices: Vec<u32>,
+}
+
+impl TileSway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Let tiles with atlas index `index` sway.
+    pub fn with(mut self, index: u32) -> Self {
+        self.indices.push(index);
+        self
+    }
+
+    /// Let tiles with an atlas index in `indices` sway.
+    pub fn with_range(mut self, indices: Range<u32>) -> Self {
+        self.indices.extend(indices);
+        self
+    }
+
+    /// One bit per atlas index, 32 per word.
+    pub(crate) fn shader_data(&self) -> Vec<u32> {
+        let n_words = self.indices.iter().map(|i| i / 32 + 1).max().unwrap_or(1);
+        let mut words = vec![0; n_words as usize];
+        for index in self.indices.iter() {
+            words[(index / 32) as usize] |= 1 << (index % 32);
+        }
+        words
+    }
+}
+
+/// Wind moving the swaying tiles (see [`TileSway`]) of all maps.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Resource)]
+pub struct MapWind {
+    /// Direction the wind blows to, in world coordinates.
+    pub direction: Vec2,
+    /// Distance (in pixels of the atlas) the top of a swaying tile moves at full strength.
+    pub strength: f32,
+    /// Swaying cycles per second (of map animation time).
+    pub frequency: f32,
+}
+
+impl Default for MapWind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            strength: 2.0,
+            frequency: 0.5,
+        }
+    }
+}
+
+impl MapWind {
+    fn shader_data(&self) -> Vec4 {
+        self.direction
+            .normalize_or_zero()
+            .extend(self.strength)
+            .extend(self.frequency)
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Let the given atlas indices sway in the wind (`None` to disable swaying),
+    /// see [`TileSway`].
+    pub fn set_tile_sway(&mut self, sway: Option<&TileSway>) {
+        match sway {
+            Some(sway) => {
+                self.sway_tiles = sway.shader_data();
+                self.changed_tiles.add_channel(Channel::SwayTiles);
+                self.tile_sway = true;
+            }
+            None => self.tile_sway = false,
+        }
+    }
+}
+
+/// Copy [`MapWind`] into the uniforms of all maps with swaying tiles.
+pub(crate) fn apply_map_wind<C: Customization>(
+    wind: Res<MapWind>,
+    mut map_materials: ResMut<Assets<Map<C>>>,
+) {
+    let wind = wind.shader_data();
+    let outdated: Vec<_> = map_materials
+        .iter()
+        .filter(|(_, map)| map.tile_sway && map.map_uniform.wind != wind)
+        .map(|(id, _)| id)
+        .collect();
+    for id in outdated {
+        if let Some(map) = map_materials.get_mut(id) {
+            map.map_uniform.wind = wind;
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use bevy::{
-    math::{mat3, vec2, vec3, Mat3},
+    math::{ivec2, mat3, vec2, vec3, Mat3},
     prelude::*,
 };
 
 /// Determines how map coordinates are related to world coordinates.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TileProjection {
     /// Projection matrix for converting map coordinates to world coordinates.
     /// This is normalized to the tile dimensions, ie. 1.0 means full tile width/height.
@@ -57,3 +57,133 @@ pub const AXONOMETRIC: TileProjection = TileProjection {
 
     tile_anchor_point: vec2(0.0, 0.5),
 };
+
+/// Pointy-top hexagonal tiles in axial coordinates.
+/// Tiles are expected to be drawn into a `tile_size` rectangle with the hexagon touching all four
+/// sides (i.e. `tile_size.y` is the distance between the top and bottom vertex).
+/// X-axis goes right, Y-axis goes down-right (each row is shifted half a tile to the right).
+pub const HEX_POINTY: TileProjection = TileProjection {
+    /*
+     *      / \
+     *    A     |
+     *    |     |
+     *      \ /
+     *
+     * (A) anchor point, the upper left vertex of the hexagon i.e. at (0.0, 0.25)
+     * in relative tile coordinates.
+     */
+    projection: mat3(
+        vec3(1.0, 0.0, 0.0),
+        vec3(0.5, -0.75, 0.75),
+        vec3(0.0, 0.0, 1.0),
+    ),
+    tile_anchor_point: vec2(0.0, 0.25),
+};
+
+/// Flat-top hexagonal tiles in axial coordinates.
+/// Tiles are expected to be drawn into a `tile_size` rectangle with the hexagon touching all four
+/// sides (i.e. `tile_size.x` is the distance between the left and right vertex).
+/// X-axis goes down-right (each column is shifted half a tile down), Y-axis goes down.
+pub const HEX_FLAT: TileProjection = TileProjection {
+    /*
+     *     A___
+     *    /    \
+     *    \____/
+     *
+     * (A) anchor point, the upper left vertex of the hexagon i.e. at (0.25, 0.0)
+     * in relative tile coordinates.
+     */
+    projection: mat3(
+        vec3(0.75, -0.5, 0.5),
+        vec3(0.0, -1.0, 1.0),
+        vec3(0.0, 0.0, 1.0),
+    ),
+    tile_anchor_point: vec2(0.25, 0.0),
+};
+
+/// Hexagon layout of a [`TileProjection`], see [`TileProjection::hex_layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum HexLayout {
+    /// [`HEX_POINTY`]: rows of hexagons, odd rows are shifted half a tile to the right in
+    /// offset coordinates ("odd-r").
+    Pointy,
+    /// [`HEX_FLAT`]: columns of hexagons, odd columns are shifted half a tile down in
+    /// offset coordinates ("odd-q").
+    Flat,
+}
+
+impl TileProjection {
+    /// Hexagon layout of this projection, `None` for non-hexagonal projections.
+    pub fn hex_layout(&self) -> Option<HexLayout> {
+        if self.projection == HEX_POINTY.projection {
+            Some(HexLayout::Pointy)
+        } else if self.projection == HEX_FLAT.projection {
+            Some(HexLayout::Flat)
+        } else {
+            None
+        }
+    }
+}
+
+impl HexLayout {
+    /// Axial tile of the hexagon containing the given (fractional) map position.
+    ///
+    /// Hexagons do not fill their axial cell, so unlike for rectangular projections the tile is
+    /// not simply `map_position.floor()`. The hexagon center is at `tile + 1/3` in map
+    /// coordinates for both layouts, as the anchor point is the upper left vertex.
+    pub fn round(&self, map_position: Vec2) -> IVec2 {
+        let p = map_position - Vec2::splat(1.0 / 3.0);
+        let cube = p.extend(-p.x - p.y);
+        let rounded = cube.round();
+        let diff = (rounded - cube).abs();
+        let (x, y) = if diff.x > diff.y && diff.x > diff.z {
+            (-rounded.y - rounded.z, rounded.y)
+        } else if diff.y > diff.z {
+            (rounded.x, -rounded.x - rounded.z)
+        } else {
+            (rounded.x, rounded.y)
+        };
+        ivec2(x as i32, y as i32)
+    }
+
+    /// Convert offset coordinates (column, row) of a rectangular hex grid to axial tile
+    /// coordinates as used by the map.
+    ///
+    /// Axial coordinates of a rectangular region form a parallelogram, so the axial `x`
+    /// (pointy) or `y` (flat) becomes negative for later rows/columns. Shift them into the map,
+    /// eg. by subtracting `offset_to_axial(ivec2(0, height - 1)).x` from `x` for pointy hexes
+    /// (the map then needs to be `width + (height - 1) / 2` tiles wide).
+    pub fn offset_to_axial(&self, offset: IVec2) -> IVec2 {
+        match self {
+            Self::Pointy => ivec2(offset.x - offset.y.div_euclid(2), offset.y),
+            Self::Flat => ivec2(offset.x, offset.y - offset.x.div_euclid(2)),
+        }
+    }
+
+    /// Inverse of [`Self::offset_to_axial`].
+    pub fn axial_to_offset(&self, axial: IVec2) -> IVec2 {
+        match self {
+            Self::Pointy => ivec2(axial.x + axial.y.div_euclid(2), axial.y),
+            Self::Flat => ivec2(axial.x, axial.y + axial.x.div_euclid(2)),
+        }
+    }
+
+    /// The six neighbors of an axial tile.
+    pub fn neighbors(&self, tile: IVec2) -> [IVec2; 6] {
+        [
+            ivec2(1, 0),
+            ivec2(0, 1),
+            ivec2(-1, 1),
+            ivec2(-1, 0),
+            ivec2(0, -1),
+            ivec2(1, -1),
+        ]
+        .map(|direction| tile + direction)
+    }
+
+    /// Number of steps between two axial tiles.
+    pub fn distance(&self, a: IVec2, b: IVec2) -> u32 {
+        let d = a - b;
+        (d.x.unsigned_abs() + d.y.unsigned_abs() + (d.x + d.y).unsigned_abs()) / 2
+    }
+}
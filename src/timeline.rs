@@ -0,0 +1,235 @@
+use bevy::{color::Mix, prelude::*};
+
+use super::{
+    map::{Map, MapAttributes},
+    plugin::Customization,
+    tile_projection::TileProjection,
+};
+
+/// Values that can be keyframed in a [`MapTimeline`].
+pub trait Interpolate: Clone {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vec2 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.mix(other, t)
+    }
+}
+
+/// Keyframes of a value, linearly interpolated in between
+/// and held constant before the first and after the last keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes<T> {
+    frames: Vec<(f32, T)>,
+}
+
+impl<T> Default for Keyframes<T> {
+    fn default() -> Self {
+        Self { frames: Vec::new() }
+    }
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a keyframe with the given value at `time` (in seconds).
+    pub fn with(mut self, time: f32, value: T) -> Self {
+        let i = self.frames.partition_point(|(t, _)| *t <= time);
+        self.frames.insert(i, (time, value));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.frames.last().map(|(t, _)| *t).unwrap_or(0.0)
+    }
+
+    /// Value at `time`, `None` if there are no keyframes.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let i = self.frames.partition_point(|(t, _)| *t <= time);
+        match (
+            i.checked_sub(1).map(|i| &self.frames[i]),
+            self.frames.get(i),
+        ) {
+            (Some((t0, v0)), Some((t1, v1))) => Some(v0.interpolate(v1, (time - t0) / (t1 - t0))),
+            (Some((_, v)), None) | (None, Some((_, v))) => Some(v.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Blend between two projections, eg. for transitioning from a top-down to an isometric view.
+#[derive(Debug, Clone)]
+pub struct ProjectionBlend {
+    pub from: TileProjection,
+    pub to: TileProjection,
+    /// `0` is `from`, `1` is `to`.
+    pub weight: Keyframes<f32>,
+}
+
+/// Keyframe animation of the presentation of a map (eg. for cutscene-style transitions),
+/// played back on the map entity it is attached to.
+///
+/// Empty tracks leave the respective property alone.
+/// Opacity and tint are applied as mix color (see [`MapAttributes`]),
+/// the offset is applied as translation of the map entity (eg. for scrolling).
+#[derive(Component, Debug, Clone)]
+pub struct MapTimeline {
+    pub opacity: Keyframes<f32>,
+    pub tint: Keyframes<Color>,
+    pub offset: Keyframes<Vec2>,
+    pub projection: Option<ProjectionBlend>,
+    /// Start over after the last keyframe of all tracks.
+    pub looping: bool,
+    pub speed: f32,
+    pub paused: bool,
+    time: f32,
+}
+
+impl Default for MapTimeline {
+    fn default() -> Self {
+        Self {
+            opacity: default(),
+            tint: default(),
+            offset: default(),
+            projection: None,
+            looping: false,
+            speed: 1.0,
+            paused: false,
+            time: 0.0,
+        }
+    }
+}
+
+impl MapTimeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_opacity(self, opacity: Keyframes<f32>) -> Self {
+        Self { opacity, ..self }
+    }
+
+    pub fn with_tint(self, tint: Keyframes<Color>) -> Self {
+        Self { tint, ..self }
+    }
+
+    pub fn with_offset(self, offset: Keyframes<Vec2>) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub fn with_projection(self, projection: ProjectionBlend) -> Self {
+        Self {
+            projection: Some(projection),
+            ..self
+        }
+    }
+
+    pub fn looping(self) -> Self {
+        Self {
+            looping: true,
+            ..self
+        }
+    }
+
+    /// Time of the last keyframe of all tracks.
+    pub fn duration(&self) -> f32 {
+        [
+            self.opacity.duration(),
+            self.tint.duration(),
+            self.offset.duration(),
+            self.projection
+                .as_ref()
+                .map(|p| p.weight.duration())
+                .unwrap_or(0.0),
+        ]
+        .into_iter()
+        .fold(0.0, f32::max)
+    }
+
+    /// Current playback position in seconds.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Whether a non-looping timeline has played past its last keyframe.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.duration()
+    }
+}
+
+pub(crate) fn advance_map_timelines<C: Customization>(
+    time: Res<Time>,
+    mut timelines: Query<(
+        &mut MapTimeline,
+        &Handle<Map<C>>,
+        Option<&mut MapAttributes>,
+        &mut Transform,
+    )>,
+    mut maps: ResMut<Assets<Map<C>>>,
+) {
+    for (mut timeline, handle, attributes, mut transform) in timelines.iter_mut() {
+        if timeline.paused || timeline.is_finished() {
+            continue;
+        }
+
+        let duration = timeline.duration();
+        let mut t = timeline.time + time.delta_seconds() * timeline.speed;
+        if timeline.looping && duration > 0.0 {
+            t %= duration;
+        }
+        timeline.time = t;
+
+        let opacity = timeline.opacity.sample(t);
+        let tint = timeline.tint.sample(t);
+        if opacity.is_some() || tint.is_some() {
+            let color = tint.unwrap_or(Color::WHITE).to_linear().to_vec4();
+            let color = color.with_w(color.w * opacity.unwrap_or(1.0));
+            if let Some(mut attributes) = attributes {
+                attributes.mix_color = vec![color; 4];
+            }
+        }
+
+        if let Some(offset) = timeline.offset.sample(t) {
+            transform.translation = offset.extend(transform.translation.z);
+        }
+
+        if let Some(blend) = &timeline.projection {
+            let Some(w) = blend.weight.sample(t) else {
+                continue;
+            };
+            if let Some(map) = maps.get_mut(handle) {
+                map.set_projection(TileProjection {
+                    projection: blend.from.projection * (1.0 - w) + blend.to.projection * w,
+                    tile_anchor_point: blend
+                        .from
+                        .tile_anchor_point
+                        .lerp(blend.to.tile_anchor_point, w),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,680 @@
+//! Import and export of maps in the [Tiled](https://www.mapeditor.org/) `.tmx` format.
+//!
+//! Export lets in-game edits or generated maps be polished in external tooling.
+//! Import loads `.tmx` files as [`TmxMap`] assets, spawn them with [`TmxMapSpawner`]:
+//!
+//! ```ignore
+//! app.add_plugins(TmxPlugin);
+//! commands.spawn((
+//!     SpatialBundle::default(),
+//!     TmxMapSpawner::new(asset_server.load("level.tmx")),
+//! ));
+//! ```
+
+use std::{fmt::Write as _, io, path::Path};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AssetPath, AsyncReadExt, LoadContext},
+    math::uvec2,
+    prelude::*,
+    utils::HashMap,
+};
+
+use super::{
+    bundle::MapBundleManaged,
+    error::TmxError,
+    flip::TILE_FLIP_MASK,
+    map::Map,
+    metadata::{TileProperty, TilesetMetadata},
+    plugin::{Customization, NoCustomization},
+    tile_projection::AXONOMETRIC,
+};
+
+/// Tiled's flag for rotating hex tiles by 120°, below its flip flags.
+const TILED_HEX_ROTATION: u32 = 1 << 28;
+/// All flag bits of Tiled global tile ids.
+const TILED_FLAGS_MASK: u32 = TILE_FLIP_MASK | TILED_HEX_ROTATION;
+
+/// Reference to the Tiled tileset (`.tsx`) matching the map atlas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TmxTilesetRef {
+    /// Path of the `.tsx` file, relative to the exported `.tmx` file.
+    pub source: String,
+    /// Global tile id of the first tile of the tileset, atlas index 0 is exported as this id.
+    /// Tiled reserves `0` for "no tile", so this is usually `1`.
+    pub first_gid: u32,
+}
+
+impl TmxTilesetRef {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            first_gid: 1,
+        }
+    }
+}
+
+/// Render the given maps as tile layers of a single Tiled map.
+///
+/// Map size, tile size and orientation are taken from the first map.
+/// Maps with [`AXONOMETRIC`] projection are exported as isometric, all others as orthogonal
+/// (Tiled has no equivalent for the other projections, tile data is exported unchanged).
+pub fn to_tmx<C: Customization>(layers: &[(&str, &Map<C>)], tileset: &TmxTilesetRef) -> String {
+    let (size, tile_size, orientation) = match layers.first() {
+        Some((_, map)) => (
+            map.map_size(),
+            map.tile_size(),
+            if map.map_uniform.projection == AXONOMETRIC.projection {
+                "isometric"
+            } else {
+                "orthogonal"
+            },
+        ),
+        None => (UVec2::ZERO, Vec2::ZERO, "orthogonal"),
+    };
+
+    let mut s = String::new();
+    // Writing to a String can not fail
+    let _ = writeln!(s, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        s,
+        r#"<map version="1.10" orientation="{}" renderorder="right-down" width="{}" height="{}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="{}" nextobjectid="1">"#,
+        orientation,
+        size.x,
+        size.y,
+        tile_size.x,
+        tile_size.y,
+        layers.len() + 1
+    );
+    let _ = writeln!(
+        s,
+        r#" <tileset firstgid="{}" source="{}"/>"#,
+        tileset.first_gid,
+        xml_escape(&tileset.source)
+    );
+
+    for (id, (name, map)) in layers.iter().enumerate() {
+        let size = map.map_size();
+        let _ = writeln!(
+            s,
+            r#" <layer id="{}" name="{}" width="{}" height="{}">"#,
+            id + 1,
+            xml_escape(name),
+            size.x,
+            size.y
+        );
+        let _ = writeln!(s, r#"  <data encoding="csv">"#);
+        let indexer = map.indexer();
+        for y in 0..size.y {
+            let row: Vec<String> = (0..size.x)
+                .map(|x| (indexer.at(x, y) + tileset.first_gid).to_string())
+                .collect();
+            let separator = if y + 1 < size.y { "," } else { "" };
+            let _ = writeln!(s, "{}{}", row.join(","), separator);
+        }
+        let _ = writeln!(s, "</data>");
+        let _ = writeln!(s, " </layer>");
+    }
+    let _ = writeln!(s, "</map>");
+    s
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl<C: Customization> Map<C> {
+    /// Write this map as a single tile layer Tiled map (`.tmx`) to `path`, see [`to_tmx`].
+    pub fn export_tmx(&self, path: impl AsRef<Path>, tileset: &TmxTilesetRef) -> io::Result<()> {
+        std::fs::write(path, to_tmx(&[("Tile Layer 1", self)], tileset))
+    }
+}
+
+/// Plugin for loading `.tmx` files as [`TmxMap`] assets and spawning them via [`TmxMapSpawner`].
+pub type TmxPlugin = CustomTmxPlugin<NoCustomization>;
+
+/// Plugin for loading `.tmx` files as [`TmxMap`] assets and spawning them via [`TmxMapSpawner`].
+#[derive(Default)]
+pub struct CustomTmxPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomTmxPlugin<C> {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Assets<TmxMap>>() {
+            app.init_asset::<TmxMap>().register_asset_loader(TmxLoader);
+        }
+        app.add_systems(Update, spawn_tmx_maps::<C>);
+    }
+}
+
+/// Map orientation of a Tiled map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum TmxOrientation {
+    Orthogonal,
+    Isometric,
+}
+
+/// Tileset of a Tiled map, with the atlas layout as given in Tiled.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TmxTileset {
+    /// Global tile id of the first tile of the tileset.
+    pub first_gid: u32,
+    pub image: Handle<Image>,
+    /// Size of the tileset image in pixels, zero if not given in the file.
+    pub image_size: Vec2,
+    pub tile_size: Vec2,
+    /// Space between tiles in pixels (inner padding).
+    pub spacing: f32,
+    /// Space around the tiles in pixels (outer padding).
+    pub margin: f32,
+    pub columns: u32,
+    pub tile_count: u32,
+    /// Custom properties of the tiles, by atlas index (the tile id within the tileset).
+    #[reflect(ignore)]
+    pub metadata: TilesetMetadata,
+}
+
+impl TmxTileset {
+    fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+}
+
+/// Tile layer of a Tiled map.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct TmxLayer {
+    pub name: String,
+    /// Global tile ids (including Tiled flip flags) row by row, `0` for no tile.
+    pub gids: Vec<u32>,
+}
+
+/// A Tiled map loaded from a `.tmx` file by [`TmxLoader`].
+///
+/// Tilesets may be embedded or external (`.tsx`), layer data may be CSV or uncompressed
+/// base64. Only finite maps with orthogonal or isometric orientation and single-image tilesets
+/// are supported. Object and image layers are ignored.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct TmxMap {
+    pub size: UVec2,
+    pub orientation: TmxOrientation,
+    pub tilesets: Vec<TmxTileset>,
+    pub layers: Vec<TmxLayer>,
+}
+
+impl TmxMap {
+    /// Build a [`Map`] for the given layer, using the tileset of its first tile as atlas
+    /// (layers mixing tilesets are not supported, tiles of other tilesets become `empty_tile`).
+    /// Empty cells become atlas index `empty_tile`, which should be transparent.
+    /// Tiled's flip flags are kept, see [`crate::flip`], its flag for rotating hex tiles by 120°
+    /// is not supported and dropped.
+    pub fn layer_map<C: Customization>(&self, layer: usize, empty_tile: u32) -> Option<Map<C>> {
+        let layer = self.layers.get(layer)?;
+        let tileset = layer
+            .gids
+            .iter()
+            .map(|gid| gid & !TILED_FLAGS_MASK)
+            .find(|gid| *gid != 0)
+            .and_then(|gid| self.tilesets.iter().find(|t| t.contains(gid)))
+            .or(self.tilesets.first())?;
+
+        let rows = tileset.tile_count.div_ceil(tileset.columns.max(1));
+        let n_tiles = uvec2(tileset.columns.max(1), rows.max(1));
+        let mut bottomright = Vec2::splat(tileset.margin);
+        if tileset.image_size != Vec2::ZERO {
+            bottomright = tileset.image_size
+                - tileset.margin
+                - n_tiles.as_vec2() * tileset.tile_size
+                - (n_tiles.as_vec2() - Vec2::ONE) * tileset.spacing;
+        }
+
+        let mut builder = Map::<C>::builder(self.size, tileset.image.clone(), tileset.tile_size)
+            .with_padding(
+                Vec2::splat(tileset.spacing),
+                Vec2::splat(tileset.margin),
+                bottomright.max(Vec2::ZERO),
+            )
+            .with_n_tiles(Some(n_tiles));
+        if self.orientation == TmxOrientation::Isometric {
+            builder = builder.with_projection(AXONOMETRIC);
+        }
+
+        let width = self.size.x as usize;
+        Some(builder.build_and_set(|pos| {
+            let value = layer.gids[pos.y as usize * width + pos.x as usize];
+            let gid = value & !TILED_FLAGS_MASK;
+            match tileset.contains(gid) {
+                true => (gid - tileset.first_gid) | (value & TILE_FLIP_MASK),
+                false => empty_tile,
+            }
+        }))
+    }
+}
+
+/// Spawns the layers of a [`TmxMap`] as child map entities once it is loaded,
+/// then removes itself. Give the entity a `SpatialBundle`.
+///
+/// Each layer becomes a child with a [`MapBundleManaged`] and a [`Name`] of the layer name,
+/// layer `i` is placed at `z = i * layer_spacing`.
+#[derive(Component, Debug, Clone)]
+pub struct TmxMapSpawner {
+    pub tmx: Handle<TmxMap>,
+    /// Atlas index used for cells without tile, see [`TmxMap::layer_map`].
+    pub empty_tile: u32,
+    pub layer_spacing: f32,
+}
+
+impl TmxMapSpawner {
+    pub fn new(tmx: Handle<TmxMap>) -> Self {
+        Self {
+            tmx,
+            empty_tile: 0,
+            layer_spacing: 1.0,
+        }
+    }
+
+    pub fn with_empty_tile(self, empty_tile: u32) -> Self {
+        Self { empty_tile, ..self }
+    }
+
+    pub fn with_layer_spacing(self, layer_spacing: f32) -> Self {
+        Self {
+            layer_spacing,
+            ..self
+        }
+    }
+}
+
+fn spawn_tmx_maps<C: Customization>(
+    mut commands: Commands,
+    spawners: Query<(Entity, &TmxMapSpawner)>,
+    tmx_maps: Res<Assets<TmxMap>>,
+    mut materials: ResMut<Assets<Map<C>>>,
+) {
+    for (entity, spawner) in spawners.iter() {
+        let Some(tmx) = tmx_maps.get(&spawner.tmx) else {
+            continue;
+        };
+
+        let mut entity = commands.entity(entity);
+        entity.remove::<TmxMapSpawner>();
+        entity.with_children(|parent| {
+            for (i, layer) in tmx.layers.iter().enumerate() {
+                let Some(map) = tmx.layer_map::<C>(i, spawner.empty_tile) else {
+                    continue;
+                };
+                parent.spawn((
+                    Name::new(layer.name.clone()),
+                    MapBundleManaged {
+                        material: materials.add(map),
+                        transform: Transform::from_xyz(0.0, 0.0, i as f32 * spawner.layer_spacing),
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Loads `.tmx` files as [`TmxMap`]s.
+#[derive(Debug, Default)]
+pub struct TmxLoader;
+
+impl AssetLoader for TmxLoader {
+    type Asset = TmxMap;
+    type Settings = ();
+    type Error = TmxError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<TmxMap, TmxError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let xml = String::from_utf8(bytes).map_err(|e| TmxError::Invalid(e.to_string()))?;
+        let tags = xml_tags(&xml);
+
+        let map = tags
+            .iter()
+            .find(|t| t.name == "map")
+            .ok_or_else(|| TmxError::Invalid("missing <map>".into()))?;
+        if map.attr("infinite") == Some("1") {
+            return Err(TmxError::Unsupported("infinite maps".into()));
+        }
+        let size = uvec2(map.parse("width")?, map.parse("height")?);
+        let orientation = match map.attr("orientation") {
+            Some("isometric") => TmxOrientation::Isometric,
+            Some("orthogonal") | None => TmxOrientation::Orthogonal,
+            Some(o) => return Err(TmxError::Unsupported(format!("{} orientation", o))),
+        };
+
+        let mut tilesets = Vec::new();
+        let mut layers = Vec::new();
+        for (i, tag) in tags.iter().enumerate() {
+            match tag.name {
+                "tileset" => {
+                    let first_gid = tag.parse("firstgid")?;
+                    let tileset = match tag.attr("source") {
+                        Some(source) => {
+                            let path = resolve(load_context.asset_path(), source)?;
+                            let tsx = load_context
+                                .read_asset_bytes(path.clone())
+                                .await
+                                .map_err(|e| TmxError::Invalid(e.to_string()))?;
+                            let tsx = String::from_utf8(tsx)
+                                .map_err(|e| TmxError::Invalid(e.to_string()))?;
+                            let tsx_tags = xml_tags(&tsx);
+                            parse_tileset(&tsx, &tsx_tags, 0, first_gid, &path, load_context)?
+                        }
+                        None => {
+                            let path = load_context.asset_path().clone();
+                            parse_tileset(&xml, &tags, i, first_gid, &path, load_context)?
+                        }
+                    };
+                    tilesets.push(tileset);
+                }
+                "layer" => {
+                    let data = tags[i + 1..]
+                        .iter()
+                        .take_while(|t| t.name != "layer")
+                        .find(|t| t.name == "data")
+                        .ok_or_else(|| TmxError::Invalid("layer without <data>".into()))?;
+                    let gids = parse_layer_data(data, &xml)?;
+                    if gids.len() != (size.x * size.y) as usize {
+                        return Err(TmxError::Invalid(format!(
+                            "layer has {} tiles, expected {}",
+                            gids.len(),
+                            size.x * size.y
+                        )));
+                    }
+                    layers.push(TmxLayer {
+                        name: tag.attr("name").unwrap_or_default().to_string(),
+                        gids,
+                    });
+                }
+                _ => (),
+            }
+        }
+        tilesets.sort_by_key(|t: &TmxTileset| t.first_gid);
+
+        Ok(TmxMap {
+            size,
+            orientation,
+            tilesets,
+            layers,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+/// Path of `source` relative to the directory of the file at `base`.
+fn resolve(base: &AssetPath, source: &str) -> Result<AssetPath<'static>, TmxError> {
+    base.resolve_embed(source)
+        .map_err(|e| TmxError::Invalid(e.to_string()))
+}
+
+/// Tileset starting at the `<tileset>` tag `tags[start]` of `xml`,
+/// image paths are relative to `path`.
+fn parse_tileset(
+    xml: &str,
+    tags: &[XmlTag],
+    start: usize,
+    first_gid: u32,
+    path: &AssetPath,
+    load_context: &mut LoadContext,
+) -> Result<TmxTileset, TmxError> {
+    let tileset = tags[start..]
+        .iter()
+        .find(|t| t.name == "tileset")
+        .ok_or_else(|| TmxError::Invalid("missing <tileset>".into()))?;
+    let image = tags[start..]
+        .iter()
+        .skip(1)
+        .take_while(|t| t.name != "tileset" && t.name != "tile")
+        .find(|t| t.name == "image")
+        .ok_or_else(|| TmxError::Unsupported("tilesets without a single image".into()))?;
+    let source = image
+        .attr("source")
+        .ok_or_else(|| TmxError::Invalid("<image> without source".into()))?;
+
+    Ok(TmxTileset {
+        first_gid,
+        image: load_context.load(resolve(path, source)?),
+        image_size: Vec2::new(
+            image.parse_or("width", 0.0)?,
+            image.parse_or("height", 0.0)?,
+        ),
+        tile_size: Vec2::new(tileset.parse("tilewidth")?, tileset.parse("tileheight")?),
+        spacing: tileset.parse_or("spacing", 0.0)?,
+        margin: tileset.parse_or("margin", 0.0)?,
+        columns: tileset.parse("columns")?,
+        tile_count: tileset.parse("tilecount")?,
+        metadata: parse_tile_properties(xml, tags, tileset)?,
+    })
+}
+
+/// Custom properties of the `<tile>`s of the given `<tileset>` tag.
+fn parse_tile_properties(
+    xml: &str,
+    tags: &[XmlTag],
+    tileset: &XmlTag,
+) -> Result<TilesetMetadata, TmxError> {
+    let mut metadata = TilesetMetadata::new();
+    let Some(len) = xml[tileset.end..].find("</tileset>") else {
+        // Self-closing, eg. a reference to an external tileset
+        return Ok(metadata);
+    };
+    let close = tileset.end + len;
+
+    let mut tile = None;
+    for tag in tags
+        .iter()
+        .filter(|t| t.end > tileset.end && t.end <= close)
+    {
+        match tag.name {
+            "tile" => tile = Some(tag.parse::<u32>("id")?),
+            // Properties of collision objects and wang sets are not tile properties
+            "objectgroup" | "wangsets" => tile = None,
+            "property" => {
+                let (Some(index), Some(name), Some(value)) =
+                    (tile, tag.attr("name"), tag.attr("value"))
+                else {
+                    continue;
+                };
+                if let Some(value) = parse_property(tag.attr("type"), value)? {
+                    metadata.set(index, name, value);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(metadata)
+}
+
+/// Value of a Tiled `<property>`, `None` for unsupported types (eg. custom classes).
+fn parse_property(ty: Option<&str>, value: &str) -> Result<Option<TileProperty>, TmxError> {
+    let bad = || TmxError::Invalid(format!("bad property value {:?}", value));
+    Ok(Some(match ty.unwrap_or("string") {
+        "string" | "file" => TileProperty::String(value.to_string()),
+        "bool" => TileProperty::Bool(value == "true"),
+        "int" | "object" => TileProperty::Int(value.parse().map_err(|_| bad())?),
+        "float" => TileProperty::Float(value.parse().map_err(|_| bad())?),
+        "color" => {
+            // #AARRGGBB or #RRGGBB, empty for "no color"
+            let hex = value.trim_start_matches('#');
+            let argb = match hex.len() {
+                0 => return Ok(None),
+                6 => 0xff000000 | u32::from_str_radix(hex, 16).map_err(|_| bad())?,
+                8 => u32::from_str_radix(hex, 16).map_err(|_| bad())?,
+                _ => return Err(bad()),
+            };
+            let [a, r, g, b] = argb.to_be_bytes().map(|c| c as f32 / 255.0);
+            TileProperty::Color([r, g, b, a])
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Global tile ids of a `<data>` tag.
+fn parse_layer_data(data: &XmlTag, xml: &str) -> Result<Vec<u32>, TmxError> {
+    if let Some(compression) = data.attr("compression") {
+        return Err(TmxError::Unsupported(format!(
+            "{} compression",
+            compression
+        )));
+    }
+    let text = &xml[data.end..];
+    let text = &text[..text.find("</data>").unwrap_or(text.len())];
+
+    match data.attr("encoding") {
+        Some("csv") => text
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| TmxError::Invalid(format!("bad tile id {:?}", s)))
+            })
+            .collect(),
+        Some("base64") => Ok(decode_base64(text)?
+            .chunks_exact(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()),
+        Some(e) => Err(TmxError::Unsupported(format!("{} encoding", e))),
+        None => Err(TmxError::Unsupported("XML tile data".into())),
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, TmxError> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+
+    let mut out = Vec::new();
+    let mut bits = 0u32;
+    let mut n_bits = 0;
+    for c in text
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let v = value(c).ok_or_else(|| TmxError::Invalid("bad base64 data".into()))?;
+        bits = (bits << 6) | v as u32;
+        n_bits += 6;
+        if n_bits >= 8 {
+            n_bits -= 8;
+            out.push((bits >> n_bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+/// Opening (or self-closing) XML tag, only as much XML as Tiled files need.
+struct XmlTag<'a> {
+    name: &'a str,
+    attrs: HashMap<&'a str, String>,
+    /// Byte offset right after the tag.
+    end: usize,
+}
+
+impl<'a> XmlTag<'a> {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(String::as_str)
+    }
+
+    fn parse<T: std::str::FromStr>(&self, name: &str) -> Result<T, TmxError> {
+        let value = self
+            .attr(name)
+            .ok_or_else(|| TmxError::Invalid(format!("<{}> without {}", self.name, name)))?;
+        value
+            .parse()
+            .map_err(|_| TmxError::Invalid(format!("bad {} {:?}", name, value)))
+    }
+
+    fn parse_or<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, TmxError> {
+        match self.attr(name) {
+            Some(_) => self.parse(name),
+            None => Ok(default),
+        }
+    }
+}
+
+/// All opening tags of `xml` in document order, skipping closing tags, comments and
+/// processing instructions.
+fn xml_tags(xml: &str) -> Vec<XmlTag> {
+    let mut tags = Vec::new();
+    let mut rest = 0;
+    while let Some(start) = xml[rest..].find('<').map(|i| rest + i) {
+        if xml[start..].starts_with("<!--") {
+            rest = xml[start..]
+                .find("-->")
+                .map_or(xml.len(), |i| start + i + 3);
+            continue;
+        }
+        let Some(end) = tag_end(xml, start) else {
+            break;
+        };
+        rest = end;
+        let inner = xml[start + 1..end - 1].trim_end_matches('/');
+        if inner.starts_with(['/', '?', '!']) {
+            continue;
+        }
+
+        let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+        let mut attrs = HashMap::default();
+        let mut s = &inner[name_end..];
+        while let Some(eq) = s.find('=') {
+            let key = s[..eq].trim();
+            let value = s[eq + 1..].trim_start();
+            let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                break;
+            };
+            let Some(len) = value[1..].find(quote) else {
+                break;
+            };
+            attrs.insert(key, xml_unescape(&value[1..1 + len]));
+            s = &value[len + 2..];
+        }
+
+        tags.push(XmlTag {
+            name: &inner[..name_end],
+            attrs,
+            end,
+        });
+    }
+    tags
+}
+
+/// Position after the `>` closing the tag at `start`, `>` in quoted attribute values is skipped.
+fn tag_end(xml: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in xml[start..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(start + i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn xml_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
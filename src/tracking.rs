@@ -0,0 +1,94 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use super::{
+    plugin::{Customization, NoCustomization},
+    query::MapQuery,
+};
+
+/// Plugin maintaining [`CurrentTile`] and [`PreviousTile`] of [`TileTracked`] entities.
+pub type TileTrackingPlugin = CustomTileTrackingPlugin<NoCustomization>;
+
+/// Plugin maintaining [`CurrentTile`] and [`PreviousTile`] of [`TileTracked`] entities.
+#[derive(Default)]
+pub struct CustomTileTrackingPlugin<C: Customization = NoCustomization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Plugin for CustomTileTrackingPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChangedTile>().add_systems(
+            PostUpdate,
+            track_tiles::<C>.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Track which tile of `map` this entity (its global translation) is on,
+/// see [`CurrentTile`] and [`ChangedTile`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct TileTracked {
+    pub map: Entity,
+}
+
+impl TileTracked {
+    pub fn new(map: Entity) -> Self {
+        Self { map }
+    }
+}
+
+/// Tile a [`TileTracked`] entity is on, absent while it is outside of the map.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct CurrentTile(pub UVec2);
+
+/// Tile a [`TileTracked`] entity was on before its [`CurrentTile`].
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct PreviousTile(pub UVec2);
+
+/// A [`TileTracked`] entity moved from one tile to another,
+/// `None` meaning outside of the map.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChangedTile {
+    pub entity: Entity,
+    pub map: Entity,
+    pub from: Option<UVec2>,
+    pub to: Option<UVec2>,
+}
+
+fn track_tiles<C: Customization>(
+    tracked: Query<(Entity, &TileTracked, &GlobalTransform, Option<&CurrentTile>)>,
+    maps: MapQuery<C>,
+    mut commands: Commands,
+    mut events: EventWriter<ChangedTile>,
+) {
+    for (entity, tracked, transform, current) in tracked.iter() {
+        // Keep the last known tile while the map is not ready
+        if !maps.is_ready(tracked.map) {
+            continue;
+        }
+
+        let from = current.map(|c| c.0);
+        let to = maps.tile_at_world(tracked.map, transform.translation().truncate());
+        if from == to {
+            continue;
+        }
+
+        let mut e = commands.entity(entity);
+        match to {
+            Some(tile) => e.insert(CurrentTile(tile)),
+            None => e.remove::<CurrentTile>(),
+        };
+        if let Some(tile) = from {
+            e.insert(PreviousTile(tile));
+        }
+
+        events.send(ChangedTile {
+            entity,
+            map: tracked.map,
+            from,
+            to,
+        });
+    }
+}
@@ -0,0 +1,156 @@
+//! Grouping of many tile edits into a single atomic operation.
+//!
+//! ```ignore
+//! let mut tx = map.transaction();
+//! tx.fill_rect(URect::new(0, 0, 8, 8), 1);
+//! tx.set(uvec2(3, 3), 2);
+//! let change = tx.commit();
+//! // later
+//! let redo = change.undo(&mut map);
+//! ```
+
+use bevy::{
+    math::{uvec2, URect},
+    prelude::*,
+    utils::HashMap,
+};
+
+use super::{
+    map::{flood_region, Map},
+    plugin::{Customization, NoCustomization},
+};
+
+/// Pending edits of a map, applied all at once by [`MapTransaction::commit`].
+///
+/// Reads through the transaction see its pending edits. Dropping the transaction without
+/// committing discards them, leaving the map untouched.
+pub struct MapTransaction<'a, C: Customization = NoCustomization> {
+    map: &'a mut Map<C>,
+    pending: HashMap<UVec2, u32>,
+}
+
+impl<'a, C: Customization> MapTransaction<'a, C> {
+    /// Size of the map being edited.
+    pub fn size(&self) -> UVec2 {
+        self.map.map_size()
+    }
+
+    /// Get tile at given position, including pending edits.
+    pub fn at_uvec(&self, i: UVec2) -> u32 {
+        match self.pending.get(&i) {
+            Some(v) => *v,
+            None => self.map.indexer().at_uvec(i),
+        }
+    }
+
+    /// Get tile at given position, including pending edits.
+    pub fn at(&self, x: u32, y: u32) -> u32 {
+        self.at_uvec(uvec2(x, y))
+    }
+
+    /// Set tile at given position.
+    pub fn set_uvec(&mut self, i: UVec2, v: u32) {
+        if i.x < self.size().x && i.y < self.size().y {
+            self.pending.insert(i, v);
+        }
+    }
+
+    /// Set tile at given position.
+    pub fn set(&mut self, x: u32, y: u32, v: u32) {
+        self.set_uvec(uvec2(x, y), v)
+    }
+
+    /// Set all tiles in `rect` (`max` is exclusive), clamped to the map.
+    pub fn fill_rect(&mut self, rect: URect, v: u32) {
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, self.size()));
+        for y in rect.min.y..rect.max.y {
+            for x in rect.min.x..rect.max.x {
+                self.set(x, y, v);
+            }
+        }
+    }
+
+    /// Replace the 4-connected region of tiles that have the same value as `start` with `v`.
+    /// Returns the number of tiles in the region.
+    pub fn flood_fill(&mut self, start: UVec2, v: u32) -> usize {
+        let region = flood_region(self.size(), start, |p| self.at_uvec(p));
+        for pos in region.iter() {
+            self.set_uvec(*pos, v);
+        }
+        region.len()
+    }
+
+    /// Apply all pending edits to the map.
+    /// The returned [`MapChange`] covers all of them and can undo them as a whole.
+    pub fn commit(self) -> MapChange {
+        let mut m = self.map.indexer_mut();
+        let mut change = MapChange::default();
+        for (pos, v) in self.pending {
+            let old = m.at_uvec(pos);
+            if old == v {
+                continue;
+            }
+            m.set_uvec(pos, v);
+            change.add(pos, old);
+        }
+        change
+    }
+}
+
+/// Tiles modified by a committed [`MapTransaction`], with their previous values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapChange {
+    region: URect,
+    previous: Vec<(UVec2, u32)>,
+}
+
+impl MapChange {
+    fn add(&mut self, pos: UVec2, old: u32) {
+        let tile = URect::from_corners(pos, pos + UVec2::ONE);
+        self.region = match self.previous.is_empty() {
+            true => tile,
+            false => self.region.union(tile),
+        };
+        self.previous.push((pos, old));
+    }
+
+    /// True if the transaction did not modify any tile.
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty()
+    }
+
+    /// Number of modified tiles.
+    pub fn len(&self) -> usize {
+        self.previous.len()
+    }
+
+    /// Bounding rectangle (`max` exclusive) of all modified tiles, empty if nothing changed.
+    pub fn region(&self) -> URect {
+        self.region
+    }
+
+    /// Positions of the modified tiles.
+    pub fn tiles(&self) -> impl Iterator<Item = UVec2> + '_ {
+        self.previous.iter().map(|(pos, _)| *pos)
+    }
+
+    /// Restore the previous values of all modified tiles as a single transaction.
+    /// Returns the change that redoes this one.
+    pub fn undo<C: Customization>(&self, map: &mut Map<C>) -> MapChange {
+        let mut tx = map.transaction();
+        for (pos, old) in self.previous.iter() {
+            tx.set_uvec(*pos, *old);
+        }
+        tx.commit()
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Start a [`MapTransaction`] to apply many edits as one operation.
+    pub fn transaction(&mut self) -> MapTransaction<C> {
+        MapTransaction {
+            map: self,
+            pending: HashMap::default(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::{prelude::*, transform::TransformSystem, utils::HashMap};
+
+use super::{
+    plugin::{Customization, NoCustomization},
+    query::MapQuery,
+};
+
+/// Plugin emitting [`TileTriggered`] events for entities with a [`TileTriggerSensor`] standing
+/// on tiles registered in [`TileTriggers`], with user payloads of type `P`.
+pub type TileTriggerPlugin<P> = CustomTileTriggerPlugin<P, NoCustomization>;
+
+/// Plugin emitting [`TileTriggered`] events for entities with a [`TileTriggerSensor`] standing
+/// on tiles registered in [`TileTriggers`], with user payloads of type `P`.
+pub struct CustomTileTriggerPlugin<P, C: Customization = NoCustomization> {
+    _marker: std::marker::PhantomData<(P, C)>,
+}
+
+impl<P, C: Customization> Default for CustomTileTriggerPlugin<P, C> {
+    fn default() -> Self {
+        Self {
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: Clone + Send + Sync + 'static, C: Customization> Plugin for CustomTileTriggerPlugin<P, C> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TileTriggers<P>>()
+            .add_event::<TileTriggered<P>>()
+            .add_systems(
+                PostUpdate,
+                check_tile_triggers::<P, C>.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TileTrigger<P> {
+    payload: P,
+    repeat: bool,
+}
+
+/// Atlas indices that trigger [`TileTriggered`] events (eg. pressure plates, damage floors, exits),
+/// with the payload to send along.
+#[derive(Resource, Debug, Clone)]
+pub struct TileTriggers<P> {
+    triggers: HashMap<u32, TileTrigger<P>>,
+}
+
+impl<P> Default for TileTriggers<P> {
+    fn default() -> Self {
+        Self {
+            triggers: HashMap::default(),
+        }
+    }
+}
+
+impl<P> TileTriggers<P> {
+    /// Trigger once whenever a sensor enters a tile with the given atlas index.
+    pub fn register(&mut self, index: u32, payload: P) {
+        self.triggers.insert(
+            index,
+            TileTrigger {
+                payload,
+                repeat: false,
+            },
+        );
+    }
+
+    /// Trigger every frame while a sensor is on a tile with the given atlas index.
+    pub fn register_repeating(&mut self, index: u32, payload: P) {
+        self.triggers.insert(
+            index,
+            TileTrigger {
+                payload,
+                repeat: true,
+            },
+        );
+    }
+
+    pub fn unregister(&mut self, index: u32) -> Option<P> {
+        self.triggers.remove(&index).map(|trigger| trigger.payload)
+    }
+
+    pub fn get(&self, index: u32) -> Option<&P> {
+        self.triggers.get(&index).map(|trigger| &trigger.payload)
+    }
+}
+
+/// Entities with this component set off [`TileTriggers`] on `map`,
+/// based on their global translation.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileTriggerSensor {
+    pub map: Entity,
+    last: Option<UVec2>,
+}
+
+impl TileTriggerSensor {
+    pub fn new(map: Entity) -> Self {
+        Self { map, last: None }
+    }
+}
+
+/// `entity` (having a [`TileTriggerSensor`]) is on the triggering tile `pos` of `map`.
+#[derive(Event, Debug, Clone)]
+pub struct TileTriggered<P> {
+    pub entity: Entity,
+    pub map: Entity,
+    pub pos: UVec2,
+    pub payload: P,
+}
+
+fn check_tile_triggers<P: Clone + Send + Sync + 'static, C: Customization>(
+    triggers: Res<TileTriggers<P>>,
+    mut sensors: Query<(Entity, &GlobalTransform, &mut TileTriggerSensor)>,
+    maps: MapQuery<C>,
+    mut events: EventWriter<TileTriggered<P>>,
+) {
+    for (entity, transform, mut sensor) in sensors.iter_mut() {
+        let pos = maps.tile_at_world(sensor.map, transform.translation().truncate());
+        let entered = pos != sensor.last;
+        if entered {
+            sensor.last = pos;
+        }
+
+        let Some(pos) = pos else {
+            continue;
+        };
+        let Some(trigger) = maps
+            .tile(sensor.map, pos)
+            .and_then(|index| triggers.triggers.get(&index))
+        else {
+            continue;
+        };
+
+        if entered || trigger.repeat {
+            events.send(TileTriggered {
+                entity,
+                map: sensor.map,
+                pos,
+                payload: trigger.payload.clone(),
+            });
+        }
+    }
+}
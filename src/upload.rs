@@ -0,0 +1,454 @@
+//! Keeping the GPU copy of the map tiles and per-tile data up to date.
+//!
+//! The tiles are not uploaded as part of the map's bind group (which bevy recreates whenever the
+//! map asset is modified), but kept in a buffer of their own, that is bound in place of the
+//! placeholder at `MAP_DATA_BINDING`.
+//! Each frame only the rows of the changed rectangle (see [`crate::changes`]) are written to it,
+//! new maps, resized maps and maps whose contents were replaced as a whole are uploaded
+//! completely.
+//!
+//! Note that tile edits still go through [`Assets::get_mut`] as the CPU copy of the tiles is part
+//! of the map asset, so the map's bind group and the other (mostly small) buffers in it are still
+//! recreated on edits, only the tiles are not re-uploaded.
+//!
+//! The per-tile and per-atlas-index data of the optional features (owners, fog, tints, heights,
+//! color ramps, ..) is packed into one buffer of `u32` and one of colors,
+//! as WebGPU (and many mobile GPUs) allow only 8 storage buffers per shader stage.
+//! The packed buffer starts with the offset and length of each channel, in the order of
+//! `CHANNEL_*` in the shader.
+//! Changed channels are tracked like the changed tiles: only those are repacked and written in
+//! place (tiles of the additional layers only in their changed rectangle), the buffers are only
+//! recreated when the length of a channel changes. Together with the tiles, high contrast table and decals the map
+//! binds 5 storage buffers, keep new features within the limit by adding channels here instead
+//! of new storage bindings.
+
+use bevy::{
+    math::URect,
+    prelude::*,
+    render::{
+        render_asset::{prepare_assets, RenderAssets},
+        render_resource::{
+            BindGroupEntry, Buffer, BufferInitDescriptor, BufferUsages, OwnedBindingResource,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    sprite::{Material2dPipeline, PreparedMaterial2d},
+    utils::HashMap,
+};
+
+use super::{map::Map, plugin::Customization, readback::MAP_DATA_BINDING};
+
+/// Binding of the packed per-tile data in [`Map`]'s bind group.
+const CHANNELS_BINDING: u32 = 103;
+/// Binding of the packed color data in [`Map`]'s bind group.
+const COLORS_BINDING: u32 = 105;
+
+/// Channels of the packed buffers, in the order of `CHANNEL_*` in the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Channel {
+    GridOffsets,
+    Owners,
+    ShadowCoverage,
+    LayerTiles,
+    TileAnimations,
+    TerrainCategories,
+    Tints,
+    SwayTiles,
+    Fog,
+    Heights,
+    RampColors,
+    TeamColors,
+}
+
+impl Channel {
+    pub(crate) const ALL: [Self; 12] = [
+        Self::GridOffsets,
+        Self::Owners,
+        Self::ShadowCoverage,
+        Self::LayerTiles,
+        Self::TileAnimations,
+        Self::TerrainCategories,
+        Self::Tints,
+        Self::SwayTiles,
+        Self::Fog,
+        Self::Heights,
+        Self::RampColors,
+        Self::TeamColors,
+    ];
+
+    pub(crate) const fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Data of a channel, either in the `u32` or in the color buffer.
+enum ChannelData<'a> {
+    Words(Vec<u32>),
+    Colors(&'a [Vec4]),
+}
+
+fn channel_data<C: Customization>(map: &Map<C>, channel: Channel) -> ChannelData<'_> {
+    let f32_bits = |values: &[f32]| -> Vec<u32> { values.iter().map(|v| v.to_bits()).collect() };
+    ChannelData::Words(match channel {
+        Channel::GridOffsets => f32_bits(&map.grid_offsets),
+        Channel::Owners => map.owners.clone(),
+        Channel::ShadowCoverage => f32_bits(&map.shadow_coverage),
+        Channel::LayerTiles => map.layer_texture.clone(),
+        Channel::TileAnimations => map.tile_animations.clone(),
+        Channel::TerrainCategories => map.terrain_categories.clone(),
+        Channel::Tints => map.tints.clone(),
+        Channel::SwayTiles => map.sway_tiles.clone(),
+        Channel::Fog => map.fog.clone(),
+        Channel::Heights => f32_bits(&map.heights),
+        Channel::RampColors => return ChannelData::Colors(&map.ramp_colors),
+        Channel::TeamColors => return ChannelData::Colors(&map.team_colors),
+    })
+}
+
+/// Added by [`crate::plugin::CustomFastTileMapPlugin`].
+pub(crate) struct MapUploadPlugin<C: Customization> {
+    _customization: std::marker::PhantomData<C>,
+}
+
+impl<C: Customization> Default for MapUploadPlugin<C> {
+    fn default() -> Self {
+        Self {
+            _customization: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<C: Customization> Plugin for MapUploadPlugin<C> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<GpuMapBuffers<C>>()
+            .init_resource::<ExtractedMapUploads<C>>()
+            .add_systems(ExtractSchedule, extract_map_uploads::<C>)
+            // Right after the bind groups of modified maps have been recreated, before anything
+            // (eg. automata) works on the tiles
+            .add_systems(
+                Render,
+                upload_map_buffers::<C>
+                    .in_set(RenderSet::PrepareAssets)
+                    .after(prepare_assets::<PreparedMaterial2d<Map<C>>>),
+            );
+    }
+}
+
+/// GPU buffers of a map, bound in place of the placeholders.
+#[derive(Default)]
+struct GpuBuffers {
+    tiles: Option<Buffer>,
+    n_tiles: usize,
+    channels: Option<Buffer>,
+    /// Offset and length of each [`Channel`] in `channels` or `colors`
+    channel_layout: Vec<(u32, u32)>,
+    colors: Option<Buffer>,
+}
+
+#[derive(Resource)]
+struct GpuMapBuffers<C: Customization>(HashMap<AssetId<Map<C>>, GpuBuffers>);
+
+impl<C: Customization> Default for GpuMapBuffers<C> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+enum TileUpload {
+    /// All tiles of the map, replacing the buffer
+    Full(Vec<u32>),
+    /// Tiles of `rect`, row by row, of a map `width` tiles wide
+    Rect {
+        rect: URect,
+        width: u32,
+        tiles: Vec<u32>,
+    },
+}
+
+enum ChannelUpload {
+    /// Packed per-tile data and colors, replacing the buffers
+    Full(Vec<u32>, Vec<Vec4>),
+    /// Data written in place, at the given offset (in elements) of the respective buffer
+    Partial {
+        words: Vec<(u32, Vec<u32>)>,
+        colors: Vec<(u32, Vec<Vec4>)>,
+    },
+}
+
+#[derive(Default)]
+struct MapUpload {
+    tiles: Option<TileUpload>,
+    channels: Option<ChannelUpload>,
+}
+
+#[derive(Resource)]
+struct ExtractedMapUploads<C: Customization>(HashMap<AssetId<Map<C>>, MapUpload>);
+
+impl<C: Customization> Default for ExtractedMapUploads<C> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+/// Pack the per-tile and per-atlas-index data of `map`, see the [module docs](self).
+fn pack_channels<C: Customization>(map: &Map<C>) -> ChannelUpload {
+    let mut packed = vec![0; 2 * Channel::ALL.len()];
+    let mut packed_colors = Vec::new();
+    for channel in Channel::ALL {
+        let header = 2 * channel as usize;
+        match channel_data(map, channel) {
+            ChannelData::Words(words) => {
+                packed[header] = packed.len() as u32;
+                packed[header + 1] = words.len() as u32;
+                packed.extend_from_slice(&words);
+            }
+            ChannelData::Colors(colors) => {
+                packed[header] = packed_colors.len() as u32;
+                packed[header + 1] = colors.len() as u32;
+                packed_colors.extend_from_slice(colors);
+            }
+        }
+    }
+    // Bindings can not be empty
+    if packed_colors.is_empty() {
+        packed_colors.push(Vec4::ZERO);
+    }
+    ChannelUpload::Full(packed, packed_colors)
+}
+
+/// Write the `changed` channels (bits of [`Channel`]) and the layer tiles in `layers` in place,
+/// or pack all channels if the buffers do not exist yet or the length of a channel changed.
+fn channel_upload<C: Customization>(
+    map: &Map<C>,
+    gpu: Option<&GpuBuffers>,
+    changed: u32,
+    layers: Option<URect>,
+) -> Option<ChannelUpload> {
+    let Some(layout) = gpu
+        .filter(|gpu| gpu.channels.is_some())
+        .map(|gpu| &gpu.channel_layout)
+    else {
+        return Some(pack_channels(map));
+    };
+    if changed == 0 && layers.is_none() {
+        return None;
+    }
+
+    let mut words = Vec::new();
+    let mut colors = Vec::new();
+    for channel in Channel::ALL {
+        if changed & channel.bit() == 0 {
+            continue;
+        }
+        let (offset, len) = layout[channel as usize];
+        match channel_data(map, channel) {
+            ChannelData::Words(data) if data.len() == len as usize => words.push((offset, data)),
+            ChannelData::Colors(data) if data.len() == len as usize => {
+                colors.push((offset, data.to_vec()))
+            }
+            _ => return Some(pack_channels(map)),
+        }
+    }
+
+    // Layers written as a whole above otherwise
+    if let Some(rect) = layers.filter(|_| changed & Channel::LayerTiles.bit() == 0) {
+        let (offset, len) = layout[Channel::LayerTiles as usize];
+        if map.layer_texture.len() != len as usize {
+            return Some(pack_channels(map));
+        }
+        let size = map.map_size();
+        let n_tiles = size.x as usize * size.y as usize;
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, size));
+        for (i, layer) in map.layer_texture.chunks_exact(n_tiles.max(1)).enumerate() {
+            for y in rect.min.y..rect.max.y {
+                let start = y as usize * size.x as usize + rect.min.x as usize;
+                let row = layer[start..start + rect.width() as usize].to_vec();
+                words.push((offset + (i * n_tiles + start) as u32, row));
+            }
+        }
+    }
+    Some(ChannelUpload::Partial { words, colors })
+}
+
+fn extract_map_uploads<C: Customization>(
+    maps: Extract<Res<Assets<Map<C>>>>,
+    mut gpu_buffers: ResMut<GpuMapBuffers<C>>,
+    mut uploads: ResMut<ExtractedMapUploads<C>>,
+) {
+    gpu_buffers.0.retain(|id, _| maps.contains(*id));
+    uploads.0.clear();
+
+    for (id, map) in maps.iter() {
+        // Too large for the GPU, see `MapFailed`
+        if map.invalid {
+            continue;
+        }
+        let gpu = gpu_buffers.0.get(&id);
+        let (changed, layers) = map.changed_tiles.take_channels();
+        if let Some(upload) = channel_upload(map, gpu, changed, layers) {
+            uploads.0.entry(id).or_default().channels = Some(upload);
+        }
+
+        let size = map.map_size();
+        let Some(rect) = map.changed_tiles.take_upload(size) else {
+            continue;
+        };
+        let rect = rect.intersect(URect::from_corners(UVec2::ZERO, size));
+        let uploaded =
+            gpu.is_some_and(|gpu| gpu.tiles.is_some() && gpu.n_tiles == map.map_texture.len());
+
+        if !uploaded || rect.size() == size {
+            uploads.0.entry(id).or_default().tiles =
+                Some(TileUpload::Full(map.map_texture.clone()));
+            continue;
+        }
+        if rect.is_empty() {
+            continue;
+        }
+        let mut tiles = Vec::with_capacity((rect.width() * rect.height()) as usize);
+        for y in rect.min.y..rect.max.y {
+            let row = (y * size.x) as usize;
+            tiles.extend_from_slice(
+                &map.map_texture[row + rect.min.x as usize..row + rect.max.x as usize],
+            );
+        }
+        uploads.0.entry(id).or_default().tiles = Some(TileUpload::Rect {
+            rect,
+            width: size.x,
+            tiles,
+        });
+    }
+}
+
+fn word_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn color_bytes(colors: &[Vec4]) -> Vec<u8> {
+    colors
+        .iter()
+        .flat_map(|c| c.to_array())
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+fn create_storage_buffer(device: &RenderDevice, label: &str, contents: &[u8]) -> Buffer {
+    device.create_buffer_with_data(&BufferInitDescriptor {
+        label: Some(label),
+        contents,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+    })
+}
+
+fn upload_map_buffers<C: Customization>(
+    mut uploads: ResMut<ExtractedMapUploads<C>>,
+    mut gpu_buffers: ResMut<GpuMapBuffers<C>>,
+    mut materials: ResMut<RenderAssets<PreparedMaterial2d<Map<C>>>>,
+    pipeline: Res<Material2dPipeline<Map<C>>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    for (id, upload) in uploads.0.drain() {
+        let gpu = gpu_buffers.0.entry(id).or_default();
+        match upload.tiles {
+            Some(TileUpload::Full(tiles)) => {
+                gpu.n_tiles = tiles.len();
+                // Bindings can not be empty
+                let contents: Vec<u8> = tiles
+                    .iter()
+                    .chain(tiles.is_empty().then_some(&0))
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect();
+                gpu.tiles = Some(create_storage_buffer(&device, "map_tiles", &contents));
+            }
+            Some(TileUpload::Rect { rect, width, tiles }) => {
+                let Some(buffer) = gpu.tiles.as_ref() else {
+                    continue;
+                };
+                for (row, y) in tiles
+                    .chunks(rect.width() as usize)
+                    .zip(rect.min.y..rect.max.y)
+                {
+                    let offset = (y as u64 * width as u64 + rect.min.x as u64) * 4;
+                    let bytes: Vec<u8> = row.iter().flat_map(|v| v.to_le_bytes()).collect();
+                    queue.write_buffer(buffer, offset, &bytes);
+                }
+            }
+            None => {}
+        }
+        match upload.channels {
+            Some(ChannelUpload::Full(channels, colors)) => {
+                gpu.channel_layout = channels[..2 * Channel::ALL.len()]
+                    .chunks(2)
+                    .map(|c| (c[0], c[1]))
+                    .collect();
+                gpu.channels = Some(create_storage_buffer(
+                    &device,
+                    "map_channels",
+                    &word_bytes(&channels),
+                ));
+                gpu.colors = Some(create_storage_buffer(
+                    &device,
+                    "map_colors",
+                    &color_bytes(&colors),
+                ));
+            }
+            Some(ChannelUpload::Partial { words, colors }) => {
+                let (Some(channels), Some(color_buffer)) = (&gpu.channels, &gpu.colors) else {
+                    continue;
+                };
+                for (offset, data) in words.iter().filter(|(_, data)| !data.is_empty()) {
+                    queue.write_buffer(channels, *offset as u64 * 4, &word_bytes(data));
+                }
+                for (offset, data) in colors.iter().filter(|(_, data)| !data.is_empty()) {
+                    queue.write_buffer(color_buffer, *offset as u64 * 16, &color_bytes(data));
+                }
+            }
+            None => {}
+        }
+    }
+
+    // Bind the buffers to maps whose bind group has been (re)created, or whose buffers have been
+    // replaced
+    for (id, gpu) in gpu_buffers.0.iter() {
+        let Some(material) = materials.get_mut(*id) else {
+            continue;
+        };
+        let mut rebind = false;
+        for (binding, resource) in material.bindings.iter_mut() {
+            let buffer = match *binding {
+                MAP_DATA_BINDING => &gpu.tiles,
+                CHANNELS_BINDING => &gpu.channels,
+                COLORS_BINDING => &gpu.colors,
+                _ => continue,
+            };
+            let Some(buffer) = buffer else {
+                continue;
+            };
+            if !matches!(resource, OwnedBindingResource::Buffer(bound) if bound.id() == buffer.id())
+            {
+                *resource = OwnedBindingResource::Buffer(buffer.clone());
+                rebind = true;
+            }
+        }
+        if !rebind {
+            continue;
+        }
+
+        let entries: Vec<BindGroupEntry> = material
+            .bindings
+            .iter()
+            .map(|(binding, resource)| BindGroupEntry {
+                binding: *binding,
+                resource: resource.get_binding(),
+            })
+            .collect();
+        material.bind_group =
+            device.create_bind_group("map_bind_group", &pipeline.material2d_layout, &entries);
+    }
+}
@@ -0,0 +1,253 @@
+//! Borrowed access to the tiles of a map for systems that process maps in parallel.
+//!
+//! [`MapView`] is a cheap, copyable read-only view that any number of tasks can hold at once.
+//! [`Map::split_rows_mut`] hands out disjoint bands of rows that can be edited concurrently,
+//! eg. from [`ComputeTaskPool`](bevy::tasks::ComputeTaskPool) tasks:
+//!
+//! ```ignore
+//! let mut bands = map.split_rows_mut(32);
+//! ComputeTaskPool::get().scope(|s| {
+//!     for band in bands.iter_mut() {
+//!         s.spawn(async move { generate(band) });
+//!     }
+//! });
+//! ```
+
+use std::ops::{Deref, DerefMut, Range};
+
+use bevy::{
+    math::{uvec2, URect},
+    prelude::*,
+    utils::HashMap,
+};
+
+use super::{
+    changes::ChangedTiles, content_hash::cell_hash, map::Map, occlusion::OcclusionGrid,
+    plugin::Customization, replay::EditRecorder, stats::TileStats,
+};
+
+/// Read-only view of the tiles of a map, see [`Map::view`].
+#[derive(Debug, Clone, Copy)]
+pub struct MapView<'a> {
+    tiles: &'a [u32],
+    size: UVec2,
+    content_hash: u64,
+}
+
+impl<'a> MapView<'a> {
+    /// Size of the viewed map in tiles.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// [`Map::content_hash`] at the time the view was created.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    /// All tiles, row by row.
+    pub fn tiles(&self) -> &'a [u32] {
+        self.tiles
+    }
+
+    /// Tiles of row `y`, empty if out of bounds.
+    pub fn row(&self, y: u32) -> &'a [u32] {
+        if y >= self.size.y {
+            return &[];
+        }
+        let start = (y * self.size.x) as usize;
+        &self.tiles[start..start + self.size.x as usize]
+    }
+
+    /// Tile at `pos`, `None` if out of bounds.
+    pub fn get(&self, pos: UVec2) -> Option<u32> {
+        (pos.x < self.size.x && pos.y < self.size.y)
+            .then(|| self.tiles[(pos.y * self.size.x + pos.x) as usize])
+    }
+
+    /// Get tile at given position, `0` if out of bounds.
+    pub fn at_uvec(&self, i: UVec2) -> u32 {
+        self.get(i).unwrap_or(0)
+    }
+
+    /// Get tile at given position, `0` if out of bounds.
+    pub fn at(&self, x: u32, y: u32) -> u32 {
+        self.at_uvec(uvec2(x, y))
+    }
+}
+
+/// Mutable access to a band of consecutive rows of a map, see [`Map::split_rows_mut`].
+///
+/// Positions are in map coordinates, positions outside of the band read as `0`
+/// and are ignored when set.
+#[derive(Debug)]
+pub struct MapRowsMut<'a> {
+    tiles: &'a mut [u32],
+    rows: Range<u32>,
+    width: u32,
+    hash_delta: u64,
+    stats_delta: HashMap<u32, isize>,
+    /// Changed positions and their new tiles, in order of the edits
+    changes: Vec<(UVec2, u32)>,
+}
+
+impl<'a> MapRowsMut<'a> {
+    /// Rows covered by this band.
+    pub fn rows(&self) -> Range<u32> {
+        self.rows.clone()
+    }
+
+    /// Width of the map in tiles.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn index(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && self.rows.contains(&y))
+            .then(|| ((y - self.rows.start) * self.width + x) as usize)
+    }
+
+    /// Get tile at given position.
+    pub fn at_uvec(&self, i: UVec2) -> u32 {
+        self.at(i.x, i.y)
+    }
+
+    /// Get tile at given position.
+    pub fn at(&self, x: u32, y: u32) -> u32 {
+        self.index(x, y).map_or(0, |i| self.tiles[i])
+    }
+
+    /// Set tile at given position.
+    pub fn set_uvec(&mut self, i: UVec2, v: u32) {
+        self.set(i.x, i.y, v)
+    }
+
+    /// Set tile at given position.
+    pub fn set(&mut self, x: u32, y: u32, v: u32) {
+        let Some(i) = self.index(x, y) else {
+            return;
+        };
+        let old = std::mem::replace(&mut self.tiles[i], v);
+        if old != v {
+            let idx = (y * self.width + x) as usize;
+            self.hash_delta ^= cell_hash(idx, old) ^ cell_hash(idx, v);
+            *self.stats_delta.entry(old).or_default() -= 1;
+            *self.stats_delta.entry(v).or_default() += 1;
+            self.changes.push((uvec2(x, y), v));
+        }
+    }
+}
+
+/// Disjoint row bands of a map, obtained from [`Map::split_rows_mut`].
+///
+/// Dereferences to a slice of [`MapRowsMut`]. The edits of all bands are accounted for like
+/// edits through [`crate::map::MapIndexerMut`] when this is dropped: the map's [`Map::stats`]
+/// and [`Map::content_hash`] are updated, the changed tiles are reported (and uploaded), their
+/// damage is reset and they are recorded by an active [`crate::replay::EditRecorder`].
+pub struct MapRowBands<'a> {
+    bands: Vec<MapRowsMut<'a>>,
+    width: u32,
+    stats: &'a mut TileStats,
+    content_hash: &'a mut u64,
+    changed_tiles: &'a mut ChangedTiles,
+    damage: &'a mut Vec<u32>,
+    occlusion: &'a mut Option<OcclusionGrid>,
+    recorder: &'a mut Option<EditRecorder>,
+}
+
+impl<'a> Deref for MapRowBands<'a> {
+    type Target = [MapRowsMut<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.bands
+    }
+}
+
+impl<'a> DerefMut for MapRowBands<'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.bands
+    }
+}
+
+impl<'a> Drop for MapRowBands<'a> {
+    fn drop(&mut self) {
+        for band in self.bands.drain(..) {
+            *self.content_hash ^= band.hash_delta;
+            for (index, delta) in band.stats_delta {
+                self.stats.apply_delta(index, delta);
+            }
+
+            let mut changed: Option<URect> = None;
+            for (pos, v) in band.changes {
+                let rect = URect::from_corners(pos, pos + UVec2::ONE);
+                changed = Some(changed.map_or(rect, |changed| changed.union(rect)));
+
+                let idx = (pos.y * self.width + pos.x) as usize;
+                if let Some(damage) = self.damage.get_mut(idx) {
+                    *damage = 0;
+                }
+                if let Some(occlusion) = self.occlusion.as_mut() {
+                    occlusion.update(idx, v);
+                }
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record(0, pos, v);
+                }
+            }
+            if let Some(changed) = changed {
+                self.changed_tiles.add(changed);
+            }
+        }
+    }
+}
+
+impl<C: Customization> Map<C> {
+    /// Read-only view of the tiles of this map.
+    pub fn view(&self) -> MapView {
+        MapView {
+            tiles: &self.map_texture,
+            size: self.map_size(),
+            content_hash: self.content_hash,
+        }
+    }
+
+    /// Split the map into disjoint bands of `band_rows` rows each (the last band may be smaller)
+    /// that can be edited concurrently.
+    pub fn split_rows_mut(&mut self, band_rows: u32) -> MapRowBands {
+        let size = self.map_size();
+        let band_rows = band_rows.max(1);
+        let Map {
+            map_texture,
+            stats,
+            content_hash,
+            changed_tiles,
+            damage,
+            occlusion,
+            recorder,
+            ..
+        } = self;
+
+        let bands = map_texture
+            .chunks_mut((band_rows * size.x).max(1) as usize)
+            .zip((0..size.y).step_by(band_rows as usize))
+            .map(|(tiles, start)| MapRowsMut {
+                tiles,
+                rows: start..(start + band_rows).min(size.y),
+                width: size.x,
+                hash_delta: 0,
+                stats_delta: HashMap::default(),
+                changes: Vec::new(),
+            })
+            .collect();
+
+        MapRowBands {
+            bands,
+            width: size.x,
+            stats,
+            content_hash,
+            changed_tiles,
+            damage,
+            occlusion,
+            recorder,
+        }
+    }
+}